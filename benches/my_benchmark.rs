@@ -1,6 +1,32 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use raytracing::hittable::{HitRecord, Hittable};
+use raytracing::materials::{Dielectric, Material};
+use raytracing::simd::{Aabbf, RayPacket};
+use raytracing::surfaces::{Aabb, BvhNode, Sphere};
 use raytracing::textures::perlin::Perlin;
-use raytracing::Vec3;
+use raytracing::{Ray, Vec3};
+
+/// Counts every allocation, to see how many tracing rays makes
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
 
 fn bench_perlin_noise(c: &mut Criterion) {
     let perlin = Perlin::new();
@@ -35,5 +61,152 @@ fn bench_tri_interp(crit: &mut Criterion) {
     });
 }
 
-criterion_group!(benches, /*bench_perlin_noise,*/ bench_tri_interp);
+/// 4 rays that start close to each other and go in almost the same direction,
+/// like the rays from the camera for neighbouring pixels
+fn coherent_rays() -> [Ray; 4] {
+    [(0.0, 0.0), (0.01, 0.0), (0.0, 0.01), (0.01, 0.01)].map(|(x, y)| {
+        Ray::new(
+            Vec3::new(0.0, 0.0, 30.0),
+            Vec3::new(x, y, -1.0).unit_vector(),
+            0.0,
+        )
+    })
+}
+
+fn bench_aabb_packet(c: &mut Criterion) {
+    let aabb = black_box(Aabb::new(
+        Vec3::new(-1.0, -1.0, -1.0),
+        Vec3::new(1.0, 1.0, 1.0),
+    ));
+    let packet_aabb = black_box(Aabbf::from(&aabb));
+    let rays = black_box(coherent_rays());
+    let packet = black_box(RayPacket::new(rays));
+
+    c.bench_function("aabb 4 rays", |b| {
+        b.iter(|| black_box(rays).map(|ray| black_box(&aabb).hit(&ray, 0.001, f64::INFINITY)))
+    });
+    c.bench_function("aabb ray packet", |b| {
+        b.iter(|| black_box(&packet_aabb).hit_packet(black_box(&packet), 0.001, [f64::INFINITY; 4]))
+    });
+}
+
+/// A BVH of 1000 small spheres spread around the origin
+fn spheres_bvh() -> BvhNode {
+    let spheres: Vec<Arc<dyn Hittable>> = (0..1000)
+        .map(|_| {
+            let center = Vec3::random_min_max(-10.0, 10.0);
+            Arc::new(Sphere::new(center, 0.2, Dielectric::new(1.5))) as Arc<dyn Hittable>
+        })
+        .collect();
+    BvhNode::from_vec(spheres, (0.0, 1.0))
+}
+
+fn bench_bvh_packet(c: &mut Criterion) {
+    let bvh = spheres_bvh();
+    let rays = black_box(coherent_rays());
+    let packet = black_box(RayPacket::new(rays));
+
+    c.bench_function("bvh 4 rays", |b| {
+        b.iter(|| black_box(rays).map(|ray| bvh.hit(&ray, 0.001, f64::INFINITY).map(|hit| hit.t)))
+    });
+    c.bench_function("bvh ray packet", |b| {
+        b.iter(|| {
+            let mut hits = [None, None, None, None];
+            bvh.hit_packet(
+                black_box(&packet),
+                0.001,
+                &mut [f64::INFINITY; 4],
+                &mut hits,
+            );
+            hits.map(|hit| hit.map(|hit| hit.t))
+        })
+    });
+}
+
+/// A sphere that boxes a copy of its material on every hit, like every surface did before
+/// hit records had a shared reference to it
+struct BoxingSphere {
+    sphere: Sphere<Dielectric>,
+    material: Dielectric,
+}
+
+impl Hittable for BoxingSphere {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let hit = self.sphere.hit(ray, t_min, t_max)?;
+        let material: Box<dyn Material> = Box::new(self.material.clone());
+        black_box(material);
+        Some(hit)
+    }
+
+    fn bounding_box(&self, time: (f64, f64)) -> Option<Aabb> {
+        self.sphere.bounding_box(time)
+    }
+}
+
+/// The same spheres as `spheres_bvh`, but boxing their materials
+fn boxing_spheres_bvh() -> BvhNode {
+    let spheres: Vec<Arc<dyn Hittable>> = (0..1000)
+        .map(|_| {
+            let center = Vec3::random_min_max(-10.0, 10.0);
+            let material = Dielectric::new(1.5);
+            Arc::new(BoxingSphere {
+                sphere: Sphere::new(center, 0.2, material.clone()),
+                material,
+            }) as Arc<dyn Hittable>
+        })
+        .collect();
+    BvhNode::from_vec(spheres, (0.0, 1.0))
+}
+
+/// How many allocations hitting `bvh` with every ray makes, and how many rays hit
+fn count_allocations(bvh: &BvhNode, rays: &[Ray]) -> (usize, usize) {
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let hits = rays
+        .iter()
+        .filter(|ray| bvh.hit(ray, 0.001, f64::INFINITY).is_some())
+        .count();
+    (ALLOCATIONS.load(Ordering::Relaxed) - allocations, hits)
+}
+
+fn bench_hit_allocations(c: &mut Criterion) {
+    let bvh = spheres_bvh();
+    let boxing_bvh = boxing_spheres_bvh();
+    let rays: Vec<Ray> = (0..10_000)
+        .map(|_| Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::random_unit_vector(), 0.0))
+        .collect();
+
+    // Hits share their material, so not even the candidates that get discarded allocate
+    let (allocations, hits) = count_allocations(&bvh, &rays);
+    assert_eq!(allocations, 0, "hitting a BVH allocated");
+    let (boxing_allocations, boxing_hits) = count_allocations(&boxing_bvh, &rays);
+    assert!(boxing_allocations >= boxing_hits);
+    println!(
+        "{allocations} allocations for {} rays, {hits} of them hit, and {boxing_allocations} \
+         boxing the materials",
+        rays.len()
+    );
+
+    c.bench_function("bvh hits", |b| {
+        b.iter(|| {
+            rays.iter()
+                .filter(|ray| bvh.hit(ray, 0.001, f64::INFINITY).is_some())
+                .count()
+        })
+    });
+    c.bench_function("bvh hits boxing materials", |b| {
+        b.iter(|| {
+            rays.iter()
+                .filter(|ray| boxing_bvh.hit(ray, 0.001, f64::INFINITY).is_some())
+                .count()
+        })
+    });
+}
+
+criterion_group!(
+    benches,
+    /*bench_perlin_noise,*/ bench_tri_interp,
+    bench_aabb_packet,
+    bench_bvh_packet,
+    bench_hit_allocations
+);
 criterion_main!(benches);
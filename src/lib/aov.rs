@@ -0,0 +1,473 @@
+//! Arbitrary output variables (AOVs)
+//!
+//! Besides the final (beauty) image, these are extra images with information about what the
+//! camera sees, used for compositing and denoising.
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::film::FilmTile;
+use crate::hittable::{HitRecord, Hittable};
+use crate::integrators::{PathSample, PathStats, SceneContext};
+use crate::samplers::Sampler;
+use crate::scene::Scene;
+use crate::{Color, Ray, Samplers, Vec3};
+
+/// Every render pass of an image
+///
+/// Each pass has one value per pixel, starting from the bottom left corner of the image
+/// and going row by row, like `get_image_coordinates`. Most passes are the average of every
+/// sample of the pixel, but the object ID is the one from the first sample.
+pub struct Aovs {
+    width: u32,
+    height: u32,
+    /// The final image, `emission + direct + indirect`
+    pub beauty: Vec<Color>,
+    /// Base color of the first surface hit
+    pub albedo: Vec<Color>,
+    /// Normal of the first surface hit, facing the camera
+    pub normal: Vec<Vec3>,
+    /// Distance to the first surface hit along the ray (`HitRecord::t`).
+    /// It's infinite if nothing was hit.
+    pub depth: Vec<f64>,
+    /// Point in the world of the first surface hit
+    pub position: Vec<Vec3>,
+    /// Texture coordinates of the first surface hit
+    pub uv: Vec<(f64, f64)>,
+    pub object_id: Vec<u32>,
+    /// Light emitted by the first surface hit, or the background if nothing was hit
+    pub emission: Vec<Color>,
+    /// Light that bounced once before getting to the camera
+    pub direct: Vec<Color>,
+    /// Light that bounced two or more times before getting to the camera
+    pub indirect: Vec<Color>,
+}
+
+/// All the passes for a single sample, or the average of the samples of a pixel
+#[derive(Clone)]
+pub(crate) struct AovSample {
+    pub(crate) albedo: Color,
+    pub(crate) normal: Vec3,
+    pub(crate) depth: Option<f64>,
+    pub(crate) position: Vec3,
+    pub(crate) uv: (f64, f64),
+    pub(crate) object_id: u32,
+    pub(crate) emission: Color,
+    pub(crate) direct: Color,
+    pub(crate) indirect: Color,
+}
+
+impl AovSample {
+    const fn miss(background: Color) -> Self {
+        Self {
+            albedo: Color::new(0.0, 0.0, 0.0),
+            normal: Vec3::new(0.0, 0.0, 0.0),
+            depth: None,
+            position: Vec3::new(0.0, 0.0, 0.0),
+            uv: (0.0, 0.0),
+            object_id: 0,
+            emission: background,
+            direct: Color::new(0.0, 0.0, 0.0),
+            indirect: Color::new(0.0, 0.0, 0.0),
+        }
+    }
+
+    /// Finds the light of a sample with the integrator of the scene, but keeps the light
+    /// from the first hit separated by how many times it bounced.
+    /// Light that's splatted onto `film_tile` isn't in any pass.
+    fn trace<T: Hittable>(
+        scene: &Scene<T>,
+        ray: &Ray,
+        first_hit: Option<HitRecord>,
+        context: &SceneContext,
+        sampler: &mut dyn Sampler,
+        film_tile: &mut FilmTile,
+    ) -> (Self, PathSample) {
+        let mut sample = Self::miss(Color::new(0.0, 0.0, 0.0));
+
+        let integrator = scene.integrator();
+        let path = integrator.sample(ray, first_hit, context, sampler, film_tile, &mut |vertex| {
+            if let (0, Some(hit)) = (vertex.bounce, vertex.hit) {
+                sample.albedo = hit.material.albedo(hit);
+                sample.normal = hit.normal;
+                sample.depth = Some(hit.t);
+                sample.position = hit.p;
+                sample.uv = (hit.u, hit.v);
+                sample.object_id = hit.object_id;
+            }
+
+            match vertex.bounce {
+                0 => sample.emission += vertex.light,
+                1 => sample.direct += vertex.light,
+                _ => sample.indirect += vertex.light,
+            }
+        });
+
+        (sample, path)
+    }
+}
+
+/// Renders the scene keeping every render pass separated
+///
+/// The beauty pass is reconstructed with the filter of the scene,
+/// but the rest are the average of the samples inside each pixel.
+pub fn render_aovs<T: Hittable>(scene: &Scene<T>) -> Aovs {
+    let (image_width, image_height) = scene.image_size();
+
+    let (samples, film, _) = crate::render_tiles(
+        scene,
+        &crate::rows(image_width, image_height),
+        |samplers, stats, x, y, film_tile| sample_pixel(scene, samplers, stats, x, y, film_tile),
+    );
+
+    let mut aovs = Aovs::from_samples(image_width, image_height, &samples);
+    aovs.beauty = film.colors();
+
+    aovs
+}
+
+/// Returns the average of the passes of all the samples of a pixel,
+/// and adds the color of each sample to `film_tile` and its path to `stats`
+pub(crate) fn sample_pixel<T: Hittable>(
+    scene: &Scene<T>,
+    samplers: &mut Samplers,
+    stats: &mut PathStats,
+    x: u32,
+    y: u32,
+    film_tile: &mut FilmTile,
+) -> AovSample {
+    let sample_weight = 1.0 / f64::from(scene.samples_per_pixel());
+
+    let mut pixel_samples = Vec::new();
+    let context = scene.context();
+    crate::trace_samples(
+        scene,
+        &context,
+        samplers,
+        (x, y),
+        |position, ray, first_hit, sampler| {
+            let (passes, path) =
+                AovSample::trace(scene, ray, first_hit, &context, sampler, film_tile);
+
+            film_tile.add_sample(
+                position,
+                passes.emission + passes.direct + passes.indirect,
+                scene.filter(),
+            );
+            stats.add(&path);
+
+            pixel_samples.push(passes);
+        },
+    );
+
+    average_samples(pixel_samples.into_iter(), sample_weight)
+}
+
+fn average_samples(samples: impl Iterator<Item = AovSample>, sample_weight: f64) -> AovSample {
+    let black = Color::new(0.0, 0.0, 0.0);
+    let zero = Vec3::new(0.0, 0.0, 0.0);
+
+    let mut average = AovSample::miss(black);
+    let mut first_object_id = None;
+
+    // Geometric passes are only averaged between the samples that hit something
+    let mut hits = 0_u32;
+    let (mut depth, mut position, mut uv) = (0.0, zero, (0.0, 0.0));
+
+    for sample in samples {
+        average.albedo += sample.albedo * sample_weight;
+        average.normal += sample.normal * sample_weight;
+        average.emission += sample.emission * sample_weight;
+        average.direct += sample.direct * sample_weight;
+        average.indirect += sample.indirect * sample_weight;
+
+        first_object_id.get_or_insert(sample.object_id);
+
+        if let Some(sample_depth) = sample.depth {
+            hits += 1;
+            depth += sample_depth;
+            position += sample.position;
+            uv = (uv.0 + sample.uv.0, uv.1 + sample.uv.1);
+        }
+    }
+
+    if hits > 0 {
+        let hits = f64::from(hits);
+
+        average.depth = Some(depth / hits);
+        average.position = position / hits;
+        average.uv = (uv.0 / hits, uv.1 / hits);
+    }
+    if !average.normal.near_zero() {
+        average.normal = average.normal.unit_vector();
+    }
+    average.object_id = first_object_id.unwrap_or(0);
+
+    average
+}
+
+impl Aovs {
+    /// Collects the passes of every pixel, in the order of `get_image_coordinates`
+    pub(crate) fn from_samples(width: u32, height: u32, samples: &[AovSample]) -> Self {
+        let mut aovs = Self::with_capacity(width, height);
+        for sample in samples {
+            aovs.push(sample);
+        }
+
+        aovs
+    }
+
+    fn with_capacity(width: u32, height: u32) -> Self {
+        let capacity = (width * height) as usize;
+
+        Self {
+            width,
+            height,
+            beauty: Vec::with_capacity(capacity),
+            albedo: Vec::with_capacity(capacity),
+            normal: Vec::with_capacity(capacity),
+            depth: Vec::with_capacity(capacity),
+            position: Vec::with_capacity(capacity),
+            uv: Vec::with_capacity(capacity),
+            object_id: Vec::with_capacity(capacity),
+            emission: Vec::with_capacity(capacity),
+            direct: Vec::with_capacity(capacity),
+            indirect: Vec::with_capacity(capacity),
+        }
+    }
+
+    fn push(&mut self, sample: &AovSample) {
+        self.beauty
+            .push(sample.emission + sample.direct + sample.indirect);
+        self.albedo.push(sample.albedo);
+        self.normal.push(sample.normal);
+        self.depth.push(sample.depth.unwrap_or(f64::INFINITY));
+        self.position.push(sample.position);
+        self.uv.push(sample.uv);
+        self.object_id.push(sample.object_id);
+        self.emission.push(sample.emission);
+        self.direct.push(sample.direct);
+        self.indirect.push(sample.indirect);
+    }
+
+    pub const fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Saves every pass into `directory`.
+    ///
+    /// Each pass is saved with its raw values as a floating point PFM image (`<pass>.pfm`),
+    /// and as a PNG image (`<pass>.png`) that's easier to look at.
+    /// Normals are remapped from \[-1,1\] to \[0,1\], depth is shown from white (close) to black (far),
+    /// and object IDs get a random looking color each.
+    ///
+    /// # Errors
+    /// If the directory or any of the files can't be written
+    pub fn save<P: AsRef<Path>>(&self, directory: P) -> image::ImageResult<()> {
+        let directory = directory.as_ref();
+        std::fs::create_dir_all(directory)?;
+
+        let remap_normal = |n: &Vec3| (n + &Vec3::new(1.0, 1.0, 1.0)) * 0.5;
+
+        let max_depth = self
+            .depth
+            .iter()
+            .copied()
+            .filter(|depth| depth.is_finite())
+            .fold(0.0, f64::max);
+        let depth_values: Vec<f64> = self
+            .depth
+            .iter()
+            .map(|depth| if depth.is_finite() { *depth } else { 0.0 })
+            .collect();
+        let depth_preview = |depth: &f64| {
+            let closeness = if depth.is_finite() && max_depth > 0.0 {
+                1.0 - depth / max_depth
+            } else {
+                0.0
+            };
+            Vec3::new(closeness, closeness, closeness)
+        };
+
+        let color_passes = [
+            ("beauty", &self.beauty),
+            ("albedo", &self.albedo),
+            ("emission", &self.emission),
+            ("direct", &self.direct),
+            ("indirect", &self.indirect),
+        ];
+        for (name, pass) in color_passes {
+            let values: Vec<Vec3> = pass
+                .iter()
+                .map(|color| Vec3::new(color.r(), color.g(), color.b()))
+                .collect();
+
+            self.save_pfm(&directory.join(format!("{name}.pfm")), &values, |v| *v)?;
+            self.save_png(&directory.join(format!("{name}.png")), &values, |v| *v)?;
+        }
+
+        self.save_pfm(&directory.join("normal.pfm"), &self.normal, |n| *n)?;
+        self.save_png(&directory.join("normal.png"), &self.normal, remap_normal)?;
+
+        self.save_pfm(&directory.join("depth.pfm"), &depth_values, |depth| {
+            Vec3::new(*depth, *depth, *depth)
+        })?;
+        self.save_png(&directory.join("depth.png"), &self.depth, depth_preview)?;
+
+        self.save_pfm(&directory.join("position.pfm"), &self.position, |p| *p)?;
+
+        let uv_color = |uv: &(f64, f64)| Vec3::new(uv.0, uv.1, 0.0);
+        self.save_pfm(&directory.join("uv.pfm"), &self.uv, uv_color)?;
+        self.save_png(&directory.join("uv.png"), &self.uv, uv_color)?;
+
+        self.save_png(
+            &directory.join("object_id.png"),
+            &self.object_id,
+            |object_id| id_color(*object_id),
+        )?;
+
+        Ok(())
+    }
+
+    /// Saves a pass as a PFM image, which stores 32 bit floats from the bottom row to the top one
+    fn save_pfm<T>(
+        &self,
+        path: &Path,
+        values: &[T],
+        to_vec: impl Fn(&T) -> Vec3,
+    ) -> std::io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+
+        // A negative scale means the floats are little endian
+        write!(writer, "PF\n{} {}\n-1.0\n", self.width, self.height)?;
+
+        for value in values {
+            let v = to_vec(value);
+            for component in [v.x(), v.y(), v.z()] {
+                writer.write_all(&(component as f32).to_le_bytes())?;
+            }
+        }
+
+        writer.flush()
+    }
+
+    /// Saves a pass as a gamma corrected PNG image
+    fn save_png<T>(
+        &self,
+        path: &Path,
+        values: &[T],
+        to_vec: impl Fn(&T) -> Vec3,
+    ) -> image::ImageResult<()> {
+        let mut preview = image::RgbImage::from_fn(self.width, self.height, |x, y| {
+            let v = to_vec(&values[(y * self.width + x) as usize]);
+            image::Rgb(Color::new(v.x(), v.y(), v.z()).to_writeable_ints(1))
+        });
+
+        image::imageops::flip_vertical_in_place(&mut preview);
+
+        preview.save(path)
+    }
+}
+
+/// Gives each object ID a different color that's easy to tell apart from the others
+fn id_color(object_id: u32) -> Vec3 {
+    if object_id == 0 {
+        return Vec3::new(0.0, 0.0, 0.0);
+    }
+
+    // Spreads the bits of the ID around so consecutive IDs get very different colors
+    let hash = object_id.wrapping_mul(0x9E37_79B9).rotate_left(13);
+    let channel = |shift: u32| f64::from((hash >> shift) & 0xFF) / 255.0;
+
+    Vec3::new(channel(0), channel(8), channel(16))
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::film::Film;
+    use crate::instances::Tagging;
+    use crate::integrators::T_MIN;
+    use crate::materials::DiffuseLight;
+    use crate::samplers::RandomSampler;
+    use crate::scene::SceneBuilder;
+    use crate::surfaces::Sphere;
+    use crate::Camera;
+
+    /// Traces `ray` in a scene that only has `world`, with the default path tracer
+    fn trace(world: impl Hittable, background: Color, ray: &Ray) -> AovSample {
+        let camera = Camera::new(
+            Vec3::new(0.0, 0.0, -5.0),
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            40.0,
+            1.0,
+            0.0,
+            1.0,
+            (0.0, 1.0),
+        );
+        let scene = SceneBuilder::new(world, camera, 1.0)
+            .background_color(background)
+            .image_width(2)
+            .max_depth(10)
+            .build();
+        let mut film_tile = Film::new(2, 2).tile((0, 0), (2, 2), scene.filter());
+        let context = scene.context();
+
+        AovSample::trace(
+            &scene,
+            ray,
+            context.world.hit(ray, T_MIN, f64::INFINITY),
+            &context,
+            &mut RandomSampler,
+            &mut film_tile,
+        )
+        .0
+    }
+
+    #[test]
+    fn test_trace_passes() {
+        let background = Color::new(0.5, 0.5, 0.5);
+        let light = Sphere::new(
+            Vec3::new(0.0, 0.0, 0.0),
+            1.0,
+            DiffuseLight::new(Color::new(4.0, 4.0, 4.0)),
+        )
+        .tagged(7);
+
+        let ray = Ray::new(Vec3::new(0.0, 0.0, -5.0), Vec3::new(0.0, 0.0, 1.0), 0.0);
+        let sample = trace(light.clone(), background, &ray);
+        assert_eq!(sample.depth, Some(4.0));
+        assert_eq!(sample.object_id, 7);
+        assert!((sample.emission.r() - 4.0).abs() < 1e-9);
+        assert!(sample.direct.r().abs() < 1e-9 && sample.indirect.r().abs() < 1e-9);
+
+        let ray = Ray::new(Vec3::new(0.0, 5.0, -5.0), Vec3::new(0.0, 0.0, 1.0), 0.0);
+        let sample = trace(light, background, &ray);
+        assert_eq!(sample.depth, None);
+        assert_eq!(sample.object_id, 0);
+        assert!((sample.emission.r() - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_lighting_split() {
+        let background = Color::new(1.0, 1.0, 1.0);
+        let diffuse = Sphere::new(
+            Vec3::new(0.0, 0.0, 0.0),
+            1.0,
+            Color::new(0.5, 0.5, 0.5).lambertian(),
+        );
+
+        // A convex object can't be hit again after bouncing off itself,
+        // so all of its light is direct
+        let ray = Ray::new(Vec3::new(0.0, 0.0, -5.0), Vec3::new(0.0, 0.0, 1.0), 0.0);
+        let sample = trace(diffuse, background, &ray);
+        assert!((sample.albedo.r() - 0.5).abs() < 1e-9);
+        assert!((sample.direct.r() - 0.5).abs() < 1e-9);
+        assert!(sample.indirect.r().abs() < 1e-9);
+        assert!(sample.emission.r().abs() < 1e-9);
+        assert!((sample.normal - Vec3::new(0.0, 0.0, -1.0)).length() < 1e-9);
+    }
+}
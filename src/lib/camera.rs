@@ -1,6 +1,6 @@
-use rand::Rng;
-
+use crate::samplers::Sampler;
 use crate::Ray;
+use crate::RayDifferentials;
 use crate::Vec3;
 
 #[derive(Clone)]
@@ -12,8 +12,25 @@ pub struct Camera {
     lens_radius: f64,
     u: Vec3,
     v: Vec3,
-    // w: Vec3,
+    w: Vec3,
+    focus_distance: f64,
     time: (f64, f64),
+    // What it was made from, so it can be moved around
+    look_at: Vec3,
+    vup: Vec3,
+    vfov: f64,
+    aspect_ratio: f64,
+}
+
+/// A point on the lens that sees a point of the scene, for tracing light towards the camera
+pub struct CameraSample {
+    pub lens_point: Vec3,
+    /// Where the point is in the image, in pixels from the bottom left corner
+    pub position: (f64, f64),
+    /// How much the light that gets to the lens from the point counts towards the image
+    pub importance: f64,
+    /// Probability density of picking this lens point, per solid angle from the point
+    pub pdf: f64,
 }
 
 impl Camera {
@@ -50,27 +67,278 @@ impl Camera {
             lens_radius,
             u,
             v,
-            // w,
+            w,
+            focus_distance,
             time,
+            look_at: lookat,
+            vup,
+            vfov,
+            aspect_ratio,
         }
     }
 
-    pub fn get_ray(&self, s: f64, t: f64) -> Ray {
-        #![allow(clippy::float_cmp)]
+    /// The same camera, looking from `look_from` at `look_at`
+    fn with_view(&self, look_from: Vec3, look_at: Vec3) -> Self {
+        Self::new(
+            look_from,
+            look_at,
+            self.vup,
+            self.vfov,
+            self.aspect_ratio,
+            self.lens_radius * 2.0,
+            self.focus_distance,
+            self.time,
+        )
+    }
+
+    /// Moves the camera around the point it looks at, `yaw` radians around the up direction
+    /// and `pitch` radians up. It stops before going over the top or under the bottom.
+    #[must_use]
+    pub fn orbit(&self, yaw: f64, pitch: f64) -> Self {
+        let offset = self.origin - self.look_at;
+        let distance = offset.length();
+        let up = self.vup.unit_vector();
+
+        // Angles of the offset from the point, around and above the up direction
+        let height = Vec3::dot(&offset, &up) / distance;
+        let max_pitch = std::f64::consts::FRAC_PI_2 - 0.01;
+        let elevation = (height.asin() + pitch).clamp(-max_pitch, max_pitch);
+
+        let horizontal = (offset - up * Vec3::dot(&offset, &up)).unit_vector();
+        let (sin_yaw, cos_yaw) = yaw.sin_cos();
+        let side = Vec3::cross(&up, &horizontal);
+        let horizontal = horizontal * cos_yaw + side * sin_yaw;
+
+        let offset = (horizontal * elevation.cos() + up * elevation.sin()) * distance;
+        self.with_view(self.look_at + offset, self.look_at)
+    }
+
+    /// Moves the camera and the point it looks at sideways and up, by `right` and `up` times
+    /// the distance between them
+    #[must_use]
+    pub fn pan(&self, right: f64, up: f64) -> Self {
+        let distance = (self.origin - self.look_at).length();
+        let offset = (self.u * right + self.v * up) * distance;
+
+        self.with_view(self.origin + offset, self.look_at + offset)
+    }
+
+    /// Moves the camera towards the point it looks at, until it's `factor` times as far
+    #[must_use]
+    pub fn zoom(&self, factor: f64) -> Self {
+        let offset = (self.origin - self.look_at) * factor;
+
+        self.with_view(self.look_at + offset, self.look_at)
+    }
 
-        let rd = Vec3::random_in_unit_disk() * self.lens_radius;
+    /// Returns the ray that goes through the point `(s, t)` of the viewport.
+    /// Takes two dimensions from `sampler` for the lens, and one for the time.
+    pub fn get_ray(&self, s: f64, t: f64, sampler: &mut dyn Sampler) -> Ray {
+        let rd = Vec3::in_unit_disk_from_sample(sampler.next_2d()) * self.lens_radius;
         let offset = self.u * rd.x() + self.v * rd.y();
 
+        // Always taken, so the next dimensions are the same with or without motion blur
+        let time_sample = sampler.next_1d();
+
         Ray::new(
             self.origin + offset,
             self.lower_left_corner + (self.horizontal * s) + (self.vertical * t)
                 - self.origin
                 - offset,
-            if self.time.0 == self.time.1 {
-                self.time.0
-            } else {
-                rand::thread_rng().gen_range(self.time.0..self.time.1)
-            },
+            self.time_from_sample(time_sample),
         )
     }
+
+    /// Like `get_ray`, but with the differentials of the rays that go `pixel_size` further
+    /// to the right and up in the viewport, from the same point of the lens
+    pub fn get_ray_differential(
+        &self,
+        s: f64,
+        t: f64,
+        pixel_size: (f64, f64),
+        sampler: &mut dyn Sampler,
+    ) -> Ray {
+        let ray = self.get_ray(s, t, sampler);
+
+        ray.with_differentials(RayDifferentials {
+            x_origin: ray.origin,
+            x_direction: ray.direction + self.horizontal * pixel_size.0,
+            y_origin: ray.origin,
+            y_direction: ray.direction + self.vertical * pixel_size.1,
+        })
+    }
+
+    /// A time between when the shutter opens and closes
+    pub(crate) fn time_from_sample(&self, sample: f64) -> f64 {
+        (self.time.1 - self.time.0).mul_add(sample, self.time.0)
+    }
+
+    /// The direction that the camera looks at
+    pub(crate) fn forward(&self) -> Vec3 {
+        -self.w
+    }
+
+    /// Area of the lens, or 1 for a pinhole camera
+    fn lens_area(&self) -> f64 {
+        if self.lens_radius > 0.0 {
+            std::f64::consts::PI * self.lens_radius * self.lens_radius
+        } else {
+            1.0
+        }
+    }
+
+    /// Area that the image covers at a distance of 1 from the camera.
+    ///
+    /// Samples go from `(0, 0)` up to `(image_width, image_height)`, but the viewport goes
+    /// up to `(image_width - 1, image_height - 1)`, so the image is a bit bigger than it.
+    fn image_area(&self, (image_width, image_height): (u32, u32)) -> f64 {
+        let viewport_area =
+            self.horizontal.length() * self.vertical.length() / self.focus_distance.powi(2);
+
+        viewport_area * f64::from(image_width) * f64::from(image_height)
+            / (f64::from(image_width - 1) * f64::from(image_height - 1))
+    }
+
+    /// Where a ray that leaves the lens at `lens_point` in `direction` ends up in the image,
+    /// in pixels, or `None` if it's outside of it
+    fn image_position(
+        &self,
+        lens_point: Vec3,
+        direction: Vec3,
+        (image_width, image_height): (u32, u32),
+    ) -> Option<(f64, f64)> {
+        let cos_theta = Vec3::dot(&direction, &self.forward());
+        if cos_theta <= 0.0 {
+            return None;
+        }
+
+        // Every ray from the lens goes through the point on the focus plane it was aimed at
+        let focus_point = lens_point + direction * (self.focus_distance / cos_theta);
+        let offset = focus_point - self.lower_left_corner;
+        let s = Vec3::dot(&offset, &self.horizontal) / self.horizontal.length_squared();
+        let t = Vec3::dot(&offset, &self.vertical) / self.vertical.length_squared();
+
+        let position = (
+            s * f64::from(image_width - 1),
+            t * f64::from(image_height - 1),
+        );
+        let inside = (0.0..f64::from(image_width)).contains(&position.0)
+            && (0.0..f64::from(image_height)).contains(&position.1);
+
+        inside.then_some(position)
+    }
+
+    /// Probability density, per solid angle, that `get_ray` returns a ray in `direction`
+    pub(crate) fn pdf_direction(&self, direction: Vec3, image_size: (u32, u32)) -> f64 {
+        let direction = direction.unit_vector();
+        if self
+            .image_position(self.origin, direction, image_size)
+            .is_none()
+        {
+            return 0.0;
+        }
+
+        let cos_theta = Vec3::dot(&direction, &self.forward());
+        1.0 / (self.image_area(image_size) * cos_theta * cos_theta * cos_theta)
+    }
+
+    /// Picks a point on the lens with `lens_sample` and returns how it sees `point`,
+    /// or `None` if the point is outside of the image
+    pub(crate) fn sample_importance(
+        &self,
+        point: Vec3,
+        lens_sample: (f64, f64),
+        image_size: (u32, u32),
+    ) -> Option<CameraSample> {
+        let rd = Vec3::in_unit_disk_from_sample(lens_sample) * self.lens_radius;
+        let lens_point = self.origin + self.u * rd.x() + self.v * rd.y();
+
+        let to_point = point - lens_point;
+        let distance_squared = to_point.length_squared();
+        let direction = to_point.unit_vector();
+
+        let position = self.image_position(lens_point, direction, image_size)?;
+
+        let cos_theta = Vec3::dot(&direction, &self.forward());
+        let cos_theta_squared = cos_theta * cos_theta;
+
+        Some(CameraSample {
+            lens_point,
+            position,
+            importance: 1.0
+                / (self.image_area(image_size)
+                    * self.lens_area()
+                    * cos_theta_squared
+                    * cos_theta_squared),
+            pdf: distance_squared / (cos_theta * self.lens_area()),
+        })
+    }
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::samplers::RandomSampler;
+
+    #[test]
+    fn test_image_position_of_camera_rays() {
+        let image_size = (40, 30);
+        let camera = Camera::new(
+            Vec3::new(1.0, 2.0, 3.0),
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            50.0,
+            4.0 / 3.0,
+            0.5,
+            3.0,
+            (0.0, 1.0),
+        );
+
+        for (s, t) in [(0.1, 0.2), (0.5, 0.5), (0.9, 0.75)] {
+            let ray = camera.get_ray(s, t, &mut RandomSampler::new());
+
+            // Points on the focus plane are in the same place of the image from anywhere on the lens
+            let sample = camera
+                .sample_importance(ray.at(1.0), (0.3, 0.8), image_size)
+                .unwrap();
+            assert!(s.mul_add(-39.0, sample.position.0).abs() < 1e-6);
+            assert!(t.mul_add(-29.0, sample.position.1).abs() < 1e-6);
+        }
+
+        let behind = camera.sample_importance(Vec3::new(2.0, 4.0, 6.0), (0.5, 0.5), image_size);
+        assert!(behind.is_none());
+    }
+
+    #[test]
+    fn test_orbit_pan_and_zoom() {
+        let camera = Camera::new(
+            Vec3::new(0.0, 1.0, 5.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            40.0,
+            1.0,
+            0.0,
+            1.0,
+            (0.0, 1.0),
+        );
+        let distance = |camera: &Camera| (camera.origin - camera.look_at).length();
+
+        // Orbiting keeps the distance, and keeps looking at the same point
+        let orbited = camera.orbit(1.0, 0.5);
+        assert!((distance(&orbited) - 5.0).abs() < 1e-9);
+        assert!((orbited.look_at - camera.look_at).near_zero());
+        let to_target = (orbited.look_at - orbited.origin).unit_vector();
+        assert!((to_target - orbited.forward()).near_zero());
+        assert!(orbited.origin.y() > camera.origin.y());
+
+        // It can't go over the top
+        assert!(camera.orbit(0.0, 10.0).origin.y() < 6.0);
+
+        let panned = camera.pan(0.1, 0.0);
+        assert!((panned.origin - camera.origin - Vec3::new(0.5, 0.0, 0.0)).near_zero());
+        assert!((panned.look_at - camera.look_at - Vec3::new(0.5, 0.0, 0.0)).near_zero());
+
+        assert!((distance(&camera.zoom(0.5)) - 2.5).abs() < 1e-9);
+    }
 }
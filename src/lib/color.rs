@@ -34,6 +34,14 @@ impl Color {
     pub const fn b(&self) -> f64 {
         self.0.z()
     }
+    /// The brightest of the three channels
+    pub const fn max_component(&self) -> f64 {
+        self.r().max(self.g()).max(self.b())
+    }
+    /// How bright the color looks, with the weights of the Rec. 709 primaries
+    pub fn luminance(&self) -> f64 {
+        0.2126f64.mul_add(self.r(), 0.7152f64.mul_add(self.g(), 0.0722 * self.b()))
+    }
     pub fn random() -> Self {
         let mut rng = rand::thread_rng();
 
@@ -76,6 +84,16 @@ impl Color {
     }
 }
 
+#[cfg(test)]
+impl Color {
+    /// Whether every channel is within `tolerance` of the one of `other`
+    pub(crate) fn is_close(&self, other: Self, tolerance: f64) -> bool {
+        (self.r() - other.r()).abs() < tolerance
+            && (self.g() - other.g()).abs() < tolerance
+            && (self.b() - other.b()).abs() < tolerance
+    }
+}
+
 impl Texture for Color {
     fn value(&self, _u: f64, _v: f64, _p: &Vec3) -> Color {
         *self
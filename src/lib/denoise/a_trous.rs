@@ -0,0 +1,143 @@
+use rayon::prelude::*;
+
+use super::{clamped_index, tone_mapped, Denoiser, EdgeStopping};
+use crate::aov::Aovs;
+use crate::{get_image_coordinates, Color, Vec3};
+
+/// B3 spline, the weights of the neighbours of a pixel on each axis
+const KERNEL: [f64; 5] = [1.0 / 16.0, 1.0 / 4.0, 3.0 / 8.0, 1.0 / 4.0, 1.0 / 16.0];
+
+/// Edge-avoiding à-trous wavelet filter
+///
+/// Each iteration blurs the image with a 5x5 kernel, doubling the space between the pixels
+/// of the kernel every time, so a few iterations cover a big area for little cost.
+/// Based on "Edge-Avoiding À-Trous Wavelet Transform for fast Global Illumination Filtering"
+/// by Dammertz et al.
+#[derive(Clone, Debug)]
+pub struct ATrous {
+    iterations: u32,
+    color_sigma: f64,
+    edge_stopping: EdgeStopping,
+}
+
+impl ATrous {
+    pub fn new() -> Self {
+        Self {
+            iterations: 5,
+            color_sigma: 4.0,
+            edge_stopping: EdgeStopping::default(),
+        }
+    }
+
+    /// How many times the image is filtered. Each one covers twice as many pixels as the last one.
+    #[must_use]
+    pub const fn iterations(mut self, iterations: u32) -> Self {
+        self.iterations = iterations;
+        self
+    }
+    /// How different the colors of two pixels can be before they stop being blended,
+    /// relative to how noisy that part of the image is.
+    #[must_use]
+    pub const fn color_sigma(mut self, color_sigma: f64) -> Self {
+        self.color_sigma = color_sigma;
+        self
+    }
+    #[must_use]
+    pub const fn edge_stopping(mut self, edge_stopping: EdgeStopping) -> Self {
+        self.edge_stopping = edge_stopping;
+        self
+    }
+
+    fn filter(&self, aovs: &Aovs, colors: &[Color], iteration: u32) -> Vec<Color> {
+        let (image_width, image_height) = aovs.size();
+        let step = 1_i64 << iteration;
+        let step_size = f64::from(1_u32 << iteration);
+        let tone_mapped_colors: Vec<Vec3> = colors.iter().map(tone_mapped).collect();
+
+        (0..(image_width * image_height))
+            .into_par_iter()
+            .map(|i| {
+                let (x, y) = get_image_coordinates(i, image_width);
+                let center = i as usize;
+                let center_color = tone_mapped_colors[center];
+
+                // Noisy pixels are blended with more of their neighbours, so very bright or
+                // dark ones aren't left behind
+                let variance = local_variance(&tone_mapped_colors, (x, y), aovs.size());
+                let color_tolerance = self.color_sigma.powi(2).mul_add(variance, 1e-6);
+
+                let mut total_color = Color::new(0.0, 0.0, 0.0);
+                let mut total_weight = 0.0;
+
+                for (kernel_y, offset_y) in (-2_i32..=2).enumerate() {
+                    for (kernel_x, offset_x) in (-2_i32..=2).enumerate() {
+                        let neighbour_x = i64::from(x) + i64::from(offset_x) * step;
+                        let neighbour_y = i64::from(y) + i64::from(offset_y) * step;
+                        if neighbour_x < 0
+                            || neighbour_y < 0
+                            || neighbour_x >= i64::from(image_width)
+                            || neighbour_y >= i64::from(image_height)
+                        {
+                            continue;
+                        }
+
+                        let neighbour =
+                            (neighbour_y * i64::from(image_width) + neighbour_x) as usize;
+                        let pixel_distance =
+                            f64::from(offset_x).hypot(f64::from(offset_y)) * step_size;
+
+                        let color_difference =
+                            (tone_mapped_colors[neighbour] - center_color).length_squared();
+                        let weight = KERNEL[kernel_x]
+                            * KERNEL[kernel_y]
+                            * f64::exp(-color_difference / color_tolerance)
+                            * self.edge_stopping.weight(
+                                aovs,
+                                center,
+                                neighbour,
+                                pixel_distance.max(1.0),
+                            );
+
+                        total_color += colors[neighbour] * weight;
+                        total_weight += weight;
+                    }
+                }
+
+                // The center pixel always has some weight, so this never divides by 0
+                total_color * (1.0 / total_weight)
+            })
+            .collect()
+    }
+}
+
+/// Variance of the colors in the 3x3 pixels around `(x, y)`
+fn local_variance(colors: &[Vec3], (x, y): (u32, u32), size: (u32, u32)) -> f64 {
+    let mut sum = Vec3::new(0.0, 0.0, 0.0);
+    let mut sum_squared = 0.0;
+
+    for offset_y in -1..=1 {
+        for offset_x in -1..=1 {
+            let color =
+                colors[clamped_index(i64::from(x) + offset_x, i64::from(y) + offset_y, size)];
+
+            sum += color;
+            sum_squared += color.length_squared();
+        }
+    }
+
+    (sum_squared / 9.0 - (sum / 9.0).length_squared()).max(0.0)
+}
+
+impl Default for ATrous {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Denoiser for ATrous {
+    fn denoise(&self, aovs: &Aovs) -> Vec<Color> {
+        (0..self.iterations).fold(aovs.beauty.clone(), |colors, iteration| {
+            self.filter(aovs, &colors, iteration)
+        })
+    }
+}
@@ -0,0 +1,165 @@
+//! Filters that remove the noise of low sample renders
+//!
+//! They blend each pixel with its neighbours, but use the features of the first surface hit
+//! (albedo, normal and depth) to avoid blending pixels from different surfaces, so edges and
+//! textures stay sharp.
+
+mod a_trous;
+mod non_local_means;
+
+pub use a_trous::ATrous;
+pub use non_local_means::NonLocalMeans;
+
+use crate::aov::Aovs;
+use crate::{Color, Vec3};
+
+pub trait Denoiser: Send + Sync {
+    /// Returns the beauty pass of `aovs` without noise, in the same order
+    fn denoise(&self, aovs: &Aovs) -> Vec<Color>;
+}
+
+/// How much the features of two pixels can differ before they stop being blended together.
+/// Smaller values keep edges sharper, but remove less noise.
+#[derive(Clone, Copy, Debug)]
+pub struct EdgeStopping {
+    pub albedo: f64,
+    pub normal: f64,
+    /// Relative to the depth of the pixel, for each pixel of distance
+    pub depth: f64,
+}
+
+impl Default for EdgeStopping {
+    fn default() -> Self {
+        Self {
+            albedo: 0.1,
+            normal: 0.3,
+            depth: 0.05,
+        }
+    }
+}
+
+impl EdgeStopping {
+    /// How much pixel `q` should be blended into pixel `p`, from 0 to 1
+    fn weight(&self, aovs: &Aovs, p: usize, q: usize, pixel_distance: f64) -> f64 {
+        let (depth_p, depth_q) = (aovs.depth[p], aovs.depth[q]);
+
+        // Pixels that didn't hit anything are only blended with each other
+        let depth_difference = match (depth_p.is_finite(), depth_q.is_finite()) {
+            (true, true) => ((depth_p - depth_q) / (depth_p * pixel_distance)).powi(2),
+            (false, false) => 0.0,
+            _ => return 0.0,
+        };
+        let albedo_difference = color_distance_squared(&aovs.albedo[p], &aovs.albedo[q]);
+        let normal_difference = (aovs.normal[p] - aovs.normal[q]).length_squared();
+
+        f64::exp(
+            -(albedo_difference / self.albedo.powi(2)
+                + normal_difference / self.normal.powi(2)
+                + depth_difference / self.depth.powi(2)),
+        )
+    }
+}
+
+/// Maps colors to \[0,1\], so bright lights don't stand out too much when comparing colors
+fn tone_mapped(color: &Color) -> Vec3 {
+    let map = |channel: f64| channel / (1.0 + channel);
+
+    Vec3::new(map(color.r()), map(color.g()), map(color.b()))
+}
+
+fn color_distance_squared(a: &Color, b: &Color) -> f64 {
+    let difference = |x: f64, y: f64| (x - y).powi(2);
+
+    difference(a.r(), b.r()) + difference(a.g(), b.g()) + difference(a.b(), b.b())
+}
+
+/// Index of the pixel at (x, y), moving it inside the image if it's outside
+fn clamped_index(x: i64, y: i64, (width, height): (u32, u32)) -> usize {
+    let x = x.clamp(0, i64::from(width) - 1);
+    let y = y.clamp(0, i64::from(height) - 1);
+
+    (y * i64::from(width) + x) as usize
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aov::AovSample;
+
+    /// An image split in two flat surfaces facing different directions, with a bit of noise
+    fn noisy_aovs() -> Aovs {
+        let (width, height) = (16, 16);
+
+        let samples: Vec<AovSample> = (0..(width * height))
+            .map(|i| {
+                let left = i % width < width / 2;
+                let noise = f64::from((i * 7919) % 13) / 13.0 - 0.5;
+                let base = if left { 0.2 } else { 0.8 };
+                let color = Color::new(noise.mul_add(0.1, base), base, noise.mul_add(-0.1, base));
+
+                AovSample {
+                    albedo: Color::new(0.5, 0.5, 0.5),
+                    normal: if left {
+                        Vec3::new(1.0, 0.0, 0.0)
+                    } else {
+                        Vec3::new(0.0, 1.0, 0.0)
+                    },
+                    depth: Some(1.0),
+                    position: Vec3::new(0.0, 0.0, 0.0),
+                    uv: (0.0, 0.0),
+                    object_id: 0,
+                    emission: color,
+                    direct: Color::new(0.0, 0.0, 0.0),
+                    indirect: Color::new(0.0, 0.0, 0.0),
+                }
+            })
+            .collect();
+
+        Aovs::from_samples(width, height, &samples)
+    }
+
+    /// Returns the average red value of each half, and how far the pixels are from it
+    fn halves(colors: &[Color]) -> [(f64, f64); 2] {
+        [0, 8].map(|start| {
+            let reds: Vec<f64> = colors
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| (start..start + 8).contains(&(i % 16)))
+                .map(|(_, color)| color.r())
+                .collect();
+
+            let mean = reds.iter().sum::<f64>() / 128.0;
+            let deviation = reds
+                .iter()
+                .map(|red| (red - mean).abs())
+                .fold(0.0, f64::max);
+
+            (mean, deviation)
+        })
+    }
+
+    fn assert_denoised(denoiser: &dyn Denoiser) {
+        let aovs = noisy_aovs();
+        let [(_, noisy_left), (_, noisy_right)] = halves(&aovs.beauty);
+
+        let [(left, left_deviation), (right, right_deviation)] = halves(&denoiser.denoise(&aovs));
+
+        assert!(left_deviation < noisy_left / 2.0);
+        assert!(right_deviation < noisy_right / 2.0);
+
+        // The edge between both halves isn't blurred
+        assert!((left - 0.2).abs() < 0.01);
+        assert!((right - 0.8).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_a_trous() {
+        assert_denoised(&ATrous::new());
+    }
+
+    #[test]
+    fn test_non_local_means() {
+        assert_denoised(&NonLocalMeans::new());
+    }
+}
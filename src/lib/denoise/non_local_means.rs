@@ -0,0 +1,140 @@
+use rayon::prelude::*;
+
+use super::{clamped_index, tone_mapped, Denoiser, EdgeStopping};
+use crate::aov::Aovs;
+use crate::{get_image_coordinates, Color, Vec3};
+
+/// Non-local means filter
+///
+/// Each pixel is blended with the pixels around it whose neighbourhood (patch) looks similar
+/// to its own, which keeps details that a blur would remove.
+#[derive(Clone, Debug)]
+pub struct NonLocalMeans {
+    search_radius: u32,
+    patch_radius: u32,
+    strength: f64,
+    edge_stopping: EdgeStopping,
+}
+
+impl NonLocalMeans {
+    pub fn new() -> Self {
+        Self {
+            search_radius: 6,
+            patch_radius: 1,
+            strength: 0.3,
+            edge_stopping: EdgeStopping::default(),
+        }
+    }
+
+    /// How far away, in pixels, the pixels that get blended can be
+    #[must_use]
+    pub const fn search_radius(mut self, search_radius: u32) -> Self {
+        self.search_radius = search_radius;
+        self
+    }
+    /// Size of the neighbourhood that's compared between two pixels
+    #[must_use]
+    pub const fn patch_radius(mut self, patch_radius: u32) -> Self {
+        self.patch_radius = patch_radius;
+        self
+    }
+    /// How different two patches can be before they stop being blended.
+    /// Higher values remove more noise, but also more detail.
+    #[must_use]
+    pub const fn strength(mut self, strength: f64) -> Self {
+        self.strength = strength;
+        self
+    }
+    #[must_use]
+    pub const fn edge_stopping(mut self, edge_stopping: EdgeStopping) -> Self {
+        self.edge_stopping = edge_stopping;
+        self
+    }
+
+    /// Mean squared difference between the patches around two pixels
+    fn patch_distance(
+        &self,
+        tone_mapped_colors: &[Vec3],
+        size: (u32, u32),
+        center: (i64, i64),
+        neighbour: (i64, i64),
+    ) -> f64 {
+        let radius = i64::from(self.patch_radius);
+        let mut distance = 0.0;
+
+        for offset_y in -radius..=radius {
+            for offset_x in -radius..=radius {
+                let a = clamped_index(center.0 + offset_x, center.1 + offset_y, size);
+                let b = clamped_index(neighbour.0 + offset_x, neighbour.1 + offset_y, size);
+
+                distance += (tone_mapped_colors[a] - tone_mapped_colors[b]).length_squared();
+            }
+        }
+
+        distance / f64::from((2 * self.patch_radius + 1).pow(2))
+    }
+}
+
+impl Default for NonLocalMeans {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Denoiser for NonLocalMeans {
+    fn denoise(&self, aovs: &Aovs) -> Vec<Color> {
+        let (image_width, image_height) = aovs.size();
+        let tone_mapped_colors: Vec<Vec3> = aovs.beauty.iter().map(tone_mapped).collect();
+        let radius = i64::from(self.search_radius);
+
+        (0..(image_width * image_height))
+            .into_par_iter()
+            .map(|i| {
+                let (x, y) = get_image_coordinates(i, image_width);
+                let center = (i64::from(x), i64::from(y));
+
+                let mut total_color = Color::new(0.0, 0.0, 0.0);
+                let mut total_weight = 0.0;
+
+                for offset_y in -radius..=radius {
+                    for offset_x in -radius..=radius {
+                        let neighbour_x = center.0 + offset_x;
+                        let neighbour_y = center.1 + offset_y;
+                        if neighbour_x < 0
+                            || neighbour_y < 0
+                            || neighbour_x >= i64::from(image_width)
+                            || neighbour_y >= i64::from(image_height)
+                        {
+                            continue;
+                        }
+
+                        let neighbour =
+                            (neighbour_y * i64::from(image_width) + neighbour_x) as usize;
+                        let pixel_distance =
+                            f64::from(offset_x as i32).hypot(f64::from(offset_y as i32));
+
+                        let patch_distance = self.patch_distance(
+                            &tone_mapped_colors,
+                            (image_width, image_height),
+                            center,
+                            (neighbour_x, neighbour_y),
+                        );
+                        let weight = f64::exp(-patch_distance / self.strength.powi(2))
+                            * self.edge_stopping.weight(
+                                aovs,
+                                i as usize,
+                                neighbour,
+                                pixel_distance.max(1.0),
+                            );
+
+                        total_color += aovs.beauty[neighbour] * weight;
+                        total_weight += weight;
+                    }
+                }
+
+                // The center pixel always has a weight of 1, so this never divides by 0
+                total_color * (1.0 / total_weight)
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,517 @@
+//! Rendering a scene with many computers over TCP
+//!
+//! A `Coordinator` splits the image in the same 16 by 16 pixel tiles as `render_chunked`, and
+//! gives them to every `Worker` that connects to it, a few at a time. Workers send back the
+//! samples of each tile before they're divided by their weights, so putting them together gives
+//! the same image as rendering it in a single computer. If a worker disconnects, or takes too
+//! long to send a tile back, its tiles are given to the other workers.
+//!
+//! Scenes can't be sent as they are, since their surfaces and materials can be anything, so the
+//! coordinator sends a description of the scene instead, and every worker builds the scene from
+//! it. Building the scene from the same description has to always give the same scene.
+
+use std::collections::VecDeque;
+use std::io::{self, BufReader, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::{mpsc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::film::{Film, FilmTile};
+use crate::hittable::Hittable;
+use crate::integrators::PathStats;
+use crate::progress::TileReport;
+use crate::scene::Scene;
+use crate::scheduler::TileScheduler;
+use crate::stats::{RayCounters, RenderStats};
+use crate::{Color, Samplers, Tile};
+
+/// Sent first by both sides, so connecting to something else fails right away
+const MAGIC: &[u8; 4] = b"RTF1";
+/// How long the coordinator waits between checking for new workers
+const ACCEPT_INTERVAL: Duration = Duration::from_millis(10);
+
+const JOB: u8 = 0;
+const FINISH: u8 = 1;
+
+/// Hands out the tiles of an image to the workers that connect to it
+pub struct Coordinator {
+    listener: TcpListener,
+    scene_description: Vec<u8>,
+    timeout: Duration,
+}
+
+/// What the coordinator knows about the tiles while rendering
+struct Progress {
+    /// Tiles that no worker is rendering
+    pending: VecDeque<usize>,
+    /// Tiles that haven't been merged into the film yet
+    remaining: usize,
+    film: Film,
+}
+
+impl Coordinator {
+    /// Listens for workers at `address`, which build their scene from `scene_description`.
+    /// Tiles are given to another worker if they aren't sent back in 60 seconds.
+    ///
+    /// # Errors
+    ///
+    /// If it can't listen at `address`
+    pub fn bind(address: impl ToSocketAddrs, scene_description: Vec<u8>) -> io::Result<Self> {
+        Ok(Self {
+            listener: TcpListener::bind(address)?,
+            scene_description,
+            timeout: Duration::from_secs(60),
+        })
+    }
+
+    /// Sets how long to wait for a worker to send back a tile before giving it to another one
+    #[must_use]
+    pub const fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// The address that workers connect to, useful when listening at port 0
+    ///
+    /// # Errors
+    ///
+    /// If the address of the socket can't be read
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Renders the scene with every worker that connects, until all of its tiles are done.
+    /// Returns the color of every pixel in the order of `get_image_coordinates`.
+    ///
+    /// # Errors
+    ///
+    /// If the socket can't be set up to check for workers while rendering
+    ///
+    /// # Panics
+    ///
+    /// If a thread that gives tiles to a worker panics
+    pub fn render<T: Hittable>(&self, scene: &Scene<T>) -> io::Result<Vec<Color>> {
+        let (image_width, image_height) = scene.image_size();
+        let tiles = TileScheduler::new().tiles(image_width, image_height);
+
+        let start_time = Instant::now();
+        let observer = scene.observer();
+        observer.start(tiles.len());
+
+        let progress = Mutex::new(Progress {
+            pending: (0..tiles.len()).collect(),
+            remaining: tiles.len(),
+            film: Film::new(image_width, image_height),
+        });
+        let changed = Condvar::new();
+
+        self.listener.set_nonblocking(true)?;
+        thread::scope(|scope| {
+            let (tiles, progress, changed) = (&tiles, &progress, &changed);
+
+            while progress.lock().unwrap().remaining > 0 {
+                match self.listener.accept() {
+                    Ok((stream, address)) => {
+                        observer.message(&format!("Worker {address} connected"));
+                        scope.spawn(move || {
+                            let result = self.serve(stream, scene, tiles, progress, changed);
+                            if let Err(error) = result {
+                                observer
+                                    .message(&format!("Worker {address} disconnected: {error}"));
+                            }
+                        });
+                    }
+                    Err(error) if error.kind() == io::ErrorKind::WouldBlock => {
+                        thread::sleep(ACCEPT_INTERVAL);
+                    }
+                    // Errors of a single connection don't stop the others
+                    Err(error) => observer.message(&format!("Couldn't accept a worker: {error}")),
+                }
+            }
+        });
+        observer.finish(&RenderStats {
+            duration: start_time.elapsed(),
+            ..RenderStats::default()
+        });
+
+        Ok(progress.into_inner().unwrap().film.colors())
+    }
+
+    /// Gives tiles to a worker until there are none left. If anything goes wrong, its tiles
+    /// go back to the pending ones.
+    fn serve<T: Hittable>(
+        &self,
+        stream: TcpStream,
+        scene: &Scene<T>,
+        tiles: &[Tile],
+        progress: &Mutex<Progress>,
+        changed: &Condvar,
+    ) -> io::Result<()> {
+        let mut assigned = Vec::new();
+
+        let result = self.give_tiles(stream, scene, tiles, progress, changed, &mut assigned);
+        if result.is_err() {
+            progress.lock().unwrap().pending.extend(assigned);
+            changed.notify_all();
+        }
+
+        result
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn give_tiles<T: Hittable>(
+        &self,
+        stream: TcpStream,
+        scene: &Scene<T>,
+        tiles: &[Tile],
+        progress: &Mutex<Progress>,
+        changed: &Condvar,
+        assigned: &mut Vec<usize>,
+    ) -> io::Result<()> {
+        let (image_width, image_height) = scene.image_size();
+
+        // Accepted streams can keep the non-blocking mode of the listener
+        stream.set_nonblocking(false)?;
+        stream.set_read_timeout(Some(self.timeout))?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut writer = stream;
+
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend((self.scene_description.len() as u32).to_le_bytes());
+        bytes.extend(&self.scene_description);
+        writer.write_all(&bytes)?;
+
+        read_magic(&mut reader)?;
+        // Enough tiles to keep all the threads of the worker busy
+        let threads = read_u32(&mut reader)?.max(1) as usize;
+
+        loop {
+            let mut new_tiles = Vec::new();
+            {
+                let mut progress = progress.lock().unwrap();
+                loop {
+                    while assigned.len() + new_tiles.len() < threads {
+                        match progress.pending.pop_front() {
+                            Some(tile) => new_tiles.push(tile),
+                            None => break,
+                        }
+                    }
+                    if !assigned.is_empty() || !new_tiles.is_empty() {
+                        break;
+                    }
+                    if progress.remaining == 0 {
+                        drop(progress);
+                        return writer.write_all(&[FINISH]);
+                    }
+
+                    // Other workers can still disconnect and leave tiles for this one
+                    progress = changed.wait(progress).unwrap();
+                }
+            }
+
+            // They're assigned before sending them, so they go back if sending fails
+            assigned.extend(&new_tiles);
+            for index in new_tiles {
+                write_job(&mut writer, index as u32, tiles[index])?;
+            }
+
+            let index = read_u32(&mut reader)? as usize;
+            let film_tile = FilmTile::read_from(&mut reader, image_width, image_height)?;
+            let position = assigned
+                .iter()
+                .position(|&tile| tile == index)
+                .ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::InvalidData, "got a tile that wasn't given")
+                })?;
+            assigned.swap_remove(position);
+
+            let mut progress = progress.lock().unwrap();
+            progress.film.merge_tile(&film_tile);
+            progress.remaining -= 1;
+            if progress.remaining == 0 {
+                changed.notify_all();
+            }
+            drop(progress);
+            scene.observer().tile_done(&TileReport {
+                min: tiles[index].min,
+                max: tiles[index].max,
+                duration: Duration::ZERO,
+                counters: RayCounters::default(),
+            });
+        }
+    }
+}
+
+/// Renders the tiles that a coordinator gives it
+pub struct Worker {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+    scene_description: Vec<u8>,
+}
+
+impl Worker {
+    /// Connects to the coordinator at `address`, and gets the description of the scene
+    ///
+    /// # Errors
+    ///
+    /// If it can't connect to the coordinator, or it doesn't send a scene description
+    pub fn connect(address: impl ToSocketAddrs) -> io::Result<Self> {
+        let writer = TcpStream::connect(address)?;
+        let mut reader = BufReader::new(writer.try_clone()?);
+
+        read_magic(&mut reader)?;
+        let length = read_u32(&mut reader)? as usize;
+        let mut scene_description = vec![0; length];
+        reader.read_exact(&mut scene_description)?;
+
+        Ok(Self {
+            reader,
+            writer,
+            scene_description,
+        })
+    }
+
+    /// The description to build the scene from, which is up to the coordinator
+    pub fn scene_description(&self) -> &[u8] {
+        &self.scene_description
+    }
+
+    /// Renders tiles of the scene with all the threads of rayon, until the coordinator has
+    /// all of them
+    ///
+    /// # Errors
+    ///
+    /// If the connection to the coordinator is lost, or it sends something unexpected
+    ///
+    /// # Panics
+    ///
+    /// If rendering a tile panics
+    pub fn run<T: Hittable>(self, scene: &Scene<T>) -> io::Result<()> {
+        let Self {
+            mut reader, writer, ..
+        } = self;
+        let (image_width, image_height) = scene.image_size();
+
+        let threads = rayon::current_num_threads();
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend((threads as u32).to_le_bytes());
+        (&writer).write_all(&bytes)?;
+
+        let film = Film::new(image_width, image_height);
+        let writer = Mutex::new(writer);
+        let (sender, receiver) = mpsc::channel();
+        let receiver = Mutex::new(receiver);
+
+        thread::scope(|scope| {
+            let reading = scope.spawn(move || -> io::Result<()> {
+                while let Some(job) = read_job(&mut reader)? {
+                    if sender.send(job).is_err() {
+                        break;
+                    }
+                }
+                Ok(())
+            });
+
+            // Threads of its own instead of the ones of rayon, which shouldn't wait for jobs
+            let rendering: Vec<_> = (0..threads)
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut samplers = crate::new_samplers(scene);
+                        let result = render_jobs(scene, &film, &mut samplers, &receiver, &writer);
+
+                        // Stop reading jobs if they can't be rendered
+                        if result.is_err() {
+                            let _ = writer.lock().unwrap().shutdown(Shutdown::Both);
+                        }
+                        result
+                    })
+                })
+                .collect();
+
+            let rendered = rendering
+                .into_iter()
+                .try_for_each(|thread| thread.join().unwrap());
+            let read = reading.join().unwrap();
+            rendered.and(read)
+        })
+    }
+}
+
+/// Renders the jobs that get to `receiver` until there are no more,
+/// sending the tiles to the coordinator
+fn render_jobs<T: Hittable>(
+    scene: &Scene<T>,
+    film: &Film,
+    samplers: &mut Samplers,
+    receiver: &Mutex<mpsc::Receiver<(u32, Tile)>>,
+    writer: &Mutex<TcpStream>,
+) -> io::Result<()> {
+    let (image_width, image_height) = scene.image_size();
+
+    loop {
+        // The channel is closed once the coordinator is done
+        let job = receiver.lock().unwrap().recv();
+        let Ok((index, tile)) = job else {
+            return Ok(());
+        };
+
+        if tile.max.0 > image_width || tile.max.1 > image_height {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "the tile doesn't fit in the image",
+            ));
+        }
+
+        let film_tile = render_tile(scene, film, samplers, tile);
+        let mut bytes = index.to_le_bytes().to_vec();
+        film_tile.write_to(&mut bytes)?;
+        writer.lock().unwrap().write_all(&bytes)?;
+    }
+}
+
+/// Renders all the samples of the pixels of a tile
+fn render_tile<T: Hittable>(
+    scene: &Scene<T>,
+    film: &Film,
+    samplers: &mut Samplers,
+    tile: Tile,
+) -> FilmTile {
+    let mut film_tile = film.tile(tile.min, tile.max, scene.filter());
+    let mut stats = PathStats::default();
+
+    for y in tile.min.1..tile.max.1 {
+        for x in tile.min.0..tile.max.0 {
+            crate::sample_pixel(scene, samplers, &mut stats, x, y, &mut film_tile);
+        }
+    }
+
+    film_tile
+}
+
+fn write_job(writer: &mut impl Write, index: u32, tile: Tile) -> io::Result<()> {
+    let mut bytes = vec![JOB];
+    for value in [index, tile.min.0, tile.min.1, tile.max.0, tile.max.1] {
+        bytes.extend(value.to_le_bytes());
+    }
+
+    writer.write_all(&bytes)
+}
+
+/// Reads the next tile to render and its index, or `None` if there are no more
+fn read_job(reader: &mut impl Read) -> io::Result<Option<(u32, Tile)>> {
+    let mut kind = [0];
+    reader.read_exact(&mut kind)?;
+
+    match kind[0] {
+        JOB => {
+            let index = read_u32(reader)?;
+            let tile = Tile {
+                min: (read_u32(reader)?, read_u32(reader)?),
+                max: (read_u32(reader)?, read_u32(reader)?),
+            };
+            if tile.min.0 > tile.max.0 || tile.min.1 > tile.max.1 {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "the tile is empty",
+                ));
+            }
+
+            Ok(Some((index, tile)))
+        }
+        FINISH => Ok(None),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "unknown kind of message",
+        )),
+    }
+}
+
+fn read_magic(reader: &mut impl Read) -> io::Result<()> {
+    let mut magic = [0; 4];
+    reader.read_exact(&mut magic)?;
+
+    if &magic == MAGIC {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "the other side isn't part of a render farm",
+        ))
+    }
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hittable::HittableList;
+    use crate::progress::SilentObserver;
+    use crate::scene::SceneBuilder;
+    use crate::{Camera, Vec3};
+
+    /// Nothing but the background, so every pixel has exactly its color
+    fn scene() -> Scene<HittableList> {
+        let camera = Camera::new(
+            Vec3::new(0.0, 0.0, 1.0),
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            40.0,
+            1.0,
+            0.0,
+            1.0,
+            (0.0, 1.0),
+        );
+
+        SceneBuilder::new(HittableList::new(), camera, 1.0)
+            .background_color(Color::new(0.25, 0.5, 0.75))
+            .image_width(40)
+            .samples_per_pixel(2)
+            .max_depth(2)
+            .observer(SilentObserver)
+            .build()
+    }
+
+    #[test]
+    fn test_reassigns_tiles_of_dropped_workers() {
+        let scene = scene();
+        let coordinator = Coordinator::bind("127.0.0.1:0", b"background".to_vec()).unwrap();
+        let address = coordinator.local_addr().unwrap();
+
+        let colors = thread::scope(|scope| {
+            let rendering = scope.spawn(|| coordinator.render(&scene));
+
+            // A worker that asks for tiles and disconnects without rendering them
+            let mut dropped = Worker::connect(address).unwrap();
+            let mut bytes = MAGIC.to_vec();
+            bytes.extend(4_u32.to_le_bytes());
+            dropped.writer.write_all(&bytes).unwrap();
+            assert!(read_job(&mut dropped.reader).unwrap().is_some());
+            drop(dropped);
+
+            for _ in 0..2 {
+                scope.spawn(|| {
+                    let worker = Worker::connect(address).unwrap();
+                    assert_eq!(worker.scene_description(), b"background");
+                    worker.run(&scene).unwrap();
+                });
+            }
+
+            rendering.join().unwrap().unwrap()
+        });
+
+        // Tiles that weren't rendered would be black
+        assert_eq!(colors.len(), 40 * 40);
+        for color in colors {
+            assert!((color.r() - 0.25).abs() < 1e-9);
+            assert!((color.g() - 0.5).abs() < 1e-9);
+            assert!((color.b() - 0.75).abs() < 1e-9);
+        }
+    }
+}
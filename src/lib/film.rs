@@ -0,0 +1,314 @@
+//! Accumulation of samples into pixels
+//!
+//! Each pixel keeps the sum of the samples around it, weighted by a reconstruction filter,
+//! and the sum of those weights. Tiles of the image are rendered separately into a `FilmTile`,
+//! which also covers the pixels around the tile that the filter reaches, and then merged.
+//!
+//! Some integrators also find light that gets to the camera through any pixel of the image,
+//! not just the one being rendered. That light is splatted straight into the pixel it hits,
+//! without a filter, and added to the final color.
+
+use std::io::{self, Read, Write};
+
+use crate::filters::Filter;
+use crate::Color;
+
+#[derive(Clone, Copy)]
+struct FilmPixel {
+    weighted_color: Color,
+    weight: f64,
+}
+
+impl FilmPixel {
+    const EMPTY: Self = Self {
+        weighted_color: Color::new(0.0, 0.0, 0.0),
+        weight: 0.0,
+    };
+}
+
+pub struct Film {
+    width: u32,
+    height: u32,
+    pixels: Vec<FilmPixel>,
+    splats: Vec<Color>,
+}
+
+impl Film {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![FilmPixel::EMPTY; (width * height) as usize],
+            splats: vec![Color::new(0.0, 0.0, 0.0); (width * height) as usize],
+        }
+    }
+
+    /// Returns a tile for the samples of the pixels from `min` up to (but not including) `max`.
+    /// It also covers every pixel that `filter` reaches from those samples.
+    pub fn tile(&self, min: (u32, u32), max: (u32, u32), filter: &dyn Filter) -> FilmTile {
+        // A sample can be anywhere in its pixel, so the filter can reach half a pixel further
+        let padding = (filter.radius() + 0.5).ceil() as u32;
+
+        let min = (min.0.saturating_sub(padding), min.1.saturating_sub(padding));
+        let max = (
+            (max.0 + padding).min(self.width),
+            (max.1 + padding).min(self.height),
+        );
+
+        FilmTile {
+            min,
+            max,
+            pixels: vec![FilmPixel::EMPTY; ((max.0 - min.0) * (max.1 - min.1)) as usize],
+            splats: Vec::new(),
+        }
+    }
+
+    /// Adds the samples of a tile to the film
+    pub fn merge_tile(&mut self, tile: &FilmTile) {
+        let tile_width = tile.max.0 - tile.min.0;
+
+        for (i, tile_pixel) in tile.pixels.iter().enumerate() {
+            let x = tile.min.0 + i as u32 % tile_width;
+            let y = tile.min.1 + i as u32 / tile_width;
+            let pixel = &mut self.pixels[(y * self.width + x) as usize];
+
+            pixel.weighted_color += tile_pixel.weighted_color;
+            pixel.weight += tile_pixel.weight;
+        }
+
+        for (position, color) in &tile.splats {
+            let x = (position.0 as u32).min(self.width - 1);
+            let y = (position.1 as u32).min(self.height - 1);
+
+            self.splats[(y * self.width + x) as usize] += *color;
+        }
+    }
+
+    /// Returns the final color of every pixel, in the order of `get_image_coordinates`
+    pub fn colors(&self) -> Vec<Color> {
+        self.pixels
+            .iter()
+            .zip(&self.splats)
+            .map(|(pixel, splat)| {
+                let color = if pixel.weight > 0.0 {
+                    pixel.weighted_color * (1.0 / pixel.weight) + *splat
+                } else {
+                    *splat
+                };
+
+                // Filters with negative lobes can make dark pixels next to bright ones negative
+                Color::new(color.r().max(0.0), color.g().max(0.0), color.b().max(0.0))
+            })
+            .collect()
+    }
+}
+
+/// Part of a `Film` where a tile of the image is rendered
+pub struct FilmTile {
+    min: (u32, u32),
+    max: (u32, u32),
+    pixels: Vec<FilmPixel>,
+    splats: Vec<((f64, f64), Color)>,
+}
+
+impl FilmTile {
+    /// Adds a sample at `position` (in pixels from the bottom left corner of the image)
+    /// to every pixel of the tile that `filter` reaches
+    pub fn add_sample(&mut self, position: (f64, f64), color: Color, filter: &dyn Filter) {
+        let radius = filter.radius();
+
+        // Pixel centers are at the middle of each pixel
+        let first_x = (position.0 - 0.5 - radius)
+            .ceil()
+            .max(f64::from(self.min.0)) as u32;
+        let first_y = (position.1 - 0.5 - radius)
+            .ceil()
+            .max(f64::from(self.min.1)) as u32;
+        let last_x = (position.0 - 0.5 + radius).floor() as i64;
+        let last_y = (position.1 - 0.5 + radius).floor() as i64;
+
+        let tile_width = self.max.0 - self.min.0;
+
+        for y in first_y..(self.max.1.min((last_y + 1).max(0) as u32)) {
+            for x in first_x..(self.max.0.min((last_x + 1).max(0) as u32)) {
+                let weight = filter.evaluate(
+                    position.0 - (f64::from(x) + 0.5),
+                    position.1 - (f64::from(y) + 0.5),
+                );
+                if weight == 0.0 {
+                    continue;
+                }
+
+                let pixel =
+                    &mut self.pixels[((y - self.min.1) * tile_width + (x - self.min.0)) as usize];
+                pixel.weighted_color += color * weight;
+                pixel.weight += weight;
+            }
+        }
+    }
+
+    /// Adds `color` to the pixel at `position`, which can be anywhere in the image.
+    /// It's added as is, so it should already be divided by the samples per pixel.
+    pub fn add_splat(&mut self, position: (f64, f64), color: Color) {
+        self.splats.push((position, color));
+    }
+
+    /// Returns the color of the pixels from `min` up to (but not including) `max`, in the order
+    /// of `get_image_coordinates`, with only the samples of this tile. Pixels next to other
+    /// tiles are missing the samples of those tiles, and splatted light isn't added.
+    pub fn colors(&self, min: (u32, u32), max: (u32, u32)) -> Vec<Color> {
+        let tile_width = self.max.0 - self.min.0;
+
+        (min.1..max.1)
+            .flat_map(|y| (min.0..max.0).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let pixel =
+                    &self.pixels[((y - self.min.1) * tile_width + (x - self.min.0)) as usize];
+                if pixel.weight > 0.0 {
+                    let color = pixel.weighted_color * (1.0 / pixel.weight);
+                    Color::new(color.r().max(0.0), color.g().max(0.0), color.b().max(0.0))
+                } else {
+                    Color::new(0.0, 0.0, 0.0)
+                }
+            })
+            .collect()
+    }
+
+    /// Writes the tile as little-endian numbers, to send it to another computer
+    pub(crate) fn write_to(&self, writer: &mut impl Write) -> io::Result<()> {
+        let mut bytes = Vec::new();
+        for value in [self.min.0, self.min.1, self.max.0, self.max.1] {
+            bytes.extend(value.to_le_bytes());
+        }
+        for pixel in &self.pixels {
+            let color = pixel.weighted_color;
+            for value in [color.r(), color.g(), color.b(), pixel.weight] {
+                bytes.extend(value.to_le_bytes());
+            }
+        }
+        bytes.extend((self.splats.len() as u32).to_le_bytes());
+        for ((x, y), color) in &self.splats {
+            for value in [*x, *y, color.r(), color.g(), color.b()] {
+                bytes.extend(value.to_le_bytes());
+            }
+        }
+
+        writer.write_all(&bytes)
+    }
+
+    /// Reads a tile written by `write_to`, checking that it fits in a film of
+    /// `width` by `height` pixels
+    pub(crate) fn read_from(reader: &mut impl Read, width: u32, height: u32) -> io::Result<Self> {
+        let min = (read_u32(reader)?, read_u32(reader)?);
+        let max = (read_u32(reader)?, read_u32(reader)?);
+        if min.0 > max.0 || min.1 > max.1 || max.0 > width || max.1 > height {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "the tile doesn't fit in the film",
+            ));
+        }
+
+        let pixels = (0..(max.0 - min.0) * (max.1 - min.1))
+            .map(|_| {
+                Ok(FilmPixel {
+                    weighted_color: Color::new(
+                        read_f64(reader)?,
+                        read_f64(reader)?,
+                        read_f64(reader)?,
+                    ),
+                    weight: read_f64(reader)?,
+                })
+            })
+            .collect::<io::Result<Vec<FilmPixel>>>()?;
+        let splats = (0..read_u32(reader)?)
+            .map(|_| {
+                let position = (read_f64(reader)?, read_f64(reader)?);
+                let color = Color::new(read_f64(reader)?, read_f64(reader)?, read_f64(reader)?);
+                Ok((position, color))
+            })
+            .collect::<io::Result<Vec<((f64, f64), Color)>>>()?;
+
+        Ok(Self {
+            min,
+            max,
+            pixels,
+            splats,
+        })
+    }
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_f64(reader: &mut impl Read) -> io::Result<f64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(f64::from_le_bytes(bytes))
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filters::{BoxFilter, TentFilter};
+
+    #[test]
+    fn test_box_filter_averages_pixel() {
+        let filter = BoxFilter::new(0.5);
+        let mut film = Film::new(2, 1);
+        let mut tile = film.tile((0, 0), (2, 1), &filter);
+
+        tile.add_sample((0.25, 0.5), Color::new(1.0, 0.0, 0.0), &filter);
+        tile.add_sample((0.75, 0.5), Color::new(0.0, 1.0, 0.0), &filter);
+        tile.add_sample((1.5, 0.5), Color::new(0.0, 0.0, 1.0), &filter);
+        film.merge_tile(&tile);
+
+        let colors = film.colors();
+        assert!((colors[0].r() - 0.5).abs() < 1e-9 && (colors[0].g() - 0.5).abs() < 1e-9);
+        assert!(colors[0].b().abs() < 1e-9);
+        assert!((colors[1].b() - 1.0).abs() < 1e-9 && colors[1].r().abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_tiles_match_whole_film() {
+        let filter = TentFilter::new(1.5);
+        let samples: Vec<((f64, f64), Color)> = (0..64)
+            .map(|i| {
+                let position = (f64::from(i % 8) + 0.3, f64::from(i / 8) + 0.6);
+                (position, Color::new(f64::from(i), 1.0, 0.0))
+            })
+            .collect();
+
+        let mut whole = Film::new(8, 8);
+        let mut tile = whole.tile((0, 0), (8, 8), &filter);
+        for (position, color) in &samples {
+            tile.add_sample(*position, *color, &filter);
+        }
+        whole.merge_tile(&tile);
+
+        // Splits the samples in four tiles, so their filters cross tile borders
+        let mut tiled = Film::new(8, 8);
+        for (min, max) in [
+            ((0, 0), (4, 4)),
+            ((4, 0), (8, 4)),
+            ((0, 4), (4, 8)),
+            ((4, 4), (8, 8)),
+        ] {
+            let mut tile = tiled.tile(min, max, &filter);
+            for (position, color) in &samples {
+                let pixel = (position.0 as u32, position.1 as u32);
+                if (min.0..max.0).contains(&pixel.0) && (min.1..max.1).contains(&pixel.1) {
+                    tile.add_sample(*position, *color, &filter);
+                }
+            }
+            tiled.merge_tile(&tile);
+        }
+
+        for (a, b) in whole.colors().iter().zip(tiled.colors()) {
+            assert!((a.r() - b.r()).abs() < 1e-9);
+        }
+    }
+}
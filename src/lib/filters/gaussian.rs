@@ -0,0 +1,36 @@
+use crate::filters::Filter;
+
+/// Gaussian bell with standard deviation `sigma`, shifted down so it reaches 0 at the radius
+#[derive(Clone, Debug)]
+pub struct GaussianFilter {
+    radius: f64,
+    sigma: f64,
+    edge: f64,
+}
+
+impl GaussianFilter {
+    pub fn new(radius: f64, sigma: f64) -> Self {
+        Self {
+            radius,
+            sigma,
+            edge: gaussian(radius, sigma),
+        }
+    }
+
+    fn evaluate_1d(&self, x: f64) -> f64 {
+        f64::max(0.0, gaussian(x, self.sigma) - self.edge)
+    }
+}
+
+fn gaussian(x: f64, sigma: f64) -> f64 {
+    f64::exp(-(x * x) / (2.0 * sigma * sigma))
+}
+
+impl Filter for GaussianFilter {
+    fn radius(&self) -> f64 {
+        self.radius
+    }
+    fn evaluate(&self, x: f64, y: f64) -> f64 {
+        self.evaluate_1d(x) * self.evaluate_1d(y)
+    }
+}
@@ -0,0 +1,41 @@
+use std::f64::consts::PI;
+
+use crate::filters::Filter;
+
+/// Sinc filter windowed by a wider sinc, which reaches 0 at the radius.
+/// It keeps images sharp, but can ring around hard edges.
+#[derive(Clone, Debug)]
+pub struct LanczosFilter {
+    radius: f64,
+}
+
+impl LanczosFilter {
+    pub const fn new(radius: f64) -> Self {
+        Self { radius }
+    }
+
+    fn evaluate_1d(&self, x: f64) -> f64 {
+        if x.abs() >= self.radius {
+            return 0.0;
+        }
+
+        sinc(x) * sinc(x / self.radius)
+    }
+}
+
+fn sinc(x: f64) -> f64 {
+    if x.abs() < 1e-5 {
+        return 1.0;
+    }
+
+    f64::sin(PI * x) / (PI * x)
+}
+
+impl Filter for LanczosFilter {
+    fn radius(&self) -> f64 {
+        self.radius
+    }
+    fn evaluate(&self, x: f64, y: f64) -> f64 {
+        self.evaluate_1d(x) * self.evaluate_1d(y)
+    }
+}
@@ -0,0 +1,56 @@
+use crate::filters::Filter;
+
+/// Mitchell-Netravali cubic filter
+///
+/// `b` and `c` trade blurring for ringing. Mitchell and Netravali recommend `b + 2c = 1`,
+/// like `b = c = 1/3`, which is what `Default` uses with a radius of 2.
+#[derive(Clone, Debug)]
+pub struct MitchellFilter {
+    radius: f64,
+    b: f64,
+    c: f64,
+}
+
+impl MitchellFilter {
+    pub const fn new(radius: f64, b: f64, c: f64) -> Self {
+        Self { radius, b, c }
+    }
+
+    /// The filter is defined from -2 to 2, so `x` gets scaled to fit the radius
+    #[allow(clippy::suboptimal_flops)] // Written like in the paper, so it's easier to check
+    fn evaluate_1d(&self, x: f64) -> f64 {
+        let x = (2.0 * x / self.radius).abs();
+        let (b, c) = (self.b, self.c);
+
+        let weight = if x < 1.0 {
+            (12.0 - 9.0 * b - 6.0 * c) * x.powi(3)
+                + (-18.0 + 12.0 * b + 6.0 * c) * x.powi(2)
+                + (6.0 - 2.0 * b)
+        } else if x < 2.0 {
+            (-b - 6.0 * c) * x.powi(3)
+                + (6.0 * b + 30.0 * c) * x.powi(2)
+                + (-12.0 * b - 48.0 * c) * x
+                + (8.0 * b + 24.0 * c)
+        } else {
+            0.0
+        };
+
+        // Keeps the area under the filter at 1 for any radius
+        weight / 6.0 * 2.0 / self.radius
+    }
+}
+
+impl Default for MitchellFilter {
+    fn default() -> Self {
+        Self::new(2.0, 1.0 / 3.0, 1.0 / 3.0)
+    }
+}
+
+impl Filter for MitchellFilter {
+    fn radius(&self) -> f64 {
+        self.radius
+    }
+    fn evaluate(&self, x: f64, y: f64) -> f64 {
+        self.evaluate_1d(x) * self.evaluate_1d(y)
+    }
+}
@@ -0,0 +1,67 @@
+//! Pixel reconstruction filters
+//!
+//! Every sample is added to all the pixels around it that the filter reaches, weighted by
+//! how far it is from the center of each pixel.
+
+mod box_filter;
+mod gaussian;
+mod lanczos;
+mod mitchell;
+mod tent;
+pub use box_filter::BoxFilter;
+pub use gaussian::GaussianFilter;
+pub use lanczos::LanczosFilter;
+pub use mitchell::MitchellFilter;
+pub use tent::TentFilter;
+
+/// A trait that all reconstruction filters must implement
+pub trait Filter: Send + Sync {
+    /// How far, in pixels, the filter reaches from its center on each axis
+    fn radius(&self) -> f64;
+    /// Weight of a sample that's `x` and `y` pixels away from the center of a pixel.
+    /// It's 0 outside of the radius, and can be negative.
+    fn evaluate(&self, x: f64, y: f64) -> f64;
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filters() -> Vec<Box<dyn Filter>> {
+        vec![
+            Box::new(BoxFilter::new(0.5)),
+            Box::new(TentFilter::new(1.0)),
+            Box::new(GaussianFilter::new(1.5, 0.5)),
+            Box::new(MitchellFilter::new(2.0, 1.0 / 3.0, 1.0 / 3.0)),
+            Box::new(LanczosFilter::new(3.0)),
+        ]
+    }
+
+    #[test]
+    fn test_filters_are_zero_outside_radius() {
+        for filter in filters() {
+            let radius = filter.radius();
+
+            assert!(filter.evaluate(0.0, 0.0) > 0.0);
+            assert!(filter.evaluate(radius + 0.01, 0.0).abs() < 1e-12);
+            assert!(filter.evaluate(0.0, -radius - 0.01).abs() < 1e-12);
+            assert!(filter.evaluate(0.3, 0.2) <= filter.evaluate(0.0, 0.0));
+        }
+    }
+
+    #[test]
+    fn test_mitchell_integrates_to_one() {
+        let filter = MitchellFilter::new(3.0, 1.0 / 3.0, 1.0 / 3.0);
+        let steps = 300;
+        let step = 6.0 / f64::from(steps);
+        let coordinate = |i: u32| (f64::from(i) + 0.5).mul_add(step, -3.0);
+
+        let integral: f64 = (0..steps)
+            .flat_map(|i| (0..steps).map(move |j| (i, j)))
+            .map(|(i, j)| filter.evaluate(coordinate(i), coordinate(j)) * step * step)
+            .sum();
+
+        assert!((integral - 1.0).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,22 @@
+use crate::filters::Filter;
+
+/// The weight goes down linearly from the center to the radius
+#[derive(Clone, Debug)]
+pub struct TentFilter {
+    radius: f64,
+}
+
+impl TentFilter {
+    pub const fn new(radius: f64) -> Self {
+        Self { radius }
+    }
+}
+
+impl Filter for TentFilter {
+    fn radius(&self) -> f64 {
+        self.radius
+    }
+    fn evaluate(&self, x: f64, y: f64) -> f64 {
+        f64::max(0.0, self.radius - x.abs()) * f64::max(0.0, self.radius - y.abs())
+    }
+}
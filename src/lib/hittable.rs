@@ -1,12 +1,15 @@
 use std::sync::Arc;
 
 use crate::materials::Material;
+use crate::simd::RayPacket;
+use crate::stats;
 use crate::surfaces::Aabb;
 use crate::surfaces::BvhNode;
 use crate::Ray;
 use crate::Vec3;
 
 /// A struct to store relevant data of a ray intersecting with a surface
+#[derive(Clone)]
 pub struct HitRecord {
     /// The value of `t` when hit occurs.
     /// Where `t` is part of the ray formula 'P(t)=A+tb'
@@ -19,24 +22,57 @@ pub struct HitRecord {
     pub normal: Vec3,
     /// If the ray hit the surface from outside then it's `true`. If it hit it from the inside, then it's `false`.
     pub front_face: bool,
-    /// The `Material` of the surface it hit.
-    pub material: Box<dyn Material>,
+    /// The hit point before any instance moved the surface, for textures in object space
+    pub object_point: Vec3,
+    /// The normal before any instance moved the surface
+    pub object_normal: Vec3,
+    /// The direction the hit point moves in when `u` grows, for materials that depend on the
+    /// direction along the surface. It's zero when the surface doesn't set it.
+    pub tangent: Vec3,
+    /// The `Material` of the surface it hit, shared with the surface so hits don't copy it.
+    pub material: Arc<dyn Material>,
+    /// Identifies the object that was hit. It's 0 unless the object was tagged with `instances::Tagged`.
+    pub object_id: u32,
+    /// How much the texture coordinates change from one pixel to the next, when the ray had
+    /// differentials and the surface knows how its texture coordinates change
+    pub differentials: Option<TextureDifferentials>,
+}
+
+/// How much the texture coordinates change between the hit point and the hit points of the
+/// rays through the pixel to the right and the pixel above
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextureDifferentials {
+    pub du_dx: f64,
+    pub dv_dx: f64,
+    pub du_dy: f64,
+    pub dv_dy: f64,
 }
 impl HitRecord {
     /// Returns a new `HitRecord`
-    pub fn new<T: Material + 'static>(t: f64, p: Vec3, material: T) -> Self {
+    pub fn new(t: f64, p: Vec3, material: Arc<dyn Material>) -> Self {
         Self {
             t,
             p,
             u: 0.0,
             v: 0.0,
-            material: Box::new(material),
+            material,
             front_face: false,
             normal: Vec3::new(0.0, 0.0, 0.0),
+            object_point: p,
+            object_normal: Vec3::new(0.0, 0.0, 0.0),
+            tangent: Vec3::new(0.0, 0.0, 0.0),
+            object_id: 0,
+            differentials: None,
         }
     }
 
     pub fn set_face_normal(&mut self, ray: &Ray, outward_normal: Vec3) {
+        self.set_transformed_normal(ray, outward_normal);
+        self.object_normal = self.normal;
+    }
+    /// Like `set_face_normal`, for instances that move the surface around.
+    /// The object normal stays the one the surface set.
+    pub fn set_transformed_normal(&mut self, ray: &Ray, outward_normal: Vec3) {
         self.front_face = Vec3::dot(&ray.direction, &outward_normal) < 0.0;
         self.normal = if self.front_face {
             outward_normal
@@ -44,10 +80,99 @@ impl HitRecord {
             -outward_normal
         };
     }
+    /// Turns the surface inside out at the hit point.
+    ///
+    /// The normal keeps pointing against the ray, so only the side of the surface that was hit changes.
+    pub const fn flip_face(&mut self) {
+        self.front_face = !self.front_face;
+    }
     pub fn set_texture_coordinates(&mut self, u: f64, v: f64) {
         self.u = u;
         self.v = v;
     }
+    pub fn set_tangent(&mut self, u_direction: Vec3) {
+        self.tangent = u_direction.unit_vector();
+    }
+    /// Returns the tangent and the bitangent, made perpendicular to each other and to the
+    /// normal. Without a tangent, or with one along the normal, any perpendicular one is used.
+    pub fn tangent_frame(&self) -> (Vec3, Vec3) {
+        let mut tangent = self.tangent - self.normal * Vec3::dot(&self.tangent, &self.normal);
+        if tangent.length_squared() < 1e-12 {
+            // Any axis that's far enough from the normal
+            let axis = if self.normal.x().abs() > 0.9 {
+                Vec3::new(0.0, 1.0, 0.0)
+            } else {
+                Vec3::new(1.0, 0.0, 0.0)
+            };
+            tangent = Vec3::cross(&self.normal, &axis);
+        }
+        let tangent = tangent.unit_vector();
+
+        (tangent, Vec3::cross(&self.normal, &tangent))
+    }
+    /// Finds how much the texture coordinates change to the next pixels, from the differentials
+    /// of `ray` and the directions the hit point moves in when `u` and `v` grow by 1.
+    /// The normal has to be set.
+    ///
+    /// The rays of the next pixels are intersected with the plane that touches the surface at
+    /// the hit point, which is close enough for pixels that are next to each other.
+    pub fn set_differentials(&mut self, ray: &Ray, u_direction: Vec3, v_direction: Vec3) {
+        let Some(differentials) = ray.differentials else {
+            return;
+        };
+
+        let plane_distance = Vec3::dot(&self.normal, &self.p);
+        let offset = |origin: Vec3, direction: Vec3| {
+            let t = (plane_distance - Vec3::dot(&self.normal, &origin))
+                / Vec3::dot(&self.normal, &direction);
+            origin + direction * t - self.p
+        };
+        let right = offset(differentials.x_origin, differentials.x_direction);
+        let up = offset(differentials.y_origin, differentials.y_direction);
+
+        // Least squares solution of offset = u_direction * du + v_direction * dv,
+        // since the offsets are in 3D
+        let u_length_squared = u_direction.length_squared();
+        let overlap = Vec3::dot(&u_direction, &v_direction);
+        let v_length_squared = v_direction.length_squared();
+        let determinant = u_length_squared.mul_add(v_length_squared, -overlap * overlap);
+        if determinant.abs() < 1e-12 {
+            return;
+        }
+        let solve = |offset: Vec3| {
+            let along_u = Vec3::dot(&u_direction, &offset);
+            let along_v = Vec3::dot(&v_direction, &offset);
+            (
+                v_length_squared.mul_add(along_u, -overlap * along_v) / determinant,
+                u_length_squared.mul_add(along_v, -overlap * along_u) / determinant,
+            )
+        };
+        let to_right = solve(right);
+        let to_top = solve(up);
+
+        let differentials = TextureDifferentials {
+            du_dx: to_right.0,
+            dv_dx: to_right.1,
+            du_dy: to_top.0,
+            dv_dy: to_top.1,
+        };
+        // Rays almost parallel to the surface don't hit the plane anywhere useful
+        let finite = [to_right.0, to_right.1, to_top.0, to_top.1]
+            .iter()
+            .all(|d| d.is_finite());
+        self.differentials = finite.then_some(differentials);
+    }
+}
+
+#[cfg(test)]
+impl HitRecord {
+    /// A hit at the origin of a surface that faces up, by a ray shot down from `(0, 1, 0)`
+    pub(crate) fn facing_up(material: Arc<dyn Material>) -> Self {
+        let mut record = Self::new(1.0, Vec3::new(0.0, 0.0, 0.0), material);
+        let ray = Ray::new(Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, -1.0, 0.0), 0.0);
+        record.set_face_normal(&ray, Vec3::new(0.0, 1.0, 0.0));
+        record
+    }
 }
 
 /// A trait that defines any hittable surface or geometry
@@ -59,6 +184,70 @@ pub trait Hittable: Send + Sync {
     fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord>;
 
     fn bounding_box(&self, time: (f64, f64)) -> Option<Aabb>;
+
+    /// Calculates the intersections of the 4 rays of `packet`, each one with its own `t_max`.
+    /// Every hit that's found replaces the one of its ray in `hits` and becomes its `t_max`.
+    /// Rays with a `t_max` that isn't bigger than `t_min` aren't tested.
+    ///
+    /// By default the rays are tested one by one, but surfaces like BVHs test them together.
+    fn hit_packet(
+        &self,
+        packet: &RayPacket,
+        t_min: f64,
+        t_max: &mut [f64; 4],
+        hits: &mut [Option<HitRecord>; 4],
+    ) {
+        for i in 0..4 {
+            if t_min >= t_max[i] {
+                continue;
+            }
+            if let Some(hit) = self.hit(&packet.rays[i], t_min, t_max[i]) {
+                t_max[i] = hit.t;
+                hits[i] = Some(hit);
+            }
+        }
+    }
+
+    /// Total area of the surface, for surfaces that can be sampled as lights
+    fn area(&self) -> f64 {
+        0.0
+    }
+    /// Picks a point of the surface from two random numbers in \[0,1), with the same
+    /// probability for every part of it. The record has the outward normal, and a `t` of 0.
+    /// Returns `None` for surfaces that can't be sampled as lights.
+    fn sample_surface(&self, _sample: (f64, f64)) -> Option<HitRecord> {
+        None
+    }
+}
+
+/// Lets surfaces be shared between several parts of a scene without copying them,
+/// since cloning an `Arc` only clones the pointer
+impl<T: Hittable + ?Sized> Hittable for Arc<T> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        (**self).hit(ray, t_min, t_max)
+    }
+
+    fn hit_packet(
+        &self,
+        packet: &RayPacket,
+        t_min: f64,
+        t_max: &mut [f64; 4],
+        hits: &mut [Option<HitRecord>; 4],
+    ) {
+        (**self).hit_packet(packet, t_min, t_max, hits);
+    }
+
+    fn bounding_box(&self, time: (f64, f64)) -> Option<Aabb> {
+        (**self).bounding_box(time)
+    }
+
+    fn area(&self) -> f64 {
+        (**self).area()
+    }
+
+    fn sample_surface(&self, sample: (f64, f64)) -> Option<HitRecord> {
+        (**self).sample_surface(sample)
+    }
 }
 
 /// A list to store hittable surfaces
@@ -97,6 +286,7 @@ impl Hittable for HittableList {
     fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
         let mut hit_record = None;
         let mut closest_distance_so_far = t_max;
+        stats::count_primitive_tests(self.surfaces.len() as u64);
 
         for surface in &self.surfaces {
             let temp_record = surface.hit(ray, t_min, closest_distance_so_far);
@@ -110,6 +300,20 @@ impl Hittable for HittableList {
         hit_record
     }
 
+    fn hit_packet(
+        &self,
+        packet: &RayPacket,
+        t_min: f64,
+        t_max: &mut [f64; 4],
+        hits: &mut [Option<HitRecord>; 4],
+    ) {
+        stats::count_primitive_tests(self.surfaces.len() as u64);
+
+        for surface in &self.surfaces {
+            surface.hit_packet(packet, t_min, t_max, hits);
+        }
+    }
+
     fn bounding_box(&self, time: (f64, f64)) -> Option<Aabb> {
         if self.surfaces.is_empty() {
             return None;
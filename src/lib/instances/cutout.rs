@@ -0,0 +1,70 @@
+use crate::{
+    hittable::{HitRecord, Hittable},
+    surfaces::Aabb,
+    Ray,
+};
+
+/// Lets rays go through the parts of a surface where the alpha of its material is below a
+/// threshold, like the transparent parts of an image texture on a leaf or a fence
+#[derive(Clone)]
+pub struct Cutout<T: Hittable> {
+    surface: T,
+    threshold: f64,
+}
+impl<T: Hittable> Cutout<T> {
+    /// Hits with an alpha below one half are ignored
+    pub const fn new(surface: T) -> Self {
+        Self {
+            surface,
+            threshold: 0.5,
+        }
+    }
+
+    #[must_use]
+    pub const fn threshold(mut self, threshold: f64) -> Self {
+        self.threshold = threshold;
+        self
+    }
+}
+impl<T: Hittable> Hittable for Cutout<T> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let mut t_min = t_min;
+        // Keep looking behind transparent hits, like the back of a sphere
+        while let Some(hit_record) = self.surface.hit(ray, t_min, t_max) {
+            if hit_record.material.alpha(&hit_record) >= self.threshold {
+                return Some(hit_record);
+            }
+            t_min = hit_record.t.next_up();
+        }
+        None
+    }
+
+    fn bounding_box(&self, time: (f64, f64)) -> Option<Aabb> {
+        self.surface.bounding_box(time)
+    }
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use image::{ImageBuffer, Rgba};
+
+    use super::*;
+    use crate::surfaces::XYRect;
+    use crate::textures::{Image, Texture, TextureFilter};
+    use crate::Vec3;
+
+    #[test]
+    fn test_cutout() {
+        // The left half of the image is transparent
+        let image = Image::new(&ImageBuffer::from_fn(2, 1, |x, _| {
+            Rgba([255_u8, 0, 0, if x == 0 { 0 } else { 255 }])
+        }))
+        .filter(TextureFilter::Nearest);
+        let rect = Cutout::new(XYRect::new((0.0, 2.0), (0.0, 1.0), 0.0, image.lambertian()));
+
+        let ray_at = |x| Ray::new(Vec3::new(x, 0.5, 1.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        assert!(rect.hit(&ray_at(0.5), 0.0, 10.0).is_none());
+        assert!(rect.hit(&ray_at(1.5), 0.0, 10.0).is_some());
+    }
+}
@@ -0,0 +1,189 @@
+use std::sync::Arc;
+
+use super::Transform;
+use crate::{
+    hittable::{HitRecord, Hittable},
+    materials::Material,
+    surfaces::Aabb,
+    Float, Ray, Vec3,
+};
+
+/// A placement of shared geometry in the scene
+///
+/// The prototype (usually a `BvhNode`) is behind an `Arc`, so any amount of instances only
+/// store their transformation and an optional material. Putting the instances themselves in a
+/// `BvhNode` gives a two level acceleration structure, where memory grows with the amount of
+/// unique geometry instead of the amount of copies.
+#[derive(Clone)]
+pub struct Instance {
+    prototype: Arc<dyn Hittable>,
+    transform: Transform,
+    material: Option<Arc<dyn Material>>,
+    bounding_box: Option<Aabb>,
+}
+
+impl Instance {
+    pub fn new(prototype: Arc<dyn Hittable>, transform: Transform) -> Self {
+        let bounding_box = prototype.bounding_box((0.0, 1.0)).map(|b_box| {
+            let mut minimum = Vec3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY);
+            let mut maximum = Vec3::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY);
+
+            // Transform every corner of the box
+            for corner in 0..8 {
+                let pick = |bit: u8| {
+                    if corner & bit == 0 {
+                        &b_box.minimum
+                    } else {
+                        &b_box.maximum
+                    }
+                };
+                let tester = transform.point(&Vec3::new(pick(1).x(), pick(2).y(), pick(4).z()));
+
+                for c in 0..3_u8 {
+                    minimum[c] = Float::min(minimum[c], tester[c]);
+                    maximum[c] = Float::max(maximum[c], tester[c]);
+                }
+            }
+
+            Aabb::new(minimum, maximum)
+        });
+
+        Self {
+            prototype,
+            transform,
+            material: None,
+            bounding_box,
+        }
+    }
+
+    /// Moves a record of the prototype into the scene
+    fn record_to_world(&self, record: &mut HitRecord) {
+        record.p = self.transform.point(&record.p);
+        record.normal = self.transform.normal(&record.normal).unit_vector();
+        if record.tangent.length_squared() > 0.0 {
+            record.tangent = self.transform.vector(&record.tangent).unit_vector();
+        }
+
+        if let Some(material) = &self.material {
+            record.material = Arc::clone(material);
+        }
+    }
+
+    /// Renders this instance with `material` instead of the materials of the prototype
+    #[must_use]
+    pub fn with_material(mut self, material: Arc<dyn Material>) -> Self {
+        self.material = Some(material);
+        self
+    }
+}
+
+impl Hittable for Instance {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        // The direction isn't normalized, so `t` is the same in both spaces
+        let object_ray = Ray {
+            origin: self.transform.inverse_point(&ray.origin),
+            direction: self.transform.inverse_vector(&ray.direction),
+            time: ray.time,
+            differentials: ray.differentials.map(|differentials| {
+                differentials.map(
+                    |point| self.transform.inverse_point(point),
+                    |vector| self.transform.inverse_vector(vector),
+                )
+            }),
+        };
+
+        let mut hit_record = self.prototype.hit(&object_ray, t_min, t_max)?;
+        // Transforming the normal this way keeps its angle with the ray on the same side,
+        // so which face was hit doesn't change
+        self.record_to_world(&mut hit_record);
+
+        Some(hit_record)
+    }
+
+    fn bounding_box(&self, _time: (f64, f64)) -> Option<Aabb> {
+        self.bounding_box.clone()
+    }
+
+    /// Only instances that keep the shape of the prototype can be sampled as lights,
+    /// since other ones would make some parts of it more likely than others
+    fn area(&self) -> f64 {
+        self.transform
+            .area_scale()
+            .map_or(0.0, |scale| scale * self.prototype.area())
+    }
+
+    fn sample_surface(&self, sample: (f64, f64)) -> Option<HitRecord> {
+        self.transform.area_scale()?;
+
+        let mut record = self.prototype.sample_surface(sample)?;
+        self.record_to_world(&mut record);
+        Some(record)
+    }
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::Dielectric;
+    use crate::surfaces::Sphere;
+
+    #[test]
+    fn test_shared_prototype() {
+        let prototype: Arc<dyn Hittable> = Arc::new(Sphere::new(
+            Vec3::new(0.0, 0.0, 0.0),
+            1.0,
+            Dielectric::new(1.5),
+        ));
+
+        let moved = Instance::new(
+            prototype.clone(),
+            Transform::translation(Vec3::new(0.0, 0.0, 5.0)),
+        );
+        let squashed = Instance::new(prototype, Transform::scaling(Vec3::new(1.0, 0.5, 1.0)));
+
+        let ray = Ray::new(Vec3::new(0.0, 0.0, -10.0), Vec3::new(0.0, 0.0, 1.0), 0.0);
+        let hit = moved.hit(&ray, 0.0, f64::INFINITY).unwrap();
+        assert!((hit.t - 14.0).abs() < 1e-9);
+        assert!((hit.p - Vec3::new(0.0, 0.0, 4.0)).length() < 1e-9);
+
+        let ray = Ray::new(Vec3::new(0.0, 10.0, 0.0), Vec3::new(0.0, -1.0, 0.0), 0.0);
+        let hit = squashed.hit(&ray, 0.0, f64::INFINITY).unwrap();
+        assert!((hit.t - 9.5).abs() < 1e-9);
+        assert!((hit.normal - Vec3::new(0.0, 1.0, 0.0)).length() < 1e-9);
+
+        assert_eq!(
+            squashed.bounding_box((0.0, 1.0)),
+            Some(Aabb::new(
+                Vec3::new(-1.0, -0.5, -1.0),
+                Vec3::new(1.0, 0.5, 1.0)
+            ))
+        );
+    }
+
+    #[test]
+    fn test_light_sampling() {
+        let prototype: Arc<dyn Hittable> = Arc::new(Sphere::new(
+            Vec3::new(0.0, 0.0, 0.0),
+            1.0,
+            Dielectric::new(1.5),
+        ));
+
+        let placed = Instance::new(
+            prototype.clone(),
+            Transform::scaling(Vec3::new(2.0, 2.0, 2.0))
+                .then(&Transform::rotation_y(1.0))
+                .then(&Transform::translation(Vec3::new(0.0, 0.0, 5.0))),
+        );
+        let quadrupled = 4.0 * prototype.area();
+        assert!((placed.area() - quadrupled).abs() < 1e-9);
+        let sample = placed.sample_surface((0.3, 0.6)).unwrap();
+        assert!(((sample.p - Vec3::new(0.0, 0.0, 5.0)).length() - 2.0).abs() < 1e-9);
+        assert!((sample.normal - (sample.p - Vec3::new(0.0, 0.0, 5.0)) / 2.0).length() < 1e-9);
+
+        // Stretched spheres can't be sampled evenly
+        let squashed = Instance::new(prototype, Transform::scaling(Vec3::new(1.0, 0.5, 1.0)));
+        assert!(squashed.area() == 0.0);
+        assert!(squashed.sample_surface((0.3, 0.6)).is_none());
+    }
+}
@@ -1,4 +1,12 @@
+mod cutout;
+mod instance;
 mod rotation;
+mod tagged;
+mod transform;
 mod translate;
+pub use cutout::Cutout;
+pub use instance::Instance;
 pub use rotation::{RotateY, RotationY};
+pub use tagged::{Tagged, Tagging};
+pub use transform::Transform;
 pub use translate::{Translate, Translation};
@@ -1,7 +1,7 @@
 use crate::{
     hittable::{HitRecord, Hittable},
-    surfaces::Aabb,
-    Ray, Vec3,
+    surfaces::{Aabb, Interval, Solid},
+    Float, Ray, Vec3,
 };
 
 #[derive(Clone)]
@@ -35,8 +35,8 @@ impl<T: Hittable> RotateY<T> {
                         let tester = Vec3::new(new_x, y, new_z);
 
                         for c in 0..3_u8 {
-                            minimum[c] = f64::min(minimum[c], tester[c]);
-                            maximum[c] = f64::max(maximum[c], tester[c]);
+                            minimum[c] = Float::min(minimum[c], tester[c]);
+                            maximum[c] = Float::max(maximum[c], tester[c]);
                         }
                     }
                 }
@@ -52,37 +52,71 @@ impl<T: Hittable> RotateY<T> {
             bounding_box,
         }
     }
-}
-impl<T: Hittable> Hittable for RotateY<T> {
-    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
-        let new_origin = Vec3::new(
-            self.cos_theta * ray.origin.x() - self.sin_theta * ray.origin.z(),
-            ray.origin.y(),
-            self.sin_theta * ray.origin.x() + self.cos_theta * ray.origin.z(),
-        );
-        let new_direction = Vec3::new(
-            self.cos_theta * ray.direction.x() - self.sin_theta * ray.direction.z(),
-            ray.direction.y(),
-            self.sin_theta * ray.direction.x() + self.cos_theta * ray.direction.z(),
-        );
 
-        let rotated_ray = Ray::new(new_origin, new_direction, ray.time);
+    /// Rotates a ray from world space into the space of the surface
+    fn rotate_ray(&self, ray: &Ray) -> Ray {
+        let rotate = |v: &Vec3| {
+            Vec3::new(
+                self.cos_theta.mul_add(v.x(), -self.sin_theta * v.z()),
+                v.y(),
+                self.sin_theta.mul_add(v.x(), self.cos_theta * v.z()),
+            )
+        };
+
+        Ray {
+            origin: rotate(&ray.origin),
+            direction: rotate(&ray.direction),
+            time: ray.time,
+            differentials: ray
+                .differentials
+                .map(|differentials| differentials.map(rotate, rotate)),
+        }
+    }
 
-        let mut hit_record = self.surface.hit(&rotated_ray, t_min, t_max)?;
+    /// Rotates the hit point back into world space and returns the rotated normal
+    fn rotate_record_back(&self, hit_record: &mut HitRecord) -> Vec3 {
+        let rotated_p = Vec3::new(
+            self.cos_theta
+                .mul_add(hit_record.p.x(), self.sin_theta * hit_record.p.z()),
+            hit_record.p.y(),
+            (-self.sin_theta).mul_add(hit_record.p.x(), self.cos_theta * hit_record.p.z()),
+        );
+        let rotated_normal = Vec3::new(
+            self.cos_theta.mul_add(
+                hit_record.normal.x(),
+                self.sin_theta * hit_record.normal.z(),
+            ),
+            hit_record.normal.y(),
+            (-self.sin_theta).mul_add(
+                hit_record.normal.x(),
+                self.cos_theta * hit_record.normal.z(),
+            ),
+        );
 
-        let mut rotated_p = hit_record.p;
-        let mut rotated_normal = hit_record.normal;
+        hit_record.p = rotated_p;
+        hit_record.tangent = Vec3::new(
+            self.cos_theta.mul_add(
+                hit_record.tangent.x(),
+                self.sin_theta * hit_record.tangent.z(),
+            ),
+            hit_record.tangent.y(),
+            (-self.sin_theta).mul_add(
+                hit_record.tangent.x(),
+                self.cos_theta * hit_record.tangent.z(),
+            ),
+        );
 
-        rotated_p[0] = self.cos_theta * hit_record.p.x() + self.sin_theta * hit_record.p.z();
-        rotated_p[2] = -self.sin_theta * hit_record.p.x() + self.cos_theta * hit_record.p.z();
+        rotated_normal
+    }
+}
+impl<T: Hittable> Hittable for RotateY<T> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let rotated_ray = self.rotate_ray(ray);
 
-        rotated_normal[0] =
-            self.cos_theta * hit_record.normal.x() + self.sin_theta * hit_record.normal.z();
-        rotated_normal[2] =
-            -self.sin_theta * hit_record.normal.x() + self.cos_theta * hit_record.normal.z();
+        let mut hit_record = self.surface.hit(&rotated_ray, t_min, t_max)?;
 
-        hit_record.p = rotated_p;
-        hit_record.set_face_normal(&rotated_ray, rotated_normal);
+        let rotated_normal = self.rotate_record_back(&mut hit_record);
+        hit_record.set_transformed_normal(&rotated_ray, rotated_normal);
 
         Some(hit_record)
     }
@@ -90,18 +124,40 @@ impl<T: Hittable> Hittable for RotateY<T> {
     fn bounding_box(&self, _time: (f64, f64)) -> Option<Aabb> {
         self.bounding_box.clone()
     }
+
+    fn area(&self) -> f64 {
+        self.surface.area()
+    }
+
+    fn sample_surface(&self, sample: (f64, f64)) -> Option<HitRecord> {
+        let mut record = self.surface.sample_surface(sample)?;
+        record.normal = self.rotate_record_back(&mut record);
+        Some(record)
+    }
+}
+
+impl<T: Solid> Solid for RotateY<T> {
+    fn intervals(&self, ray: &Ray) -> Vec<Interval> {
+        let rotated_ray = self.rotate_ray(ray);
+
+        let mut intervals = self.surface.intervals(&rotated_ray);
+        for interval in &mut intervals {
+            // Rotations keep the angle between the ray and the normal, so which side was hit stays the same
+            interval.enter.normal = self.rotate_record_back(&mut interval.enter);
+            interval.exit.normal = self.rotate_record_back(&mut interval.exit);
+        }
+
+        intervals
+    }
 }
 
 pub trait RotationY {
-    fn rotate_y_by(&self, angle: f64) -> RotateY<Self>
+    fn rotate_y_by(self, angle: f64) -> RotateY<Self>
     where
-        Self: Hittable + Sized + Clone;
+        Self: Hittable + Sized;
 }
-impl<T: Hittable + Sized + Clone> RotationY for T {
-    fn rotate_y_by(&self, angle: f64) -> RotateY<Self>
-    where
-        Self: Hittable + Sized + Clone,
-    {
-        RotateY::new(self.clone(), angle)
+impl<T: Hittable + Sized> RotationY for T {
+    fn rotate_y_by(self, angle: f64) -> RotateY<Self> {
+        RotateY::new(self, angle)
     }
 }
@@ -0,0 +1,40 @@
+use crate::{
+    hittable::{HitRecord, Hittable},
+    surfaces::Aabb,
+    Ray,
+};
+
+/// Marks every hit on a surface with an object ID, used by the object ID render pass
+#[derive(Clone)]
+pub struct Tagged<T: Hittable> {
+    surface: T,
+    object_id: u32,
+}
+impl<T: Hittable> Tagged<T> {
+    pub const fn new(surface: T, object_id: u32) -> Self {
+        Self { surface, object_id }
+    }
+}
+impl<T: Hittable> Hittable for Tagged<T> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        self.surface.hit(ray, t_min, t_max).map(|mut hit_record| {
+            hit_record.object_id = self.object_id;
+            hit_record
+        })
+    }
+
+    fn bounding_box(&self, time: (f64, f64)) -> Option<Aabb> {
+        self.surface.bounding_box(time)
+    }
+}
+
+pub trait Tagging {
+    fn tagged(self, object_id: u32) -> Tagged<Self>
+    where
+        Self: Hittable + Sized;
+}
+impl<T: Hittable + Sized> Tagging for T {
+    fn tagged(self, object_id: u32) -> Tagged<Self> {
+        Tagged::new(self, object_id)
+    }
+}
@@ -0,0 +1,243 @@
+use crate::Vec3;
+
+/// A 3x3 matrix followed by a translation
+#[derive(Clone, Debug, PartialEq)]
+struct Affine {
+    rows: [Vec3; 3],
+    translation: Vec3,
+}
+impl Affine {
+    const IDENTITY: Self = Self {
+        rows: [
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(0.0, 0.0, 1.0),
+        ],
+        translation: Vec3::new(0.0, 0.0, 0.0),
+    };
+
+    fn vector(&self, v: &Vec3) -> Vec3 {
+        Vec3::new(
+            Vec3::dot(&self.rows[0], v),
+            Vec3::dot(&self.rows[1], v),
+            Vec3::dot(&self.rows[2], v),
+        )
+    }
+    fn point(&self, p: &Vec3) -> Vec3 {
+        self.vector(p) + self.translation
+    }
+    fn transposed_vector(&self, v: &Vec3) -> Vec3 {
+        self.rows[0] * v.x() + self.rows[1] * v.y() + self.rows[2] * v.z()
+    }
+    /// Returns the affine transformation that applies `self` first and then `other`
+    fn then(&self, other: &Self) -> Self {
+        let columns = [
+            Vec3::new(self.rows[0].x(), self.rows[1].x(), self.rows[2].x()),
+            Vec3::new(self.rows[0].y(), self.rows[1].y(), self.rows[2].y()),
+            Vec3::new(self.rows[0].z(), self.rows[1].z(), self.rows[2].z()),
+        ];
+        let row = |i: usize| {
+            Vec3::new(
+                Vec3::dot(&other.rows[i], &columns[0]),
+                Vec3::dot(&other.rows[i], &columns[1]),
+                Vec3::dot(&other.rows[i], &columns[2]),
+            )
+        };
+
+        Self {
+            rows: [row(0), row(1), row(2)],
+            translation: other.point(&self.translation),
+        }
+    }
+}
+
+/// An affine transformation that also keeps track of its inverse
+///
+/// Transformations are built by chaining simple ones with `then`, so the inverse never
+/// has to be calculated from a general matrix.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Transform {
+    forward: Affine,
+    inverse: Affine,
+}
+
+impl Transform {
+    pub const fn identity() -> Self {
+        Self {
+            forward: Affine::IDENTITY,
+            inverse: Affine::IDENTITY,
+        }
+    }
+    pub const fn translation(offset: Vec3) -> Self {
+        Self {
+            forward: Affine {
+                translation: offset,
+                ..Affine::IDENTITY
+            },
+            inverse: Affine {
+                translation: Vec3::new(-offset.x(), -offset.y(), -offset.z()),
+                ..Affine::IDENTITY
+            },
+        }
+    }
+    /// Scales each axis by the given factor. None of them should be zero.
+    pub fn scaling(factors: Vec3) -> Self {
+        let diagonal = |x: f64, y: f64, z: f64| Affine {
+            rows: [
+                Vec3::new(x, 0.0, 0.0),
+                Vec3::new(0.0, y, 0.0),
+                Vec3::new(0.0, 0.0, z),
+            ],
+            translation: Vec3::new(0.0, 0.0, 0.0),
+        };
+
+        Self {
+            forward: diagonal(factors.x(), factors.y(), factors.z()),
+            inverse: diagonal(1.0 / factors.x(), 1.0 / factors.y(), 1.0 / factors.z()),
+        }
+    }
+    /// Rotation by `angle` radians around the X axis
+    pub fn rotation_x(angle: f64) -> Self {
+        Self::rotation(angle, |sin, cos| {
+            [
+                Vec3::new(1.0, 0.0, 0.0),
+                Vec3::new(0.0, cos, -sin),
+                Vec3::new(0.0, sin, cos),
+            ]
+        })
+    }
+    /// Rotation by `angle` radians around the Y axis, in the same direction as `RotateY`
+    pub fn rotation_y(angle: f64) -> Self {
+        Self::rotation(angle, |sin, cos| {
+            [
+                Vec3::new(cos, 0.0, sin),
+                Vec3::new(0.0, 1.0, 0.0),
+                Vec3::new(-sin, 0.0, cos),
+            ]
+        })
+    }
+    /// Rotation by `angle` radians around the Z axis
+    pub fn rotation_z(angle: f64) -> Self {
+        Self::rotation(angle, |sin, cos| {
+            [
+                Vec3::new(cos, -sin, 0.0),
+                Vec3::new(sin, cos, 0.0),
+                Vec3::new(0.0, 0.0, 1.0),
+            ]
+        })
+    }
+    fn rotation(angle: f64, rows: impl Fn(f64, f64) -> [Vec3; 3]) -> Self {
+        let (sin, cos) = f64::sin_cos(angle);
+
+        // Rotating by the opposite angle undoes the rotation
+        Self {
+            forward: Affine {
+                rows: rows(sin, cos),
+                translation: Vec3::new(0.0, 0.0, 0.0),
+            },
+            inverse: Affine {
+                rows: rows(-sin, cos),
+                translation: Vec3::new(0.0, 0.0, 0.0),
+            },
+        }
+    }
+
+    /// Returns the transformation that applies `self` first and then `other`
+    #[must_use]
+    pub fn then(&self, other: &Self) -> Self {
+        Self {
+            forward: self.forward.then(&other.forward),
+            inverse: other.inverse.then(&self.inverse),
+        }
+    }
+    #[must_use]
+    pub fn inverse(&self) -> Self {
+        Self {
+            forward: self.inverse.clone(),
+            inverse: self.forward.clone(),
+        }
+    }
+
+    pub fn point(&self, p: &Vec3) -> Vec3 {
+        self.forward.point(p)
+    }
+    /// Transforms a direction, which isn't affected by the translation
+    pub fn vector(&self, v: &Vec3) -> Vec3 {
+        self.forward.vector(v)
+    }
+    /// Transforms a surface normal, so it stays perpendicular to the transformed surface.
+    /// The result isn't normalized.
+    pub fn normal(&self, n: &Vec3) -> Vec3 {
+        self.inverse.transposed_vector(n)
+    }
+    pub fn inverse_point(&self, p: &Vec3) -> Vec3 {
+        self.inverse.point(p)
+    }
+    pub fn inverse_vector(&self, v: &Vec3) -> Vec3 {
+        self.inverse.vector(v)
+    }
+    /// How many times bigger areas get, when the transformation only rotates, moves and
+    /// scales the same along every axis. Other ones change areas differently in each direction.
+    pub fn area_scale(&self) -> Option<f64> {
+        let [x, y, z] = [
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(0.0, 0.0, 1.0),
+        ]
+        .map(|axis| self.vector(&axis));
+
+        let scale = x.length_squared();
+        let tolerance = 1e-9 * scale;
+        let uniform = (y.length_squared() - scale).abs() < tolerance
+            && (z.length_squared() - scale).abs() < tolerance;
+        let perpendicular = Vec3::dot(&x, &y).abs() < tolerance
+            && Vec3::dot(&x, &z).abs() < tolerance
+            && Vec3::dot(&y, &z).abs() < tolerance;
+
+        (uniform && perpendicular).then_some(scale)
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: Vec3, b: Vec3) {
+        assert!((a - b).length() < 1e-9, "{a:?} != {b:?}");
+    }
+
+    #[test]
+    fn test_composition() {
+        let transform = Transform::scaling(Vec3::new(2.0, 2.0, 2.0))
+            .then(&Transform::rotation_z(std::f64::consts::FRAC_PI_2))
+            .then(&Transform::translation(Vec3::new(0.0, 0.0, 5.0)));
+
+        let p = Vec3::new(1.0, 0.0, 0.0);
+        assert_close(transform.point(&p), Vec3::new(0.0, 2.0, 5.0));
+        assert_close(transform.vector(&p), Vec3::new(0.0, 2.0, 0.0));
+        assert_close(transform.inverse_point(&transform.point(&p)), p);
+        assert_close(transform.inverse().point(&Vec3::new(0.0, 2.0, 5.0)), p);
+    }
+
+    #[test]
+    fn test_normal_stays_perpendicular() {
+        let transform = Transform::scaling(Vec3::new(1.0, 4.0, 1.0))
+            .then(&Transform::rotation_x(0.3))
+            .then(&Transform::rotation_y(1.1));
+
+        let tangent = Vec3::new(1.0, 1.0, 0.0);
+        let normal = Vec3::new(1.0, -1.0, 0.0);
+
+        let transformed_tangent = transform.vector(&tangent);
+        let transformed_normal = transform.normal(&normal);
+
+        assert!(Vec3::dot(&transformed_tangent, &transformed_normal).abs() < 1e-9);
+    }
+}
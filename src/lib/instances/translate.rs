@@ -1,6 +1,6 @@
 use crate::{
     hittable::{HitRecord, Hittable},
-    surfaces::Aabb,
+    surfaces::{Aabb, Interval, Solid},
     Ray, Vec3,
 };
 
@@ -16,13 +16,19 @@ impl<T: Hittable> Translate<T> {
 }
 impl<T: Hittable> Hittable for Translate<T> {
     fn hit(&self, ray: &crate::Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
-        let moved_ray = Ray::new(ray.origin - self.offset, ray.direction, ray.time);
+        let moved_ray = Ray {
+            origin: ray.origin - self.offset,
+            differentials: ray
+                .differentials
+                .map(|differentials| differentials.map(|point| point - &self.offset, |v| *v)),
+            ..*ray
+        };
 
         self.surface
             .hit(&moved_ray, t_min, t_max)
             .map(|mut hit_record| {
                 hit_record.p += self.offset;
-                hit_record.set_face_normal(&moved_ray, hit_record.normal);
+                hit_record.set_transformed_normal(&moved_ray, hit_record.normal);
                 hit_record
             })
     }
@@ -32,15 +38,39 @@ impl<T: Hittable> Hittable for Translate<T> {
             .bounding_box(time)
             .map(|bb| Aabb::new(bb.minimum + self.offset, bb.maximum + self.offset))
     }
+
+    fn area(&self) -> f64 {
+        self.surface.area()
+    }
+
+    fn sample_surface(&self, sample: (f64, f64)) -> Option<HitRecord> {
+        let mut record = self.surface.sample_surface(sample)?;
+        record.p += self.offset;
+        Some(record)
+    }
+}
+
+impl<T: Solid> Solid for Translate<T> {
+    fn intervals(&self, ray: &Ray) -> Vec<Interval> {
+        let moved_ray = Ray::new(ray.origin - self.offset, ray.direction, ray.time);
+
+        let mut intervals = self.surface.intervals(&moved_ray);
+        for interval in &mut intervals {
+            interval.enter.p += self.offset;
+            interval.exit.p += self.offset;
+        }
+
+        intervals
+    }
 }
 
 pub trait Translation {
-    fn translate_by(&self, offset: Vec3) -> Translate<Self>
+    fn translate_by(self, offset: Vec3) -> Translate<Self>
     where
-        Self: Hittable + Sized + Clone;
+        Self: Hittable + Sized;
 }
-impl<T: Hittable + Sized + Clone> Translation for T {
-    fn translate_by(&self, offset: Vec3) -> Translate<Self> {
-        Translate::new(self.clone(), offset)
+impl<T: Hittable + Sized> Translation for T {
+    fn translate_by(self, offset: Vec3) -> Translate<Self> {
+        Translate::new(self, offset)
     }
 }
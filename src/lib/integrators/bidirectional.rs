@@ -0,0 +1,658 @@
+use crate::film::FilmTile;
+use crate::hittable::{HitRecord, HittableList};
+use crate::integrators::{
+    emission_pdf, sample_emission, sample_light, Integrator, PathSample, PathVertex, SceneContext,
+    Termination, T_MIN,
+};
+use crate::samplers::Sampler;
+use crate::{Color, Ray, Vec3};
+
+/// Bidirectional path tracing
+///
+/// Every sample traces a path from the camera and another one from a random point of the
+/// lights of the scene, and then connects every vertex of one with every vertex of the
+/// other. Each of those connections is a different way (strategy) of finding the same kind
+/// of path, and they're weighted with multiple importance sampling (the balance heuristic),
+/// so each path is mostly found by the strategy that's the best at finding it.
+///
+/// Paths that start from a light and hit the camera go to whichever pixel they hit, so light
+/// that's focused by glass (caustics) shows up even if it comes from a small light.
+/// Only the lights added to the scene with `SceneBuilder::light` are traced from, and the
+/// light from the background is only found by the paths from the camera.
+///
+/// From "Robust Monte Carlo Methods for Light Transport Simulation" by Eric Veach,
+/// and the implementation in "Physically Based Rendering" by Pharr, Jakob and Humphreys.
+#[derive(Clone, Debug, Default)]
+pub struct BidirectionalPathTracer;
+
+impl BidirectionalPathTracer {
+    pub const fn new() -> Self {
+        Self
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum VertexKind {
+    Camera,
+    Light,
+    Surface,
+}
+
+/// A vertex of a path from the camera or from a light
+struct Vertex {
+    kind: VertexKind,
+    point: Vec3,
+    normal: Vec3,
+    record: Option<HitRecord>,
+    /// Light (or importance, for paths from the camera) that got to this vertex,
+    /// divided by the probability of getting here
+    throughput: Color,
+    /// Unit vector towards the vertex before this one
+    to_previous: Vec3,
+    /// Whether the material scatters in an exact direction, so it can't be connected
+    delta: bool,
+    /// Probability density, per area, of getting to this vertex from the one before it
+    pdf_forward: f64,
+    /// Probability density, per area, of getting to this vertex from the one after it,
+    /// if the path was traced the other way around
+    pdf_reverse: f64,
+    /// Probability density, per area, that light sampling picks this point.
+    /// It's 0 if it's not on any of the lights of the scene.
+    light_pdf: f64,
+}
+
+/// The probability densities of a vertex for a single strategy
+#[derive(Clone, Copy)]
+struct VertexPdfs {
+    forward: f64,
+    reverse: f64,
+    delta: bool,
+}
+
+impl VertexPdfs {
+    const fn of(vertex: &Vertex) -> Self {
+        Self {
+            forward: vertex.pdf_forward,
+            reverse: vertex.pdf_reverse,
+            delta: vertex.delta,
+        }
+    }
+}
+
+impl Vertex {
+    const fn camera(point: Vec3, forward: Vec3, throughput: Color) -> Self {
+        Self {
+            kind: VertexKind::Camera,
+            point,
+            normal: forward,
+            record: None,
+            throughput,
+            to_previous: Vec3::new(0.0, 0.0, 0.0),
+            delta: false,
+            pdf_forward: 0.0,
+            pdf_reverse: 0.0,
+            light_pdf: 0.0,
+        }
+    }
+
+    const fn light(record: HitRecord, throughput: Color, position_pdf: f64) -> Self {
+        Self {
+            kind: VertexKind::Light,
+            point: record.p,
+            normal: record.normal,
+            record: Some(record),
+            throughput,
+            to_previous: Vec3::new(0.0, 0.0, 0.0),
+            delta: false,
+            pdf_forward: position_pdf,
+            pdf_reverse: 0.0,
+            light_pdf: position_pdf,
+        }
+    }
+
+    const fn surface(record: HitRecord, throughput: Color, to_previous: Vec3) -> Self {
+        Self {
+            kind: VertexKind::Surface,
+            point: record.p,
+            normal: record.normal,
+            record: Some(record),
+            throughput,
+            to_previous,
+            delta: false,
+            pdf_forward: 0.0,
+            pdf_reverse: 0.0,
+            light_pdf: 0.0,
+        }
+    }
+
+    fn is_on_surface(&self) -> bool {
+        self.kind != VertexKind::Camera
+    }
+
+    const fn is_connectible(&self) -> bool {
+        !self.delta
+    }
+
+    /// Light emitted by the surface of the vertex
+    fn emitted(&self) -> Color {
+        self.record
+            .as_ref()
+            .map_or(Color::new(0.0, 0.0, 0.0), |record| {
+                record.material.emitted(record.u, record.v, &record.p)
+            })
+    }
+
+    /// How much of the light from `next` is scattered towards the previous vertex,
+    /// including the cosine with the surface
+    fn scattered(&self, next: &Self) -> Color {
+        let Some(record) = &self.record else {
+            return Color::new(0.0, 0.0, 0.0);
+        };
+
+        let to_next = (next.point - self.point).unit_vector();
+        record
+            .material
+            .evaluate(record, &self.to_previous, &to_next)
+    }
+
+    /// Turns a probability density per solid angle from this vertex into a density per area
+    /// at `next`
+    fn convert_density(&self, pdf: f64, next: &Self) -> f64 {
+        let to_next = next.point - self.point;
+        let distance_squared = to_next.length_squared();
+        if distance_squared == 0.0 {
+            return 0.0;
+        }
+
+        let mut pdf = pdf / distance_squared;
+        if next.is_on_surface() {
+            pdf *= Vec3::dot(&next.normal, &(to_next / distance_squared.sqrt())).abs();
+        }
+
+        pdf
+    }
+
+    /// Probability density, per area, of getting to `next` from this vertex,
+    /// after getting here from `previous` (or the actual previous vertex if it's `None`)
+    fn pdf(&self, scene: &SceneContext, previous: Option<&Self>, next: &Self) -> f64 {
+        let to_next = next.point - self.point;
+        if to_next.near_zero() {
+            return 0.0;
+        }
+        let to_next = to_next.unit_vector();
+
+        let pdf = match (self.kind, &self.record) {
+            (VertexKind::Light, _) => return self.pdf_emission(next),
+            (VertexKind::Camera, _) => scene.camera.pdf_direction(to_next, scene.image_size),
+            (VertexKind::Surface, Some(record)) => {
+                let to_previous = previous.map_or(self.to_previous, |previous| {
+                    (previous.point - self.point).unit_vector()
+                });
+                record.material.pdf(record, &to_previous, &to_next)
+            }
+            (VertexKind::Surface, None) => 0.0,
+        };
+
+        self.convert_density(pdf, next)
+    }
+
+    /// Probability density, per area, that a light at this vertex emits towards `next`
+    fn pdf_emission(&self, next: &Self) -> f64 {
+        let to_next = (next.point - self.point).unit_vector();
+
+        self.convert_density(emission_pdf(&self.normal, &to_next), next)
+    }
+}
+
+/// Probability density, per area, that light sampling picks the point where `ray` hits at `t`
+fn light_pdf(lights: &HittableList, ray: &Ray, t: f64) -> f64 {
+    let count = f64::from(lights.surfaces.len() as u32);
+
+    lights
+        .surfaces
+        .iter()
+        .filter(|light| light.area() > 0.0)
+        .filter(|light| light.hit(ray, t * (1.0 - 1e-6), t * (1.0 + 1e-6)).is_some())
+        .map(|light| 1.0 / (count * light.area()))
+        .sum()
+}
+
+/// Whether nothing is between `from` and `to`
+fn visible(scene: &SceneContext, from: Vec3, to: Vec3, time: f64) -> bool {
+    let to_target = to - from;
+    let distance = to_target.length();
+    let ray = Ray::new(from, to_target / distance, time);
+
+    scene.world.hit(&ray, T_MIN, distance - T_MIN).is_none()
+}
+
+fn is_black(color: &Color) -> bool {
+    color.max_component() <= 0.0
+}
+
+impl BidirectionalPathTracer {
+    /// Traces a path from the camera, starting with `ray` and its hit `first_hit`.
+    /// It has at most `max_vertices` vertices, counting the one on the camera.
+    fn camera_subpath(
+        ray: &Ray,
+        first_hit: Option<HitRecord>,
+        scene: &SceneContext,
+        sampler: &mut dyn Sampler,
+        max_vertices: usize,
+    ) -> (Vec<Vertex>, Termination, Color) {
+        let white = Color::new(1.0, 1.0, 1.0);
+        let mut path = vec![Vertex::camera(ray.origin, scene.camera.forward(), white)];
+        let pdf_direction = scene.camera.pdf_direction(ray.direction, scene.image_size);
+
+        let (termination, throughput) = Self::random_walk(
+            scene,
+            *ray,
+            first_hit,
+            white,
+            pdf_direction,
+            sampler,
+            max_vertices,
+            &mut path,
+        );
+
+        (path, termination, throughput)
+    }
+
+    /// Traces a path from a random point of the lights, with at most `max_vertices`
+    /// vertices, counting the one on the light
+    fn light_subpath(
+        scene: &SceneContext,
+        time: f64,
+        sampler: &mut dyn Sampler,
+        max_vertices: usize,
+    ) -> Vec<Vertex> {
+        if max_vertices == 0 {
+            return Vec::new();
+        }
+        let Some(light) = Self::sample_light(scene, sampler) else {
+            return Vec::new();
+        };
+
+        let Some((direction, direction_pdf)) = sample_emission(&light.normal, sampler) else {
+            return Vec::new();
+        };
+
+        let emitted = light.emitted();
+        let cosine = Vec3::dot(&light.normal, &direction).abs();
+        let throughput = emitted * (cosine / (light.pdf_forward * direction_pdf));
+        let ray = Ray::new(light.point, direction, time);
+
+        let mut path = vec![Vertex::light(
+            light.record.expect("Light vertices have a record"),
+            emitted,
+            light.pdf_forward,
+        )];
+        if max_vertices > 1 {
+            let hit = scene.world.hit(&ray, T_MIN, f64::INFINITY);
+            Self::random_walk(
+                scene,
+                ray,
+                hit,
+                throughput,
+                direction_pdf,
+                sampler,
+                max_vertices,
+                &mut path,
+            );
+        }
+
+        path
+    }
+
+    /// Picks a random point of the lights. The throughput of the vertex is the light it emits,
+    /// divided by the probability density of picking it.
+    fn sample_light(scene: &SceneContext, sampler: &mut dyn Sampler) -> Option<Vertex> {
+        let (record, position_pdf) = sample_light(scene.lights, sampler)?;
+        let emitted = record.material.emitted(record.u, record.v, &record.p);
+
+        Some(Vertex::light(
+            record,
+            emitted * (1.0 / position_pdf),
+            position_pdf,
+        ))
+    }
+
+    /// Keeps scattering `ray` until it reaches `max_vertices`, adding a vertex to `path`
+    /// every time it hits something. `hit` is where `ray` hits the world first.
+    /// Returns why it stopped, and its throughput when it stopped.
+    #[allow(clippy::too_many_arguments)]
+    fn random_walk(
+        scene: &SceneContext,
+        mut ray: Ray,
+        mut hit: Option<HitRecord>,
+        mut throughput: Color,
+        mut pdf_forward: f64,
+        sampler: &mut dyn Sampler,
+        max_vertices: usize,
+        path: &mut Vec<Vertex>,
+    ) -> (Termination, Color) {
+        loop {
+            if path.len() >= max_vertices {
+                return (Termination::MaxDepth, throughput);
+            }
+
+            let Some(record) = hit.take() else {
+                return (Termination::Escaped, throughput);
+            };
+            let previous = path.last().expect("Paths start with an endpoint");
+
+            let t = record.t;
+            let mut vertex = Vertex::surface(record, throughput, -ray.direction.unit_vector());
+            vertex.pdf_forward = previous.convert_density(pdf_forward, &vertex);
+            if !is_black(&vertex.emitted()) {
+                vertex.light_pdf = light_pdf(scene.lights, &ray, t);
+            }
+
+            if path.len() + 1 >= max_vertices {
+                path.push(vertex);
+                return (Termination::MaxDepth, throughput);
+            }
+
+            let record = vertex
+                .record
+                .as_ref()
+                .expect("Surface vertices have a record");
+            let Some(scatter) = record.material.scatter(&ray, record, sampler) else {
+                path.push(vertex);
+                return (Termination::Absorbed, throughput);
+            };
+
+            let to_next = scatter.scattered_ray.direction.unit_vector();
+            let pdf_reverse = if record.material.is_specular() {
+                vertex.delta = true;
+                pdf_forward = 0.0;
+                0.0
+            } else {
+                pdf_forward = record.material.pdf(record, &vertex.to_previous, &to_next);
+                record.material.pdf(record, &to_next, &vertex.to_previous)
+            };
+
+            throughput = throughput * scatter.attenuation;
+            ray = scatter.scattered_ray;
+            hit = scene.world.hit(&ray, T_MIN, f64::INFINITY);
+
+            let previous = path.last_mut().expect("Paths start with an endpoint");
+            previous.pdf_reverse = vertex.convert_density(pdf_reverse, previous);
+            path.push(vertex);
+        }
+    }
+
+    /// Returns the light found by connecting the first `s` vertices of the light path with the
+    /// first `t` vertices of the camera path, weighted with MIS, and where it is in the image
+    /// if it has to be splatted (when `t` is 1)
+    #[allow(clippy::too_many_arguments)]
+    fn connect(
+        scene: &SceneContext,
+        light_path: &[Vertex],
+        camera_path: &[Vertex],
+        s: usize,
+        t: usize,
+        sampler: &mut dyn Sampler,
+        time: f64,
+    ) -> (Color, Option<(f64, f64)>) {
+        let black = Color::new(0.0, 0.0, 0.0);
+        let mut sampled_vertex = None;
+        let mut position = None;
+
+        let light = if s == 0 {
+            // The camera path hit a light by itself
+            let pt = &camera_path[t - 1];
+            pt.emitted() * pt.throughput
+        } else if t == 1 {
+            // The light path is connected to a new point on the lens
+            let qs = &light_path[s - 1];
+            if !qs.is_connectible() {
+                return (black, None);
+            }
+            let Some(camera_sample) =
+                scene
+                    .camera
+                    .sample_importance(qs.point, sampler.next_2d(), scene.image_size)
+            else {
+                return (black, None);
+            };
+
+            let camera = Vertex::camera(
+                camera_sample.lens_point,
+                scene.camera.forward(),
+                Color::new(1.0, 1.0, 1.0) * (camera_sample.importance / camera_sample.pdf),
+            );
+            let light = qs.throughput * qs.scattered(&camera) * camera.throughput;
+            if is_black(&light) || !visible(scene, qs.point, camera.point, time) {
+                return (black, None);
+            }
+
+            position = Some(camera_sample.position);
+            sampled_vertex = Some(camera);
+            light
+        } else if s == 1 {
+            // The camera path is connected to a new point on a light
+            let pt = &camera_path[t - 1];
+            if !pt.is_connectible() {
+                return (black, None);
+            }
+            let Some(light_vertex) = Self::sample_light(scene, sampler) else {
+                return (black, None);
+            };
+
+            let to_light = light_vertex.point - pt.point;
+            let distance_squared = to_light.length_squared();
+            let cosine = Vec3::dot(&light_vertex.normal, &to_light).abs() / distance_squared.sqrt();
+
+            let light = pt.throughput
+                * pt.scattered(&light_vertex)
+                * light_vertex.throughput
+                * (cosine / distance_squared);
+            if is_black(&light) || !visible(scene, pt.point, light_vertex.point, time) {
+                return (black, None);
+            }
+
+            sampled_vertex = Some(light_vertex);
+            light
+        } else {
+            let qs = &light_path[s - 1];
+            let pt = &camera_path[t - 1];
+            if !qs.is_connectible() || !pt.is_connectible() {
+                return (black, None);
+            }
+
+            let distance_squared = (qs.point - pt.point).length_squared();
+            let light = qs.throughput
+                * qs.scattered(pt)
+                * pt.scattered(qs)
+                * pt.throughput
+                * (1.0 / distance_squared);
+            if is_black(&light) || !visible(scene, pt.point, qs.point, time) {
+                return (black, None);
+            }
+
+            light
+        };
+
+        if is_black(&light) {
+            return (black, None);
+        }
+
+        let weight = Self::mis_weight(
+            scene,
+            light_path,
+            camera_path,
+            sampled_vertex.as_ref(),
+            s,
+            t,
+        );
+        (light * weight, position)
+    }
+
+    /// Balance heuristic weight of the strategy with `s` light vertices and `t` camera vertices.
+    ///
+    /// Instead of finding the probability of every strategy, it finds how much more likely
+    /// each strategy is than this one, by swapping the forward densities of the vertices
+    /// between them with their reverse densities one at a time.
+    fn mis_weight(
+        scene: &SceneContext,
+        light_path: &[Vertex],
+        camera_path: &[Vertex],
+        sampled: Option<&Vertex>,
+        s: usize,
+        t: usize,
+    ) -> f64 {
+        if s + t == 2 {
+            return 1.0;
+        }
+
+        // The sampled vertex, if there's one, replaces the end of its path
+        let pt = match sampled {
+            Some(sampled) if t == 1 => sampled,
+            _ => &camera_path[t - 1],
+        };
+        let qs = (s > 0).then(|| match sampled {
+            Some(sampled) if s == 1 => sampled,
+            _ => &light_path[s - 1],
+        });
+        let pt_minus = (t > 1).then(|| &camera_path[t - 2]);
+        let qs_minus = (s > 1).then(|| &light_path[s - 2]);
+
+        // Only paths from the camera can get to lights that can't be sampled
+        if qs.is_none() && pt.light_pdf == 0.0 {
+            return 1.0;
+        }
+
+        let mut camera_pdfs: Vec<VertexPdfs> =
+            camera_path[..t].iter().map(VertexPdfs::of).collect();
+        let mut light_pdfs: Vec<VertexPdfs> = light_path[..s].iter().map(VertexPdfs::of).collect();
+
+        // The ends of the paths are connected, so their reverse densities change
+        camera_pdfs[t - 1] = VertexPdfs {
+            delta: false,
+            reverse: qs.map_or(pt.light_pdf, |qs| qs.pdf(scene, qs_minus, pt)),
+            ..VertexPdfs::of(pt)
+        };
+        if let Some(pt_minus) = pt_minus {
+            camera_pdfs[t - 2].reverse = qs.map_or_else(
+                || pt.pdf_emission(pt_minus),
+                |qs| pt.pdf(scene, Some(qs), pt_minus),
+            );
+        }
+        if let Some(qs) = qs {
+            light_pdfs[s - 1] = VertexPdfs {
+                delta: false,
+                reverse: pt.pdf(scene, pt_minus, qs),
+                ..VertexPdfs::of(qs)
+            };
+        }
+        if let (Some(qs), Some(qs_minus)) = (qs, qs_minus) {
+            light_pdfs[s - 2].reverse = qs.pdf(scene, Some(pt), qs_minus);
+        }
+
+        // Delta vertices have densities of 0, which cancel out
+        let remap = |pdf: f64| if pdf == 0.0 { 1.0 } else { pdf };
+        let mut sum = 0.0;
+
+        let mut ratio = 1.0;
+        for i in (1..t).rev() {
+            ratio *= remap(camera_pdfs[i].reverse) / remap(camera_pdfs[i].forward);
+            if !camera_pdfs[i].delta && !camera_pdfs[i - 1].delta {
+                sum += ratio;
+            }
+        }
+
+        let mut ratio = 1.0;
+        for i in (0..s).rev() {
+            ratio *= remap(light_pdfs[i].reverse) / remap(light_pdfs[i].forward);
+            let previous_delta = i > 0 && light_pdfs[i - 1].delta;
+            if !light_pdfs[i].delta && !previous_delta {
+                sum += ratio;
+            }
+        }
+
+        1.0 / (1.0 + sum)
+    }
+}
+
+impl Integrator for BidirectionalPathTracer {
+    fn sample(
+        &self,
+        ray: &Ray,
+        first_hit: Option<HitRecord>,
+        scene: &SceneContext,
+        sampler: &mut dyn Sampler,
+        film_tile: &mut FilmTile,
+        on_vertex: &mut dyn FnMut(&PathVertex),
+    ) -> PathSample {
+        let max_depth = scene.max_depth as usize;
+        let (camera_path, termination, throughput) =
+            Self::camera_subpath(ray, first_hit, scene, sampler, max_depth + 1);
+
+        let mut color = Color::new(0.0, 0.0, 0.0);
+        if let Some(first_hit) = camera_path.get(1) {
+            on_vertex(&PathVertex {
+                bounce: 0,
+                hit: first_hit.record.as_ref(),
+                light: color,
+            });
+        }
+
+        let hits = camera_path.len() as u32 - 1;
+        if termination == Termination::Escaped {
+            // The background can't be sampled, so only the camera path finds its light
+            let light = throughput * scene.background;
+            color += light;
+            on_vertex(&PathVertex {
+                bounce: hits,
+                hit: None,
+                light,
+            });
+        }
+
+        let light_path = Self::light_subpath(scene, ray.time, sampler, max_depth);
+        let splat_weight = 1.0 / f64::from(scene.samples_per_pixel);
+
+        for t in 1..=camera_path.len() {
+            for s in 0..=light_path.len() {
+                if (s == 1 && t == 1) || s + t < 2 || s + t - 1 > max_depth {
+                    continue;
+                }
+
+                let (light, position) =
+                    Self::connect(scene, &light_path, &camera_path, s, t, sampler, ray.time);
+
+                if let Some(position) = position {
+                    film_tile.add_splat(position, light * splat_weight);
+                } else if t > 1 {
+                    color += light;
+                    on_vertex(&PathVertex {
+                        bounce: (s + t - 2) as u32,
+                        hit: None,
+                        light,
+                    });
+                }
+            }
+        }
+
+        let depth = if termination == Termination::Absorbed {
+            hits - 1
+        } else {
+            hits
+        };
+        PathSample::new(color, depth, termination)
+    }
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::integrators::tests::{assert_matches_path_tracer, lit_corner, traced};
+
+    #[test]
+    fn test_matches_path_tracer() {
+        let bidirectional = traced(&lit_corner(BidirectionalPathTracer::new(), 512));
+        assert_matches_path_tracer(&bidirectional, 512, 1, 0.05);
+    }
+}
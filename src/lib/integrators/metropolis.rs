@@ -0,0 +1,291 @@
+use std::time::Instant;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+
+use crate::hittable::Hittable;
+use crate::integrators::PathTracer;
+use crate::samplers::{MetropolisSampler, Sampler};
+use crate::scene::Scene;
+use crate::stats::RenderStats;
+use crate::Color;
+
+/// Primary sample space Metropolis light transport
+///
+/// Every sample of a path tracer is a function of the random numbers it uses. Instead of
+/// picking new random numbers for every sample, this keeps changing the numbers of the last
+/// sample a little, and keeps the change with a probability that depends on how bright the
+/// new sample is compared to the last one. That way, once a chain of samples finds a path that
+/// carries a lot of light, it explores the paths around it, which is good for scenes where
+/// most of the light gets through small openings.
+///
+/// The chains only find how the brightness is distributed over the image, so a bootstrap phase
+/// of independent samples first finds the average brightness of the image.
+/// Many chains run in parallel, and there are as many mutations in total as samples in the
+/// image, so the samples per pixel of the scene are the average mutations per pixel.
+///
+/// From "A simple and robust mutation strategy for the Metropolis light transport algorithm"
+/// by Csaba Kelemen et al.
+#[derive(Clone, Debug)]
+pub struct MetropolisLightTransport {
+    bootstrap_samples: u32,
+    chains: u32,
+    large_step_probability: f64,
+    sigma: f64,
+    path_tracer: PathTracer,
+}
+
+impl MetropolisLightTransport {
+    /// 100,000 bootstrap samples, 1,000 chains, a large step probability of 0.3
+    /// and small steps with a deviation of 0.01
+    pub const fn new() -> Self {
+        Self {
+            bootstrap_samples: 100_000,
+            chains: 1000,
+            large_step_probability: 0.3,
+            sigma: 0.01,
+            path_tracer: PathTracer::new(),
+        }
+    }
+
+    #[must_use]
+    pub const fn bootstrap_samples(mut self, samples: u32) -> Self {
+        self.bootstrap_samples = samples;
+        self
+    }
+
+    #[must_use]
+    pub const fn chains(mut self, chains: u32) -> Self {
+        self.chains = chains;
+        self
+    }
+
+    /// Sets the probability that a mutation picks completely new random numbers
+    #[must_use]
+    pub const fn large_step_probability(mut self, probability: f64) -> Self {
+        self.large_step_probability = probability;
+        self
+    }
+
+    /// Sets how far small steps move each random number
+    #[must_use]
+    pub const fn sigma(mut self, sigma: f64) -> Self {
+        self.sigma = sigma;
+        self
+    }
+
+    /// Sets the path tracer that finds the light of each sample
+    #[must_use]
+    pub const fn path_tracer(mut self, path_tracer: PathTracer) -> Self {
+        self.path_tracer = path_tracer;
+        self
+    }
+
+    /// Renders the scene, returning the color of every pixel in the order of
+    /// `get_image_coordinates`
+    pub fn render<T: Hittable>(&self, scene: &Scene<T>) -> Vec<Color> {
+        let (image_width, image_height) = scene.image_size();
+        let pixel_count = (image_width * image_height) as usize;
+        let seed = rand::random::<u64>();
+
+        // Bootstrap
+        let weights: Vec<f64> = (0..self.bootstrap_samples)
+            .into_par_iter()
+            .map(|i| {
+                let mut sampler = self.sampler(seed, i);
+                self.sample(scene, &mut sampler).0.luminance()
+            })
+            .collect();
+
+        let total_weight: f64 = weights.iter().sum();
+        if total_weight <= 0.0 {
+            return vec![Color::new(0.0, 0.0, 0.0); pixel_count];
+        }
+        let average_brightness = total_weight / f64::from(self.bootstrap_samples);
+        let cumulative_weights: Vec<f64> = weights
+            .iter()
+            .scan(0.0, |total, weight| {
+                *total += weight;
+                Some(*total)
+            })
+            .collect();
+
+        // Chains
+        let mutations = u64::from(scene.samples_per_pixel()) * pixel_count as u64;
+        let chains = u64::from(self.chains.max(1));
+
+        let start_time = Instant::now();
+        let observer = scene.observer();
+        observer.start(chains as usize);
+
+        let splats = (0..chains)
+            .into_par_iter()
+            .fold(
+                || vec![Color::new(0.0, 0.0, 0.0); pixel_count],
+                |mut splats, chain| {
+                    // Spread the mutations that don't divide evenly between the first chains
+                    let chain_mutations =
+                        mutations / chains + u64::from(chain < mutations % chains);
+                    self.run_chain(
+                        scene,
+                        seed,
+                        chain,
+                        chain_mutations,
+                        &weights,
+                        &cumulative_weights,
+                        &mut splats,
+                    );
+                    observer.step_done();
+                    splats
+                },
+            )
+            .reduce_with(|mut total, splats| {
+                for (total, splat) in total.iter_mut().zip(splats) {
+                    *total += splat;
+                }
+                total
+            })
+            .unwrap_or_else(|| vec![Color::new(0.0, 0.0, 0.0); pixel_count]);
+        observer.finish(&RenderStats {
+            duration: start_time.elapsed(),
+            ..RenderStats::default()
+        });
+
+        let scale = average_brightness / f64::from(scene.samples_per_pixel().max(1));
+        splats.into_iter().map(|splat| splat * scale).collect()
+    }
+
+    /// The sampler of bootstrap sample number `index`
+    fn sampler(&self, seed: u64, index: u32) -> MetropolisSampler {
+        MetropolisSampler::new(
+            seed.wrapping_add(u64::from(index)),
+            self.large_step_probability,
+            self.sigma,
+        )
+    }
+
+    /// Runs a chain of `mutations` mutations, adding the light of each one to `splats`
+    #[allow(clippy::too_many_arguments)]
+    fn run_chain<T: Hittable>(
+        &self,
+        scene: &Scene<T>,
+        seed: u64,
+        chain: u64,
+        mutations: u64,
+        weights: &[f64],
+        cumulative_weights: &[f64],
+        splats: &mut [Color],
+    ) {
+        let mut rng = StdRng::seed_from_u64(seed ^ chain.wrapping_mul(0x9E37_79B9_7F4A_7C15));
+
+        // Start from a bootstrap sample, picked in proportion to its brightness
+        let total_weight = cumulative_weights.last().copied().unwrap_or(0.0);
+        let target = rng.gen::<f64>() * total_weight;
+        let index = cumulative_weights
+            .partition_point(|&weight| weight <= target)
+            .min(weights.len() - 1);
+
+        let mut sampler = self.sampler(seed, index as u32);
+        let (mut current, mut current_position) = self.sample(scene, &mut sampler);
+
+        for _ in 0..mutations {
+            sampler.start_iteration();
+            let (proposed, proposed_position) = self.sample(scene, &mut sampler);
+
+            let current_brightness = current.luminance();
+            let proposed_brightness = proposed.luminance();
+            let acceptance = if current_brightness > 0.0 {
+                (proposed_brightness / current_brightness).min(1.0)
+            } else {
+                1.0
+            };
+
+            // Both samples are added, weighted by the probability of keeping each,
+            // so rejected mutations still count
+            if proposed_brightness > 0.0 {
+                splat(
+                    scene,
+                    splats,
+                    proposed_position,
+                    proposed * (acceptance / proposed_brightness),
+                );
+            }
+            if current_brightness > 0.0 {
+                splat(
+                    scene,
+                    splats,
+                    current_position,
+                    current * ((1.0 - acceptance) / current_brightness),
+                );
+            }
+
+            if rng.gen::<f64>() < acceptance {
+                current = proposed;
+                current_position = proposed_position;
+                sampler.accept();
+            } else {
+                sampler.reject();
+            }
+        }
+    }
+
+    /// Traces the sample given by the current values of `sampler`.
+    /// Returns its light and where it is in the image.
+    fn sample<T: Hittable>(
+        &self,
+        scene: &Scene<T>,
+        sampler: &mut MetropolisSampler,
+    ) -> (Color, (f64, f64)) {
+        let (image_width, image_height) = scene.image_size();
+
+        let (offset_x, offset_y) = sampler.next_2d();
+        let sample_x = offset_x * f64::from(image_width);
+        let sample_y = offset_y * f64::from(image_height);
+
+        let ray = scene.camera_ray((sample_x, sample_y), sampler);
+        let path = self.path_tracer.trace(
+            &ray,
+            scene.context().world,
+            scene.background_color(),
+            scene.max_depth(),
+            sampler,
+        );
+
+        (path.color, (sample_x, sample_y))
+    }
+}
+
+impl Default for MetropolisLightTransport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Adds `color` to the pixel at `position`
+fn splat<T: Hittable>(scene: &Scene<T>, splats: &mut [Color], position: (f64, f64), color: Color) {
+    let (image_width, image_height) = scene.image_size();
+    let x = (position.0 as u32).min(image_width - 1);
+    let y = (position.1 as u32).min(image_height - 1);
+
+    splats[(y * image_width + x) as usize] += color;
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::integrators::tests::{assert_matches_path_tracer, lit_corner};
+
+    #[test]
+    fn test_matches_path_tracer() {
+        let scene = lit_corner(PathTracer::new().russian_roulette_depth(None), 2048);
+        let metropolis = MetropolisLightTransport::new()
+            .chains(256)
+            .path_tracer(PathTracer::new().russian_roulette_depth(None))
+            .render(&scene);
+
+        // The bottom half (the floor) and the top half (mostly the wall) match too
+        assert_matches_path_tracer(&metropolis, 2048, 2, 0.15);
+    }
+}
@@ -0,0 +1,341 @@
+//! Integrators turn the rays that leave the camera into colors
+//!
+//! They decide how paths are traced through the scene, and when they stop.
+
+use std::f64::consts::PI;
+use std::fmt;
+
+use crate::film::FilmTile;
+use crate::hittable::{HitRecord, Hittable, HittableList};
+use crate::samplers::Sampler;
+use crate::{Camera, Color, Ray, Vec3};
+
+mod bidirectional;
+mod metropolis;
+mod path_tracer;
+mod photon_map;
+mod photon_mapping;
+pub use bidirectional::BidirectionalPathTracer;
+pub use metropolis::MetropolisLightTransport;
+pub use path_tracer::PathTracer;
+pub use photon_mapping::ProgressivePhotonMapper;
+
+/// Where rays start looking for hits, a bit past their origin so that rays that leave a
+/// surface don't hit it again
+pub(crate) const T_MIN: f64 = 0.0001;
+
+/// A trait that all integrators must implement
+pub trait Integrator: Send + Sync {
+    /// Finds the light of a sample, which starts with the camera ray `ray`.
+    ///
+    /// Light that gets to the camera through other pixels is splatted onto `film_tile`.
+    /// `on_vertex` gets the first surface hit, and the light that the sample finds after each
+    /// amount of bounces, which is what the AOVs are made of.
+    ///
+    /// `first_hit` is where `ray` hits the world first, from `T_MIN` on. Callers find it,
+    /// so that camera rays can be tested in packets.
+    fn sample(
+        &self,
+        ray: &Ray,
+        first_hit: Option<HitRecord>,
+        scene: &SceneContext,
+        sampler: &mut dyn Sampler,
+        film_tile: &mut FilmTile,
+        on_vertex: &mut dyn FnMut(&PathVertex),
+    ) -> PathSample;
+}
+
+/// The parts of a scene that integrators use
+pub struct SceneContext<'a> {
+    pub world: &'a dyn Hittable,
+    /// Surfaces that light can be traced from. They should also be in the world.
+    pub lights: &'a HittableList,
+    pub camera: &'a Camera,
+    pub background: Color,
+    pub max_depth: u32,
+    pub image_size: (u32, u32),
+    pub samples_per_pixel: u32,
+}
+
+/// Picks a random point on a random light of `lights`.
+/// Returns it with the probability density, per area, of picking it.
+pub(crate) fn sample_light(
+    lights: &HittableList,
+    sampler: &mut dyn Sampler,
+) -> Option<(HitRecord, f64)> {
+    let count = lights.surfaces.len();
+    if count == 0 {
+        return None;
+    }
+
+    let count = f64::from(count as u32);
+    let index = ((sampler.next_1d() * count) as usize).min(lights.surfaces.len() - 1);
+    let light = &lights.surfaces[index];
+
+    let record = light.sample_surface(sampler.next_2d())?;
+    let area = light.area();
+    if area <= 0.0 {
+        return None;
+    }
+
+    Some((record, 1.0 / (count * area)))
+}
+
+/// Picks a direction for light to leave a light with normal `normal`.
+/// Returns it with its probability density, per solid angle.
+pub(crate) fn sample_emission(normal: &Vec3, sampler: &mut dyn Sampler) -> Option<(Vec3, f64)> {
+    // A cosine distribution around the normal, on a random side of the light
+    let mut direction = *normal + Vec3::unit_vector_from_sample(sampler.next_2d());
+    if direction.near_zero() {
+        direction = *normal;
+    }
+    direction = direction.unit_vector();
+    if sampler.next_1d() < 0.5 {
+        direction = -direction;
+    }
+
+    let pdf = emission_pdf(normal, &direction);
+    (pdf > 0.0).then_some((direction, pdf))
+}
+
+/// Probability density, per solid angle, that a light emits in `direction`.
+/// Lights emit from both sides, with a cosine distribution around the normal.
+pub(crate) fn emission_pdf(normal: &Vec3, direction: &Vec3) -> f64 {
+    Vec3::dot(normal, direction).abs() / (2.0 * PI)
+}
+
+/// A surface hit (or a miss) along a path, and the light it added to the path's color
+pub struct PathVertex<'a> {
+    /// How many times the path scattered before getting here
+    pub bounce: u32,
+    pub hit: Option<&'a HitRecord>,
+    pub light: Color,
+}
+
+/// Why a path stopped bouncing
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Termination {
+    /// It didn't hit anything, so it got the background color
+    Escaped,
+    /// It hit something that doesn't scatter light, like a light
+    Absorbed,
+    /// Russian roulette decided it wasn't worth tracing any further
+    RussianRoulette,
+    /// It bounced the maximum amount of times
+    MaxDepth,
+}
+
+impl Termination {
+    const ALL: [Self; 4] = [
+        Self::Escaped,
+        Self::Absorbed,
+        Self::RussianRoulette,
+        Self::MaxDepth,
+    ];
+
+    const fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// The result of tracing a single path
+#[derive(Clone, Copy)]
+pub struct PathSample {
+    pub color: Color,
+    /// How many times the path scattered
+    pub depth: u32,
+    pub termination: Termination,
+}
+
+impl PathSample {
+    pub(crate) const fn new(color: Color, depth: u32, termination: Termination) -> Self {
+        Self {
+            color,
+            depth,
+            termination,
+        }
+    }
+}
+
+/// Statistics about many traced paths
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PathStats {
+    paths: u64,
+    total_depth: u64,
+    terminations: [u64; 4],
+}
+
+impl PathStats {
+    pub fn add(&mut self, sample: &PathSample) {
+        self.paths += 1;
+        self.total_depth += u64::from(sample.depth);
+        self.terminations[sample.termination.index()] += 1;
+    }
+
+    /// Adds all the paths of `other`, like when joining the statistics of different threads
+    pub fn merge(&mut self, other: &Self) {
+        self.paths += other.paths;
+        self.total_depth += other.total_depth;
+        for (count, other_count) in self.terminations.iter_mut().zip(other.terminations) {
+            *count += other_count;
+        }
+    }
+
+    pub const fn paths(&self) -> u64 {
+        self.paths
+    }
+
+    /// How many paths stopped because of `termination`
+    pub const fn terminations(&self, termination: Termination) -> u64 {
+        self.terminations[termination.index()]
+    }
+
+    /// Average amount of times that each path scattered
+    #[allow(clippy::cast_precision_loss)] // Counts don't get anywhere close to 2^52
+    pub fn average_depth(&self) -> f64 {
+        if self.paths == 0 {
+            return 0.0;
+        }
+
+        self.total_depth as f64 / self.paths as f64
+    }
+}
+
+impl fmt::Display for PathStats {
+    #[allow(clippy::cast_precision_loss)] // Counts don't get anywhere close to 2^52
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} paths, average depth {:.2}.",
+            self.paths,
+            self.average_depth()
+        )?;
+
+        for termination in Termination::ALL {
+            let percentage = if self.paths == 0 {
+                0.0
+            } else {
+                100.0 * self.terminations(termination) as f64 / self.paths as f64
+            };
+            write!(f, " {termination:?}: {percentage:.1}%.")?;
+        }
+
+        Ok(())
+    }
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::DiffuseLight;
+    use crate::progress::SilentObserver;
+    use crate::scene::{Scene, SceneBuilder};
+    use crate::surfaces::{XYRect, XZRect};
+
+    /// A gray floor and wall lit by a light above them, that every integrator is tested with
+    pub(super) fn lit_corner(
+        integrator: impl Integrator + 'static,
+        samples_per_pixel: u32,
+    ) -> Scene<impl Hittable> {
+        let light = XZRect::new(
+            (-0.5, 0.5),
+            (-0.5, 0.5),
+            1.0,
+            DiffuseLight::new(Color::new(4.0, 4.0, 4.0)),
+        );
+
+        let mut world = HittableList::new();
+        let gray = Color::new(0.5, 0.5, 0.5).lambertian();
+        world.push(XZRect::new((-2.0, 2.0), (-2.0, 2.0), 0.0, gray.clone()));
+        world.push(XYRect::new((-2.0, 2.0), (0.0, 2.0), -1.0, gray));
+        world.push(light.clone());
+
+        let camera = Camera::new(
+            Vec3::new(0.0, 0.6, 3.0),
+            Vec3::new(0.0, 0.4, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            50.0,
+            1.0,
+            0.0,
+            1.0,
+            (0.0, 1.0),
+        );
+
+        SceneBuilder::new(world, camera, 1.0)
+            .background_color(Color::new(0.0, 0.0, 0.0))
+            .image_width(12)
+            .samples_per_pixel(samples_per_pixel)
+            .max_depth(5)
+            .integrator(integrator)
+            .light(light)
+            .observer(SilentObserver)
+            .build()
+    }
+
+    /// The color of every pixel, traced with the integrator of the scene
+    pub(super) fn traced<T: Hittable>(scene: &Scene<T>) -> Vec<Color> {
+        let (image_width, image_height) = scene.image_size();
+        let (_, film, _) = crate::render_tiles(
+            scene,
+            &crate::rows(image_width, image_height),
+            |samplers, stats, x, y, film_tile| {
+                crate::sample_pixel(scene, samplers, stats, x, y, film_tile);
+            },
+        );
+        film.colors()
+    }
+
+    /// Checks that `colors` of `lit_corner` are within `tolerance`, relative to the brightness,
+    /// of the path tracer with as many samples. The image is compared in `parts` rows of the
+    /// same height, so the light has to be spread over it in the same way too.
+    pub(super) fn assert_matches_path_tracer(
+        colors: &[Color],
+        samples_per_pixel: u32,
+        parts: usize,
+        tolerance: f64,
+    ) {
+        let path_traced = traced(&lit_corner(
+            PathTracer::new().russian_roulette_depth(None),
+            samples_per_pixel,
+        ));
+        let average = |colors: &[Color]| {
+            colors.iter().map(Color::r).sum::<f64>() / f64::from(colors.len() as u32)
+        };
+
+        let part = path_traced.len() / parts;
+        for (path_traced, colors) in path_traced.chunks(part).zip(colors.chunks(part)) {
+            let (path_traced, other) = (average(path_traced), average(colors));
+            assert!(path_traced > 0.0);
+            assert!(
+                (other - path_traced).abs() < tolerance * path_traced,
+                "{other} != {path_traced}"
+            );
+        }
+    }
+
+    fn sample(depth: u32, termination: Termination) -> PathSample {
+        PathSample {
+            color: Color::new(0.0, 0.0, 0.0),
+            depth,
+            termination,
+        }
+    }
+
+    #[test]
+    fn test_path_stats() {
+        let mut first = PathStats::default();
+        first.add(&sample(1, Termination::Escaped));
+        first.add(&sample(4, Termination::RussianRoulette));
+
+        let mut second = PathStats::default();
+        second.add(&sample(7, Termination::RussianRoulette));
+        first.merge(&second);
+
+        assert_eq!(first.paths(), 3);
+        assert!((first.average_depth() - 4.0).abs() < 1e-12);
+        assert_eq!(first.terminations(Termination::Escaped), 1);
+        assert_eq!(first.terminations(Termination::RussianRoulette), 2);
+        assert_eq!(first.terminations(Termination::MaxDepth), 0);
+    }
+}
@@ -0,0 +1,219 @@
+use crate::film::FilmTile;
+use crate::hittable::{HitRecord, Hittable};
+use crate::integrators::{Integrator, PathSample, PathVertex, SceneContext, Termination, T_MIN};
+use crate::samplers::Sampler;
+use crate::{Color, Ray};
+
+/// Traces paths from the camera one bounce at a time, in a loop instead of recursively.
+///
+/// Each path carries its throughput, how much of the light found from then on still gets
+/// to the camera. After `russian_roulette_depth` bounces, paths keep going with a probability
+/// equal to their throughput, and the ones that survive are made brighter to make up for
+/// the ones that didn't, so dark paths stop early without changing the average color.
+#[derive(Clone, Debug)]
+pub struct PathTracer {
+    russian_roulette_depth: Option<u32>,
+}
+
+impl PathTracer {
+    /// A path tracer that starts Russian roulette after 3 bounces
+    pub const fn new() -> Self {
+        Self {
+            russian_roulette_depth: Some(3),
+        }
+    }
+
+    /// Sets after how many bounces Russian roulette starts, or turns it off with `None`
+    #[must_use]
+    pub const fn russian_roulette_depth(mut self, depth: Option<u32>) -> Self {
+        self.russian_roulette_depth = depth;
+        self
+    }
+
+    /// Traces a path of at most `max_depth` rays, starting with `ray`
+    pub fn trace<T: Hittable + ?Sized>(
+        &self,
+        ray: &Ray,
+        world: &T,
+        background: &Color,
+        max_depth: u32,
+        sampler: &mut dyn Sampler,
+    ) -> PathSample {
+        let first_hit = world.hit(ray, T_MIN, f64::INFINITY);
+        self.trace_vertices(
+            ray,
+            first_hit,
+            world,
+            background,
+            max_depth,
+            sampler,
+            |_| {},
+        )
+    }
+
+    /// Like `trace`, but starts from the hit of `ray` that was already found, and calls
+    /// `on_vertex` every time the path hits something or escapes
+    #[allow(clippy::too_many_arguments)]
+    fn trace_vertices<T: Hittable + ?Sized>(
+        &self,
+        ray: &Ray,
+        first_hit: Option<HitRecord>,
+        world: &T,
+        background: &Color,
+        max_depth: u32,
+        sampler: &mut dyn Sampler,
+        mut on_vertex: impl FnMut(&PathVertex),
+    ) -> PathSample {
+        let mut ray = *ray;
+        let mut throughput = Color::new(1.0, 1.0, 1.0);
+        let mut color = Color::new(0.0, 0.0, 0.0);
+        let mut first_hit = Some(first_hit);
+
+        for bounce in 0..max_depth {
+            let hit = first_hit
+                .take()
+                .unwrap_or_else(|| world.hit(&ray, T_MIN, f64::INFINITY));
+            let Some(hit) = hit else {
+                let light = throughput * *background;
+                color += light;
+                on_vertex(&PathVertex {
+                    bounce,
+                    hit: None,
+                    light,
+                });
+
+                return PathSample::new(color, bounce, Termination::Escaped);
+            };
+
+            let light = throughput * hit.material.emitted(hit.u, hit.v, &hit.p);
+            color += light;
+            on_vertex(&PathVertex {
+                bounce,
+                hit: Some(&hit),
+                light,
+            });
+
+            let Some(scatter) = hit.material.scatter(&ray, &hit, sampler) else {
+                return PathSample::new(color, bounce, Termination::Absorbed);
+            };
+
+            throughput = throughput * scatter.attenuation;
+            ray = scatter.scattered_ray;
+
+            if self
+                .russian_roulette_depth
+                .is_some_and(|depth| bounce + 1 >= depth)
+            {
+                let survival_probability = throughput.max_component().min(1.0);
+
+                if sampler.next_1d() >= survival_probability {
+                    return PathSample::new(color, bounce + 1, Termination::RussianRoulette);
+                }
+                throughput = throughput * (1.0 / survival_probability);
+            }
+        }
+
+        PathSample::new(color, max_depth, Termination::MaxDepth)
+    }
+}
+
+impl Integrator for PathTracer {
+    fn sample(
+        &self,
+        ray: &Ray,
+        first_hit: Option<HitRecord>,
+        scene: &SceneContext,
+        sampler: &mut dyn Sampler,
+        _film_tile: &mut FilmTile,
+        on_vertex: &mut dyn FnMut(&PathVertex),
+    ) -> PathSample {
+        self.trace_vertices(
+            ray,
+            first_hit,
+            scene.world,
+            &scene.background,
+            scene.max_depth,
+            sampler,
+            on_vertex,
+        )
+    }
+}
+
+impl Default for PathTracer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hittable::HittableList;
+    use crate::samplers::RandomSampler;
+    use crate::surfaces::Sphere;
+    use crate::Vec3;
+
+    /// Average brightness of many paths that hit a gray sphere under a white sky
+    fn average_brightness(path_tracer: &PathTracer) -> f64 {
+        const PATHS: u32 = 20_000;
+
+        let mut world = HittableList::new();
+        world.push(Sphere::new(
+            Vec3::new(0.0, 0.0, 0.0),
+            1.0,
+            Color::new(0.5, 0.5, 0.5).lambertian(),
+        ));
+        let ray = Ray::new(Vec3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let sky = Color::new(1.0, 1.0, 1.0);
+
+        let total: f64 = (0..PATHS)
+            .map(|_| {
+                path_tracer
+                    .trace(&ray, &world, &sky, 50, &mut RandomSampler::new())
+                    .color
+                    .r()
+            })
+            .sum();
+
+        total / f64::from(PATHS)
+    }
+
+    #[test]
+    fn test_terminations() {
+        let mut world = HittableList::new();
+        world.push(Sphere::new(
+            Vec3::new(0.0, 0.0, 0.0),
+            1.0,
+            Color::new(0.5, 0.5, 0.5).lambertian(),
+        ));
+        let sky = Color::new(1.0, 1.0, 1.0);
+        let path_tracer = PathTracer::new().russian_roulette_depth(None);
+        let mut sampler = RandomSampler::new();
+
+        let miss = Ray::new(Vec3::new(0.0, 5.0, 5.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let sample = path_tracer.trace(&miss, &world, &sky, 50, &mut sampler);
+        assert_eq!(sample.termination, Termination::Escaped);
+        assert_eq!(sample.depth, 0);
+
+        let hit = Ray::new(Vec3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0), 0.0);
+        let sample = path_tracer.trace(&hit, &world, &sky, 50, &mut sampler);
+        assert_eq!(sample.termination, Termination::Escaped);
+        assert_eq!(sample.depth, 1);
+
+        let sample = path_tracer.trace(&hit, &world, &sky, 1, &mut sampler);
+        assert_eq!(sample.termination, Termination::MaxDepth);
+        assert!(sample.color.r().abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_russian_roulette_is_unbiased() {
+        // Without Russian roulette every path is exactly 0.5
+        let expected = average_brightness(&PathTracer::new().russian_roulette_depth(None));
+        assert!((expected - 0.5).abs() < 1e-9);
+
+        // With it, half of the paths are 0 and the other half are 1
+        let with_roulette = average_brightness(&PathTracer::new().russian_roulette_depth(Some(0)));
+        assert!((with_roulette - expected).abs() < 0.03);
+    }
+}
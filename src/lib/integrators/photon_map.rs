@@ -0,0 +1,141 @@
+use crate::vec3::to_f64;
+use crate::{Color, Float, Vec3};
+
+/// Light that got to a point of a surface, traced from a light
+#[derive(Clone, Copy)]
+pub struct Photon {
+    pub position: Vec3,
+    /// Unit vector towards where the photon came from
+    pub incoming: Vec3,
+    pub power: Color,
+}
+
+/// A kd-tree of photons, for finding the ones close to a point quickly.
+///
+/// The tree is stored in the order of the photons: the middle photon of every range splits it
+/// in two along its axis in `split_axes`, and the photons before and after it are its subtrees.
+pub struct PhotonMap {
+    photons: Vec<Photon>,
+    split_axes: Vec<u8>,
+}
+
+impl PhotonMap {
+    pub fn new(mut photons: Vec<Photon>) -> Self {
+        let mut split_axes = vec![0; photons.len()];
+        build(&mut photons, &mut split_axes);
+
+        Self {
+            photons,
+            split_axes,
+        }
+    }
+
+    /// Calls `f` with every photon that's closer than `radius` to `point`
+    pub fn for_each_within(&self, point: Vec3, radius: f64, mut f: impl FnMut(&Photon)) {
+        search(
+            &self.photons,
+            &self.split_axes,
+            point,
+            radius * radius,
+            &mut f,
+        );
+    }
+}
+
+fn build(photons: &mut [Photon], split_axes: &mut [u8]) {
+    if photons.len() <= 1 {
+        return;
+    }
+
+    // Split along the axis where the photons are the most spread out
+    let extent = |axis: u8| {
+        let (min, max) = photons
+            .iter()
+            .map(|photon| photon.position[axis])
+            .fold((Float::INFINITY, Float::NEG_INFINITY), |(min, max), x| {
+                (min.min(x), max.max(x))
+            });
+        max - min
+    };
+    let axis = (0..3)
+        .max_by(|&a, &b| extent(a).total_cmp(&extent(b)))
+        .unwrap_or(0);
+
+    let middle = photons.len() / 2;
+    photons.select_nth_unstable_by(middle, |a, b| a.position[axis].total_cmp(&b.position[axis]));
+    split_axes[middle] = axis;
+
+    let (left, right) = photons.split_at_mut(middle);
+    let (left_split_axes, right_split_axes) = split_axes.split_at_mut(middle);
+    build(left, left_split_axes);
+    build(&mut right[1..], &mut right_split_axes[1..]);
+}
+
+fn search<F: FnMut(&Photon)>(
+    photons: &[Photon],
+    split_axes: &[u8],
+    point: Vec3,
+    radius_squared: f64,
+    f: &mut F,
+) {
+    if photons.is_empty() {
+        return;
+    }
+
+    let middle = photons.len() / 2;
+    let photon = &photons[middle];
+    if (photon.position - point).length_squared() < radius_squared {
+        f(photon);
+    }
+
+    let axis = split_axes[middle];
+    let distance = point[axis] - photon.position[axis];
+    let left = (&photons[..middle], &split_axes[..middle]);
+    let right = (&photons[middle + 1..], &split_axes[middle + 1..]);
+    let (near, far) = if distance < 0.0 {
+        (left, right)
+    } else {
+        (right, left)
+    };
+
+    search(near.0, near.1, point, radius_squared, f);
+    // The other side can only have photons inside the radius if the splitting plane is
+    if to_f64(distance * distance) < radius_squared {
+        search(far.0, far.1, point, radius_squared, f);
+    }
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_finds_same_photons_as_brute_force() {
+        let photons: Vec<Photon> = (0..500)
+            .map(|_| Photon {
+                position: Vec3::random_min_max(-10.0, 10.0),
+                incoming: Vec3::new(0.0, 1.0, 0.0),
+                power: Color::new(1.0, 1.0, 1.0),
+            })
+            .collect();
+        let map = PhotonMap::new(photons.clone());
+
+        for _ in 0..50 {
+            let point = Vec3::random_min_max(-10.0, 10.0);
+            let radius = 3.0;
+
+            let mut found = Vec::new();
+            map.for_each_within(point, radius, |photon| found.push(photon.position));
+
+            let expected = photons
+                .iter()
+                .filter(|photon| (photon.position - point).length() < radius)
+                .count();
+            assert_eq!(found.len(), expected);
+            assert!(found
+                .iter()
+                .all(|position| (*position - point).length() < radius));
+        }
+    }
+}
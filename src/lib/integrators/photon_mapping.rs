@@ -0,0 +1,335 @@
+use std::f64::consts::PI;
+use std::time::Instant;
+
+use rayon::prelude::*;
+
+use crate::get_image_coordinates;
+use crate::hittable::{HitRecord, Hittable};
+use crate::integrators::photon_map::{Photon, PhotonMap};
+use crate::integrators::{sample_emission, sample_light, SceneContext, T_MIN};
+use crate::samplers::{RandomSampler, Sampler};
+use crate::scene::Scene;
+use crate::stats::RenderStats;
+use crate::{Color, Ray, Vec3};
+
+/// Stochastic progressive photon mapping
+///
+/// Light that glass focuses onto a diffuse surface (a caustic) is almost never found by paths
+/// from the camera, since they'd have to get to the light through the glass by chance.
+/// Photon mapping finds it the other way around: it traces photons from the lights, stores
+/// where they land on diffuse surfaces, and estimates the light at a point from the photons
+/// around it.
+///
+/// Every pass traces a ray through each pixel until it hits a diffuse surface, and then a new
+/// batch of photons. The radius around each pixel's hit point shrinks a bit after every pass,
+/// so the blur of the estimate goes away as more passes are added.
+/// There's one pass for every sample per pixel of the scene, and only the lights added with
+/// `SceneBuilder::light` emit photons.
+///
+/// From "Stochastic Progressive Photon Mapping" by Toshiya Hachisuka and Henrik Wann Jensen.
+#[derive(Clone, Debug)]
+pub struct ProgressivePhotonMapper {
+    photons_per_pass: u32,
+    initial_radius: Option<f64>,
+    alpha: f64,
+}
+
+impl ProgressivePhotonMapper {
+    /// 100,000 photons per pass, a radius that starts at 1/500 of the size of the world,
+    /// and an alpha of 2/3
+    pub const fn new() -> Self {
+        Self {
+            photons_per_pass: 100_000,
+            initial_radius: None,
+            alpha: 2.0 / 3.0,
+        }
+    }
+
+    #[must_use]
+    pub const fn photons_per_pass(mut self, photons: u32) -> Self {
+        self.photons_per_pass = photons;
+        self
+    }
+
+    /// Sets the radius that photons are gathered from in the first pass
+    #[must_use]
+    pub const fn initial_radius(mut self, radius: f64) -> Self {
+        self.initial_radius = Some(radius);
+        self
+    }
+
+    /// Sets how much of the photons found in each pass are kept when the radius shrinks,
+    /// between 0 and 1. Lower values shrink the radius faster, which blurs less but is noisier.
+    #[must_use]
+    pub const fn alpha(mut self, alpha: f64) -> Self {
+        self.alpha = alpha;
+        self
+    }
+
+    /// Renders the scene, returning the color of every pixel in the order of
+    /// `get_image_coordinates`
+    pub fn render<T: Hittable>(&self, scene: &Scene<T>) -> Vec<Color> {
+        let (image_width, image_height) = scene.image_size();
+        let passes = scene.samples_per_pixel().max(1);
+        let context = scene.context();
+
+        let initial_radius = self.initial_radius.unwrap_or_else(|| {
+            scene.world().bounding_box((0.0, 1.0)).map_or(1.0, |b_box| {
+                (b_box.maximum - b_box.minimum).length() / 500.0
+            })
+        });
+        let mut pixels =
+            vec![PixelState::new(initial_radius); (image_width * image_height) as usize];
+
+        let start_time = Instant::now();
+        let observer = scene.observer();
+        observer.start(passes as usize);
+
+        for pass in 0..passes {
+            let visible_points: Vec<Option<VisiblePoint>> = pixels
+                .par_iter_mut()
+                .enumerate()
+                .map_init(
+                    || scene.sampler().boxed_clone(),
+                    |sampler, (i, pixel)| {
+                        let (x, y) = get_image_coordinates(i as u32, image_width);
+                        sampler.start_sample((x, y), pass, passes);
+
+                        let (offset_x, offset_y) = sampler.next_2d();
+                        let sample = (f64::from(x) + offset_x, f64::from(y) + offset_y);
+                        let ray = scene.camera_ray(sample, sampler.as_mut());
+
+                        let (light, visible_point) =
+                            trace_camera_ray(&context, ray, sampler.as_mut());
+                        pixel.direct += light;
+
+                        visible_point
+                    },
+                )
+                .collect();
+
+            let photons: Vec<Photon> = (0..self.photons_per_pass)
+                .into_par_iter()
+                .map_init(RandomSampler::new, |sampler, _| {
+                    trace_photon(&context, sampler)
+                })
+                .flatten()
+                .collect();
+            let photon_map = PhotonMap::new(photons);
+
+            pixels
+                .par_iter_mut()
+                .zip(visible_points.par_iter())
+                .for_each(|(pixel, visible_point)| {
+                    if let Some(visible_point) = visible_point {
+                        let (flux, count) = visible_point.gather(&photon_map, pixel.radius);
+                        pixel.add_photons(flux, count, self.alpha);
+                    }
+                });
+
+            observer.step_done();
+        }
+        observer.finish(&RenderStats {
+            duration: start_time.elapsed(),
+            ..RenderStats::default()
+        });
+
+        let photons = f64::from(passes) * f64::from(self.photons_per_pass);
+        pixels
+            .iter()
+            .map(|pixel| {
+                pixel.direct * (1.0 / f64::from(passes))
+                    + pixel.flux * (1.0 / (PI * pixel.radius * pixel.radius * photons))
+            })
+            .collect()
+    }
+}
+
+impl Default for ProgressivePhotonMapper {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// What each pixel keeps between passes
+#[derive(Clone)]
+struct PixelState {
+    radius: f64,
+    /// How many photons the estimate is made of
+    photons: f64,
+    /// Power of the photons inside the radius, scaled down every time the radius shrinks
+    flux: Color,
+    /// Light that the camera rays found by themselves, like lights and the background
+    direct: Color,
+}
+
+impl PixelState {
+    const fn new(radius: f64) -> Self {
+        Self {
+            radius,
+            photons: 0.0,
+            flux: Color::new(0.0, 0.0, 0.0),
+            direct: Color::new(0.0, 0.0, 0.0),
+        }
+    }
+
+    /// Adds the photons found in a pass, and shrinks the radius so that only `alpha`
+    /// of them count towards the amount of photons
+    fn add_photons(&mut self, flux: Color, count: u32, alpha: f64) {
+        if count == 0 {
+            return;
+        }
+
+        let count = f64::from(count);
+        let photons = alpha.mul_add(count, self.photons);
+        let radius = self.radius * (photons / (self.photons + count)).sqrt();
+
+        self.flux = (self.flux + flux) * ((radius * radius) / (self.radius * self.radius));
+        self.photons = photons;
+        self.radius = radius;
+    }
+}
+
+/// Where the camera ray of a pixel hit a diffuse surface
+struct VisiblePoint {
+    record: HitRecord,
+    /// Unit vector towards where the camera ray came from
+    to_camera: Vec3,
+    /// How much of the light at this point gets to the camera
+    throughput: Color,
+}
+
+impl VisiblePoint {
+    /// Returns the power that the photons within `radius` of the point scatter towards the
+    /// camera, and how many of them there are
+    fn gather(&self, photon_map: &PhotonMap, radius: f64) -> (Color, u32) {
+        let record = &self.record;
+        let mut flux = Color::new(0.0, 0.0, 0.0);
+        let mut count = 0;
+
+        photon_map.for_each_within(record.p, radius, |photon| {
+            let cosine = Vec3::dot(&record.normal, &photon.incoming);
+            if cosine <= 0.0 {
+                return;
+            }
+
+            // The power of photons is already per area, so the cosine in `evaluate` isn't needed
+            let scattered = record
+                .material
+                .evaluate(record, &self.to_camera, &photon.incoming);
+            flux += scattered * photon.power * (1.0 / cosine);
+            count += 1;
+        });
+
+        (flux * self.throughput, count)
+    }
+}
+
+/// Follows a camera ray through specular surfaces until it hits a diffuse one.
+/// Returns the light it found on the way, and where it stopped.
+fn trace_camera_ray(
+    scene: &SceneContext,
+    mut ray: Ray,
+    sampler: &mut dyn Sampler,
+) -> (Color, Option<VisiblePoint>) {
+    let mut throughput = Color::new(1.0, 1.0, 1.0);
+    let mut light = Color::new(0.0, 0.0, 0.0);
+
+    for _ in 0..scene.max_depth {
+        let Some(record) = scene.world.hit(&ray, T_MIN, f64::INFINITY) else {
+            return (light + throughput * scene.background, None);
+        };
+
+        light += throughput * record.material.emitted(record.u, record.v, &record.p);
+
+        if !record.material.is_specular() {
+            let visible_point = VisiblePoint {
+                to_camera: -ray.direction.unit_vector(),
+                record,
+                throughput,
+            };
+            return (light, Some(visible_point));
+        }
+
+        let Some(scatter) = record.material.scatter(&ray, &record, sampler) else {
+            return (light, None);
+        };
+        throughput = throughput * scatter.attenuation;
+        ray = scatter.scattered_ray;
+    }
+
+    (light, None)
+}
+
+/// Traces a photon from a random point of the lights, returning every place where it
+/// hit a diffuse surface
+fn trace_photon(scene: &SceneContext, sampler: &mut dyn Sampler) -> Vec<Photon> {
+    let mut photons = Vec::new();
+
+    let Some((record, position_pdf)) = sample_light(scene.lights, sampler) else {
+        return photons;
+    };
+    let Some((direction, direction_pdf)) = sample_emission(&record.normal, sampler) else {
+        return photons;
+    };
+
+    let cosine = Vec3::dot(&record.normal, &direction).abs();
+    let emitted = record.material.emitted(record.u, record.v, &record.p);
+    let initial_power = emitted * (cosine / (position_pdf * direction_pdf));
+    let time = scene.camera.time_from_sample(sampler.next_1d());
+
+    let mut power = initial_power;
+    let mut ray = Ray::new(record.p, direction, time);
+
+    for bounce in 0..scene.max_depth {
+        let Some(hit) = scene.world.hit(&ray, T_MIN, f64::INFINITY) else {
+            break;
+        };
+
+        if !hit.material.is_specular() {
+            photons.push(Photon {
+                position: hit.p,
+                incoming: -ray.direction.unit_vector(),
+                power,
+            });
+        }
+
+        let Some(scatter) = hit.material.scatter(&ray, &hit, sampler) else {
+            break;
+        };
+        power = power * scatter.attenuation;
+        ray = scatter.scattered_ray;
+
+        // Russian roulette, like in `PathTracer`, relative to the power the photon started with
+        if bounce >= 2 {
+            let survival_probability =
+                (power.max_component() / initial_power.max_component()).min(1.0);
+
+            if sampler.next_1d() >= survival_probability {
+                break;
+            }
+            power = power * (1.0 / survival_probability);
+        }
+    }
+
+    photons
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::integrators::tests::{assert_matches_path_tracer, lit_corner};
+    use crate::integrators::PathTracer;
+
+    #[test]
+    fn test_matches_path_tracer() {
+        let scene = lit_corner(PathTracer::new().russian_roulette_depth(None), 256);
+        let photon_mapped = ProgressivePhotonMapper::new()
+            .photons_per_pass(2000)
+            .initial_radius(0.2)
+            .render(&scene);
+
+        assert_matches_path_tracer(&photon_mapped, 256, 1, 0.1);
+    }
+}
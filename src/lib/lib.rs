@@ -12,141 +12,481 @@
 #![allow(clippy::cast_sign_loss)]
 #![allow(clippy::module_name_repetitions)]
 
-use indicatif::{ParallelProgressIterator, ProgressBar, ProgressStyle};
-use rand::Rng;
+use std::time::Instant;
+
 use rayon::prelude::*;
 
+pub use aov::render_aovs;
+use aov::Aovs;
 pub use camera::Camera;
 pub use color::Color;
-use hittable::Hittable;
-pub use ray::Ray;
+use farm::Coordinator;
+use film::{Film, FilmTile};
+use hittable::{HitRecord, Hittable};
+use integrators::{
+    MetropolisLightTransport, PathSample, PathStats, ProgressivePhotonMapper, SceneContext, T_MIN,
+};
+use progress::TileReport;
+pub use ray::{Ray, RayDifferentials};
+use samplers::Sampler;
 use scene::Scene;
-pub use vec3::Vec3;
+use scheduler::TileScheduler;
+use simd::RayPacket;
+use stats::{RayCounters, RenderStats};
+use terminal::TerminalPreview;
+pub use vec3::{Float, Vec3};
 
+pub mod aov;
 mod camera;
 pub mod color;
+pub mod denoise;
+pub mod farm;
+pub mod film;
+pub mod filters;
 pub mod hittable;
 pub mod instances;
+pub mod integrators;
 pub mod materials;
+#[cfg(feature = "preview")]
+pub mod preview;
+pub mod progress;
 mod ray;
+pub mod samplers;
 pub mod scene;
+pub mod scheduler;
+pub mod simd;
+pub mod stats;
 pub mod surfaces;
+pub mod terminal;
 pub mod textures;
 pub mod vec3;
 
 pub fn render<T: Hittable>(scene: &Scene<T>) -> image::ImageBuffer<image::Rgb<u8>, Vec<u8>> {
     let (image_width, image_height) = scene.image_size();
 
-    let bar = ProgressBar::new(u64::from(image_width * image_height));
-    bar.set_style(
-        ProgressStyle::default_bar()
-            .template("[{elapsed_precise}] Rendering {percent}% done. ETA: {eta_precise}"),
-    );
-
-    let rendered_colors = (0..(image_width * image_height))
-        .into_par_iter()
-        .progress_with(bar)
-        .map(|i| {
-            let mut rng = rand::thread_rng();
-            let mut pixel_color = Color::new(0.0, 0.0, 0.0);
+    render_tiled(
+        scene,
+        &rows(image_width, image_height),
+        &TileScheduler::new(),
+    )
+    .map_or_else(
+        || unreachable!("nothing else has its cancellation token"),
+        |(image, _)| image,
+    )
+}
 
-            let (x, y) = get_image_coordinates(i as u32, image_width);
+pub fn render_chunked<T: Hittable>(
+    scene: &Scene<T>,
+) -> image::ImageBuffer<image::Rgb<u8>, Vec<u8>> {
+    render_chunked_with_stats(scene).0
+}
 
-            for _ in 0..scene.samples_per_pixel() {
-                let u = (f64::from(x) + rng.gen::<f64>()) / f64::from(image_width - 1);
-                let v = (f64::from(y) + rng.gen::<f64>()) / f64::from(image_height - 1);
+/// Like `render_chunked`, but also returns statistics about all the paths and rays that were
+/// traced
+pub fn render_chunked_with_stats<T: Hittable>(
+    scene: &Scene<T>,
+) -> (image::ImageBuffer<image::Rgb<u8>, Vec<u8>>, RenderStats) {
+    render_scheduled(scene, &TileScheduler::new())
+        .unwrap_or_else(|| unreachable!("nothing else has its cancellation token"))
+}
 
-                let ray = scene.camera().get_ray(u, v);
-                pixel_color +=
-                    ray.calculate_color(scene.world(), scene.background_color(), scene.max_depth());
-            }
+/// Renders the scene in the tiles of `scheduler`, in its order, calling its callback with
+/// every tile that's done. Returns `None` if it's cancelled before every tile is done.
+pub fn render_scheduled<T: Hittable>(
+    scene: &Scene<T>,
+    scheduler: &TileScheduler,
+) -> Option<(image::RgbImage, RenderStats)> {
+    let (image_width, image_height) = scene.image_size();
 
-            pixel_color
-        })
-        .map(|pixel| image::Rgb(pixel.to_writeable_ints(scene.samples_per_pixel())))
-        .collect::<Vec<image::Rgb<u8>>>();
+    render_tiled(
+        scene,
+        &scheduler.tiles(image_width, image_height),
+        scheduler,
+    )
+}
 
-    let mut rendered_image =
-        image::RgbImage::from_fn(scene.image_size().0, scene.image_size().1, |x, y| {
-            rendered_colors[(y * image_width + x) as usize]
-        });
+/// Renders the scene with progressive photon mapping instead of the integrator of the scene.
+/// The sampler of the scene is only used for the rays from the camera, and its filter and
+/// denoiser aren't used.
+pub fn render_photon_mapped<T: Hittable>(
+    scene: &Scene<T>,
+    photon_mapper: &ProgressivePhotonMapper,
+) -> image::ImageBuffer<image::Rgb<u8>, Vec<u8>> {
+    to_image(scene, &photon_mapper.render(scene))
+}
 
-    image::imageops::flip_vertical_in_place(&mut rendered_image);
+/// Renders the scene with Metropolis light transport instead of the integrator of the scene.
+/// The filter, sampler and denoiser of the scene aren't used.
+pub fn render_metropolis<T: Hittable>(
+    scene: &Scene<T>,
+    metropolis: &MetropolisLightTransport,
+) -> image::ImageBuffer<image::Rgb<u8>, Vec<u8>> {
+    to_image(scene, &metropolis.render(scene))
+}
 
-    rendered_image
+/// Renders the scene with the workers that connect to `coordinator`.
+/// The denoiser of the scene isn't used.
+///
+/// # Errors
+///
+/// If the coordinator can't check for workers
+pub fn render_distributed<T: Hittable>(
+    scene: &Scene<T>,
+    coordinator: &Coordinator,
+) -> std::io::Result<image::ImageBuffer<image::Rgb<u8>, Vec<u8>>> {
+    Ok(to_image(scene, &coordinator.render(scene)?))
 }
 
-pub fn render_chunked<T: Hittable>(
+/// Renders the scene one sample per pixel at a time, drawing the image in the terminal as it
+/// gets better. The denoiser of the scene isn't used.
+pub fn render_in_terminal<T: Hittable>(
     scene: &Scene<T>,
+    preview: &TerminalPreview,
 ) -> image::ImageBuffer<image::Rgb<u8>, Vec<u8>> {
-    const CHUNK_SIZE: u32 = 16;
+    to_image(scene, &preview.render(scene))
+}
 
+fn render_tiled<T: Hittable>(
+    scene: &Scene<T>,
+    tiles: &[Tile],
+    scheduler: &TileScheduler,
+) -> Option<(image::RgbImage, RenderStats)> {
     let (image_width, image_height) = scene.image_size();
 
-    let num_chunks_per_line = if image_width % CHUNK_SIZE == 0 {
-        image_width / CHUNK_SIZE
-    } else {
-        image_width / CHUNK_SIZE + 1
-    };
-    let num_chunks_per_col = if image_height % CHUNK_SIZE == 0 {
-        image_height / CHUNK_SIZE
-    } else {
-        image_height / CHUNK_SIZE + 1
+    let (rendered_colors, stats) = match scene.denoiser() {
+        None => {
+            let (_, film, stats) = render_scheduled_tiles(
+                scene,
+                tiles,
+                scheduler,
+                |samplers, stats, x, y, film_tile| {
+                    sample_pixel(scene, samplers, stats, x, y, film_tile);
+                },
+            )?;
+            (film.colors(), stats)
+        }
+        Some(denoiser) => {
+            // The denoiser needs the features of the first hit of each pixel
+            let (samples, film, stats) = render_scheduled_tiles(
+                scene,
+                tiles,
+                scheduler,
+                |samplers, stats, x, y, film_tile| {
+                    aov::sample_pixel(scene, samplers, stats, x, y, film_tile)
+                },
+            )?;
+
+            let mut aovs = Aovs::from_samples(image_width, image_height, &samples);
+            aovs.beauty = film.colors();
+            (denoiser.denoise(&aovs), stats)
+        }
     };
 
-    let num_chunks = num_chunks_per_line * num_chunks_per_col;
+    Some((to_image(scene, &rendered_colors), stats))
+}
 
-    let bar = ProgressBar::new(u64::from(num_chunks));
-    bar.set_style(
-        ProgressStyle::default_bar()
-            .template("[{elapsed_precise}] Rendering {percent}% done. ETA: {eta_precise}"),
+/// The samplers of a thread, one for each ray of a `RayPacket`
+type Samplers = [Box<dyn Sampler>; 4];
+
+fn new_samplers<T: Hittable>(scene: &Scene<T>) -> Samplers {
+    [(); 4].map(|()| scene.sampler().boxed_clone())
+}
+
+/// Traces all the samples of a pixel, adding them to `film_tile` and their paths to `stats`
+fn sample_pixel<T: Hittable>(
+    scene: &Scene<T>,
+    samplers: &mut Samplers,
+    stats: &mut PathStats,
+    x: u32,
+    y: u32,
+    film_tile: &mut FilmTile,
+) {
+    let context = scene.context();
+    trace_samples(
+        scene,
+        &context,
+        samplers,
+        (x, y),
+        |position, ray, first_hit, sampler| {
+            let path = scene.integrator().sample(
+                ray,
+                first_hit,
+                &context,
+                sampler,
+                film_tile,
+                &mut |_| {},
+            );
+            film_tile.add_sample(position, path.color, scene.filter());
+            stats.add(&path);
+        },
     );
+}
 
-    let rendered_chunks = (0..num_chunks)
-        .into_par_iter()
-        .progress_with(bar)
-        .map_init(rand::thread_rng, |rng, chunk_index| {
-            let chunk_x_offset = (chunk_index % num_chunks_per_line) * CHUNK_SIZE;
-            let chunk_y_offset = (chunk_index / num_chunks_per_line) * CHUNK_SIZE;
+/// Starts the samples of a pixel 4 at a time, one with each sampler, and finds where their
+/// rays from the camera hit together with `Hittable::hit_packet`. Then calls `trace` with the
+/// position of each sample in the image, its ray, where that ray hits first, and its sampler.
+fn trace_samples<T: Hittable>(
+    scene: &Scene<T>,
+    context: &SceneContext,
+    samplers: &mut Samplers,
+    (x, y): (u32, u32),
+    mut trace: impl FnMut((f64, f64), &Ray, Option<HitRecord>, &mut dyn Sampler),
+) {
+    for first_sample in (0..context.samples_per_pixel).step_by(4) {
+        let count = (context.samples_per_pixel - first_sample).min(4);
+        let mut camera_samples = [None; 4];
+        for (i, sample) in (0..count).zip(&mut camera_samples) {
+            let sampler = samplers[i as usize].as_mut();
+            *sample = Some(camera_sample(
+                scene,
+                context,
+                sampler,
+                (x, y),
+                first_sample + i,
+            ));
+        }
+
+        // The last packet of a pixel can have fewer samples. The rest of its rays are copies
+        // of the first one, with an empty range so they aren't tested.
+        let Some((_, first_ray)) = camera_samples[0] else {
+            break;
+        };
+        let rays = camera_samples.map(|sample| sample.map_or(first_ray, |(_, ray)| ray));
+        let mut t_max = camera_samples.map(|sample| sample.map_or(T_MIN, |_| f64::INFINITY));
+        let mut hits = [None, None, None, None];
+        context
+            .world
+            .hit_packet(&RayPacket::new(rays), T_MIN, &mut t_max, &mut hits);
+
+        for ((sample, hit), sampler) in camera_samples
+            .into_iter()
+            .zip(hits)
+            .zip(samplers.iter_mut())
+        {
+            let Some((position, ray)) = sample else {
+                break;
+            };
+            trace(position, &ray, hit, sampler.as_mut());
+        }
+    }
+}
 
-            let mut pixels = [Color::new(0.0, 0.0, 0.0); (CHUNK_SIZE * CHUNK_SIZE) as usize];
+/// Starts sample number `sample_index` of a pixel. Returns where it is in the image and its
+/// ray from the camera.
+fn camera_sample<T: Hittable>(
+    scene: &Scene<T>,
+    context: &SceneContext,
+    sampler: &mut dyn Sampler,
+    (x, y): (u32, u32),
+    sample_index: u32,
+) -> ((f64, f64), Ray) {
+    sampler.start_sample((x, y), sample_index, context.samples_per_pixel);
 
-            for (i, color) in pixels.iter_mut().enumerate() {
-                let (in_chunk_x, in_chunk_y) = get_image_coordinates(i as u32, CHUNK_SIZE);
+    let (offset_x, offset_y) = sampler.next_2d();
+    let position = (f64::from(x) + offset_x, f64::from(y) + offset_y);
 
-                let (pixel_x, pixel_y) = (chunk_x_offset + in_chunk_x, chunk_y_offset + in_chunk_y);
+    (position, scene.camera_ray(position, sampler))
+}
+
+/// Traces sample number `sample_index` of a pixel with the integrator of the scene,
+/// and adds it to `film_tile`
+fn trace_sample<T: Hittable>(
+    scene: &Scene<T>,
+    context: &SceneContext,
+    sampler: &mut dyn Sampler,
+    x: u32,
+    y: u32,
+    sample_index: u32,
+    film_tile: &mut FilmTile,
+) -> PathSample {
+    let (position, ray) = camera_sample(scene, context, sampler, (x, y), sample_index);
+    let first_hit = context.world.hit(&ray, T_MIN, f64::INFINITY);
+    let path = scene
+        .integrator()
+        .sample(&ray, first_hit, context, sampler, film_tile, &mut |_| {});
+
+    film_tile.add_sample(position, path.color, scene.filter());
+
+    path
+}
+
+/// Renders a single sample of every pixel, the sample number `sample_index`.
+/// Returns the color of every pixel in the order of `get_image_coordinates`.
+fn render_pass<T: Hittable>(scene: &Scene<T>, sample_index: u32) -> Vec<Color> {
+    let (image_width, image_height) = scene.image_size();
+    let mut film = Film::new(image_width, image_height);
 
-                if pixel_x < image_width && pixel_y < image_height {
-                    for _ in 0..scene.samples_per_pixel() {
-                        let u =
-                            (f64::from(pixel_x) + rng.gen::<f64>()) / f64::from(image_width - 1);
-                        let v =
-                            (f64::from(pixel_y) + rng.gen::<f64>()) / f64::from(image_height - 1);
+    // Light splatted by the integrator is divided by the samples per pixel, and every pass
+    // only has one
+    let context = SceneContext {
+        samples_per_pixel: 1,
+        ..scene.context()
+    };
 
-                        let ray = scene.camera().get_ray(u, v);
-                        *color += ray.calculate_color(
-                            scene.world(),
-                            scene.background_color(),
-                            scene.max_depth(),
+    let film_tiles: Vec<FilmTile> = rows(image_width, image_height)
+        .par_iter()
+        .map_init(
+            || scene.sampler().boxed_clone(),
+            |sampler, tile| {
+                let mut film_tile = film.tile(tile.min, tile.max, scene.filter());
+                for y in tile.min.1..tile.max.1 {
+                    for x in tile.min.0..tile.max.0 {
+                        trace_sample(
+                            scene,
+                            &context,
+                            sampler.as_mut(),
+                            x,
+                            y,
+                            sample_index,
+                            &mut film_tile,
                         );
                     }
                 }
-            }
+                film_tile
+            },
+        )
+        .collect();
+
+    for film_tile in &film_tiles {
+        film.merge_tile(film_tile);
+    }
 
-            pixels
+    film.colors()
+}
+
+/// A rectangle of pixels that's rendered by a single thread,
+/// from `min` up to (but not including) `max`
+#[derive(Clone, Copy)]
+struct Tile {
+    min: (u32, u32),
+    max: (u32, u32),
+}
+
+/// Splits the image in tiles that are one row tall
+fn rows(image_width: u32, image_height: u32) -> Vec<Tile> {
+    (0..image_height)
+        .map(|y| Tile {
+            min: (0, y),
+            max: (image_width, y + 1),
         })
-        .collect::<Vec<[Color; (CHUNK_SIZE * CHUNK_SIZE) as usize]>>();
+        .collect()
+}
 
-    let mut rendered_image =
-        image::RgbImage::from_fn(scene.image_size().0, scene.image_size().1, |x, y| {
-            let chunk_index = (y / CHUNK_SIZE) * num_chunks_per_line + x / CHUNK_SIZE;
-            let inner_index = (y % CHUNK_SIZE) * CHUNK_SIZE + x % CHUNK_SIZE;
+/// Renders every tile in parallel, calling `render_pixel` for each of their pixels.
+/// Returns the values returned by `render_pixel`, in the order of `get_image_coordinates`,
+/// the film with every sample that was added to the tiles, and the stats of every path.
+fn render_tiles<T, P, F>(
+    scene: &Scene<T>,
+    tiles: &[Tile],
+    render_pixel: F,
+) -> (Vec<P>, Film, RenderStats)
+where
+    T: Hittable,
+    P: Send,
+    F: Fn(&mut Samplers, &mut PathStats, u32, u32, &mut FilmTile) -> P + Send + Sync,
+{
+    render_scheduled_tiles(scene, tiles, &TileScheduler::new(), render_pixel)
+        .unwrap_or_else(|| unreachable!("nothing else has its cancellation token"))
+}
 
-            image::Rgb(
-                rendered_chunks[chunk_index as usize][inner_index as usize]
-                    .to_writeable_ints(scene.samples_per_pixel()),
-            )
-        });
+/// Like `render_tiles`, but starts the tiles in the order they're in, calls the callback of
+/// `scheduler` with every tile that's done, and returns `None` if it's cancelled
+fn render_scheduled_tiles<T, P, F>(
+    scene: &Scene<T>,
+    tiles: &[Tile],
+    scheduler: &TileScheduler,
+    render_pixel: F,
+) -> Option<(Vec<P>, Film, RenderStats)>
+where
+    T: Hittable,
+    P: Send,
+    F: Fn(&mut Samplers, &mut PathStats, u32, u32, &mut FilmTile) -> P + Send + Sync,
+{
+    let (image_width, image_height) = scene.image_size();
+    let mut film = Film::new(image_width, image_height);
+    let start_time = Instant::now();
+    let observer = scene.observer();
+
+    observer.start(tiles.len());
+
+    // Bridged, so threads take the next tile in order when they're done with one,
+    // instead of each one starting from a different part of the list
+    let rendered_tiles = tiles
+        .iter()
+        .enumerate()
+        .par_bridge()
+        .map_init(
+            || new_samplers(scene),
+            |samplers, (index, tile)| {
+                let tile_start_time = Instant::now();
+                let counters = RayCounters::current_thread();
+                let mut film_tile = film.tile(tile.min, tile.max, scene.filter());
+                let mut tile_paths = PathStats::default();
+
+                let pixels = (tile.min.1..tile.max.1)
+                    .flat_map(|y| (tile.min.0..tile.max.0).map(move |x| (x, y)))
+                    .map(|(x, y)| {
+                        (!scheduler.is_cancelled())
+                            .then(|| render_pixel(samplers, &mut tile_paths, x, y, &mut film_tile))
+                    })
+                    .collect::<Option<Vec<P>>>()?;
+
+                let tile_stats = RenderStats {
+                    paths: tile_paths,
+                    counters: RayCounters::current_thread().since(&counters),
+                    duration: tile_start_time.elapsed(),
+                };
+                scheduler.finish_tile(*tile, &film_tile);
+                observer.tile_done(&TileReport {
+                    min: tile.min,
+                    max: tile.max,
+                    duration: tile_stats.duration,
+                    counters: tile_stats.counters,
+                });
+
+                Some((index, pixels, film_tile, tile_stats))
+            },
+        )
+        .collect::<Option<Vec<(usize, Vec<P>, FilmTile, RenderStats)>>>()?;
+
+    // Tiles that finished right when it was cancelled don't make it complete
+    if scheduler.is_cancelled() {
+        return None;
+    }
+
+    let mut pixels: Vec<Option<P>> = (0..(image_width * image_height)).map(|_| None).collect();
+    let mut stats = RenderStats::default();
+
+    for (index, tile_pixels, film_tile, tile_stats) in rendered_tiles {
+        let tile = tiles[index];
+        film.merge_tile(&film_tile);
+        stats.merge(&tile_stats);
+
+        let tile_width = tile.max.0 - tile.min.0;
+        for (i, pixel) in tile_pixels.into_iter().enumerate() {
+            let (x, y) = get_image_coordinates(i as u32, tile_width);
+            let (x, y) = (tile.min.0 + x, tile.min.1 + y);
+
+            pixels[(y * image_width + x) as usize] = Some(pixel);
+        }
+    }
+    stats.duration = start_time.elapsed();
+    observer.finish(&stats);
+
+    // The tiles cover the whole image, so every pixel has a value
+    Some((pixels.into_iter().flatten().collect(), film, stats))
+}
+
+/// Turns the color of every pixel, in the order of `get_image_coordinates`, into an image
+fn to_image<T: Hittable>(
+    scene: &Scene<T>,
+    colors: &[Color],
+) -> image::ImageBuffer<image::Rgb<u8>, Vec<u8>> {
+    let (image_width, image_height) = scene.image_size();
+
+    let mut rendered_image = image::RgbImage::from_fn(image_width, image_height, |x, y| {
+        image::Rgb(colors[(y * image_width + x) as usize].to_writeable_ints(1))
+    });
 
     image::imageops::flip_vertical_in_place(&mut rendered_image);
 
@@ -163,6 +503,85 @@ pub const fn get_image_coordinates(i: u32, width: u32) -> (u32, u32) {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::hittable::HittableList;
+    use crate::samplers::SobolSampler;
+    use crate::scene::SceneBuilder;
+    use crate::surfaces::Sphere;
+
+    #[test]
+    fn test_camera_packets() {
+        let mut world = HittableList::new();
+        for x in -3..=3 {
+            for y in -3..=3 {
+                world.push(Sphere::new(
+                    Vec3::new(f64::from(x), f64::from(y), 0.0),
+                    0.4,
+                    Color::new(0.5, 0.5, 0.5).lambertian(),
+                ));
+            }
+        }
+        let camera = Camera::new(
+            Vec3::new(0.0, 0.0, 10.0),
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            40.0,
+            1.0,
+            0.0,
+            1.0,
+            (0.0, 1.0),
+        );
+        // Not a multiple of 4, so the last packet of every pixel isn't full
+        let scene = SceneBuilder::new(world.into_bvh((0.0, 1.0)), camera, 1.0)
+            .image_width(16)
+            .samples_per_pixel(6)
+            .max_depth(3)
+            .sampler(SobolSampler::new())
+            .build();
+
+        let film = Film::new(16, 16);
+        let context = scene.context();
+        let mut samplers = new_samplers(&scene);
+        let mut packets = film.tile((0, 0), (16, 16), scene.filter());
+        let mut one_by_one = film.tile((0, 0), (16, 16), scene.filter());
+        let (mut packet_stats, mut stats) = (PathStats::default(), PathStats::default());
+
+        let start = RayCounters::current_thread();
+        for (x, y) in (0..16).flat_map(|y| (0..16).map(move |x| (x, y))) {
+            sample_pixel(&scene, &mut samplers, &mut packet_stats, x, y, &mut packets);
+        }
+        let packet_counters = RayCounters::current_thread().since(&start);
+
+        let start = RayCounters::current_thread();
+        for (x, y) in (0..16).flat_map(|y| (0..16).map(move |x| (x, y))) {
+            for sample_index in 0..6 {
+                let sampler = samplers[0].as_mut();
+                let path = trace_sample(
+                    &scene,
+                    &context,
+                    sampler,
+                    x,
+                    y,
+                    sample_index,
+                    &mut one_by_one,
+                );
+                stats.add(&path);
+            }
+        }
+        let counters = RayCounters::current_thread().since(&start);
+
+        // The same image, and the hits of the camera rays aren't looked for again
+        let colors = |tile: &FilmTile| {
+            let mut film = Film::new(16, 16);
+            film.merge_tile(tile);
+            film.colors()
+                .iter()
+                .map(|color| (color.r(), color.g(), color.b()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(colors(&packets), colors(&one_by_one));
+        assert_eq!(packet_stats, stats);
+        assert_eq!(packet_counters.rays, counters.rays);
+    }
 
     #[test]
     fn test_get_image_coordinates() {
@@ -0,0 +1,197 @@
+use std::f64::consts::{PI, TAU};
+
+use crate::hittable::HitRecord;
+use crate::materials::{Material, ScatterRecord};
+use crate::samplers::Sampler;
+use crate::Color;
+use crate::Ray;
+use crate::Vec3;
+
+/// A metal that's rougher in one direction along the surface than in the other, like
+/// brushed metal, whose reflections are stretched across the brushing.
+///
+/// The surface is made of tiny mirrors with a GGX distribution of normals. The directions
+/// are from the tangent of the `HitRecord`, so surfaces that don't set it get an arbitrary one.
+#[derive(Clone)]
+pub struct AnisotropicMetal {
+    pub albedo: Color,
+    /// The GGX alpha along the tangent and along the bitangent
+    alpha: (f64, f64),
+}
+
+impl AnisotropicMetal {
+    /// The roughnesses go from 0 to 1 like the fuzz of `Metal`. Brushed metal is smooth
+    /// along the brushing, so it has a low roughness along the tangent and a higher one across.
+    pub fn new(albedo: Color, along_tangent: f64, across_tangent: f64) -> Self {
+        // Perfect mirrors can't be evaluated, so they're made a bit rough
+        let alpha = |roughness: f64| roughness.clamp(0.01, 1.0).powi(2);
+        Self {
+            albedo,
+            alpha: (alpha(along_tangent), alpha(across_tangent)),
+        }
+    }
+
+    /// `direction` in the tangent frame of the hit, with the normal as z
+    fn to_local(record: &HitRecord, direction: &Vec3) -> Vec3 {
+        let (tangent, bitangent) = record.tangent_frame();
+        Vec3::new(
+            Vec3::dot(direction, &tangent),
+            Vec3::dot(direction, &bitangent),
+            Vec3::dot(direction, &record.normal),
+        )
+    }
+
+    /// How much of the surface has the local `normal`, per solid angle
+    fn distribution(&self, normal: &Vec3) -> f64 {
+        let (along, across) = self.alpha;
+        let tangent_slope = normal.x() / along;
+        let bitangent_slope = normal.y() / across;
+        let denominator = normal.z().mul_add(
+            normal.z(),
+            tangent_slope.mul_add(tangent_slope, bitangent_slope * bitangent_slope),
+        );
+        1.0 / (PI * along * across * denominator * denominator)
+    }
+
+    /// The Smith shadowing function of the distribution for the local `direction`
+    fn lambda(&self, direction: &Vec3) -> f64 {
+        let (along, across) = self.alpha;
+        let tangent_part = along * direction.x();
+        let bitangent_part = across * direction.y();
+        let tan_squared = tangent_part.mul_add(tangent_part, bitangent_part * bitangent_part);
+        0.5 * ((1.0 + tan_squared / (direction.z() * direction.z())).sqrt() - 1.0)
+    }
+
+    /// Schlick's approximation with the albedo as the reflectance straight on
+    fn fresnel(&self, cosine: f64) -> Color {
+        let weight = (1.0 - cosine).powi(5);
+        Color::new(
+            (1.0 - self.albedo.r()).mul_add(weight, self.albedo.r()),
+            (1.0 - self.albedo.g()).mul_add(weight, self.albedo.g()),
+            (1.0 - self.albedo.b()).mul_add(weight, self.albedo.b()),
+        )
+    }
+}
+
+impl Material for AnisotropicMetal {
+    fn scatter(
+        &self,
+        ray: &Ray,
+        record: &HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
+        let outgoing = -ray.direction.unit_vector();
+
+        // Sampling the slopes of an unstretched surface and stretching them picks the
+        // normals of the tiny mirrors as often as they're seen from above
+        let (u, v) = sampler.next_2d();
+        let slope = (u / (1.0 - u)).sqrt();
+        let angle = TAU * v;
+        let (tangent, bitangent) = record.tangent_frame();
+        let mirror_normal = (tangent * (-self.alpha.0 * slope * angle.cos())
+            + bitangent * (-self.alpha.1 * slope * angle.sin())
+            + record.normal)
+            .unit_vector();
+
+        let incoming = Vec3::reflect(&-outgoing, &mirror_normal);
+        if Vec3::dot(&incoming, &record.normal) <= 0.0 {
+            return None;
+        }
+
+        let pdf = self.pdf(record, &outgoing, &incoming);
+        if pdf <= 0.0 {
+            return None;
+        }
+        Some(ScatterRecord::new(
+            self.evaluate(record, &outgoing, &incoming) * (1.0 / pdf),
+            Ray::new(record.p, incoming, ray.time),
+        ))
+    }
+
+    fn albedo(&self, _record: &HitRecord) -> Color {
+        self.albedo
+    }
+
+    fn is_specular(&self) -> bool {
+        false
+    }
+
+    fn evaluate(&self, record: &HitRecord, outgoing: &Vec3, incoming: &Vec3) -> Color {
+        let local_outgoing = Self::to_local(record, outgoing);
+        let local_incoming = Self::to_local(record, incoming);
+        if local_outgoing.z() <= 0.0 || local_incoming.z() <= 0.0 {
+            return Color::new(0.0, 0.0, 0.0);
+        }
+        let halfway = (local_outgoing + local_incoming).unit_vector();
+
+        let shadowing = 1.0 / (1.0 + self.lambda(&local_outgoing) + self.lambda(&local_incoming));
+        // The cosine of `incoming` cancels out with the one in the denominator
+        self.fresnel(Vec3::dot(&local_incoming, &halfway))
+            * (self.distribution(&halfway) * shadowing / (4.0 * local_outgoing.z()))
+    }
+
+    fn pdf(&self, record: &HitRecord, outgoing: &Vec3, incoming: &Vec3) -> f64 {
+        let local_outgoing = Self::to_local(record, outgoing);
+        let local_incoming = Self::to_local(record, incoming);
+        if local_outgoing.z() <= 0.0 || local_incoming.z() <= 0.0 {
+            return 0.0;
+        }
+        let halfway = (local_outgoing + local_incoming).unit_vector();
+
+        // Changing from the density of mirror normals to the one of reflected directions
+        self.distribution(&halfway) * halfway.z() / (4.0 * Vec3::dot(&local_outgoing, &halfway))
+    }
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::samplers::RandomSampler;
+
+    fn record() -> HitRecord {
+        let mut record = HitRecord::facing_up(Arc::new(AnisotropicMetal::new(
+            Color::new(1.0, 1.0, 1.0),
+            0.5,
+            0.5,
+        )));
+        record.set_tangent(Vec3::new(1.0, 0.0, 0.0));
+        record
+    }
+
+    #[test]
+    fn test_anisotropic() {
+        let record = record();
+        let metal = AnisotropicMetal::new(Color::new(0.9, 0.9, 0.9), 0.1, 0.6);
+        let outgoing = Vec3::new(0.0, 1.0, 0.0);
+
+        // The reflections spread out more across the tangent than along it
+        let tilted = |x: f64, z: f64| Vec3::new(x, 1.0, z).unit_vector();
+        let along = metal.evaluate(&record, &outgoing, &tilted(0.3, 0.0));
+        let across = metal.evaluate(&record, &outgoing, &tilted(0.0, 0.3));
+        assert!(across.r() > 10.0 * along.r());
+
+        // The density of the scattered directions integrates to how often rays aren't
+        // reflected under the surface
+        let rough = AnisotropicMetal::new(Color::new(0.9, 0.9, 0.9), 0.4, 0.7);
+        let mut sampler = RandomSampler::new();
+        let count = 200_000;
+        let total: f64 = (0..count)
+            .map(|_| {
+                let direction = Vec3::unit_vector_from_sample(sampler.next_2d());
+                let incoming = Vec3::new(direction.x(), direction.y().abs(), direction.z());
+                rough.pdf(&record, &outgoing, &incoming) * 2.0 * PI
+            })
+            .sum();
+        let ray = Ray::new(Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, -1.0, 0.0), 0.0);
+        let scattered = (0..count)
+            .filter_map(|_| rough.scatter(&ray, &record, &mut sampler))
+            .inspect(|scatter| assert!(scatter.scattered_ray.direction.y() > 0.0))
+            .count();
+        let integral = total / f64::from(count);
+        let fraction = f64::from(u32::try_from(scattered).unwrap()) / f64::from(count);
+        assert!((integral - fraction).abs() < 0.02, "{integral} {fraction}");
+    }
+}
@@ -0,0 +1,84 @@
+use crate::hittable::HitRecord;
+use crate::materials::{Dielectric, Material, ScatterRecord};
+use crate::samplers::Sampler;
+use crate::Color;
+use crate::Ray;
+use crate::Vec3;
+
+/// A thin clear coat, like varnish or the clear coat of car paint, over a `base` material.
+///
+/// Rays are reflected off the coat as often as the Fresnel reflectance of the coat says,
+/// which is much more often at grazing angles, and scattered by the base the rest of the time.
+#[derive(Clone)]
+pub struct Coated<M: Material> {
+    base: M,
+    refraction_index: f64,
+    /// How blurry the reflections of the coat are, from 0 to 1 like the fuzz of `Metal`
+    roughness: f64,
+}
+
+impl<M: Material> Coated<M> {
+    /// A smooth coat
+    pub const fn new(base: M, refraction_index: f64) -> Self {
+        Self {
+            base,
+            refraction_index,
+            roughness: 0.0,
+        }
+    }
+
+    #[must_use]
+    pub const fn roughness(mut self, roughness: f64) -> Self {
+        self.roughness = roughness.clamp(0.0, 1.0);
+        self
+    }
+}
+
+impl<M: Material> Material for Coated<M> {
+    fn scatter(
+        &self,
+        ray: &Ray,
+        record: &HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
+        // The coat is on the outside
+        if !record.front_face {
+            return self.base.scatter(ray, record, sampler);
+        }
+
+        let unit_direction = ray.direction.unit_vector();
+        let cosine = Vec3::dot(&-unit_direction, &record.normal).min(1.0);
+        let reflectance = Dielectric::reflectance(cosine, self.refraction_index);
+
+        if sampler.next_1d() >= reflectance {
+            return self.base.scatter(ray, record, sampler);
+        }
+
+        let reflected = Vec3::reflect(&unit_direction, &record.normal)
+            + Vec3::in_unit_sphere_from_sample(sampler.next_2d(), sampler.next_1d())
+                * self.roughness;
+        // Rough reflections that would go into the surface are absorbed, like with `Metal`
+        if Vec3::dot(&reflected, &record.normal) <= 0.0 {
+            return None;
+        }
+
+        Some(ScatterRecord::new(
+            Color::new(1.0, 1.0, 1.0),
+            Ray::new(record.p, reflected, ray.time),
+        ))
+    }
+
+    fn emitted(&self, u: f64, v: f64, point: &Vec3) -> Color {
+        self.base.emitted(u, v, point)
+    }
+
+    fn albedo(&self, record: &HitRecord) -> Color {
+        self.base.albedo(record)
+    }
+
+    fn alpha(&self, record: &HitRecord) -> f64 {
+        self.base.alpha(record)
+    }
+
+    // The reflections of the coat are in exact directions, so it's always specular
+}
@@ -1,7 +1,6 @@
-use rand::Rng;
-
 use crate::hittable::HitRecord;
 use crate::materials::{Material, ScatterRecord};
+use crate::samplers::Sampler;
 use crate::Color;
 use crate::Ray;
 use crate::Vec3;
@@ -15,7 +14,8 @@ impl Dielectric {
     pub const fn new(refraction_index: f64) -> Self {
         Self { refraction_index }
     }
-    fn reflectance(cosine: f64, refraction_index: f64) -> f64 {
+    /// The fraction of light that's reflected at an angle with `cosine` to the normal
+    pub(super) fn reflectance(cosine: f64, refraction_index: f64) -> f64 {
         // Use Schlick's approximation for reflectance.
         // let r0 = ((1.0 - refraction_index) / (1.0 + refraction_index)).powi(2);
         let r0 = ((1.0 - refraction_index) / (1.0 + refraction_index))
@@ -27,7 +27,12 @@ impl Dielectric {
 }
 
 impl Material for Dielectric {
-    fn scatter(&self, ray: &Ray, record: &HitRecord) -> Option<ScatterRecord> {
+    fn scatter(
+        &self,
+        ray: &Ray,
+        record: &HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
         let refraction_ratio = if record.front_face {
             1.0 / self.refraction_index
         } else {
@@ -38,14 +43,14 @@ impl Material for Dielectric {
         let cos_theta = Vec3::dot(&-unit_direction, &record.normal).min(1.0);
         let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
         let cannot_refract = (refraction_ratio * sin_theta) > 1.0;
+        let reflect_sample = sampler.next_1d();
 
-        let direction = if cannot_refract
-            || Self::reflectance(cos_theta, refraction_ratio) > rand::thread_rng().gen::<f64>()
-        {
-            Vec3::reflect(&unit_direction, &record.normal)
-        } else {
-            Vec3::refract(&unit_direction, &record.normal, refraction_ratio)
-        };
+        let direction =
+            if cannot_refract || Self::reflectance(cos_theta, refraction_ratio) > reflect_sample {
+                Vec3::reflect(&unit_direction, &record.normal)
+            } else {
+                Vec3::refract(&unit_direction, &record.normal, refraction_ratio)
+            };
 
         let scatter_record = ScatterRecord::new(
             Color::new(1.0, 1.0, 1.0),
@@ -54,4 +59,8 @@ impl Material for Dielectric {
 
         Some(scatter_record)
     }
+
+    fn albedo(&self, _record: &HitRecord) -> Color {
+        Color::new(1.0, 1.0, 1.0)
+    }
 }
@@ -1,5 +1,6 @@
 use crate::hittable::HitRecord;
 use crate::materials::{Material, ScatterRecord};
+use crate::samplers::Sampler;
 use crate::textures::Texture;
 use crate::Color;
 use crate::Ray;
@@ -17,7 +18,12 @@ impl<T: Texture> DiffuseLight<T> {
 }
 
 impl<T: Texture> Material for DiffuseLight<T> {
-    fn scatter(&self, _ray: &Ray, _record: &HitRecord) -> Option<ScatterRecord> {
+    fn scatter(
+        &self,
+        _ray: &Ray,
+        _record: &HitRecord,
+        _sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
         None
     }
 
@@ -1,4 +1,4 @@
-use crate::{hittable::HitRecord, textures::Texture, Ray, Vec3};
+use crate::{hittable::HitRecord, samplers::Sampler, textures::Texture, Color, Ray, Vec3};
 
 use super::{Material, ScatterRecord};
 
@@ -13,10 +13,23 @@ impl<T: Texture> Isotropic<T> {
     }
 }
 impl<T: Texture> Material for Isotropic<T> {
-    fn scatter(&self, ray: &Ray, record: &HitRecord) -> Option<ScatterRecord> {
-        let scattered_ray = Ray::new(record.p, Vec3::random_in_unit_sphere(), ray.time);
-        let attenuation = self.albedo.value(record.u, record.v, &record.p);
+    fn scatter(
+        &self,
+        ray: &Ray,
+        record: &HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
+        let scattered_ray = Ray::new(
+            record.p,
+            Vec3::unit_vector_from_sample(sampler.next_2d()),
+            ray.time,
+        );
+        let attenuation = self.albedo.filtered_value(record);
 
         Some(ScatterRecord::new(attenuation, scattered_ray))
     }
+
+    fn albedo(&self, record: &HitRecord) -> Color {
+        self.albedo.filtered_value(record)
+    }
 }
@@ -1,6 +1,10 @@
+use std::f64::consts::PI;
+
 use crate::hittable::HitRecord;
 use crate::materials::{Material, ScatterRecord};
+use crate::samplers::Sampler;
 use crate::textures::Texture;
+use crate::Color;
 use crate::Ray;
 use crate::Vec3;
 
@@ -17,8 +21,14 @@ impl<T: Texture + Clone> Lambertian<T> {
 }
 
 impl<T: Texture + Clone> Material for Lambertian<T> {
-    fn scatter(&self, ray: &Ray, record: &HitRecord) -> Option<ScatterRecord> {
-        let mut scatter_direction = record.normal + Vec3::random_unit_vector();
+    fn scatter(
+        &self,
+        ray: &Ray,
+        record: &HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
+        let mut scatter_direction =
+            record.normal + Vec3::unit_vector_from_sample(sampler.next_2d());
 
         if scatter_direction.near_zero() {
             scatter_direction = record.normal;
@@ -26,7 +36,37 @@ impl<T: Texture + Clone> Material for Lambertian<T> {
 
         Some(ScatterRecord {
             scattered_ray: Ray::new(record.p, scatter_direction, ray.time),
-            attenuation: self.albedo.value(record.u, record.v, &record.p),
+            attenuation: self.albedo.filtered_value(record),
         })
     }
+
+    fn albedo(&self, record: &HitRecord) -> Color {
+        self.albedo.filtered_value(record)
+    }
+
+    fn alpha(&self, record: &HitRecord) -> f64 {
+        self.albedo.alpha(record.u, record.v, &record.p)
+    }
+
+    fn is_specular(&self) -> bool {
+        false
+    }
+
+    fn evaluate(&self, record: &HitRecord, outgoing: &Vec3, incoming: &Vec3) -> Color {
+        // Only reflects light on the side of the surface it was hit from
+        if Vec3::dot(outgoing, &record.normal) <= 0.0 {
+            return Color::new(0.0, 0.0, 0.0);
+        }
+        let cosine = Vec3::dot(incoming, &record.normal);
+        if cosine <= 0.0 {
+            return Color::new(0.0, 0.0, 0.0);
+        }
+
+        self.albedo.filtered_value(record) * (cosine / PI)
+    }
+
+    fn pdf(&self, record: &HitRecord, _outgoing: &Vec3, incoming: &Vec3) -> f64 {
+        // Scattered rays are a unit vector away from the normal, which is a cosine distribution
+        Vec3::dot(incoming, &record.normal).max(0.0) / PI
+    }
 }
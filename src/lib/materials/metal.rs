@@ -1,5 +1,6 @@
 use crate::hittable::HitRecord;
 use crate::materials::{Material, ScatterRecord};
+use crate::samplers::Sampler;
 use crate::Color;
 use crate::Ray;
 use crate::Vec3;
@@ -20,14 +21,21 @@ impl Metal {
 }
 
 impl Material for Metal {
-    fn scatter(&self, ray: &Ray, record: &HitRecord) -> Option<ScatterRecord> {
+    fn scatter(
+        &self,
+        ray: &Ray,
+        record: &HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
         let reflected = Vec3::reflect(&ray.direction.unit_vector(), &record.normal);
 
         let scatter_record = ScatterRecord::new(
             self.albedo,
             Ray::new(
                 record.p,
-                reflected + Vec3::random_in_unit_sphere() * self.fuzz,
+                reflected
+                    + Vec3::in_unit_sphere_from_sample(sampler.next_2d(), sampler.next_1d())
+                        * self.fuzz,
                 ray.time,
             ),
         );
@@ -38,4 +46,8 @@ impl Material for Metal {
             None
         }
     }
+
+    fn albedo(&self, _record: &HitRecord) -> Color {
+        self.albedo
+    }
 }
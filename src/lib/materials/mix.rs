@@ -0,0 +1,145 @@
+use crate::hittable::HitRecord;
+use crate::materials::{Material, ScatterRecord};
+use crate::samplers::Sampler;
+use crate::textures::Texture;
+use crate::Color;
+use crate::Ray;
+use crate::Vec3;
+
+/// Blends material `a` into material `b` by the luminance of `mask`, so a mask of 0.2 is
+/// 80% `a` and 20% `b`. Every scatter picks one of them at random with those odds.
+#[derive(Clone)]
+pub struct MixMaterial<A: Material, B: Material, T: Texture> {
+    a: A,
+    b: B,
+    mask: T,
+}
+
+impl<A: Material, B: Material, T: Texture> MixMaterial<A, B, T> {
+    pub const fn new(a: A, b: B, mask: T) -> Self {
+        Self { a, b, mask }
+    }
+
+    fn amount(&self, record: &HitRecord) -> f64 {
+        self.mask.filtered_value(record).luminance().clamp(0.0, 1.0)
+    }
+}
+
+impl<A: Material, B: Material> MixMaterial<A, B, Color> {
+    /// The same `amount` of `b` everywhere
+    pub const fn constant(a: A, b: B, amount: f64) -> Self {
+        Self::new(a, b, Color::new(amount, amount, amount))
+    }
+}
+
+impl<A: Material, B: Material, T: Texture> Material for MixMaterial<A, B, T> {
+    fn scatter(
+        &self,
+        ray: &Ray,
+        record: &HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
+        let mut scatter = if sampler.next_1d() < self.amount(record) {
+            self.b.scatter(ray, record, sampler)?
+        } else {
+            self.a.scatter(ray, record, sampler)?
+        };
+
+        // When both can be evaluated, the attenuation of the whole mix for the direction
+        // that was picked has less noise than the one of the material that picked it
+        if !self.is_specular() {
+            let outgoing = -ray.direction.unit_vector();
+            let incoming = scatter.scattered_ray.direction.unit_vector();
+            let pdf = self.pdf(record, &outgoing, &incoming);
+            if pdf > 0.0 {
+                scatter.attenuation = self.evaluate(record, &outgoing, &incoming) * (1.0 / pdf);
+            }
+        }
+
+        Some(scatter)
+    }
+
+    fn emitted(&self, u: f64, v: f64, point: &Vec3) -> Color {
+        let amount = self.mask.value(u, v, point).luminance().clamp(0.0, 1.0);
+        Color::linear_blend(
+            amount,
+            &self.a.emitted(u, v, point),
+            &self.b.emitted(u, v, point),
+        )
+    }
+
+    fn albedo(&self, record: &HitRecord) -> Color {
+        Color::linear_blend(
+            self.amount(record),
+            &self.a.albedo(record),
+            &self.b.albedo(record),
+        )
+    }
+
+    fn alpha(&self, record: &HitRecord) -> f64 {
+        let amount = self.amount(record);
+        amount.mul_add(self.b.alpha(record), (1.0 - amount) * self.a.alpha(record))
+    }
+
+    /// When either one is, since the mix can't say how likely a direction is without both
+    fn is_specular(&self) -> bool {
+        self.a.is_specular() || self.b.is_specular()
+    }
+
+    fn evaluate(&self, record: &HitRecord, outgoing: &Vec3, incoming: &Vec3) -> Color {
+        Color::linear_blend(
+            self.amount(record),
+            &self.a.evaluate(record, outgoing, incoming),
+            &self.b.evaluate(record, outgoing, incoming),
+        )
+    }
+
+    fn pdf(&self, record: &HitRecord, outgoing: &Vec3, incoming: &Vec3) -> f64 {
+        let amount = self.amount(record);
+        amount.mul_add(
+            self.b.pdf(record, outgoing, incoming),
+            (1.0 - amount) * self.a.pdf(record, outgoing, incoming),
+        )
+    }
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::materials::{Lambertian, Metal};
+    use crate::samplers::RandomSampler;
+
+    #[test]
+    fn test_mix() {
+        let red = Lambertian::new(Color::new(1.0, 0.0, 0.0));
+        let blue = Lambertian::new(Color::new(0.0, 0.0, 1.0));
+        let mix = MixMaterial::constant(red, blue, 0.25);
+        let record = HitRecord::facing_up(Arc::new(Metal::new(Color::new(1.0, 1.0, 1.0), 0.0)));
+
+        let up = Vec3::new(0.0, 1.0, 0.0);
+        let color = mix.evaluate(&record, &up, &up);
+        assert!((color.r() - 0.75 / std::f64::consts::PI).abs() < 1e-9);
+        assert!((color.b() - 0.25 / std::f64::consts::PI).abs() < 1e-9);
+        assert!(!mix.is_specular());
+
+        // Both are diffuse, so every scatter has the attenuation of the whole mix
+        let ray = Ray::new(Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, -1.0, 0.0), 0.0);
+        let scatter = mix
+            .scatter(&ray, &record, &mut RandomSampler::new())
+            .unwrap();
+        assert!((scatter.attenuation.r() - 0.75).abs() < 1e-9);
+
+        // With a mirror the picked material is used as it is
+        let mirror = Metal::new(Color::new(1.0, 1.0, 1.0), 0.0);
+        let mix = MixMaterial::constant(mix, mirror, 0.5);
+        assert!(mix.is_specular());
+        let mirrored = (0..1000)
+            .filter_map(|_| mix.scatter(&ray, &record, &mut RandomSampler::new()))
+            .filter(|scatter| (scatter.attenuation.g() - 1.0).abs() < 1e-9)
+            .count();
+        assert!((400..600).contains(&mirrored));
+    }
+}
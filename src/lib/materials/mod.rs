@@ -1,28 +1,122 @@
+use std::sync::Arc;
+
+use crate::samplers::Sampler;
+use crate::textures::Texture;
 use crate::Vec3;
 
 use super::hittable::HitRecord;
 use super::Color;
 use super::Ray;
 
+mod anisotropic;
+mod coated;
 mod dielectric;
 mod diffuse_light;
 mod isotropic;
 mod lambertian;
 mod metal;
+mod mix;
+mod thin_film;
+mod velvet;
+pub use anisotropic::AnisotropicMetal;
+pub use coated::Coated;
 pub use dielectric::Dielectric;
 pub use diffuse_light::DiffuseLight;
 pub use isotropic::Isotropic;
 pub use lambertian::Lambertian;
 pub use metal::Metal;
+pub use mix::MixMaterial;
+pub use thin_film::ThinFilm;
+pub use velvet::Velvet;
 
 /// A trait that all materials must implement
 ///
 /// Defines the scattering behaviour of that material
 pub trait Material: Send + Sync {
-    fn scatter(&self, ray: &Ray, record: &HitRecord) -> Option<ScatterRecord>;
+    /// Takes the random numbers it needs from `sampler`
+    fn scatter(
+        &self,
+        ray: &Ray,
+        record: &HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord>;
     fn emitted(&self, _u: f64, _v: f64, _point: &Vec3) -> Color {
         Color::new(0.0, 0.0, 0.0)
     }
+    /// The base color of the surface at the hit point, without any lighting
+    fn albedo(&self, _record: &HitRecord) -> Color {
+        Color::new(0.0, 0.0, 0.0)
+    }
+    /// How opaque the surface is at the hit point, from 0 to 1. Only `Cutout` surfaces use it.
+    fn alpha(&self, _record: &HitRecord) -> f64 {
+        1.0
+    }
+    /// Whether `evaluate` and `pdf` can't be used, because `scatter` only sends rays in exact
+    /// directions (like mirrors and glass) or the material doesn't know how likely they are.
+    /// Paths can't be connected through these materials, only scattered.
+    fn is_specular(&self) -> bool {
+        true
+    }
+    /// How much of the light coming from `incoming` is scattered towards `outgoing`,
+    /// including the cosine of `incoming` with the surface.
+    /// Both directions are unit vectors that point away from the hit point.
+    fn evaluate(&self, _record: &HitRecord, _outgoing: &Vec3, _incoming: &Vec3) -> Color {
+        Color::new(0.0, 0.0, 0.0)
+    }
+    /// Probability density, per solid angle, that a ray that came back along `outgoing`
+    /// is scattered towards `incoming`
+    fn pdf(&self, _record: &HitRecord, _outgoing: &Vec3, _incoming: &Vec3) -> f64 {
+        0.0
+    }
+}
+
+impl<T: Material + ?Sized> Material for Arc<T> {
+    fn scatter(
+        &self,
+        ray: &Ray,
+        record: &HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
+        (**self).scatter(ray, record, sampler)
+    }
+    fn emitted(&self, u: f64, v: f64, point: &Vec3) -> Color {
+        (**self).emitted(u, v, point)
+    }
+    fn albedo(&self, record: &HitRecord) -> Color {
+        (**self).albedo(record)
+    }
+    fn alpha(&self, record: &HitRecord) -> f64 {
+        (**self).alpha(record)
+    }
+    fn is_specular(&self) -> bool {
+        (**self).is_specular()
+    }
+    fn evaluate(&self, record: &HitRecord, outgoing: &Vec3, incoming: &Vec3) -> Color {
+        (**self).evaluate(record, outgoing, incoming)
+    }
+    fn pdf(&self, record: &HitRecord, outgoing: &Vec3, incoming: &Vec3) -> f64 {
+        (**self).pdf(record, outgoing, incoming)
+    }
+}
+
+/// Combines materials into new ones, so mixes and coats can be nested in any order
+pub trait Layering {
+    /// Blends `other` in by the luminance of `mask`
+    fn mixed_with<B: Material, T: Texture>(self, other: B, mask: T) -> MixMaterial<Self, B, T>
+    where
+        Self: Material + Sized;
+    /// A clear coat with `refraction_index` over the material
+    fn coated(self, refraction_index: f64) -> Coated<Self>
+    where
+        Self: Material + Sized;
+}
+impl<M: Material + Sized> Layering for M {
+    fn mixed_with<B: Material, T: Texture>(self, other: B, mask: T) -> MixMaterial<Self, B, T> {
+        MixMaterial::new(self, other, mask)
+    }
+    fn coated(self, refraction_index: f64) -> Coated<Self> {
+        Coated::new(self, refraction_index)
+    }
 }
 
 /// A struct to store relevant data of a ray scattering off something
@@ -0,0 +1,168 @@
+use std::f64::consts::PI;
+
+use crate::hittable::HitRecord;
+use crate::materials::{Material, ScatterRecord};
+use crate::samplers::Sampler;
+use crate::Color;
+use crate::Ray;
+use crate::Vec3;
+
+/// The wavelengths, in nanometers, that the red, green and blue reflectances are found at
+const WAVELENGTHS: [f64; 3] = [650.0, 550.0, 450.0];
+
+/// A dielectric with a thin film on it, like a soap bubble or oil on water.
+///
+/// Light reflected off the top of the film and off the bottom interferes, so how much of it is
+/// reflected depends on its wavelength, the angle and the thickness of the film. That's what
+/// makes the colors shift across the surface.
+#[derive(Clone)]
+pub struct ThinFilm {
+    /// In nanometers
+    thickness: f64,
+    film_index: f64,
+    /// Of what's inside the surface, under the film
+    refraction_index: f64,
+}
+
+impl ThinFilm {
+    /// A film with air on both sides, like a soap bubble
+    pub const fn new(thickness: f64, film_index: f64) -> Self {
+        Self {
+            thickness,
+            film_index,
+            refraction_index: 1.0,
+        }
+    }
+
+    #[must_use]
+    pub const fn refraction_index(mut self, refraction_index: f64) -> Self {
+        self.refraction_index = refraction_index;
+        self
+    }
+
+    /// The Fresnel amplitude coefficients, for s and p polarized light, from a medium with
+    /// index `n1` at an angle with `cos1` into one with index `n2` at an angle with `cos2`
+    fn amplitudes(n1: f64, cos1: f64, n2: f64, cos2: f64) -> (f64, f64) {
+        let s = n1.mul_add(cos1, -n2 * cos2) / n1.mul_add(cos1, n2 * cos2);
+        let p = n2.mul_add(cos1, -n1 * cos2) / n2.mul_add(cos1, n1 * cos2);
+        (s, p)
+    }
+
+    /// The fraction of the light of each color that's reflected, for light coming at an angle
+    /// with `cosine` to the normal from outside the surface when `front_face`
+    fn reflectance(&self, cosine: f64, front_face: bool) -> Color {
+        let (outer, inner) = if front_face {
+            (1.0, self.refraction_index)
+        } else {
+            (self.refraction_index, 1.0)
+        };
+
+        // Snell's law for the angles in the film and past it
+        let sin_squared = cosine.mul_add(-cosine, 1.0);
+        let cosine_in = |index: f64| {
+            let sin_squared = (outer / index).powi(2) * sin_squared;
+            (sin_squared < 1.0).then(|| (1.0 - sin_squared).sqrt())
+        };
+        let (Some(film_cosine), Some(inner_cosine)) =
+            (cosine_in(self.film_index), cosine_in(inner))
+        else {
+            // Total internal reflection
+            return Color::new(1.0, 1.0, 1.0);
+        };
+
+        let top = Self::amplitudes(outer, cosine, self.film_index, film_cosine);
+        let bottom = Self::amplitudes(self.film_index, film_cosine, inner, inner_cosine);
+
+        let [red, green, blue] = WAVELENGTHS.map(|wavelength| {
+            // How far out of step the light from the bottom of the film is
+            let phase = 4.0 * PI * self.film_index * self.thickness * film_cosine / wavelength;
+            let airy = |r12: f64, r23: f64| {
+                let interference = 2.0 * r12 * r23 * phase.cos();
+                r12.mul_add(r12, r23.mul_add(r23, interference))
+                    / (r12 * r23).mul_add(r12 * r23, 1.0 + interference)
+            };
+            // Unpolarized light is half of each
+            0.5 * (airy(top.0, bottom.0) + airy(top.1, bottom.1))
+        });
+        Color::new(red, green, blue)
+    }
+}
+
+impl Material for ThinFilm {
+    fn scatter(
+        &self,
+        ray: &Ray,
+        record: &HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
+        let unit_direction = ray.direction.unit_vector();
+        let cosine = Vec3::dot(&-unit_direction, &record.normal).min(1.0);
+        let reflectance = self.reflectance(cosine, record.front_face);
+
+        // Reflected as often as the average color is, and the attenuation makes up for
+        // the colors being reflected more or less than that
+        let chance = (reflectance.r() + reflectance.g() + reflectance.b()) / 3.0;
+        if sampler.next_1d() < chance {
+            return Some(ScatterRecord::new(
+                reflectance * (1.0 / chance),
+                Ray::new(
+                    record.p,
+                    Vec3::reflect(&unit_direction, &record.normal),
+                    ray.time,
+                ),
+            ));
+        }
+
+        let refraction_ratio = if record.front_face {
+            1.0 / self.refraction_index
+        } else {
+            self.refraction_index
+        };
+        let transmittance = Color::new(
+            1.0 - reflectance.r(),
+            1.0 - reflectance.g(),
+            1.0 - reflectance.b(),
+        );
+        Some(ScatterRecord::new(
+            transmittance * (1.0 / (1.0 - chance)),
+            Ray::new(
+                record.p,
+                Vec3::refract(&unit_direction, &record.normal, refraction_ratio),
+                ray.time,
+            ),
+        ))
+    }
+
+    fn albedo(&self, _record: &HitRecord) -> Color {
+        Color::new(1.0, 1.0, 1.0)
+    }
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::Dielectric;
+
+    #[test]
+    fn test_thin_film() {
+        // Without a film it's the reflectance of the surface under it
+        let glass = ThinFilm::new(0.0, 1.33).refraction_index(1.5);
+        let straight = glass.reflectance(1.0, true);
+        assert!((straight.r() - 0.04).abs() < 1e-9);
+        assert!((straight.r() - straight.b()).abs() < 1e-9);
+        assert!((straight.r() - Dielectric::reflectance(1.0, 1.5)).abs() < 1e-9);
+
+        // A soap bubble a quarter of a green wavelength thick reflects green the most
+        let bubble = ThinFilm::new(550.0 / (4.0 * 1.33), 1.33);
+        let color = bubble.reflectance(1.0, true);
+        assert!(color.g() > color.r() && color.g() > color.b());
+        // And half a wavelength thick it doesn't reflect green at all
+        let bubble = ThinFilm::new(550.0 / (2.0 * 1.33), 1.33);
+        assert!(bubble.reflectance(1.0, true).g() < 1e-9);
+
+        // Every color is reflected at grazing angles
+        let grazing = bubble.reflectance(1e-6, true);
+        assert!(grazing.r() > 0.99 && grazing.b() > 0.99);
+    }
+}
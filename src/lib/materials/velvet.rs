@@ -0,0 +1,137 @@
+use std::f64::consts::{PI, TAU};
+
+use crate::hittable::HitRecord;
+use crate::materials::{Material, ScatterRecord};
+use crate::samplers::Sampler;
+use crate::textures::Texture;
+use crate::Color;
+use crate::Ray;
+use crate::Vec3;
+
+/// Cloth like velvet or satin: a matte `base` with a `sheen` on top, the soft glow that the
+/// fibers sticking out of the fabric give it at grazing angles.
+///
+/// The sheen is the "Charlie" distribution of fibers by Estevez and Kulla.
+#[derive(Clone)]
+pub struct Velvet<T: Texture> {
+    pub base: T,
+    pub sheen: Color,
+    /// From 0 to 1, how far from the edges the sheen spreads
+    roughness: f64,
+}
+
+impl<T: Texture> Velvet<T> {
+    /// With a roughness of 0.5
+    pub const fn new(base: T, sheen: Color) -> Self {
+        Self {
+            base,
+            sheen,
+            roughness: 0.5,
+        }
+    }
+
+    #[must_use]
+    pub const fn roughness(mut self, roughness: f64) -> Self {
+        self.roughness = roughness.clamp(0.01, 1.0);
+        self
+    }
+
+    /// How much of the fibers face the `halfway` direction, per solid angle
+    fn distribution(&self, record: &HitRecord, halfway: &Vec3) -> f64 {
+        let cosine = Vec3::dot(halfway, &record.normal);
+        let sin_squared = cosine.mul_add(-cosine, 1.0).max(0.0);
+        let exponent = 1.0 / self.roughness;
+        (2.0 + exponent) * sin_squared.powf(0.5 * exponent) / TAU
+    }
+}
+
+impl<T: Texture> Material for Velvet<T> {
+    fn scatter(
+        &self,
+        ray: &Ray,
+        record: &HitRecord,
+        sampler: &mut dyn Sampler,
+    ) -> Option<ScatterRecord> {
+        // Scattered like `Lambertian`, and weighted by how much the sheen adds
+        let mut direction = record.normal + Vec3::unit_vector_from_sample(sampler.next_2d());
+        if direction.near_zero() {
+            direction = record.normal;
+        }
+
+        let outgoing = -ray.direction.unit_vector();
+        let incoming = direction.unit_vector();
+        let pdf = self.pdf(record, &outgoing, &incoming);
+        if pdf <= 0.0 {
+            return None;
+        }
+        Some(ScatterRecord::new(
+            self.evaluate(record, &outgoing, &incoming) * (1.0 / pdf),
+            Ray::new(record.p, direction, ray.time),
+        ))
+    }
+
+    fn albedo(&self, record: &HitRecord) -> Color {
+        self.base.filtered_value(record)
+    }
+
+    fn alpha(&self, record: &HitRecord) -> f64 {
+        self.base.alpha(record.u, record.v, &record.p)
+    }
+
+    fn is_specular(&self) -> bool {
+        false
+    }
+
+    fn evaluate(&self, record: &HitRecord, outgoing: &Vec3, incoming: &Vec3) -> Color {
+        let outgoing_cosine = Vec3::dot(outgoing, &record.normal);
+        let incoming_cosine = Vec3::dot(incoming, &record.normal);
+        if outgoing_cosine <= 0.0 || incoming_cosine <= 0.0 {
+            return Color::new(0.0, 0.0, 0.0);
+        }
+        let halfway = (outgoing + incoming).unit_vector();
+
+        // Neubelt and Pettineo's visibility term, which is softer than the shadowing of
+        // mirror-like surfaces since the fibers don't hide each other as much
+        let visibility = 1.0
+            / (4.0 * incoming_cosine.mul_add(-outgoing_cosine, incoming_cosine + outgoing_cosine));
+        let sheen = self.distribution(record, &halfway) * visibility;
+
+        (self.base.filtered_value(record) * (1.0 / PI) + self.sheen * sheen) * incoming_cosine
+    }
+
+    fn pdf(&self, record: &HitRecord, _outgoing: &Vec3, incoming: &Vec3) -> f64 {
+        Vec3::dot(incoming, &record.normal).max(0.0) / PI
+    }
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    #[test]
+    fn test_velvet() {
+        let record = HitRecord::facing_up(Arc::new(Color::new(1.0, 1.0, 1.0).lambertian()));
+
+        let black = Color::new(0.0, 0.0, 0.0);
+        let velvet = Velvet::new(black, Color::new(1.0, 1.0, 1.0));
+        let up = Vec3::new(0.0, 1.0, 0.0);
+        let grazing = Vec3::new(1.0, 0.05, 0.0).unit_vector();
+        let tilted = Vec3::new(1.0, 1.0, 0.0).unit_vector();
+
+        // Looked at straight on there's no sheen, and it's brighter the more grazing the view
+        assert!(velvet.evaluate(&record, &up, &up).r() < 1e-9);
+        let side = velvet.evaluate(&record, &grazing, &tilted).r();
+        let above = velvet.evaluate(&record, &tilted, &tilted).r();
+        assert!(side > above && above > 0.0);
+
+        // With no sheen it's lambertian
+        let matte = Velvet::new(Color::new(0.5, 0.5, 0.5), black);
+        let lambertian = Color::new(0.5, 0.5, 0.5).lambertian();
+        let difference = matte.evaluate(&record, &up, &tilted).r()
+            - lambertian.evaluate(&record, &up, &tilted).r();
+        assert!(difference.abs() < 1e-9);
+    }
+}
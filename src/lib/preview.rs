@@ -0,0 +1,165 @@
+//! A window that shows the image while it's being rendered
+//!
+//! Every frame adds one sample to each pixel, and the window shows the average of all of them,
+//! so the image starts noisy and gets cleaner until it has all the samples per pixel of the
+//! scene. Moving the camera starts over. It's drawn by the CPU, so it doesn't need a GPU.
+//!
+//! Controls:
+//! - Drag with the left mouse button, or use the arrow keys, to orbit around the scene
+//! - Drag with the right mouse button, or use the arrow keys while holding shift, to pan
+//! - Scroll, or use `+` and `-`, to zoom
+//! - `S` saves the current image to `preview.png`
+//! - `Escape` closes the window
+//!
+//! Only available with the `preview` feature.
+
+use crate::hittable::Hittable;
+use crate::scene::Scene;
+use crate::{render_pass, to_image, Camera, Color};
+use minifb::{Key, KeyRepeat, MouseButton, MouseMode, Window, WindowOptions};
+
+/// How far the camera orbits for each pixel that the mouse moves, in radians
+const ORBIT_PER_PIXEL: f64 = 0.01;
+/// How far the camera orbits every frame that an arrow key is down, in radians
+const ORBIT_PER_FRAME: f64 = 0.05;
+/// How far the camera pans every frame that an arrow key is down, relative to its distance
+/// to the point it looks at
+const PAN_PER_FRAME: f64 = 0.02;
+/// How much closer the camera gets for each step of the scroll wheel
+const ZOOM_PER_STEP: f64 = 0.9;
+
+/// Opens a window with the scene, and keeps rendering it until the window is closed
+///
+/// # Errors
+///
+/// If the window can't be opened or updated, like when there's no display
+pub fn run<T: Hittable>(mut scene: Scene<T>) -> Result<(), minifb::Error> {
+    let (image_width, image_height) = scene.image_size();
+    let samples_per_pixel = scene.samples_per_pixel();
+
+    let mut window = Window::new(
+        "Preview",
+        image_width as usize,
+        image_height as usize,
+        WindowOptions::default(),
+    )?;
+    window.set_target_fps(60);
+
+    let mut accumulated = vec![Color::new(0.0, 0.0, 0.0); (image_width * image_height) as usize];
+    let mut frames = 0;
+    let mut buffer = vec![0; accumulated.len()];
+    let mut last_mouse_position = None;
+
+    while window.is_open() && !window.is_key_down(Key::Escape) {
+        if let Some(camera) = moved_camera(&window, scene.camera(), &mut last_mouse_position) {
+            scene.set_camera(camera);
+            accumulated.fill(Color::new(0.0, 0.0, 0.0));
+            frames = 0;
+        }
+
+        if frames < samples_per_pixel {
+            let frame = render_pass(&scene, frames);
+            for (total, color) in accumulated.iter_mut().zip(frame) {
+                *total += color;
+            }
+            frames += 1;
+
+            window.set_title(&format!("Preview: {frames}/{samples_per_pixel} samples"));
+        }
+
+        let colors = average(&accumulated, frames);
+        if window.is_key_pressed(Key::S, KeyRepeat::No) {
+            let message = match to_image(&scene, &colors).save("preview.png") {
+                Ok(()) => format!("Saved preview.png with {frames} samples per pixel"),
+                Err(error) => format!("Couldn't save preview.png: {error}"),
+            };
+            scene.observer().message(&message);
+        }
+
+        // The film starts from the bottom, but the window starts from the top
+        for (i, color) in colors.iter().enumerate() {
+            let (column, row) = crate::get_image_coordinates(i as u32, image_width);
+            let [red, green, blue] = color.to_writeable_ints(1);
+
+            buffer[((image_height - 1 - row) * image_width + column) as usize] =
+                u32::from_be_bytes([0, red, green, blue]);
+        }
+        window.update_with_buffer(&buffer, image_width as usize, image_height as usize)?;
+    }
+
+    Ok(())
+}
+
+fn average(accumulated: &[Color], frames: u32) -> Vec<Color> {
+    let scale = 1.0 / f64::from(frames.max(1));
+
+    accumulated.iter().map(|color| *color * scale).collect()
+}
+
+/// Returns the camera moved by the mouse and keys, if they moved it
+fn moved_camera(
+    window: &Window,
+    camera: &Camera,
+    last_mouse_position: &mut Option<(f32, f32)>,
+) -> Option<Camera> {
+    let mut moved = camera.clone();
+    let mut changed = false;
+    let mut update = |change: &dyn Fn(&Camera) -> Camera| {
+        moved = change(&moved);
+        changed = true;
+    };
+
+    // Mouse
+    let mouse_position = window.get_mouse_pos(MouseMode::Discard);
+    let dragging_left = window.get_mouse_down(MouseButton::Left);
+    let dragging_right = window.get_mouse_down(MouseButton::Right);
+    if let (Some((x, y)), Some((last_x, last_y))) = (mouse_position, *last_mouse_position) {
+        let (delta_x, delta_y) = (f64::from(x - last_x), f64::from(y - last_y));
+
+        if (delta_x != 0.0 || delta_y != 0.0) && dragging_left {
+            update(&|camera| camera.orbit(-delta_x * ORBIT_PER_PIXEL, delta_y * ORBIT_PER_PIXEL));
+        } else if (delta_x != 0.0 || delta_y != 0.0) && dragging_right {
+            let (width, _) = window.get_size();
+            let scale = 1.0 / f64::from(width as u32);
+            update(&|camera| camera.pan(-delta_x * scale, delta_y * scale));
+        }
+    }
+    *last_mouse_position = if dragging_left || dragging_right {
+        mouse_position
+    } else {
+        None
+    };
+
+    if let Some((_, scroll)) = window.get_scroll_wheel() {
+        if scroll != 0.0 {
+            update(&|camera| camera.zoom(ZOOM_PER_STEP.powf(f64::from(scroll.signum()))));
+        }
+    }
+
+    // Keys
+    let (right, up) = [
+        (Key::Left, (-1.0, 0.0)),
+        (Key::Right, (1.0, 0.0)),
+        (Key::Down, (0.0, -1.0)),
+        (Key::Up, (0.0, 1.0)),
+    ]
+    .into_iter()
+    .filter(|(key, _)| window.is_key_down(*key))
+    .fold((0.0, 0.0), |(right, up), (_, (x, y))| (right + x, up + y));
+
+    if right != 0.0 || up != 0.0 {
+        if window.is_key_down(Key::LeftShift) || window.is_key_down(Key::RightShift) {
+            update(&|camera| camera.pan(right * PAN_PER_FRAME, up * PAN_PER_FRAME));
+        } else {
+            update(&|camera| camera.orbit(-right * ORBIT_PER_FRAME, up * ORBIT_PER_FRAME));
+        }
+    }
+
+    if window.is_key_down(Key::Equal) || window.is_key_down(Key::NumPadPlus) {
+        update(&|camera| camera.zoom(ZOOM_PER_STEP));
+    } else if window.is_key_down(Key::Minus) || window.is_key_down(Key::NumPadMinus) {
+        update(&|camera| camera.zoom(1.0 / ZOOM_PER_STEP));
+    }
+
+    changed.then_some(moved)
+}
@@ -0,0 +1,187 @@
+//! Following how a render is going
+//!
+//! Renders tell the `RenderObserver` of their scene when they start, when every tile is done
+//! and when they finish. Renders that aren't split in tiles, like the passes of photon mapping
+//! or the chains of Metropolis, tell it when each of their steps is done instead. By default
+//! it's a progress bar in the terminal, but it can be anything else, like nothing at all in
+//! tests or a log in a service.
+
+use std::time::Duration;
+
+use indicatif::{ProgressBar, ProgressStyle};
+
+use crate::stats::{RayCounters, RenderStats};
+
+/// Gets told how a render is going, from any of the threads rendering it
+pub trait RenderObserver: Send + Sync {
+    /// The render started, and it has `tiles` tiles, or steps if it isn't split in tiles
+    fn start(&self, _tiles: usize) {}
+    /// A tile is done
+    fn tile_done(&self, _tile: &TileReport) {}
+    /// A step of a render that isn't split in tiles is done
+    fn step_done(&self) {}
+    /// Something to show to whoever is following the render, like a worker connecting
+    fn message(&self, _message: &str) {}
+    /// Every tile is done
+    fn finish(&self, _stats: &RenderStats) {}
+}
+
+/// How rendering a tile went
+#[derive(Clone, Copy, Debug)]
+pub struct TileReport {
+    /// The first pixel of the tile, from the bottom left corner of the image
+    pub min: (u32, u32),
+    /// The pixel after the last one of the tile
+    pub max: (u32, u32),
+    /// How long its thread spent on it. Tiles rendered by other computers have no duration
+    /// or counters.
+    pub duration: Duration,
+    pub counters: RayCounters,
+}
+
+/// Shows a progress bar in the terminal, with how many tiles are done and how long is left
+pub struct ProgressBarObserver {
+    bar: ProgressBar,
+}
+
+impl ProgressBarObserver {
+    pub fn new() -> Self {
+        let bar = ProgressBar::new(0);
+        bar.set_style(
+            ProgressStyle::default_bar()
+                .template("[{elapsed_precise}] Rendering {percent}% done. ETA: {eta_precise}"),
+        );
+
+        Self { bar }
+    }
+}
+
+impl Default for ProgressBarObserver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RenderObserver for ProgressBarObserver {
+    fn start(&self, tiles: usize) {
+        // The same scene can be rendered more than once
+        self.bar.reset();
+        self.bar.set_length(tiles as u64);
+    }
+    fn tile_done(&self, _tile: &TileReport) {
+        self.bar.inc(1);
+    }
+    fn step_done(&self) {
+        self.bar.inc(1);
+    }
+    fn message(&self, message: &str) {
+        // Printed above the bar, so they don't draw over each other
+        self.bar.println(message);
+    }
+    fn finish(&self, _stats: &RenderStats) {
+        self.bar.finish();
+    }
+}
+
+/// Doesn't show anything
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SilentObserver;
+
+impl RenderObserver for SilentObserver {}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::hittable::{Hittable, HittableList};
+    use crate::integrators::ProgressivePhotonMapper;
+    use crate::scene::{Scene, SceneBuilder};
+    use crate::surfaces::Sphere;
+    use crate::{Camera, Color, Vec3};
+
+    /// Keeps everything it's told
+    #[derive(Default)]
+    struct Recorder {
+        tiles: Mutex<(usize, Vec<TileReport>)>,
+        steps: Mutex<usize>,
+        stats: Mutex<Option<RenderStats>>,
+    }
+
+    impl RenderObserver for Arc<Recorder> {
+        fn start(&self, tiles: usize) {
+            self.tiles.lock().unwrap().0 = tiles;
+        }
+        fn tile_done(&self, tile: &TileReport) {
+            self.tiles.lock().unwrap().1.push(*tile);
+        }
+        fn step_done(&self) {
+            *self.steps.lock().unwrap() += 1;
+        }
+        fn finish(&self, stats: &RenderStats) {
+            *self.stats.lock().unwrap() = Some(*stats);
+        }
+    }
+
+    fn scene(recorder: &Arc<Recorder>) -> Scene<impl Hittable> {
+        let mut world = HittableList::new();
+        for x in -2..=2 {
+            world.push(Sphere::new(
+                Vec3::new(f64::from(x), 0.0, 0.0),
+                0.4,
+                Color::new(0.5, 0.5, 0.5).lambertian(),
+            ));
+        }
+        let camera = Camera::new(
+            Vec3::new(0.0, 0.0, 6.0),
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            40.0,
+            1.0,
+            0.0,
+            1.0,
+            (0.0, 1.0),
+        );
+
+        SceneBuilder::new(world.into_bvh((0.0, 1.0)), camera, 1.0)
+            .image_width(32)
+            .samples_per_pixel(4)
+            .max_depth(4)
+            .observer(Arc::clone(recorder))
+            .build()
+    }
+
+    #[test]
+    fn test_observer_gets_tiles_and_stats() {
+        let recorder = Arc::new(Recorder::default());
+        let scene = scene(&recorder);
+        let (_, stats) = crate::render_chunked_with_stats(&scene);
+
+        let (tiles, reports) = &*recorder.tiles.lock().unwrap();
+        assert_eq!(*tiles, 4);
+        assert_eq!(reports.len(), 4);
+        assert_eq!(*recorder.stats.lock().unwrap(), Some(stats));
+
+        // Every path has at least the ray from the camera, and the tiles add up to the total
+        assert!(stats.counters.rays >= stats.paths.paths());
+        assert!(stats.counters.bvh_node_visits > 0);
+        assert!(stats.counters.primitive_tests > 0);
+        let rays: u64 = reports.iter().map(|report| report.counters.rays).sum();
+        assert_eq!(rays, stats.counters.rays);
+        assert!(stats.rays_per_second() > 0.0);
+    }
+
+    #[test]
+    fn test_observer_gets_steps() {
+        // Every pass of photon mapping is a step
+        let recorder = Arc::new(Recorder::default());
+        ProgressivePhotonMapper::new()
+            .photons_per_pass(100)
+            .render(&scene(&recorder));
+
+        assert_eq!(recorder.tiles.lock().unwrap().0, 4);
+        assert_eq!(*recorder.steps.lock().unwrap(), 4);
+        assert!(recorder.stats.lock().unwrap().is_some());
+    }
+}
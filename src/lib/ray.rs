@@ -1,12 +1,39 @@
 use crate::hittable::Hittable;
+use crate::integrators::PathTracer;
+use crate::samplers::Sampler;
 use crate::Color;
 use crate::Vec3;
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug)]
 pub struct Ray {
     pub origin: Vec3,
     pub direction: Vec3,
     pub time: f64,
+    /// The rays from the camera through the next pixels, to know how much of a texture a
+    /// pixel covers. Only rays from the camera have them.
+    pub differentials: Option<RayDifferentials>,
+}
+
+/// The rays that go through the pixel to the right of a ray, and the pixel above it
+#[derive(Clone, Copy, Debug)]
+pub struct RayDifferentials {
+    pub x_origin: Vec3,
+    pub x_direction: Vec3,
+    pub y_origin: Vec3,
+    pub y_direction: Vec3,
+}
+
+impl RayDifferentials {
+    /// Moves the rays to another space, with functions that move points and directions
+    #[must_use]
+    pub fn map(&self, point: impl Fn(&Vec3) -> Vec3, direction: impl Fn(&Vec3) -> Vec3) -> Self {
+        Self {
+            x_origin: point(&self.x_origin),
+            x_direction: direction(&self.x_direction),
+            y_origin: point(&self.y_origin),
+            y_direction: direction(&self.y_direction),
+        }
+    }
 }
 
 impl Ray {
@@ -15,47 +42,35 @@ impl Ray {
             origin,
             direction,
             time,
+            differentials: None,
         }
     }
 
+    #[must_use]
+    pub const fn with_differentials(mut self, differentials: RayDifferentials) -> Self {
+        self.differentials = Some(differentials);
+        self
+    }
+
     /// Returns the position of the ray when it travels `t` in its direction.
     /// Based on the ray formula 'P(t)=A+tb'
     pub fn at(&self, t: f64) -> Vec3 {
         self.origin + (self.direction * t)
     }
 
-    /// Calculates the final color of the ray
-    #[allow(clippy::missing_panics_doc)] // We check for nones, so unwrapping will never fail
-    pub fn calculate_color<T: Hittable>(&self, world: &T, background: &Color, depth: u32) -> Color {
-        // If ray has bounced too many times
-        if depth == 0 {
-            return Color::new(0.0, 0.0, 0.0);
-        }
-
-        let hit_record = world.hit(self, 0.0001, f64::INFINITY);
-
-        if hit_record.is_none() {
-            return *background;
-        }
-
-        let intersection = hit_record.unwrap();
-        let scatter_record = intersection.material.scatter(self, &intersection);
-        let emitted =
-            intersection
-                .material
-                .emitted(intersection.u, intersection.v, &intersection.p);
-
-        if scatter_record.is_none() {
-            return emitted;
-        }
-
-        let scatter = scatter_record.unwrap();
-
-        emitted
-            + scatter.attenuation
-                * scatter
-                    .scattered_ray
-                    .calculate_color(world, background, depth - 1)
+    /// Calculates the final color of the ray, taking the random numbers of every bounce from `sampler`.
+    /// Every path goes up to `depth` bounces, without Russian roulette.
+    pub fn calculate_color<T: Hittable>(
+        &self,
+        world: &T,
+        background: &Color,
+        depth: u32,
+        sampler: &mut dyn Sampler,
+    ) -> Color {
+        PathTracer::new()
+            .russian_roulette_depth(None)
+            .trace(self, world, background, depth, sampler)
+            .color
     }
 }
 
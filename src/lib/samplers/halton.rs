@@ -0,0 +1,79 @@
+use crate::samplers::{hash, to_unit, SampleState, Sampler};
+
+/// A different prime base for each dimension
+const PRIMES: [u32; 32] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+    101, 103, 107, 109, 113, 127, 131,
+];
+
+/// Halton sequence, where each dimension is the radical inverse of the sample index in a
+/// different prime base.
+///
+/// Every pixel shifts its points by a random offset (Cranley-Patterson rotation), so
+/// neighbouring pixels don't have the same pattern. Dimensions after the last prime
+/// in the table are independent random numbers.
+#[derive(Clone, Debug, Default)]
+pub struct HaltonSampler {
+    state: SampleState,
+}
+
+impl HaltonSampler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn sample_dimension(&self, dimension: u32) -> f64 {
+        let Some(base) = PRIMES.get(dimension as usize) else {
+            let state = &self.state;
+            return to_unit(hash(&[
+                state.pixel.0,
+                state.pixel.1,
+                state.index,
+                dimension,
+            ]));
+        };
+
+        let shifted =
+            radical_inverse(*base, self.state.index) + to_unit(self.state.seed(dimension));
+
+        // Wraps around, but stays below 1 even if rounding makes it exactly 1
+        (shifted - shifted.floor()).min(1.0 - f64::EPSILON)
+    }
+}
+
+/// Mirrors the digits of `index` in `base` around the decimal point
+fn radical_inverse(base: u32, mut index: u32) -> f64 {
+    let inverse_base = 1.0 / f64::from(base);
+    let mut digit_value = inverse_base;
+    let mut result = 0.0;
+
+    while index > 0 {
+        result = f64::from(index % base).mul_add(digit_value, result);
+        index /= base;
+        digit_value *= inverse_base;
+    }
+
+    result
+}
+
+impl Sampler for HaltonSampler {
+    fn start_sample(&mut self, pixel: (u32, u32), sample_index: u32, samples_per_pixel: u32) {
+        self.state.start(pixel, sample_index, samples_per_pixel);
+    }
+    fn next_1d(&mut self) -> f64 {
+        let dimension = self.state.take_dimensions(1);
+
+        self.sample_dimension(dimension)
+    }
+    fn next_2d(&mut self) -> (f64, f64) {
+        let dimension = self.state.take_dimensions(2);
+
+        (
+            self.sample_dimension(dimension),
+            self.sample_dimension(dimension + 1),
+        )
+    }
+    fn boxed_clone(&self) -> Box<dyn Sampler> {
+        Box::new(self.clone())
+    }
+}
@@ -0,0 +1,172 @@
+use std::f64::consts::TAU;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::samplers::Sampler;
+
+/// Gives out the values of a point in primary sample space, for Metropolis light transport
+///
+/// The point is the list of all the random numbers that a sample uses, and every iteration
+/// changes it a little (a small step) or completely (a large step).
+/// Dimensions are only created and mutated when they're used, so it works with paths of
+/// any length. The same seed always gives the same sequence of values.
+#[derive(Clone, Debug)]
+pub struct MetropolisSampler {
+    rng: StdRng,
+    values: Vec<PrimarySample>,
+    dimension: usize,
+    iteration: u64,
+    last_large_step: u64,
+    large_step: bool,
+    large_step_probability: f64,
+    sigma: f64,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct PrimarySample {
+    value: f64,
+    /// Iteration when the value was last changed
+    last_modified: u64,
+    /// Value before the current iteration, in case the mutation is rejected
+    backup: f64,
+    backup_modified: u64,
+}
+
+impl MetropolisSampler {
+    /// A sampler that makes a large step with probability `large_step_probability`, and
+    /// otherwise moves every dimension by a normal distribution with deviation `sigma`
+    pub fn new(seed: u64, large_step_probability: f64, sigma: f64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+            values: Vec::new(),
+            dimension: 0,
+            iteration: 0,
+            last_large_step: 0,
+            // The first point is always a completely random one
+            large_step: true,
+            large_step_probability,
+            sigma,
+        }
+    }
+
+    /// Starts a new mutation, going back to the first dimension
+    pub fn start_iteration(&mut self) {
+        self.iteration += 1;
+        self.large_step = self.rng.gen::<f64>() < self.large_step_probability;
+        self.dimension = 0;
+    }
+
+    /// Keeps the values of the current mutation
+    pub const fn accept(&mut self) {
+        if self.large_step {
+            self.last_large_step = self.iteration;
+        }
+    }
+
+    /// Goes back to the values from before the current mutation
+    pub fn reject(&mut self) {
+        for sample in &mut self.values {
+            if sample.last_modified == self.iteration {
+                sample.value = sample.backup;
+                sample.last_modified = sample.backup_modified;
+            }
+        }
+        self.iteration -= 1;
+    }
+
+    /// Brings the dimension up to date with the current iteration and returns it
+    fn get(&mut self, index: usize) -> f64 {
+        if index >= self.values.len() {
+            self.values.resize(index + 1, PrimarySample::default());
+        }
+        let sample = &mut self.values[index];
+
+        // Dimensions that weren't used since the last large step would've been reset by it
+        if sample.last_modified < self.last_large_step {
+            sample.value = self.rng.gen();
+            sample.last_modified = self.last_large_step;
+        }
+
+        sample.backup = sample.value;
+        sample.backup_modified = sample.last_modified;
+
+        if self.large_step {
+            sample.value = self.rng.gen();
+        } else {
+            // All the small steps it missed, added together, are a single normal distribution
+            #[allow(clippy::cast_precision_loss)] // Iterations don't get anywhere close to 2^52
+            let steps = (self.iteration - sample.last_modified) as f64;
+            let deviation = self.sigma * steps.sqrt();
+
+            sample.value += normal(&mut self.rng) * deviation;
+            sample.value -= sample.value.floor();
+        }
+        sample.last_modified = self.iteration;
+
+        sample.value
+    }
+}
+
+/// A random number with the standard normal distribution, with the Box-Muller transform
+fn normal(rng: &mut StdRng) -> f64 {
+    let radius = (-2.0 * (1.0 - rng.gen::<f64>()).ln()).sqrt();
+    radius * (TAU * rng.gen::<f64>()).cos()
+}
+
+impl Sampler for MetropolisSampler {
+    /// Samples are started with `start_iteration` instead
+    fn start_sample(&mut self, _pixel: (u32, u32), _sample_index: u32, _samples_per_pixel: u32) {}
+    fn next_1d(&mut self) -> f64 {
+        let value = self.get(self.dimension);
+        self.dimension += 1;
+
+        value
+    }
+    fn next_2d(&mut self) -> (f64, f64) {
+        (self.next_1d(), self.next_1d())
+    }
+    fn boxed_clone(&self) -> Box<dyn Sampler> {
+        Box::new(self.clone())
+    }
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(sampler: &mut MetropolisSampler) -> Vec<f64> {
+        (0..4).map(|_| sampler.next_1d()).collect()
+    }
+
+    #[test]
+    fn test_same_seed_gives_same_values() {
+        let first = values(&mut MetropolisSampler::new(7, 0.3, 0.01));
+        let second = values(&mut MetropolisSampler::new(7, 0.3, 0.01));
+
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn test_reject_restores_values() {
+        let mut sampler = MetropolisSampler::new(7, 0.0, 0.01);
+        let original = values(&mut sampler);
+
+        sampler.start_iteration();
+        let mutated = values(&mut sampler);
+        assert_ne!(original, mutated);
+        // Small steps don't move values far, counting the wrap around from 1 to 0
+        for (a, b) in original.iter().zip(&mutated) {
+            let distance = (a - b).abs();
+            assert!(distance.min(1.0 - distance) < 0.1);
+        }
+        sampler.reject();
+
+        // Reading the values again mutates them from the original ones
+        sampler.start_iteration();
+        sampler.reject();
+        let restored: Vec<f64> = sampler.values.iter().map(|sample| sample.value).collect();
+        assert_eq!(restored, original);
+    }
+}
@@ -0,0 +1,208 @@
+//! Samplers give out the random numbers that each sample of a pixel uses
+//!
+//! Every random number that a sample uses is one of its dimensions: the first two are the
+//! position inside the pixel, the next three are the lens and time of the camera, and every
+//! bounce uses a few more. Instead of independent random numbers, low discrepancy samplers
+//! spread the values of each dimension evenly between the samples of a pixel, so the noise
+//! goes down faster with the same amount of samples.
+
+mod halton;
+mod metropolis;
+mod random;
+mod sobol;
+mod stratified;
+pub use halton::HaltonSampler;
+pub use metropolis::MetropolisSampler;
+pub use random::RandomSampler;
+pub use sobol::SobolSampler;
+pub use stratified::StratifiedSampler;
+
+/// A trait that all samplers must implement
+pub trait Sampler: Send + Sync {
+    /// Starts the sample number `sample_index` (out of `samples_per_pixel`) of a pixel,
+    /// going back to its first dimension
+    fn start_sample(&mut self, pixel: (u32, u32), sample_index: u32, samples_per_pixel: u32);
+    /// Returns the next dimension of the current sample, in \[0,1)
+    fn next_1d(&mut self) -> f64;
+    /// Returns the next two dimensions of the current sample, in \[0,1)
+    fn next_2d(&mut self) -> (f64, f64);
+    /// Returns a new sampler with the same settings, so each thread can have its own
+    fn boxed_clone(&self) -> Box<dyn Sampler>;
+}
+
+/// Which sample (and which of its dimensions) a sampler is on
+#[derive(Clone, Copy, Debug, Default)]
+struct SampleState {
+    pixel: (u32, u32),
+    index: u32,
+    count: u32,
+    dimension: u32,
+}
+
+impl SampleState {
+    const fn start(&mut self, pixel: (u32, u32), index: u32, count: u32) {
+        *self = Self {
+            pixel,
+            index,
+            count,
+            dimension: 0,
+        };
+    }
+
+    /// Returns the current dimension, and moves on to the one after the next `amount`
+    const fn take_dimensions(&mut self, amount: u32) -> u32 {
+        let dimension = self.dimension;
+        self.dimension += amount;
+
+        dimension
+    }
+
+    /// Seed that's different for each pixel and dimension, but the same for every sample
+    fn seed(&self, dimension: u32) -> u32 {
+        hash(&[self.pixel.0, self.pixel.1, dimension])
+    }
+}
+
+/// Mixes the bits of all the values into a random looking number
+fn hash(values: &[u32]) -> u32 {
+    values.iter().fold(0x9E37_79B9, |hash, value| {
+        mix_bits(hash ^ mix_bits(value.wrapping_add(0x85EB_CA6B)))
+    })
+}
+
+/// "lowbias32" by Chris Wellons
+const fn mix_bits(mut x: u32) -> u32 {
+    x ^= x >> 16;
+    x = x.wrapping_mul(0x7FEB_352D);
+    x ^= x >> 15;
+    x = x.wrapping_mul(0x846C_A68B);
+    x ^= x >> 16;
+    x
+}
+
+/// Turns 32 random bits into a number in \[0,1)
+fn to_unit(bits: u32) -> f64 {
+    f64::from(bits) / 4_294_967_296.0
+}
+
+/// Returns where `i` goes in a random permutation of `0..length`, chosen by `seed`.
+/// From "Correlated Multi-Jittered Sampling" by Andrew Kensler.
+const fn permute(mut i: u32, length: u32, seed: u32) -> u32 {
+    let mut mask = length - 1;
+    mask |= mask >> 1;
+    mask |= mask >> 2;
+    mask |= mask >> 4;
+    mask |= mask >> 8;
+    mask |= mask >> 16;
+
+    // Permutes inside the next power of 2, until the result fits in the length
+    loop {
+        i ^= seed;
+        i = i.wrapping_mul(0xE170_893D);
+        i ^= seed >> 16;
+        i ^= (i & mask) >> 4;
+        i ^= seed >> 8;
+        i = i.wrapping_mul(0x0929_EB3F);
+        i ^= seed >> 23;
+        i ^= (i & mask) >> 1;
+        i = i.wrapping_mul(1 | seed >> 27);
+        i = i.wrapping_mul(0x6935_FA69);
+        i ^= (i & mask) >> 11;
+        i = i.wrapping_mul(0x74DC_B303);
+        i ^= (i & mask) >> 2;
+        i = i.wrapping_mul(0x9E50_1CC3);
+        i ^= (i & mask) >> 2;
+        i = i.wrapping_mul(0xC860_A3DF);
+        i &= mask;
+        i ^= i >> 5;
+
+        if i < length {
+            break;
+        }
+    }
+
+    (i + seed) % length
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn samplers() -> Vec<Box<dyn Sampler>> {
+        vec![
+            Box::new(StratifiedSampler::new()),
+            Box::new(HaltonSampler::new()),
+            Box::new(SobolSampler::new()),
+        ]
+    }
+
+    #[test]
+    fn test_permute() {
+        for length in [1, 5, 16, 100] {
+            let mut seen = vec![false; length as usize];
+            for i in 0..length {
+                seen[permute(i, length, 1234) as usize] = true;
+            }
+
+            assert!(seen.iter().all(|seen| *seen));
+        }
+    }
+
+    #[test]
+    fn test_samples_are_stratified() {
+        const SAMPLES: u32 = 16;
+
+        // Halton points are only spread this evenly in the first dimension
+        let samplers: Vec<Box<dyn Sampler>> = vec![
+            Box::new(StratifiedSampler::new()),
+            Box::new(SobolSampler::new()),
+        ];
+
+        for mut sampler in samplers {
+            let mut strata_1d = [0; SAMPLES as usize];
+            let mut strata_2d = [0; SAMPLES as usize];
+
+            for sample_index in 0..SAMPLES {
+                sampler.start_sample((3, 7), sample_index, SAMPLES);
+
+                let (x, y) = sampler.next_2d();
+                let z = sampler.next_1d();
+                assert!((0.0..1.0).contains(&x) && (0.0..1.0).contains(&y));
+                assert!((0.0..1.0).contains(&z));
+
+                strata_2d[(x * 4.0) as usize * 4 + (y * 4.0) as usize] += 1;
+                strata_1d[(z * f64::from(SAMPLES)) as usize] += 1;
+            }
+
+            // Every stratum has exactly one of the samples
+            assert!(strata_1d.iter().all(|count| *count == 1));
+            assert!(strata_2d.iter().all(|count| *count == 1));
+        }
+    }
+
+    #[test]
+    fn test_halton_first_dimension() {
+        let mut sampler = HaltonSampler::new();
+        let mut strata = [0; 16];
+
+        for sample_index in 0..16 {
+            sampler.start_sample((3, 7), sample_index, 16);
+            strata[(sampler.next_1d() * 16.0) as usize] += 1;
+        }
+
+        assert!(strata.iter().all(|count| *count == 1));
+    }
+
+    #[test]
+    fn test_pixels_are_different() {
+        for mut sampler in samplers() {
+            sampler.start_sample((0, 0), 0, 4);
+            let first = sampler.next_2d();
+            sampler.start_sample((1, 0), 0, 4);
+            let second = sampler.next_2d();
+
+            assert!(first != second);
+        }
+    }
+}
@@ -0,0 +1,28 @@
+use rand::Rng;
+
+use crate::samplers::Sampler;
+
+/// Independent uniform random numbers for every dimension
+#[derive(Clone, Debug, Default)]
+pub struct RandomSampler;
+
+impl RandomSampler {
+    pub const fn new() -> Self {
+        Self
+    }
+}
+
+impl Sampler for RandomSampler {
+    fn start_sample(&mut self, _pixel: (u32, u32), _sample_index: u32, _samples_per_pixel: u32) {}
+    fn next_1d(&mut self) -> f64 {
+        rand::thread_rng().gen()
+    }
+    fn next_2d(&mut self) -> (f64, f64) {
+        let mut rng = rand::thread_rng();
+
+        (rng.gen(), rng.gen())
+    }
+    fn boxed_clone(&self) -> Box<dyn Sampler> {
+        Box::new(self.clone())
+    }
+}
@@ -0,0 +1,95 @@
+use crate::samplers::{hash, to_unit, SampleState, Sampler};
+
+/// Owen scrambled Sobol sequence
+///
+/// Only the first two Sobol dimensions are used. Every pair of dimensions shuffles the order
+/// of the samples and scrambles their values with a different seed, so they're independent
+/// from each other but each one is still well distributed.
+/// From "Practical Hash-based Owen Scrambling" by Brent Burley.
+#[derive(Clone, Debug, Default)]
+pub struct SobolSampler {
+    state: SampleState,
+}
+
+impl SobolSampler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The sample index, shuffled differently for each dimension
+    const fn shuffled_index(&self, seed: u32) -> u32 {
+        nested_uniform_scramble(self.state.index, seed)
+    }
+}
+
+/// First dimension of the Sobol sequence, as bits after the decimal point
+const fn sobol_first(index: u32) -> u32 {
+    index.reverse_bits()
+}
+
+/// Second dimension of the Sobol sequence, as bits after the decimal point
+const fn sobol_second(mut index: u32) -> u32 {
+    let mut direction = 1 << 31;
+    let mut result = 0;
+
+    while index != 0 {
+        if index & 1 != 0 {
+            result ^= direction;
+        }
+        index >>= 1;
+        direction ^= direction >> 1;
+    }
+
+    result
+}
+
+/// Randomly permutes the bits of `x` from the most significant one down, like an Owen
+/// scramble, which keeps how well distributed the points are
+const fn nested_uniform_scramble(x: u32, seed: u32) -> u32 {
+    laine_karras_permutation(x.reverse_bits(), seed).reverse_bits()
+}
+
+/// Hash where each bit only depends on the bits below it
+const fn laine_karras_permutation(mut x: u32, seed: u32) -> u32 {
+    x = x.wrapping_add(seed);
+    x ^= x.wrapping_mul(0x6C50_B47C);
+    x ^= x.wrapping_mul(0xB82F_1E52);
+    x ^= x.wrapping_mul(0xC7AF_E638);
+    x ^= x.wrapping_mul(0x8D22_F6E6);
+    x
+}
+
+impl Sampler for SobolSampler {
+    fn start_sample(&mut self, pixel: (u32, u32), sample_index: u32, samples_per_pixel: u32) {
+        self.state.start(pixel, sample_index, samples_per_pixel);
+    }
+    fn next_1d(&mut self) -> f64 {
+        let dimension = self.state.take_dimensions(1);
+        let seed = self.state.seed(dimension);
+
+        let index = self.shuffled_index(seed);
+        to_unit(nested_uniform_scramble(
+            sobol_first(index),
+            hash(&[seed, 0]),
+        ))
+    }
+    fn next_2d(&mut self) -> (f64, f64) {
+        let dimension = self.state.take_dimensions(2);
+        let seed = self.state.seed(dimension);
+
+        let index = self.shuffled_index(seed);
+        (
+            to_unit(nested_uniform_scramble(
+                sobol_first(index),
+                hash(&[seed, 0]),
+            )),
+            to_unit(nested_uniform_scramble(
+                sobol_second(index),
+                hash(&[seed, 1]),
+            )),
+        )
+    }
+    fn boxed_clone(&self) -> Box<dyn Sampler> {
+        Box::new(self.clone())
+    }
+}
@@ -0,0 +1,69 @@
+use crate::samplers::{hash, permute, to_unit, SampleState, Sampler};
+
+/// Splits each dimension in as many strata as there are samples per pixel (a grid of them
+/// for 2D), and puts each sample in a random point of a different stratum.
+///
+/// Which sample goes in which stratum is shuffled for every dimension, so dimensions
+/// aren't correlated with each other.
+#[derive(Clone, Debug, Default)]
+pub struct StratifiedSampler {
+    state: SampleState,
+}
+
+impl StratifiedSampler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A random offset inside the stratum of the current sample
+    fn jitter(&self, dimension: u32) -> f64 {
+        let state = &self.state;
+
+        to_unit(hash(&[
+            state.pixel.0,
+            state.pixel.1,
+            state.index,
+            dimension,
+        ]))
+    }
+}
+
+impl Sampler for StratifiedSampler {
+    fn start_sample(&mut self, pixel: (u32, u32), sample_index: u32, samples_per_pixel: u32) {
+        self.state
+            .start(pixel, sample_index, samples_per_pixel.max(1));
+    }
+    fn next_1d(&mut self) -> f64 {
+        let dimension = self.state.take_dimensions(1);
+        let strata = self.state.count;
+        let stratum = permute(
+            self.state.index % strata,
+            strata,
+            self.state.seed(dimension),
+        );
+
+        (f64::from(stratum) + self.jitter(dimension)) / f64::from(strata)
+    }
+    fn next_2d(&mut self) -> (f64, f64) {
+        let dimension = self.state.take_dimensions(2);
+
+        // The grid has at least as many cells as samples. If the amount of samples isn't
+        // a square number, some cells won't get a sample.
+        let columns = f64::from(self.state.count).sqrt().ceil() as u32;
+        let rows = self.state.count.div_ceil(columns);
+
+        let cell = permute(
+            self.state.index % (columns * rows),
+            columns * rows,
+            self.state.seed(dimension),
+        );
+
+        (
+            (f64::from(cell % columns) + self.jitter(dimension)) / f64::from(columns),
+            (f64::from(cell / columns) + self.jitter(dimension + 1)) / f64::from(rows),
+        )
+    }
+    fn boxed_clone(&self) -> Box<dyn Sampler> {
+        Box::new(self.clone())
+    }
+}
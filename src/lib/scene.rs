@@ -1,12 +1,30 @@
-use crate::{color, Camera, Color, Hittable};
+use crate::{
+    color,
+    denoise::Denoiser,
+    filters::{BoxFilter, Filter},
+    hittable::{HitRecord, HittableList},
+    integrators::{Integrator, PathTracer, SceneContext},
+    progress::{ProgressBarObserver, RenderObserver},
+    samplers::{RandomSampler, Sampler},
+    simd::RayPacket,
+    stats,
+    surfaces::Aabb,
+    Camera, Color, Hittable, Ray,
+};
 
 pub struct Scene<T: Hittable> {
-    world: T,
+    world: CountRays<T>,
     background_color: Color,
     camera: Camera,
     image_size: (u32, u32),
     samples_per_pixel: u32,
     max_depth: u32,
+    denoiser: Option<Box<dyn Denoiser>>,
+    filter: Box<dyn Filter>,
+    sampler: Box<dyn Sampler>,
+    integrator: Box<dyn Integrator>,
+    lights: HittableList,
+    observer: Box<dyn RenderObserver>,
 }
 impl<T: Hittable> Scene<T> {
     pub fn new(
@@ -18,16 +36,22 @@ impl<T: Hittable> Scene<T> {
         max_depth: u32,
     ) -> Self {
         Self {
-            world,
+            world: CountRays(world),
             background_color,
             camera,
             image_size,
             samples_per_pixel,
             max_depth,
+            denoiser: None,
+            filter: Box::new(BoxFilter::new(0.5)),
+            sampler: Box::new(RandomSampler::new()),
+            integrator: Box::new(PathTracer::new()),
+            lights: HittableList::new(),
+            observer: Box::new(ProgressBarObserver::new()),
         }
     }
     pub fn world(&self) -> &T {
-        &self.world
+        &self.world.0
     }
     pub fn background_color(&self) -> &Color {
         &self.background_color
@@ -35,6 +59,27 @@ impl<T: Hittable> Scene<T> {
     pub fn camera(&self) -> &Camera {
         &self.camera
     }
+    /// The ray from the camera through `(sample_x, sample_y)` of the image, in pixels, with
+    /// differentials for the part of a pixel that each of its samples covers
+    pub fn camera_ray(&self, (sample_x, sample_y): (f64, f64), sampler: &mut dyn Sampler) -> Ray {
+        let (image_width, image_height) = self.image_size;
+        let u = sample_x / f64::from(image_width - 1);
+        let v = sample_y / f64::from(image_height - 1);
+
+        // Samples are spread over the pixel, so each one covers less of it, but not too little
+        // so textures don't start aliasing again with a lot of samples
+        let scale = (1.0 / f64::from(self.samples_per_pixel).sqrt()).max(0.125);
+        let pixel_size = (
+            scale / f64::from(image_width - 1),
+            scale / f64::from(image_height - 1),
+        );
+
+        self.camera.get_ray_differential(u, v, pixel_size, sampler)
+    }
+    /// Replaces the camera, like when it's moved around in the preview
+    pub const fn set_camera(&mut self, camera: Camera) {
+        self.camera = camera;
+    }
     pub fn image_size(&self) -> (u32, u32) {
         self.image_size
     }
@@ -44,6 +89,78 @@ impl<T: Hittable> Scene<T> {
     pub fn max_depth(&self) -> u32 {
         self.max_depth
     }
+    /// The denoiser that runs after rendering, if there's any
+    pub fn denoiser(&self) -> Option<&dyn Denoiser> {
+        self.denoiser.as_deref()
+    }
+    /// The filter that reconstructs pixels from samples
+    pub fn filter(&self) -> &dyn Filter {
+        self.filter.as_ref()
+    }
+    /// The sampler that gives out the random numbers of each sample.
+    /// Every thread uses its own copy of it.
+    pub fn sampler(&self) -> &dyn Sampler {
+        self.sampler.as_ref()
+    }
+    /// The integrator that finds the light of every sample
+    pub fn integrator(&self) -> &dyn Integrator {
+        self.integrator.as_ref()
+    }
+    /// Surfaces that integrators can trace light from, besides finding them in the world
+    pub const fn lights(&self) -> &HittableList {
+        &self.lights
+    }
+    /// What gets told how renders of the scene are going
+    pub fn observer(&self) -> &dyn RenderObserver {
+        self.observer.as_ref()
+    }
+    /// The parts of the scene that integrators use. Rays traced through its world are counted.
+    pub fn context(&self) -> SceneContext<'_> {
+        SceneContext {
+            world: &self.world,
+            lights: &self.lights,
+            camera: &self.camera,
+            background: self.background_color,
+            max_depth: self.max_depth,
+            image_size: self.image_size,
+            samples_per_pixel: self.samples_per_pixel,
+        }
+    }
+}
+
+/// Counts every ray that's traced through the world of a scene
+struct CountRays<T>(T);
+
+impl<T: Hittable> Hittable for CountRays<T> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        stats::count_ray();
+        self.0.hit(ray, t_min, t_max)
+    }
+
+    fn hit_packet(
+        &self,
+        packet: &RayPacket,
+        t_min: f64,
+        t_max: &mut [f64; 4],
+        hits: &mut [Option<HitRecord>; 4],
+    ) {
+        for _ in t_max.iter().filter(|&&t_max| t_min < t_max) {
+            stats::count_ray();
+        }
+        self.0.hit_packet(packet, t_min, t_max, hits);
+    }
+
+    fn bounding_box(&self, time: (f64, f64)) -> Option<Aabb> {
+        self.0.bounding_box(time)
+    }
+
+    fn area(&self) -> f64 {
+        self.0.area()
+    }
+
+    fn sample_surface(&self, sample: (f64, f64)) -> Option<HitRecord> {
+        self.0.sample_surface(sample)
+    }
 }
 
 pub struct SceneBuilder<T: Hittable> {
@@ -54,6 +171,12 @@ pub struct SceneBuilder<T: Hittable> {
     image_size: Option<(u32, u32)>,
     samples_per_pixel: Option<u32>,
     max_depth: Option<u32>,
+    denoiser: Option<Box<dyn Denoiser>>,
+    filter: Option<Box<dyn Filter>>,
+    sampler: Option<Box<dyn Sampler>>,
+    integrator: Option<Box<dyn Integrator>>,
+    lights: HittableList,
+    observer: Option<Box<dyn RenderObserver>>,
 }
 
 impl<T: Hittable> SceneBuilder<T> {
@@ -66,6 +189,12 @@ impl<T: Hittable> SceneBuilder<T> {
             image_size: None,
             samples_per_pixel: None,
             max_depth: None,
+            denoiser: None,
+            filter: None,
+            sampler: None,
+            integrator: None,
+            lights: HittableList::new(),
+            observer: None,
         }
     }
 
@@ -79,14 +208,30 @@ impl<T: Hittable> SceneBuilder<T> {
         let samples_per_pixel = self.samples_per_pixel.unwrap_or(50);
         let max_depth = self.max_depth.unwrap_or(50);
 
-        Scene::new(
+        let mut scene = Scene::new(
             world,
             background_color,
             camera,
             image_size,
             samples_per_pixel,
             max_depth,
-        )
+        );
+        scene.denoiser = self.denoiser;
+        if let Some(filter) = self.filter {
+            scene.filter = filter;
+        }
+        if let Some(sampler) = self.sampler {
+            scene.sampler = sampler;
+        }
+        if let Some(integrator) = self.integrator {
+            scene.integrator = integrator;
+        }
+        scene.lights = self.lights;
+        if let Some(observer) = self.observer {
+            scene.observer = observer;
+        }
+
+        scene
     }
 
     pub fn background_color(mut self, color: Color) -> Self {
@@ -105,6 +250,56 @@ impl<T: Hittable> SceneBuilder<T> {
         self.max_depth = Some(max_depth);
         self
     }
+    /// Removes the noise of the rendered image with `denoiser`
+    #[must_use]
+    pub fn denoiser(mut self, denoiser: impl Denoiser + 'static) -> Self {
+        self.denoiser = Some(Box::new(denoiser));
+        self
+    }
+    /// Reconstructs pixels with `filter`. By default, pixels are the average of their samples,
+    /// which is a `BoxFilter` with a radius of 0.5.
+    #[must_use]
+    pub fn filter(mut self, filter: impl Filter + 'static) -> Self {
+        self.filter = Some(Box::new(filter));
+        self
+    }
+    /// Takes the random numbers of every sample from `sampler`.
+    /// By default, they're independent random numbers from `RandomSampler`.
+    #[must_use]
+    pub fn sampler(mut self, sampler: impl Sampler + 'static) -> Self {
+        self.sampler = Some(Box::new(sampler));
+        self
+    }
+    /// Finds the light of every sample with `integrator`. By default, it's a `PathTracer`
+    /// that starts Russian roulette after 3 bounces.
+    #[must_use]
+    pub fn integrator(mut self, integrator: impl Integrator + 'static) -> Self {
+        self.integrator = Some(Box::new(integrator));
+        self
+    }
+    /// Lets integrators trace light from `light`, which should also be in the world.
+    /// Only surfaces that can be sampled, like rectangles and spheres, work as lights,
+    /// also when they're moved, rotated or placed by an `Instance` that doesn't stretch them.
+    ///
+    /// # Panics
+    ///
+    /// If `light` can't be sampled, since integrators would never trace light from it
+    #[must_use]
+    pub fn light(mut self, light: impl Hittable + 'static) -> Self {
+        assert!(
+            light.area() > 0.0,
+            "Lights have to be surfaces that can be sampled"
+        );
+        self.lights.push(light);
+        self
+    }
+    /// Tells `observer` how renders of the scene are going.
+    /// By default, it's a `ProgressBarObserver`.
+    #[must_use]
+    pub fn observer(mut self, observer: impl RenderObserver + 'static) -> Self {
+        self.observer = Some(Box::new(observer));
+        self
+    }
 }
 
 fn get_height(width: u32, aspect_ratio: f64) -> u32 {
@@ -0,0 +1,301 @@
+//! Splitting the image in tiles, and following their rendering
+//!
+//! Applications that embed the renderer can get every tile as soon as it's done, to show the
+//! image while it's being rendered, and stop the render early with a `CancellationToken`.
+
+use std::f64::consts::PI;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crate::film::FilmTile;
+use crate::{Color, Tile};
+
+/// The order in which tiles are rendered
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TileOrder {
+    /// Row by row, from the bottom left corner of the image
+    Raster,
+    /// Around the center of the image, getting further away, so the middle of the image is
+    /// done first
+    Spiral,
+    /// Along a Hilbert curve, so consecutive tiles are always close to each other and
+    /// share more of the scene in the caches
+    Hilbert,
+}
+
+/// Stops a render that's using it, from any thread. Clones of a token cancel the same renders.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stops the renders that are using this token. The tiles that are being rendered are
+    /// left unfinished, and no more are started.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// A tile that's done, given to the callback of a `TileScheduler`
+pub struct RenderedTile {
+    /// The first pixel of the tile, from the bottom left corner of the image
+    pub min: (u32, u32),
+    /// The pixel after the last one of the tile
+    pub max: (u32, u32),
+    /// The color of every pixel of the tile, in the order of `get_image_coordinates`.
+    /// Pixels next to other tiles don't have the samples of those tiles yet.
+    pub colors: Vec<Color>,
+}
+
+/// Splits the image in square tiles and picks the order to render them in
+pub struct TileScheduler {
+    tile_size: u32,
+    order: TileOrder,
+    cancellation_token: CancellationToken,
+    on_tile: Option<TileCallback>,
+}
+
+type TileCallback = Box<dyn Fn(&RenderedTile) + Send + Sync>;
+
+impl TileScheduler {
+    /// Tiles of 16 by 16 pixels in raster order, like `render_chunked`
+    pub fn new() -> Self {
+        Self {
+            tile_size: 16,
+            order: TileOrder::Raster,
+            cancellation_token: CancellationToken::new(),
+            on_tile: None,
+        }
+    }
+
+    /// Sets the width and height of the tiles, in pixels.
+    /// Tiles on the right and top edges can be smaller.
+    #[must_use]
+    pub const fn tile_size(mut self, tile_size: u32) -> Self {
+        self.tile_size = tile_size;
+        self
+    }
+
+    #[must_use]
+    pub const fn order(mut self, order: TileOrder) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// Sets the token that stops the render when it's cancelled
+    #[must_use]
+    pub fn cancellation_token(mut self, token: CancellationToken) -> Self {
+        self.cancellation_token = token;
+        self
+    }
+
+    /// Sets a function that's called with every tile that's done, from the thread that
+    /// rendered it
+    #[must_use]
+    pub fn on_tile(mut self, on_tile: impl Fn(&RenderedTile) + Send + Sync + 'static) -> Self {
+        self.on_tile = Some(Box::new(on_tile));
+        self
+    }
+
+    pub(crate) fn is_cancelled(&self) -> bool {
+        self.cancellation_token.is_cancelled()
+    }
+
+    /// Calls the callback with the pixels of `tile` in `film_tile`
+    pub(crate) fn finish_tile(&self, tile: Tile, film_tile: &FilmTile) {
+        if let Some(on_tile) = &self.on_tile {
+            on_tile(&RenderedTile {
+                min: tile.min,
+                max: tile.max,
+                colors: film_tile.colors(tile.min, tile.max),
+            });
+        }
+    }
+
+    /// Splits the image in tiles, in the order they should be rendered in
+    pub(crate) fn tiles(&self, image_width: u32, image_height: u32) -> Vec<Tile> {
+        let tile_size = self.tile_size.max(1);
+        let columns = image_width.div_ceil(tile_size);
+        let rows = image_height.div_ceil(tile_size);
+
+        let mut positions: Vec<(u32, u32)> = (0..rows)
+            .flat_map(|row| (0..columns).map(move |column| (column, row)))
+            .collect();
+
+        match self.order {
+            TileOrder::Raster => {}
+            TileOrder::Spiral => {
+                let center = (f64::from(columns - 1) / 2.0, f64::from(rows - 1) / 2.0);
+                // Every ring of tiles around the center is done before the next one,
+                // going around it counterclockwise
+                let key = |&(column, row): &(u32, u32)| {
+                    let (x, y) = (f64::from(column) - center.0, f64::from(row) - center.1);
+                    let ring = x.abs().max(y.abs());
+                    let angle = y.atan2(x).rem_euclid(2.0 * PI);
+                    (ring, angle)
+                };
+                positions.sort_by(|a, b| {
+                    let (a, b) = (key(a), key(b));
+                    a.0.total_cmp(&b.0).then(a.1.total_cmp(&b.1))
+                });
+            }
+            TileOrder::Hilbert => {
+                let size = columns.max(rows).next_power_of_two();
+                positions.sort_by_key(|&(column, row)| hilbert_index(size, column, row));
+            }
+        }
+
+        positions
+            .into_iter()
+            .map(|(column, row)| {
+                let min = (column * tile_size, row * tile_size);
+                Tile {
+                    min,
+                    max: (
+                        (min.0 + tile_size).min(image_width),
+                        (min.1 + tile_size).min(image_height),
+                    ),
+                }
+            })
+            .collect()
+    }
+}
+
+impl Default for TileScheduler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// How far along the Hilbert curve that fills a `size` by `size` grid the cell at `x`, `y` is.
+/// `size` has to be a power of two.
+fn hilbert_index(size: u32, mut x: u32, mut y: u32) -> u64 {
+    let mut index = 0;
+    let mut half = size / 2;
+
+    while half > 0 {
+        let right = u32::from(x & half > 0);
+        let top = u32::from(y & half > 0);
+        index += u64::from(half) * u64::from(half) * u64::from((3 * right) ^ top);
+
+        // Rotate the quadrant so the curve inside it starts and ends in the right corners
+        if top == 0 {
+            if right == 1 {
+                x = size - 1 - x;
+                y = size - 1 - y;
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        half /= 2;
+    }
+
+    index
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::hittable::HittableList;
+    use crate::progress::SilentObserver;
+    use crate::scene::{Scene, SceneBuilder};
+    use crate::{Camera, Vec3};
+
+    #[test]
+    fn test_tiles_cover_image_once() {
+        let (image_width, image_height) = (50, 37);
+
+        for order in [TileOrder::Raster, TileOrder::Spiral, TileOrder::Hilbert] {
+            let tiles = TileScheduler::new()
+                .tile_size(16)
+                .order(order)
+                .tiles(image_width, image_height);
+
+            let mut covered = vec![0; (image_width * image_height) as usize];
+            for tile in &tiles {
+                for y in tile.min.1..tile.max.1 {
+                    for x in tile.min.0..tile.max.0 {
+                        covered[(y * image_width + x) as usize] += 1;
+                    }
+                }
+            }
+            assert!(covered.iter().all(|&count| count == 1), "{order:?}");
+        }
+    }
+
+    #[test]
+    fn test_orders() {
+        let scheduler = TileScheduler::new().tile_size(10);
+
+        // The first tile of a spiral is the one in the middle
+        let spiral = scheduler.order(TileOrder::Spiral).tiles(50, 50);
+        assert_eq!(spiral[0].min, (20, 20));
+
+        // Consecutive tiles of a Hilbert curve are next to each other
+        let scheduler = TileScheduler::new().tile_size(10);
+        let hilbert = scheduler.order(TileOrder::Hilbert).tiles(80, 80);
+        for pair in hilbert.windows(2) {
+            let distance =
+                pair[0].min.0.abs_diff(pair[1].min.0) + pair[0].min.1.abs_diff(pair[1].min.1);
+            assert_eq!(distance, 10);
+        }
+    }
+
+    fn scene() -> Scene<HittableList> {
+        let camera = Camera::new(
+            Vec3::new(0.0, 0.0, 1.0),
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            40.0,
+            1.0,
+            0.0,
+            1.0,
+            (0.0, 1.0),
+        );
+
+        SceneBuilder::new(HittableList::new(), camera, 1.0)
+            .background_color(Color::new(0.25, 0.5, 0.75))
+            .image_width(32)
+            .samples_per_pixel(2)
+            .max_depth(2)
+            .observer(SilentObserver)
+            .build()
+    }
+
+    #[test]
+    fn test_callback_and_cancellation() {
+        let scene = scene();
+
+        let finished = Arc::new(Mutex::new(Vec::new()));
+        let tiles = Arc::clone(&finished);
+        let scheduler = TileScheduler::new()
+            .tile_size(8)
+            .on_tile(move |tile| tiles.lock().unwrap().push((tile.min, tile.colors.len())));
+        assert!(crate::render_scheduled(&scene, &scheduler).is_some());
+
+        let mut finished = finished.lock().unwrap().clone();
+        finished.sort_unstable();
+        assert_eq!(finished.len(), 16);
+        assert!(finished.iter().all(|&(_, pixels)| pixels == 64));
+
+        // Cancelling it after the first tile stops the render
+        let token = CancellationToken::new();
+        let cancel = token.clone();
+        let scheduler = TileScheduler::new()
+            .tile_size(8)
+            .cancellation_token(token)
+            .on_tile(move |_| cancel.cancel());
+        assert!(crate::render_scheduled(&scene, &scheduler).is_none());
+    }
+}
@@ -0,0 +1,193 @@
+//! Testing 4 rays against a box at once
+//!
+//! Everything is stored in arrays of 4 `f32`, and every operation does the same thing to every
+//! lane, so the compiler turns them into single SIMD instructions. `RayPacket` has 4 rays stored
+//! lane by lane to test them against an `Aabbf` at once, which is how BVHs find where the rays
+//! from the camera hit. Everything else stays in double precision.
+
+use std::ops;
+
+use crate::surfaces::Aabb;
+use crate::{Float, Ray};
+
+/// 4 `f32` that are operated on at the same time
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[repr(C, align(16))]
+pub struct F32x4(pub [f32; 4]);
+
+impl F32x4 {
+    pub const fn splat(value: f32) -> Self {
+        Self([value; 4])
+    }
+
+    fn map(self, other: Self, f: impl Fn(f32, f32) -> f32) -> Self {
+        Self(std::array::from_fn(|i| f(self.0[i], other.0[i])))
+    }
+
+    /// The smaller of every lane. Unlike `f32::min`, it's `other` if a lane is NaN,
+    /// which is what makes it a single instruction.
+    #[must_use]
+    pub fn min(self, other: Self) -> Self {
+        self.map(other, |a, b| if a < b { a } else { b })
+    }
+    /// The bigger of every lane, and `other` if a lane is NaN
+    #[must_use]
+    pub fn max(self, other: Self) -> Self {
+        self.map(other, |a, b| if a > b { a } else { b })
+    }
+
+    /// Whether every lane is less than or equal to the same lane of `other`
+    pub fn le(self, other: Self) -> [bool; 4] {
+        std::array::from_fn(|i| self.0[i] <= other.0[i])
+    }
+
+    /// The sum of all the lanes
+    pub fn sum(self) -> f32 {
+        (self.0[0] + self.0[1]) + (self.0[2] + self.0[3])
+    }
+}
+
+impl ops::Add for F32x4 {
+    type Output = Self;
+    fn add(self, other: Self) -> Self {
+        self.map(other, ops::Add::add)
+    }
+}
+impl ops::Sub for F32x4 {
+    type Output = Self;
+    fn sub(self, other: Self) -> Self {
+        self.map(other, ops::Sub::sub)
+    }
+}
+impl ops::Mul for F32x4 {
+    type Output = Self;
+    fn mul(self, other: Self) -> Self {
+        self.map(other, ops::Mul::mul)
+    }
+}
+impl ops::Div for F32x4 {
+    type Output = Self;
+    fn div(self, other: Self) -> Self {
+        self.map(other, ops::Div::div)
+    }
+}
+
+/// The closest `f32` that's smaller than `value`, or equal
+fn rounded_down(value: f64) -> f32 {
+    let rounded = value as f32;
+    if f64::from(rounded) > value {
+        rounded.next_down()
+    } else {
+        rounded
+    }
+}
+
+/// The closest `f32` that's bigger than `value`, or equal
+fn rounded_up(value: f64) -> f32 {
+    -rounded_down(-value)
+}
+
+/// 4 rays, with each coordinate of their origins and directions stored together
+/// so they can be tested against a box at once
+#[derive(Clone, Copy, Debug)]
+pub struct RayPacket {
+    /// The rays, to test them one by one against surfaces
+    pub rays: [Ray; 4],
+    /// The x, y and z of the origins
+    pub origin: [F32x4; 3],
+    /// The x, y and z of the inverses of the directions
+    pub inverse_direction: [F32x4; 3],
+}
+
+impl RayPacket {
+    pub fn new(rays: [Ray; 4]) -> Self {
+        let lanes = |f: &dyn Fn(&Ray, u8) -> Float| {
+            [0, 1, 2].map(|axis| F32x4(rays.each_ref().map(|ray| f(ray, axis) as f32)))
+        };
+
+        Self {
+            rays,
+            origin: lanes(&|ray, axis| ray.origin[axis]),
+            inverse_direction: lanes(&|ray, axis| 1.0 / ray.direction[axis]),
+        }
+    }
+}
+
+/// An `Aabb` in single precision, made a bit bigger so it always has the whole `Aabb` inside
+#[derive(Clone, Debug)]
+pub struct Aabbf {
+    minimum: [f32; 3],
+    maximum: [f32; 3],
+}
+
+impl Aabbf {
+    /// Like `Aabb::hit`, but for the 4 rays of `packet` at once, each one with its own `t_max`.
+    ///
+    /// The range is also made a bit bigger, so it can say a ray hits when it barely misses,
+    /// but never the other way around.
+    pub fn hit_packet(&self, packet: &RayPacket, t_min: f64, t_max: [f64; 4]) -> [bool; 4] {
+        let mut t_enter = F32x4::splat(t_min as f32);
+        let mut t_exit = F32x4(t_max.map(|t| t as f32));
+
+        for axis in 0..3 {
+            let origin = packet.origin[axis];
+            let inverse_direction = packet.inverse_direction[axis];
+
+            let t0 = (F32x4::splat(self.minimum[axis]) - origin) * inverse_direction;
+            let t1 = (F32x4::splat(self.maximum[axis]) - origin) * inverse_direction;
+
+            t_enter = t_enter.max(t0.min(t1));
+            t_exit = t_exit.min(t0.max(t1));
+        }
+
+        // Rounding errors of the 3 operations of every slab
+        let error = 2.0f32.mul_add(3.0 * f32::EPSILON, 1.0);
+        t_enter.le(t_exit * F32x4::splat(error))
+    }
+}
+
+impl From<&Aabb> for Aabbf {
+    fn from(aabb: &Aabb) -> Self {
+        Self {
+            minimum: [aabb.minimum.x(), aabb.minimum.y(), aabb.minimum.z()].map(rounded_down),
+            maximum: [aabb.maximum.x(), aabb.maximum.y(), aabb.maximum.z()].map(rounded_up),
+        }
+    }
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    #![allow(clippy::float_cmp)]
+    use super::*;
+    use crate::Vec3;
+
+    #[test]
+    fn test_rounding() {
+        for value in [0.1, -0.1, 1.0 / 3.0, 1e-40] {
+            assert!(f64::from(rounded_down(value)) <= value);
+            assert!(f64::from(rounded_up(value)) >= value);
+        }
+        assert_eq!(rounded_down(2.0), 2.0);
+        assert_eq!(rounded_up(2.0), 2.0);
+    }
+
+    #[test]
+    fn test_hit_packet() {
+        let aabb = Aabbf::from(&Aabb::new(
+            Vec3::new(-1.0, -1.0, -1.0),
+            Vec3::new(1.0, 1.0, 1.0),
+        ));
+        let origin = Vec3::new(0.0, 0.0, 5.0);
+        let rays = [(0.0, 0.0), (0.24, 0.0), (0.0, 0.26), (0.5, 0.5)]
+            .map(|(x, y)| Ray::new(origin, Vec3::new(x, y, -1.0), 0.0));
+
+        // The front of the box is 4 away, so the second ray barely hits it and the third
+        // barely misses it
+        let hits = aabb.hit_packet(&RayPacket::new(rays), 0.001, [f64::INFINITY; 4]);
+        assert_eq!(hits, [true, true, false, false]);
+        // And nothing is hit past `t_max`
+        let hits = aabb.hit_packet(&RayPacket::new(rays), 0.001, [3.0; 4]);
+        assert_eq!(hits, [false; 4]);
+    }
+}
@@ -0,0 +1,117 @@
+//! Statistics about a render
+//!
+//! Rays, BVH nodes and primitives are counted separately by every thread, so counting them
+//! doesn't slow the threads down waiting for each other.
+
+use std::cell::Cell;
+use std::fmt;
+use std::time::Duration;
+
+use crate::integrators::PathStats;
+
+/// How much work finding what rays hit took
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RayCounters {
+    /// Rays traced through the world of a scene
+    pub rays: u64,
+    /// BVH nodes whose children were tested, after the ray hit their bounding box
+    pub bvh_node_visits: u64,
+    /// Surfaces tested directly by lists of surfaces and the leaves of BVHs
+    pub primitive_tests: u64,
+}
+
+impl RayCounters {
+    const ZERO: Self = Self {
+        rays: 0,
+        bvh_node_visits: 0,
+        primitive_tests: 0,
+    };
+
+    /// Adds the counts of `other`, like when joining the counters of different threads
+    pub const fn merge(&mut self, other: &Self) {
+        self.rays += other.rays;
+        self.bvh_node_visits += other.bvh_node_visits;
+        self.primitive_tests += other.primitive_tests;
+    }
+
+    /// The counts since `earlier`, which were the counters of the same thread
+    #[must_use]
+    pub const fn since(&self, earlier: &Self) -> Self {
+        Self {
+            rays: self.rays - earlier.rays,
+            bvh_node_visits: self.bvh_node_visits - earlier.bvh_node_visits,
+            primitive_tests: self.primitive_tests - earlier.primitive_tests,
+        }
+    }
+
+    /// Everything counted by the current thread so far
+    pub fn current_thread() -> Self {
+        COUNTERS.with(Cell::get)
+    }
+}
+
+thread_local! {
+    static COUNTERS: Cell<RayCounters> = const { Cell::new(RayCounters::ZERO) };
+}
+
+fn count(add: impl FnOnce(&mut RayCounters)) {
+    COUNTERS.with(|counters| {
+        let mut current = counters.get();
+        add(&mut current);
+        counters.set(current);
+    });
+}
+
+pub(crate) fn count_ray() {
+    count(|counters| counters.rays += 1);
+}
+
+pub(crate) fn count_bvh_node_visit() {
+    count(|counters| counters.bvh_node_visits += 1);
+}
+
+pub(crate) fn count_primitive_tests(tests: u64) {
+    count(|counters| counters.primitive_tests += tests);
+}
+
+/// Statistics about everything that was rendered
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RenderStats {
+    pub paths: PathStats,
+    pub counters: RayCounters,
+    /// How long it took. For a single tile, it's how long its thread spent on it.
+    pub duration: Duration,
+}
+
+impl RenderStats {
+    /// Adds the paths and counters of `other`, but not its duration, since tiles are rendered
+    /// at the same time
+    pub fn merge(&mut self, other: &Self) {
+        self.paths.merge(&other.paths);
+        self.counters.merge(&other.counters);
+    }
+
+    #[allow(clippy::cast_precision_loss)] // Counts don't get anywhere close to 2^52
+    pub fn rays_per_second(&self) -> f64 {
+        let seconds = self.duration.as_secs_f64();
+        if seconds == 0.0 {
+            return 0.0;
+        }
+
+        self.counters.rays as f64 / seconds
+    }
+}
+
+impl fmt::Display for RenderStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.paths)?;
+        write!(
+            f,
+            "{} rays, {:.0} rays per second. {} BVH nodes visited, {} primitives tested.",
+            self.counters.rays,
+            self.rays_per_second(),
+            self.counters.bvh_node_visits,
+            self.counters.primitive_tests
+        )
+    }
+}
@@ -1,16 +1,20 @@
+use std::sync::Arc;
+
 use crate::{
-    hittable::{Hittable, HittableList},
+    hittable::{HitRecord, Hittable, HittableList},
     materials::Material,
-    Vec3,
+    vec3::to_f64,
+    Float, Ray, Vec3,
 };
 
-use super::{Aabb, XYRect, XZRect, YZRect};
+use super::{Aabb, Interval, Solid, XYRect, XZRect, YZRect};
 
 #[derive(Clone)]
 pub struct AABox {
     box_min: Vec3,
     box_max: Vec3,
     sides: HittableList,
+    material: Arc<dyn Material>,
 }
 impl AABox {
     pub fn new<T: Material + Clone + 'static>(p0: Vec3, p1: Vec3, material: T) -> Self {
@@ -54,22 +58,87 @@ impl AABox {
             (p0.y(), p1.y()),
             (p0.z(), p1.z()),
             p1.x(),
-            material,
+            material.clone(),
         ));
 
         Self {
             box_min,
             box_max,
             sides,
+            material: Arc::new(material),
         }
     }
+
+    /// Builds the hit record on the face of the box perpendicular to `axis`,
+    /// using the same texture coordinates as the rectangles making up the sides.
+    fn record_at(&self, ray: &Ray, t: f64, axis: u8, outward_sign: f64) -> HitRecord {
+        let hit_point = ray.at(t);
+        let mut record = HitRecord::new(t, hit_point, Arc::clone(&self.material));
+
+        let relative = (hit_point - self.box_min) / (self.box_max - self.box_min);
+        let (u, v) = match axis {
+            0 => (relative.y(), relative.z()),
+            1 => (relative.x(), relative.z()),
+            _ => (relative.x(), relative.y()),
+        };
+        record.set_texture_coordinates(u, v);
+        let mut u_direction = Vec3::new(0.0, 0.0, 0.0);
+        u_direction[u8::from(axis == 0)] = 1.0;
+        record.set_tangent(u_direction);
+
+        let mut outward_normal = Vec3::new(0.0, 0.0, 0.0);
+        outward_normal[axis] = 1.0;
+        record.set_face_normal(ray, outward_normal * outward_sign);
+
+        record
+    }
 }
 impl Hittable for AABox {
-    fn hit(&self, ray: &crate::Ray, t_min: f64, t_max: f64) -> Option<crate::hittable::HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
         self.sides.hit(ray, t_min, t_max)
     }
 
-    fn bounding_box(&self, _time: (f64, f64)) -> Option<super::Aabb> {
+    fn bounding_box(&self, _time: (f64, f64)) -> Option<Aabb> {
         Some(Aabb::new(self.box_min, self.box_max))
     }
 }
+impl Solid for AABox {
+    fn intervals(&self, ray: &Ray) -> Vec<Interval> {
+        // Slab method, keeping track of which face the ray enters and exits through
+        let (mut t_enter, mut enter_axis) = (Float::NEG_INFINITY, 0);
+        let (mut t_exit, mut exit_axis) = (Float::INFINITY, 0);
+
+        for axis in 0_u8..3 {
+            let inverse_direction = 1.0 / ray.direction[axis];
+
+            let mut t0 = (self.box_min[axis] - ray.origin[axis]) * inverse_direction;
+            let mut t1 = (self.box_max[axis] - ray.origin[axis]) * inverse_direction;
+
+            if inverse_direction < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+
+            if t0 > t_enter {
+                t_enter = t0;
+                enter_axis = axis;
+            }
+            if t1 < t_exit {
+                t_exit = t1;
+                exit_axis = axis;
+            }
+        }
+
+        if t_exit <= t_enter {
+            return Vec::new();
+        }
+
+        // The ray enters through the face that looks against it and exits through the opposite one
+        let enter_sign = -ray.direction[enter_axis].signum();
+        let exit_sign = ray.direction[exit_axis].signum();
+
+        vec![Interval::new(
+            self.record_at(ray, to_f64(t_enter), enter_axis, to_f64(enter_sign)),
+            self.record_at(ray, to_f64(t_exit), exit_axis, to_f64(exit_sign)),
+        )]
+    }
+}
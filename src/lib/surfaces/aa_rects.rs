@@ -1,3 +1,5 @@
+use std::sync::Arc;
+
 use super::Aabb;
 use crate::hittable::{HitRecord, Hittable};
 use crate::materials::Material;
@@ -9,11 +11,16 @@ pub struct XYRect<T: Material + Clone + 'static> {
     x: (f64, f64),
     y: (f64, f64),
     k: f64,
-    material: T,
+    material: Arc<T>,
 }
 impl<T: Material + Clone + 'static> XYRect<T> {
     pub fn new(x: (f64, f64), y: (f64, f64), k: f64, material: T) -> Self {
-        Self { x, y, k, material }
+        Self {
+            x,
+            y,
+            k,
+            material: Arc::new(material),
+        }
     }
 }
 impl<T: Material + Clone + 'static> Hittable for XYRect<T> {
@@ -38,6 +45,12 @@ impl<T: Material + Clone + 'static> Hittable for XYRect<T> {
         record.v = (hit_point.y() - self.y.0) / (self.y.1 - self.y.0);
         let outward_normal = Vec3::new(0.0, 0.0, 1.0);
         record.set_face_normal(ray, outward_normal);
+        record.set_tangent(Vec3::new(1.0, 0.0, 0.0));
+        record.set_differentials(
+            ray,
+            Vec3::new(self.x.1 - self.x.0, 0.0, 0.0),
+            Vec3::new(0.0, self.y.1 - self.y.0, 0.0),
+        );
 
         Some(record)
     }
@@ -50,6 +63,22 @@ impl<T: Material + Clone + 'static> Hittable for XYRect<T> {
             Vec3::new(self.x.1, self.y.1, self.k + 0.0001),
         ))
     }
+
+    fn area(&self) -> f64 {
+        (self.x.1 - self.x.0) * (self.y.1 - self.y.0)
+    }
+
+    fn sample_surface(&self, (u, v): (f64, f64)) -> Option<HitRecord> {
+        let x = (self.x.1 - self.x.0).mul_add(u, self.x.0);
+        let y = (self.y.1 - self.y.0).mul_add(v, self.y.0);
+
+        let mut record = HitRecord::new(0.0, Vec3::new(x, y, self.k), self.material.clone());
+        record.set_texture_coordinates(u, v);
+        record.normal = Vec3::new(0.0, 0.0, 1.0);
+        record.front_face = true;
+
+        Some(record)
+    }
 }
 
 #[derive(Clone)]
@@ -57,11 +86,16 @@ pub struct XZRect<T: Material + Clone + 'static> {
     x: (f64, f64),
     z: (f64, f64),
     k: f64,
-    material: T,
+    material: Arc<T>,
 }
 impl<T: Material + Clone + 'static> XZRect<T> {
     pub fn new(x: (f64, f64), z: (f64, f64), k: f64, material: T) -> Self {
-        Self { x, z, k, material }
+        Self {
+            x,
+            z,
+            k,
+            material: Arc::new(material),
+        }
     }
 }
 impl<T: Material + Clone + 'static> Hittable for XZRect<T> {
@@ -86,6 +120,12 @@ impl<T: Material + Clone + 'static> Hittable for XZRect<T> {
         record.v = (hit_point.z() - self.z.0) / (self.z.1 - self.z.0);
         let outward_normal = Vec3::new(0.0, 1.0, 0.0);
         record.set_face_normal(ray, outward_normal);
+        record.set_tangent(Vec3::new(1.0, 0.0, 0.0));
+        record.set_differentials(
+            ray,
+            Vec3::new(self.x.1 - self.x.0, 0.0, 0.0),
+            Vec3::new(0.0, 0.0, self.z.1 - self.z.0),
+        );
 
         Some(record)
     }
@@ -98,6 +138,22 @@ impl<T: Material + Clone + 'static> Hittable for XZRect<T> {
             Vec3::new(self.x.1, self.k + 0.0001, self.z.1),
         ))
     }
+
+    fn area(&self) -> f64 {
+        (self.x.1 - self.x.0) * (self.z.1 - self.z.0)
+    }
+
+    fn sample_surface(&self, (u, v): (f64, f64)) -> Option<HitRecord> {
+        let x = (self.x.1 - self.x.0).mul_add(u, self.x.0);
+        let z = (self.z.1 - self.z.0).mul_add(v, self.z.0);
+
+        let mut record = HitRecord::new(0.0, Vec3::new(x, self.k, z), self.material.clone());
+        record.set_texture_coordinates(u, v);
+        record.normal = Vec3::new(0.0, 1.0, 0.0);
+        record.front_face = true;
+
+        Some(record)
+    }
 }
 
 #[derive(Clone)]
@@ -105,11 +161,16 @@ pub struct YZRect<T: Material + Clone + 'static> {
     y: (f64, f64),
     z: (f64, f64),
     k: f64,
-    material: T,
+    material: Arc<T>,
 }
 impl<T: Material + Clone + 'static> YZRect<T> {
     pub fn new(y: (f64, f64), z: (f64, f64), k: f64, material: T) -> Self {
-        Self { y, z, k, material }
+        Self {
+            y,
+            z,
+            k,
+            material: Arc::new(material),
+        }
     }
 }
 impl<T: Material + Clone + 'static> Hittable for YZRect<T> {
@@ -134,6 +195,12 @@ impl<T: Material + Clone + 'static> Hittable for YZRect<T> {
         record.v = (hit_point.z() - self.z.0) / (self.z.1 - self.z.0);
         let outward_normal = Vec3::new(1.0, 0.0, 0.0);
         record.set_face_normal(ray, outward_normal);
+        record.set_tangent(Vec3::new(0.0, 1.0, 0.0));
+        record.set_differentials(
+            ray,
+            Vec3::new(0.0, self.y.1 - self.y.0, 0.0),
+            Vec3::new(0.0, 0.0, self.z.1 - self.z.0),
+        );
 
         Some(record)
     }
@@ -146,4 +213,52 @@ impl<T: Material + Clone + 'static> Hittable for YZRect<T> {
             Vec3::new(self.k + 0.0001, self.y.1, self.z.1),
         ))
     }
+
+    fn area(&self) -> f64 {
+        (self.y.1 - self.y.0) * (self.z.1 - self.z.0)
+    }
+
+    fn sample_surface(&self, (u, v): (f64, f64)) -> Option<HitRecord> {
+        let y = (self.y.1 - self.y.0).mul_add(u, self.y.0);
+        let z = (self.z.1 - self.z.0).mul_add(v, self.z.0);
+
+        let mut record = HitRecord::new(0.0, Vec3::new(self.k, y, z), self.material.clone());
+        record.set_texture_coordinates(u, v);
+        record.normal = Vec3::new(1.0, 0.0, 0.0);
+        record.front_face = true;
+
+        Some(record)
+    }
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::Dielectric;
+    use crate::RayDifferentials;
+
+    #[test]
+    fn test_differentials() {
+        let rect = XYRect::new((-1.0, 1.0), (-2.0, 2.0), 0.0, Dielectric::new(1.5));
+        let origin = Vec3::new(0.0, 0.0, 1.0);
+        let ray = Ray::new(origin, Vec3::new(0.0, 0.0, -1.0), 0.0);
+
+        // Without differentials there's nothing to know
+        assert!(rect.hit(&ray, 0.0, 10.0).unwrap().differentials.is_none());
+
+        // The next pixels are 0.01 to the right and 0.02 up on the rectangle
+        let ray = ray.with_differentials(RayDifferentials {
+            x_origin: origin,
+            x_direction: Vec3::new(0.01, 0.0, -1.0),
+            y_origin: origin,
+            y_direction: Vec3::new(0.0, 0.02, -1.0),
+        });
+        let differentials = rect.hit(&ray, 0.0, 10.0).unwrap().differentials.unwrap();
+
+        assert!((differentials.du_dx - 0.005).abs() < 1e-9);
+        assert!(differentials.dv_dx.abs() < 1e-9);
+        assert!(differentials.du_dy.abs() < 1e-9);
+        assert!((differentials.dv_dy - 0.005).abs() < 1e-9);
+    }
 }
@@ -1,3 +1,5 @@
+use crate::vec3::to_f64;
+use crate::Float;
 use crate::Ray;
 use crate::Vec3;
 
@@ -16,8 +18,8 @@ impl Aabb {
     pub fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> bool {
         // Optimized version by Andrew Kensler
 
-        let mut t_min = t_min;
-        let mut t_max = t_max;
+        let mut t_min = t_min as Float;
+        let mut t_max = t_max as Float;
 
         for a in 0_u8..3 {
             let inverse_direction = 1.0 / ray.direction[a];
@@ -40,6 +42,32 @@ impl Aabb {
         true
     }
 
+    /// Returns the part of the range `t_min` < `t` < `t_max` where the ray is inside the box
+    pub fn clip(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<(f64, f64)> {
+        let mut t_min = t_min as Float;
+        let mut t_max = t_max as Float;
+
+        for a in 0_u8..3 {
+            let inverse_direction = 1.0 / ray.direction[a];
+
+            let mut t0 = (self.minimum[a] - ray.origin[a]) * inverse_direction;
+            let mut t1 = (self.maximum[a] - ray.origin[a]) * inverse_direction;
+
+            if inverse_direction < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+
+            t_min = t_min.max(t0);
+            t_max = t_max.min(t1);
+
+            if t_max <= t_min {
+                return None;
+            }
+        }
+
+        Some((to_f64(t_min), to_f64(t_max)))
+    }
+
     pub fn surrounding_box(box0: &Self, box1: &Self) -> Self {
         let min = Vec3::new(
             f64::min(box0.minimum.x(), box1.minimum.x()),
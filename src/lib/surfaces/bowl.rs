@@ -1,4 +1,5 @@
 use std::f64::consts::{PI, TAU};
+use std::sync::Arc;
 
 use crate::{
     hittable::{HitRecord, Hittable},
@@ -6,13 +7,13 @@ use crate::{
     Ray, Vec3,
 };
 
-use super::Aabb;
+use super::{parabola::parabola_arc_length, Aabb};
 
 #[derive(Clone)]
 pub struct Bowl<T: Material + Clone + 'static> {
     radius: f64,
     curvature: f64,
-    material: T,
+    material: Arc<T>,
 }
 
 impl<T: Material + Clone + 'static> Bowl<T> {
@@ -20,7 +21,7 @@ impl<T: Material + Clone + 'static> Bowl<T> {
         Self {
             radius,
             curvature,
-            material,
+            material: Arc::new(material),
         }
     }
 
@@ -28,10 +29,10 @@ impl<T: Material + Clone + 'static> Bowl<T> {
         // A map of the Earth should end up looking kind of like the UN logo
         (
             1.0 - (f64::atan2(hit.z(), hit.x()) + PI) / TAU,
-            // This one is incorrect, it maps the v coordinate linearly depending
-            // on how far it is from the center divided by the radius. But since
-            // the surface is curved, this should be calculated in a different way.
-            1.0 - dist_from_origin / self.radius,
+            // The v coordinate follows the length along the curved surface from the rim
+            // to the center, so the texture doesn't get stretched near the rim
+            1.0 - parabola_arc_length(self.curvature, 0.0, 0.0, dist_from_origin)
+                / parabola_arc_length(self.curvature, 0.0, 0.0, self.radius),
         )
     }
 }
@@ -4,6 +4,8 @@ use std::sync::Arc;
 
 use super::Aabb;
 use crate::hittable::{HitRecord, Hittable};
+use crate::simd::{Aabbf, RayPacket};
+use crate::stats;
 use crate::vec3::Axis;
 use crate::Ray;
 
@@ -11,6 +13,10 @@ pub struct BvhNode {
     left: Arc<dyn Hittable>,
     right: Arc<dyn Hittable>,
     aabb: Aabb,
+    /// The same box, for testing ray packets against it
+    packet_aabb: Aabbf,
+    /// How many of the children are surfaces instead of other nodes, for the statistics
+    primitives: u64,
 }
 
 impl BvhNode {
@@ -26,10 +32,13 @@ impl BvhNode {
             .bounding_box(time)
             .expect("No bounding box in bvh_node constructor.");
 
+        let aabb = Aabb::surrounding_box(&box_left, &box_right);
         Self {
             left: Arc::new(left),
             right: Arc::new(right),
-            aabb: Aabb::surrounding_box(&box_left, &box_right),
+            packet_aabb: Aabbf::from(&aabb),
+            aabb,
+            primitives: 2,
         }
     }
     pub fn from_vec(src_objects: Vec<Arc<dyn Hittable>>, time: (f64, f64)) -> Self {
@@ -43,16 +52,16 @@ impl BvhNode {
             Axis::Z => |a, b| box_compare(a, b, Axis::Z),
         };
 
-        let (left, right) = match objects.len() {
-            1 => (objects[0].clone(), objects[0].clone()),
+        let (left, right, primitives) = match objects.len() {
+            1 => (objects[0].clone(), objects[0].clone(), 2),
             2 => {
                 let order_of_objects = comparison_fn(&objects[0], &objects[1]);
                 let first_is_left = matches!(order_of_objects, Ordering::Less);
 
                 if first_is_left {
-                    (objects[0].clone(), objects[1].clone())
+                    (objects[0].clone(), objects[1].clone(), 2)
                 } else {
-                    (objects[1].clone(), objects[0].clone())
+                    (objects[1].clone(), objects[0].clone(), 2)
                 }
             }
             _ => {
@@ -65,6 +74,7 @@ impl BvhNode {
                 (
                     Arc::new(Self::from_vec(list_a.to_vec(), time)) as Arc<dyn Hittable>,
                     Arc::new(Self::from_vec(list_b.to_vec(), time)) as Arc<dyn Hittable>,
+                    0,
                 )
             }
         };
@@ -76,10 +86,13 @@ impl BvhNode {
             .bounding_box(time)
             .expect("No bounding box in bvh_node constructor.");
 
+        let aabb = Aabb::surrounding_box(&box_left, &box_right);
         Self {
             left,
             right,
-            aabb: Aabb::surrounding_box(&box_left, &box_right),
+            packet_aabb: Aabbf::from(&aabb),
+            aabb,
+            primitives,
         }
     }
 }
@@ -109,6 +122,8 @@ impl Hittable for BvhNode {
         if !self.aabb.hit(ray, t_min, t_max) {
             return None;
         };
+        stats::count_bvh_node_visit();
+        stats::count_primitive_tests(self.primitives);
 
         let hit_left: Option<HitRecord> = self.left.hit(ray, t_min, t_max);
 
@@ -126,6 +141,46 @@ impl Hittable for BvhNode {
         )
     }
 
+    fn hit_packet(
+        &self,
+        packet: &RayPacket,
+        t_min: f64,
+        t_max: &mut [f64; 4],
+        hits: &mut [Option<HitRecord>; 4],
+    ) {
+        let hits_box = self.packet_aabb.hit_packet(packet, t_min, *t_max);
+
+        // A single ray is faster on its own
+        match hits_box.iter().filter(|&&hit| hit).count() {
+            0 => return,
+            1 => {
+                let i = hits_box.iter().position(|&hit| hit).unwrap_or_default();
+                if let Some(hit) = self.hit(&packet.rays[i], t_min, t_max[i]) {
+                    t_max[i] = hit.t;
+                    hits[i] = Some(hit);
+                }
+                return;
+            }
+            _ => {}
+        }
+        stats::count_bvh_node_visit();
+        stats::count_primitive_tests(self.primitives);
+
+        // Rays that missed the box don't go any further
+        let mut t_max_inside = *t_max;
+        for i in (0..4).filter(|&i| !hits_box[i]) {
+            t_max_inside[i] = t_min;
+        }
+
+        self.left.hit_packet(packet, t_min, &mut t_max_inside, hits);
+        self.right
+            .hit_packet(packet, t_min, &mut t_max_inside, hits);
+
+        for i in (0..4).filter(|&i| hits_box[i]) {
+            t_max[i] = t_max_inside[i];
+        }
+    }
+
     fn bounding_box(&self, _time: (f64, f64)) -> Option<Aabb> {
         Some(self.aabb.clone())
     }
@@ -159,6 +214,38 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_hit_packet() {
+        let mut rng = rand::thread_rng();
+        let mut scene: Vec<Arc<dyn Hittable>> = Vec::new();
+        for _ in 0..50 {
+            let center = Vec3::random_min_max(-10.0, 10.0);
+            scene.push(Arc::new(Sphere::new(center, 1.0, Dielectric::new(1.5))));
+        }
+        let bvh = BvhNode::from_vec(scene, (0.0, 1.0));
+
+        for _ in 0..100 {
+            let rays = [(); 4].map(|()| {
+                let origin = Vec3::random_min_max(-20.0, 20.0);
+                Ray::new(origin, Vec3::random_unit_vector(), 0.0)
+            });
+            let t_max = [(); 4].map(|()| rng.gen_range(1.0..40.0));
+
+            let mut packet_hits = [None, None, None, None];
+            let mut packet_t_max = t_max;
+            bvh.hit_packet(
+                &RayPacket::new(rays),
+                0.001,
+                &mut packet_t_max,
+                &mut packet_hits,
+            );
+            for (i, packet_hit) in packet_hits.iter().enumerate() {
+                let hit = bvh.hit(&rays[i], 0.001, t_max[i]);
+                assert_eq!(hit.map(|hit| hit.t), packet_hit.as_ref().map(|hit| hit.t));
+            }
+        }
+    }
+
     #[test]
     fn test_new_2() {
         let mut scene: Vec<Arc<dyn Hittable>> = Vec::new();
@@ -1,4 +1,5 @@
 use rand::Rng;
+use std::sync::Arc;
 
 use super::Aabb;
 use crate::hittable::HitRecord;
@@ -9,7 +10,7 @@ use crate::{Ray, Vec3};
 #[derive(Clone)]
 pub struct ConstantMedium<T: Hittable, G: Material + Clone> {
     boundary: T,
-    phase_function: G,
+    phase_function: Arc<G>,
     neg_inv_density: f64,
 }
 
@@ -17,7 +18,7 @@ impl<T: Hittable, S: Texture> ConstantMedium<T, Isotropic<S>> {
     pub fn new(boundary: T, texture: S, density: f64) -> Self {
         Self {
             boundary,
-            phase_function: Isotropic::new(texture),
+            phase_function: Arc::new(Isotropic::new(texture)),
             neg_inv_density: -1.0 / density,
         }
     }
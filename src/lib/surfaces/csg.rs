@@ -0,0 +1,296 @@
+use std::cmp::Ordering;
+
+use super::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::Ray;
+use crate::Vec3;
+
+/// A span along a ray where the ray is inside a solid
+pub struct Interval {
+    /// Where the ray enters the solid
+    pub enter: HitRecord,
+    /// Where the ray exits the solid
+    pub exit: HitRecord,
+}
+impl Interval {
+    pub const fn new(enter: HitRecord, exit: HitRecord) -> Self {
+        Self { enter, exit }
+    }
+}
+
+/// A closed surface with a well defined inside and outside
+///
+/// Unlike `Hittable::hit`, which only returns the nearest intersection, a `Solid`
+/// can report every span of a ray that lies inside of it, which is what's needed
+/// to combine solids with `Csg` operations.
+pub trait Solid: Hittable {
+    /// Returns every interval along the whole ray (for any `t`, even negative ones)
+    /// where the ray is inside of the solid.
+    ///
+    /// The intervals are sorted by `t` and don't overlap. The normals of the hit records
+    /// are set with `HitRecord::set_face_normal` against the given ray.
+    fn intervals(&self, ray: &Ray) -> Vec<Interval>;
+}
+
+/// Returns the first boundary of the intervals that's within `t_min` < `t` < `t_max`
+fn first_boundary_in_range(intervals: Vec<Interval>, t_min: f64, t_max: f64) -> Option<HitRecord> {
+    intervals
+        .into_iter()
+        .flat_map(|interval| [interval.enter, interval.exit])
+        .find(|boundary| boundary.t > t_min && boundary.t < t_max)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CsgOperation {
+    /// Inside either of the solids
+    Union,
+    /// Inside both of the solids
+    Intersection,
+    /// Inside the left solid, but not inside the right one
+    Difference,
+}
+impl CsgOperation {
+    const fn is_inside(self, inside_left: bool, inside_right: bool) -> bool {
+        match self {
+            Self::Union => inside_left || inside_right,
+            Self::Intersection => inside_left && inside_right,
+            Self::Difference => inside_left && !inside_right,
+        }
+    }
+}
+
+/// Constructive solid geometry node combining two solids
+///
+/// Since it's a `Solid` too, nodes can be nested to build more complex shapes.
+#[derive(Clone)]
+pub struct Csg<A: Solid, B: Solid> {
+    left: A,
+    right: B,
+    operation: CsgOperation,
+    /// Found once, since finding it goes through every node below this one
+    aabb: Option<Aabb>,
+}
+
+impl<A: Solid, B: Solid> Csg<A, B> {
+    pub fn new(left: A, right: B, operation: CsgOperation) -> Self {
+        let aabb = Self::combined_box(
+            left.bounding_box((0.0, 1.0)),
+            right.bounding_box((0.0, 1.0)),
+            operation,
+        );
+        Self {
+            left,
+            right,
+            operation,
+            aabb,
+        }
+    }
+    pub fn union(left: A, right: B) -> Self {
+        Self::new(left, right, CsgOperation::Union)
+    }
+    pub fn intersection(left: A, right: B) -> Self {
+        Self::new(left, right, CsgOperation::Intersection)
+    }
+    /// The left solid with the right solid cut out of it
+    pub fn difference(left: A, right: B) -> Self {
+        Self::new(left, right, CsgOperation::Difference)
+    }
+
+    fn combined_box(
+        left_box: Option<Aabb>,
+        right_box: Option<Aabb>,
+        operation: CsgOperation,
+    ) -> Option<Aabb> {
+        match operation {
+            CsgOperation::Union => Some(Aabb::surrounding_box(&left_box?, &right_box?)),
+            CsgOperation::Intersection => match (left_box, right_box) {
+                (Some(left_box), Some(right_box)) => {
+                    let minimum = Vec3::new(
+                        f64::max(left_box.minimum.x(), right_box.minimum.x()),
+                        f64::max(left_box.minimum.y(), right_box.minimum.y()),
+                        f64::max(left_box.minimum.z(), right_box.minimum.z()),
+                    );
+                    let maximum = Vec3::new(
+                        f64::min(left_box.maximum.x(), right_box.maximum.x()),
+                        f64::min(left_box.maximum.y(), right_box.maximum.y()),
+                        f64::min(left_box.maximum.z(), right_box.maximum.z()),
+                    );
+
+                    Some(Aabb::new(minimum, maximum))
+                }
+                (left_box, right_box) => left_box.or(right_box),
+            },
+            CsgOperation::Difference => left_box,
+        }
+    }
+}
+
+/// A single point where a ray crosses the boundary of one of the two solids
+struct Boundary {
+    record: HitRecord,
+    from_left: bool,
+    entering: bool,
+}
+
+impl<A: Solid, B: Solid> Solid for Csg<A, B> {
+    fn intervals(&self, ray: &Ray) -> Vec<Interval> {
+        let to_boundaries = |intervals: Vec<Interval>, from_left: bool| {
+            intervals.into_iter().flat_map(move |interval| {
+                [
+                    Boundary {
+                        record: interval.enter,
+                        from_left,
+                        entering: true,
+                    },
+                    Boundary {
+                        record: interval.exit,
+                        from_left,
+                        entering: false,
+                    },
+                ]
+            })
+        };
+
+        let mut boundaries: Vec<Boundary> = to_boundaries(self.left.intervals(ray), true)
+            .chain(to_boundaries(self.right.intervals(ray), false))
+            .collect();
+
+        boundaries.sort_by(|a, b| {
+            a.record
+                .t
+                .partial_cmp(&b.record.t)
+                .unwrap_or(Ordering::Equal)
+        });
+
+        let mut result = Vec::new();
+        let mut current_enter: Option<HitRecord> = None;
+        let (mut inside_left, mut inside_right) = (false, false);
+
+        for boundary in boundaries {
+            let was_inside = self.operation.is_inside(inside_left, inside_right);
+
+            if boundary.from_left {
+                inside_left = boundary.entering;
+            } else {
+                inside_right = boundary.entering;
+            }
+
+            let is_inside = self.operation.is_inside(inside_left, inside_right);
+
+            if was_inside == is_inside {
+                continue;
+            }
+
+            let mut record = boundary.record;
+
+            // The surface of the right solid is turned inside out when it's cut out of the left one
+            if !boundary.from_left && self.operation == CsgOperation::Difference {
+                record.flip_face();
+            }
+
+            if is_inside {
+                current_enter = Some(record);
+            } else if let Some(enter) = current_enter.take() {
+                result.push(Interval::new(enter, record));
+            }
+        }
+
+        result
+    }
+}
+
+impl<A: Solid, B: Solid> Hittable for Csg<A, B> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        if let Some(aabb) = &self.aabb {
+            if !aabb.hit(ray, t_min, t_max) {
+                return None;
+            }
+        }
+
+        first_boundary_in_range(self.intervals(ray), t_min, t_max)
+    }
+
+    fn bounding_box(&self, _time: (f64, f64)) -> Option<Aabb> {
+        self.aabb.clone()
+    }
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::Dielectric;
+    use crate::surfaces::{AABox, Sphere};
+
+    fn ray_along_x() -> Ray {
+        Ray::new(Vec3::new(-10.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0), 0.0)
+    }
+    fn sphere(x: f64, radius: f64) -> Sphere<Dielectric> {
+        Sphere::new(Vec3::new(x, 0.0, 0.0), radius, Dielectric::new(1.5))
+    }
+    fn spans(intervals: &[Interval]) -> Vec<(f64, f64)> {
+        intervals
+            .iter()
+            .map(|interval| {
+                (
+                    (interval.enter.t * 1000.0).round() / 1000.0,
+                    (interval.exit.t * 1000.0).round() / 1000.0,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_sphere_intervals() {
+        let intervals = sphere(0.0, 1.0).intervals(&ray_along_x());
+
+        assert_eq!(spans(&intervals), vec![(9.0, 11.0)]);
+        assert!(intervals[0].enter.front_face);
+        assert!(!intervals[0].exit.front_face);
+    }
+
+    #[test]
+    fn test_union() {
+        let overlapping = Csg::union(sphere(0.0, 1.0), sphere(1.0, 1.0));
+        assert_eq!(
+            spans(&overlapping.intervals(&ray_along_x())),
+            vec![(9.0, 12.0)]
+        );
+
+        let separate = Csg::union(sphere(0.0, 1.0), sphere(5.0, 1.0));
+        assert_eq!(
+            spans(&separate.intervals(&ray_along_x())),
+            vec![(9.0, 11.0), (14.0, 16.0)]
+        );
+    }
+
+    #[test]
+    fn test_intersection() {
+        let lens = Csg::intersection(sphere(0.0, 1.0), sphere(1.0, 1.0));
+        assert_eq!(spans(&lens.intervals(&ray_along_x())), vec![(10.0, 11.0)]);
+
+        let disjoint = Csg::intersection(sphere(0.0, 1.0), sphere(5.0, 1.0));
+        assert!(disjoint.intervals(&ray_along_x()).is_empty());
+    }
+
+    #[test]
+    fn test_difference() {
+        let material = Dielectric::new(1.5);
+        let cube = AABox::new(
+            Vec3::new(-1.0, -1.0, -1.0),
+            Vec3::new(1.0, 1.0, 1.0),
+            material,
+        );
+        let hollow_cube = Csg::difference(cube, sphere(0.0, 0.5));
+        let intervals = hollow_cube.intervals(&ray_along_x());
+
+        assert_eq!(spans(&intervals), vec![(9.0, 9.5), (10.5, 11.0)]);
+
+        // The cut out sphere's surface faces towards the inside of the hole
+        assert!(!intervals[0].exit.front_face);
+        assert!(intervals[1].enter.front_face);
+
+        let hit = hollow_cube.hit(&ray_along_x(), 9.2, f64::INFINITY).unwrap();
+        assert!((hit.t - 9.5).abs() < 1e-9);
+    }
+}
@@ -4,8 +4,11 @@ mod aabb;
 mod bowl;
 mod bvh;
 mod constant_medium;
+mod csg;
 mod moving_sphere;
 mod parabola;
+mod quadric;
+pub mod sdf;
 mod sphere;
 pub use aa_box::AABox;
 pub use aa_rects::{XYRect, XZRect, YZRect};
@@ -13,6 +16,9 @@ pub use aabb::Aabb;
 pub use bowl::Bowl;
 pub use bvh::BvhNode;
 pub use constant_medium::ConstantMedium;
+pub use csg::{Csg, CsgOperation, Interval, Solid};
 pub use moving_sphere::MovingSphere;
 pub use parabola::ParabolaX;
+pub use quadric::Quadric;
+pub use sdf::{ImplicitSurface, Sdf};
 pub use sphere::Sphere;
@@ -1,3 +1,6 @@
+use std::sync::Arc;
+
+use super::sphere::set_sphere_coordinates;
 use super::Aabb;
 use crate::hittable::{HitRecord, Hittable};
 use crate::materials::Material;
@@ -7,7 +10,7 @@ use crate::Vec3;
 pub struct MovingSphere<T: Material + Clone + 'static> {
     center: (Vec3, Vec3),
     radius: f64,
-    material: T,
+    material: Arc<T>,
     time: (f64, f64),
 }
 
@@ -16,7 +19,7 @@ impl<T: Material + Clone + 'static> MovingSphere<T> {
         Self {
             center,
             radius,
-            material,
+            material: Arc::new(material),
             time,
         }
     }
@@ -72,6 +75,7 @@ impl<T: Material + Clone + 'static> Hittable for MovingSphere<T> {
 
         let outward_normal = (record.p - self.center(ray.time)) / self.radius;
         record.set_face_normal(ray, outward_normal);
+        set_sphere_coordinates(&mut record, ray, outward_normal, self.radius);
 
         Some(record)
     }
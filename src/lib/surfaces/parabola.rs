@@ -1,3 +1,5 @@
+use std::sync::Arc;
+
 use crate::{
     hittable::{HitRecord, Hittable},
     materials::Material,
@@ -11,7 +13,7 @@ pub struct ParabolaX<T: Material + Clone + 'static> {
     x_range: (f64, f64),
     z_range: (f64, f64),
     quadratic_params: (f64, f64, f64),
-    material: T,
+    material: Arc<T>,
 }
 
 impl<T: Material + Clone + 'static> ParabolaX<T> {
@@ -25,7 +27,7 @@ impl<T: Material + Clone + 'static> ParabolaX<T> {
             x_range,
             z_range,
             quadratic_params,
-            material,
+            material: Arc::new(material),
         }
     }
 
@@ -33,14 +35,20 @@ impl<T: Material + Clone + 'static> ParabolaX<T> {
         let (min_x, max_x) = self.x_range;
         let (min_z, max_z) = self.z_range;
 
+        // The u coordinate follows the length along the curve, so the texture doesn't get stretched
         (
-            // This one is incorrect, the u coordinate isn't taking
-            // into account that the surface is curved
-            (hit.x() - min_x) / (max_x - min_x),
+            self.arc_length(min_x, hit.x()) / self.arc_length(min_x, max_x),
             (max_z - hit.z()) / (max_z - min_z),
         )
     }
 
+    /// Length along the parabola between `x0` and `x1`
+    fn arc_length(&self, x0: f64, x1: f64) -> f64 {
+        let (a, b, _) = self.quadratic_params;
+
+        parabola_arc_length(a, b, x0, x1)
+    }
+
     fn at(&self, x: f64) -> f64 {
         let (a, b, c) = self.quadratic_params;
         a * x * x + b * x + c
@@ -128,3 +136,18 @@ impl<T: Material + Clone + 'static> Hittable for ParabolaX<T> {
         ))
     }
 }
+
+/// Length of the curve `y = ax² + bx + c` between `x0` and `x1`
+pub(super) fn parabola_arc_length(a: f64, b: f64, x0: f64, x1: f64) -> f64 {
+    // The antiderivative of sqrt(1 + w²), where w = y' = 2ax + b
+    let antiderivative = |w: f64| 0.5 * w.mul_add(w.hypot(1.0), w.asinh());
+
+    if a.abs() < 1e-12 {
+        // It's a straight line
+        return b.hypot(1.0) * (x1 - x0);
+    }
+
+    let derivative = |x: f64| 2.0f64.mul_add(a * x, b);
+
+    (antiderivative(derivative(x1)) - antiderivative(derivative(x0))) / (2.0 * a)
+}
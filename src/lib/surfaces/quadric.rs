@@ -0,0 +1,509 @@
+use std::f64::consts::{PI, TAU};
+use std::sync::Arc;
+
+use super::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::materials::Material;
+use crate::Vec3;
+use crate::{Float, Ray};
+
+/// Amount of segments used to approximate the meridian when calculating texture coordinates
+const MERIDIAN_SEGMENTS: u32 = 64;
+/// Amount of meridians around the axis whose lengths are measured
+const MERIDIANS: u32 = 64;
+
+/// General quadric surface clipped to a box
+///
+/// The surface is made of every point inside the clip region where
+/// `Ax² + By² + Cz² + Dxy + Exz + Fyz + Gx + Hy + Iz + J = 0`.
+///
+/// Texture coordinates are made for surfaces around a vertical axis going through the center of
+/// the clip region. `u` is the angle around that axis, and `v` is the distance traveled along the
+/// surface from the bottom of the clip region, so textures don't get stretched where the surface curves.
+#[derive(Clone)]
+pub struct Quadric<T: Material + Clone + 'static> {
+    /// Rows of the symmetric matrix of the quadratic terms
+    matrix: [Vec3; 3],
+    /// `(G, H, I)`
+    linear: Vec3,
+    /// `J`
+    constant: f64,
+    clip: Aabb,
+    material: Arc<T>,
+    /// For each meridian, the length along it from the bottom of the clip region to each of the
+    /// `MERIDIAN_SEGMENTS + 1` heights it's sampled at. Measured once, since it's slow.
+    arc_lengths: Arc<[f64]>,
+}
+
+impl<T: Material + Clone + 'static> Quadric<T> {
+    /// Creates a quadric from the coefficients `[A, B, C, D, E, F, G, H, I, J]`
+    pub fn new(coefficients: [f64; 10], clip: Aabb, material: T) -> Self {
+        let c = coefficients;
+
+        let mut quadric = Self {
+            matrix: [
+                Vec3::new(c[0], c[3] / 2.0, c[4] / 2.0),
+                Vec3::new(c[3] / 2.0, c[1], c[5] / 2.0),
+                Vec3::new(c[4] / 2.0, c[5] / 2.0, c[2]),
+            ],
+            linear: Vec3::new(c[6], c[7], c[8]),
+            constant: c[9],
+            clip,
+            material: Arc::new(material),
+            arc_lengths: Arc::new([]),
+        };
+        quadric.arc_lengths = quadric.measure_meridians();
+        quadric
+    }
+
+    /// `((x-cx)/rx)² + ((y-cy)/ry)² + ((z-cz)/rz)² = 1`
+    pub fn ellipsoid(center: Vec3, radii: Vec3, material: T) -> Self {
+        let (a, b, c) = (
+            1.0 / (radii.x() * radii.x()),
+            1.0 / (radii.y() * radii.y()),
+            1.0 / (radii.z() * radii.z()),
+        );
+
+        let coefficients = [
+            a,
+            b,
+            c,
+            0.0,
+            0.0,
+            0.0,
+            -2.0 * center.x() * a,
+            -2.0 * center.y() * b,
+            -2.0 * center.z() * c,
+            Vec3::dot(&(center * center), &Vec3::new(a, b, c)) - 1.0,
+        ];
+
+        Self::new(
+            coefficients,
+            Aabb::new(center - radii, center + radii),
+            material,
+        )
+    }
+
+    /// Paraboloid opening upwards from `vertex`, `y = curvature * (x² + z²)`, cut at `height` above the vertex.
+    /// With a negative curvature and height it opens downwards instead.
+    ///
+    /// A `Bowl` is the same as a paraboloid with a height of `curvature * radius²`.
+    pub fn paraboloid(vertex: Vec3, curvature: f64, height: f64, material: T) -> Self {
+        let coefficients = [
+            curvature,
+            0.0,
+            curvature,
+            0.0,
+            0.0,
+            0.0,
+            -2.0 * curvature * vertex.x(),
+            -1.0,
+            -2.0 * curvature * vertex.z(),
+            curvature.mul_add(
+                vertex.x().mul_add(vertex.x(), vertex.z() * vertex.z()),
+                vertex.y(),
+            ),
+        ];
+
+        let radius = (height / curvature).sqrt();
+        let (bottom, top) = if height < 0.0 {
+            (height, 0.0)
+        } else {
+            (0.0, height)
+        };
+        let clip = Aabb::new(
+            vertex + Vec3::new(-radius, bottom, -radius),
+            vertex + Vec3::new(radius, top, radius),
+        );
+
+        Self::new(coefficients, clip, material)
+    }
+
+    /// Hyperboloid of one sheet around the vertical axis through `center`,
+    /// `((x-cx)/rx)² - ((y-cy)/ry)² + ((z-cz)/rz)² = 1`, cut at `height / 2` above and below the center.
+    pub fn hyperboloid(center: Vec3, radii: Vec3, height: f64, material: T) -> Self {
+        let (a, b, c) = (
+            1.0 / (radii.x() * radii.x()),
+            -1.0 / (radii.y() * radii.y()),
+            1.0 / (radii.z() * radii.z()),
+        );
+
+        let coefficients = [
+            a,
+            b,
+            c,
+            0.0,
+            0.0,
+            0.0,
+            -2.0 * center.x() * a,
+            -2.0 * center.y() * b,
+            -2.0 * center.z() * c,
+            Vec3::dot(&(center * center), &Vec3::new(a, b, c)) - 1.0,
+        ];
+
+        // The widest part is at the top and bottom
+        let half_height = height / 2.0;
+        let widening = (half_height * half_height).mul_add(-b, 1.0).sqrt();
+        let half_extents = Vec3::new(radii.x() * widening, half_height, radii.z() * widening);
+
+        Self::new(
+            coefficients,
+            Aabb::new(center - half_extents, center + half_extents),
+            material,
+        )
+    }
+
+    /// Open cylinder around the vertical axis through `center`, `(x-cx)² + (z-cz)² = radius²`
+    pub fn cylinder(center: Vec3, radius: f64, height: f64, material: T) -> Self {
+        let coefficients = [
+            1.0,
+            0.0,
+            1.0,
+            0.0,
+            0.0,
+            0.0,
+            -2.0 * center.x(),
+            0.0,
+            -2.0 * center.z(),
+            radius.mul_add(
+                -radius,
+                center.x().mul_add(center.x(), center.z() * center.z()),
+            ),
+        ];
+
+        let half_extents = Vec3::new(radius, height / 2.0, radius);
+
+        Self::new(
+            coefficients,
+            Aabb::new(center - half_extents, center + half_extents),
+            material,
+        )
+    }
+
+    /// Replaces the clip region, which is also the bounding box of the surface
+    #[must_use]
+    pub fn clipped_to(mut self, clip: Aabb) -> Self {
+        self.clip = clip;
+        self.arc_lengths = self.measure_meridians();
+        self
+    }
+
+    /// Coefficients `(a, b, c)` of the quadratic `at² + bt + c = 0` along a line
+    fn quadratic_along(&self, origin: &Vec3, direction: &Vec3) -> (f64, f64, f64) {
+        let matrix_direction = self.transform(direction);
+
+        let a = Vec3::dot(direction, &matrix_direction);
+        let b = 2.0f64.mul_add(
+            Vec3::dot(origin, &matrix_direction),
+            Vec3::dot(&self.linear, direction),
+        );
+        let c = Vec3::dot(origin, &self.transform(origin))
+            + Vec3::dot(&self.linear, origin)
+            + self.constant;
+
+        (a, b, c)
+    }
+
+    /// Multiplies a vector by the matrix of the quadratic terms
+    fn transform(&self, v: &Vec3) -> Vec3 {
+        Vec3::new(
+            Vec3::dot(&self.matrix[0], v),
+            Vec3::dot(&self.matrix[1], v),
+            Vec3::dot(&self.matrix[2], v),
+        )
+    }
+
+    /// Roots of `at² + bt + c = 0` in increasing order
+    fn solve((a, b, c): (f64, f64, f64)) -> Option<[f64; 2]> {
+        if a.abs() < 1e-12 {
+            // It's actually linear
+            if b.abs() < 1e-12 {
+                return None;
+            }
+            let root = -c / b;
+            return Some([root, root]);
+        }
+
+        let discriminant = b.mul_add(b, -4.0 * a * c);
+
+        if discriminant < 0.0 {
+            return None;
+        }
+
+        let disc_sqrt = discriminant.sqrt();
+        let root1 = (-b - disc_sqrt) / (2.0 * a);
+        let root2 = (-b + disc_sqrt) / (2.0 * a);
+
+        Some([root1.min(root2), root1.max(root2)])
+    }
+
+    fn gradient(&self, p: &Vec3) -> Vec3 {
+        self.transform(p) * 2.0 + self.linear
+    }
+
+    fn is_inside_clip(&self, p: &Vec3) -> bool {
+        const TOLERANCE: Float = 1e-9;
+
+        (0_u8..3).all(|axis| {
+            p[axis] >= self.clip.minimum[axis] - TOLERANCE
+                && p[axis] <= self.clip.maximum[axis] + TOLERANCE
+        })
+    }
+
+    fn center(&self) -> Vec3 {
+        (self.clip.minimum + self.clip.maximum) / 2.0
+    }
+
+    /// Follows the meridians of the surface from the bottom of the clip region to the top,
+    /// adding up the lengths of the segments between sampled points.
+    /// The meridians go around the axis the same way as `u`.
+    fn measure_meridians(&self) -> Arc<[f64]> {
+        let center = self.center();
+        let (y_min, y_max) = (self.clip.minimum.y(), self.clip.maximum.y());
+
+        (0..MERIDIANS)
+            .flat_map(|meridian| {
+                let phi = TAU * f64::from(meridian) / f64::from(MERIDIANS);
+                let outwards = Vec3::new(-phi.cos(), 0.0, phi.sin());
+
+                // The root closest to the previous point keeps it on the same sheet
+                let mut previous: Option<(Vec3, f64)> = None;
+                let mut length = 0.0;
+
+                (0..=MERIDIAN_SEGMENTS).map(move |i| {
+                    let y =
+                        (y_max - y_min).mul_add(f64::from(i) / f64::from(MERIDIAN_SEGMENTS), y_min);
+                    let origin = Vec3::new(center.x(), y, center.z());
+                    let closest_to = previous.map_or(0.0, |(_, distance)| distance);
+                    let point = Self::solve(self.quadratic_along(&origin, &outwards))
+                        .and_then(|roots| {
+                            roots
+                                .into_iter()
+                                .filter(|root| *root >= 0.0)
+                                .min_by(|r1, r2| {
+                                    (r1 - closest_to).abs().total_cmp(&(r2 - closest_to).abs())
+                                })
+                        })
+                        .map(|distance| (origin + outwards * distance, distance))
+                        .filter(|(point, _)| self.is_inside_clip(point));
+
+                    if let Some((point, distance)) = point {
+                        if let Some((previous_point, _)) = previous {
+                            length += (point - previous_point).length();
+                        }
+                        previous = Some((point, distance));
+                    }
+                    length
+                })
+            })
+            .collect()
+    }
+
+    fn get_uv(&self, hit: &Vec3) -> (f64, f64) {
+        let center = self.center();
+        let offset = Vec3::new(hit.x() - center.x(), 0.0, hit.z() - center.z());
+
+        let phi = f64::atan2(-offset.z(), offset.x()) + PI;
+        let u = phi / TAU;
+
+        let (y_min, y_max) = (self.clip.minimum.y(), self.clip.maximum.y());
+        let linear_v = (hit.y() - y_min) / (y_max - y_min);
+
+        if offset.length() < 1e-12 {
+            return (u, linear_v);
+        }
+
+        // How far up the meridian the hit is, between the heights it was measured at
+        let segments = f64::from(MERIDIAN_SEGMENTS);
+        let height = (linear_v * segments).clamp(0.0, segments);
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let below = (height as usize).min(MERIDIAN_SEGMENTS as usize - 1);
+        let above_fraction = height - height.floor().min(segments - 1.0);
+
+        let meridian_v = |meridian: usize| {
+            let start = meridian * (MERIDIAN_SEGMENTS as usize + 1);
+            let lengths = &self.arc_lengths[start..=start + MERIDIAN_SEGMENTS as usize];
+            let total_length = lengths[MERIDIAN_SEGMENTS as usize];
+            let length_to_hit =
+                (lengths[below + 1] - lengths[below]).mul_add(above_fraction, lengths[below]);
+            (total_length >= 1e-12).then(|| (length_to_hit / total_length).clamp(0.0, 1.0))
+        };
+
+        // Between the two closest meridians that were measured
+        let (previous, next, fraction) = Self::closest_meridians(u);
+        match (meridian_v(previous), meridian_v(next)) {
+            (Some(previous_v), Some(next_v)) => {
+                (u, (next_v - previous_v).mul_add(fraction, previous_v))
+            }
+            // The surface isn't around the vertical axis (e.g. a lying cylinder)
+            _ => (u, linear_v),
+        }
+    }
+
+    /// The measured meridians right before and after `u`, and how far `u` is between them
+    fn closest_meridians(u: f64) -> (usize, usize, f64) {
+        let around = u * f64::from(MERIDIANS);
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let previous = (around as usize) % MERIDIANS as usize;
+        let next = (previous + 1) % MERIDIANS as usize;
+
+        (previous, next, around - around.floor())
+    }
+
+    /// How the point `hit`, with texture coordinate `u`, moves along the surface when `u` and
+    /// `v` grow by 1, if they change there
+    fn uv_directions(&self, hit: &Vec3, u: f64, normal: &Vec3) -> Option<(Vec3, Vec3)> {
+        let center = self.center();
+        let offset = Vec3::new(hit.x() - center.x(), 0.0, hit.z() - center.z());
+        let around_axis = Vec3::new(offset.z(), 0.0, -offset.x()) * TAU;
+
+        // Up the meridian, across the surface from the way around the axis
+        let across = Vec3::cross(normal, &around_axis);
+        if across.length() < 1e-12 {
+            return None;
+        }
+        let mut up = across.unit_vector();
+        if up.y() < 0.0 {
+            up = -up;
+        }
+
+        let (previous, next, fraction) = Self::closest_meridians(u);
+        let total_length = |meridian: usize| {
+            self.arc_lengths[(meridian + 1) * (MERIDIAN_SEGMENTS as usize + 1) - 1]
+        };
+        let (previous_length, next_length) = (total_length(previous), total_length(next));
+        let meridian_length = if previous_length >= 1e-12 && next_length >= 1e-12 {
+            (next_length - previous_length).mul_add(fraction, previous_length)
+        } else if up.y() > 1e-6 {
+            // `v` goes straight up the clip region, like in `get_uv`
+            (self.clip.maximum.y() - self.clip.minimum.y()) / up.y()
+        } else {
+            return None;
+        };
+
+        Some((around_axis, up * meridian_length))
+    }
+}
+
+impl<T: Material + Clone + 'static> Hittable for Quadric<T> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        if !self.clip.hit(ray, t_min, t_max) {
+            return None;
+        }
+
+        let quadratic = self.quadratic_along(&ray.origin, &ray.direction);
+
+        let root = Self::solve(quadratic)?
+            .into_iter()
+            .find(|root| *root > t_min && *root < t_max && self.is_inside_clip(&ray.at(*root)))?;
+
+        let hit = ray.at(root);
+        let mut record = HitRecord::new(root, hit, self.material.clone());
+
+        record.set_face_normal(ray, self.gradient(&hit).unit_vector());
+
+        let (u, v) = self.get_uv(&hit);
+        record.set_texture_coordinates(u, v);
+        if let Some((around_axis, up_meridian)) = self.uv_directions(&hit, u, &record.normal) {
+            record.set_tangent(around_axis);
+            record.set_differentials(ray, around_axis, up_meridian);
+        }
+
+        Some(record)
+    }
+
+    fn bounding_box(&self, _time: (f64, f64)) -> Option<Aabb> {
+        Some(self.clip.clone())
+    }
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::Dielectric;
+    use crate::surfaces::Sphere;
+    use crate::RayDifferentials;
+
+    #[test]
+    fn test_ellipsoid_matches_sphere() {
+        let ellipsoid = Quadric::ellipsoid(
+            Vec3::new(1.0, 2.0, 3.0),
+            Vec3::new(2.0, 2.0, 2.0),
+            Dielectric::new(1.5),
+        );
+        let ray = Ray::new(Vec3::new(-5.0, 2.0, 3.0), Vec3::new(1.0, 0.0, 0.0), 0.0);
+
+        let hit = ellipsoid.hit(&ray, 0.0, f64::INFINITY).unwrap();
+        assert!((hit.t - 4.0).abs() < 1e-9);
+        assert!((hit.normal - Vec3::new(-1.0, 0.0, 0.0)).length() < 1e-9);
+
+        // Halfway up a sphere is half of the meridian
+        assert!((hit.v - 0.5).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_differentials_match_sphere() {
+        let center = Vec3::new(1.0, 2.0, 3.0);
+        let ellipsoid = Quadric::ellipsoid(center, Vec3::new(2.0, 2.0, 2.0), Dielectric::new(1.5));
+        let sphere = Sphere::new(center, 2.0, Dielectric::new(1.5));
+
+        // Above the equator and away from the seam of `u`
+        let origin = Vec3::new(-5.0, 3.0, 4.0);
+        let direction = center + Vec3::new(-1.0, 1.0, 0.5) - origin;
+        let ray = Ray::new(origin, direction, 0.0).with_differentials(RayDifferentials {
+            x_origin: origin,
+            x_direction: direction + Vec3::new(0.0, 0.0, 0.01),
+            y_origin: origin,
+            y_direction: direction + Vec3::new(0.0, 0.01, 0.0),
+        });
+
+        let quadric_hit = ellipsoid.hit(&ray, 0.0, f64::INFINITY).unwrap();
+        let sphere_hit = sphere.hit(&ray, 0.0, f64::INFINITY).unwrap();
+        let (quadric, sphere) = (
+            quadric_hit.differentials.unwrap(),
+            sphere_hit.differentials.unwrap(),
+        );
+        for (a, b) in [
+            (quadric.du_dx, sphere.du_dx),
+            (quadric.dv_dx, sphere.dv_dx),
+            (quadric.du_dy, sphere.du_dy),
+            (quadric.dv_dy, sphere.dv_dy),
+        ] {
+            assert!((a - b).abs() < 1e-2 * b.abs().max(1e-3), "{a} {b}");
+        }
+    }
+
+    #[test]
+    fn test_clipped_cylinder() {
+        let cylinder = Quadric::cylinder(Vec3::new(0.0, 0.0, 0.0), 1.0, 2.0, Dielectric::new(1.5));
+
+        let ray = Ray::new(Vec3::new(-5.0, 0.5, 0.0), Vec3::new(1.0, 0.0, 0.0), 0.0);
+        let hit = cylinder.hit(&ray, 0.0, f64::INFINITY).unwrap();
+        assert!((hit.t - 4.0).abs() < 1e-9);
+        assert!((hit.v - 0.75).abs() < 1e-3);
+
+        // Above the top of the cylinder
+        let ray = Ray::new(Vec3::new(-5.0, 1.5, 0.0), Vec3::new(1.0, 0.0, 0.0), 0.0);
+        assert!(cylinder.hit(&ray, 0.0, f64::INFINITY).is_none());
+
+        // Looking down through the open top hits the inside
+        let ray = Ray::new(Vec3::new(0.0, 3.0, 0.0), Vec3::new(0.4, -1.0, 0.0), 0.0);
+        let hit = cylinder.hit(&ray, 0.0, f64::INFINITY).unwrap();
+        assert!(!hit.front_face);
+    }
+
+    #[test]
+    fn test_paraboloid_bounding_box() {
+        let paraboloid =
+            Quadric::paraboloid(Vec3::new(0.0, 1.0, 0.0), 0.5, 2.0, Dielectric::new(1.5));
+
+        assert_eq!(
+            paraboloid.bounding_box((0.0, 1.0)),
+            Some(Aabb::new(
+                Vec3::new(-2.0, 1.0, -2.0),
+                Vec3::new(2.0, 3.0, 2.0)
+            ))
+        );
+    }
+}
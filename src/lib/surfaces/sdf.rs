@@ -0,0 +1,496 @@
+//! Signed distance functions and a surface that ray-marches them
+//!
+//! A signed distance function returns how far a point is from the closest point of a
+//! surface, negative when the point is inside of it. Besides the primitives in this module,
+//! any closure `Fn(&Vec3) -> f64` can be used as an `Sdf`.
+
+use std::f64::consts::{PI, TAU};
+use std::sync::Arc;
+
+use super::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::materials::Material;
+use crate::Ray;
+use crate::Vec3;
+
+pub trait Sdf: Send + Sync {
+    /// Signed distance from `p` to the surface. Negative if `p` is inside.
+    fn distance(&self, p: &Vec3) -> f64;
+
+    fn union<T: Sdf>(self, other: T) -> Union<Self, T>
+    where
+        Self: Sized,
+    {
+        Union(self, other)
+    }
+    fn intersection<T: Sdf>(self, other: T) -> Intersection<Self, T>
+    where
+        Self: Sized,
+    {
+        Intersection(self, other)
+    }
+    /// Cuts `other` out of this shape
+    fn subtract<T: Sdf>(self, other: T) -> Subtraction<Self, T>
+    where
+        Self: Sized,
+    {
+        Subtraction(self, other)
+    }
+    /// Union that blends both shapes together over a distance of roughly `k`
+    fn smooth_union<T: Sdf>(self, other: T, k: f64) -> SmoothUnion<Self, T>
+    where
+        Self: Sized,
+    {
+        SmoothUnion {
+            a: self,
+            b: other,
+            k,
+        }
+    }
+    fn translate(self, offset: Vec3) -> Translated<Self>
+    where
+        Self: Sized,
+    {
+        Translated { sdf: self, offset }
+    }
+    /// Twists the shape around the Y axis by `rate` radians per unit of height
+    fn twist(self, rate: f64) -> Twist<Self>
+    where
+        Self: Sized,
+    {
+        Twist { sdf: self, rate }
+    }
+    /// Repeats the shape infinitely in a grid with the given spacing.
+    /// A spacing of zero in an axis disables repetition along it.
+    fn repeat(self, spacing: Vec3) -> Repeat<Self>
+    where
+        Self: Sized,
+    {
+        Repeat { sdf: self, spacing }
+    }
+}
+
+impl<F: Fn(&Vec3) -> f64 + Send + Sync> Sdf for F {
+    fn distance(&self, p: &Vec3) -> f64 {
+        self(p)
+    }
+}
+
+/// Sphere centered at the origin
+#[derive(Clone)]
+pub struct Sphere {
+    pub radius: f64,
+}
+impl Sdf for Sphere {
+    fn distance(&self, p: &Vec3) -> f64 {
+        p.length() - self.radius
+    }
+}
+
+/// Box centered at the origin
+#[derive(Clone)]
+pub struct Cuboid {
+    /// Half of the size of the box in each dimension
+    pub half_extents: Vec3,
+}
+impl Sdf for Cuboid {
+    fn distance(&self, p: &Vec3) -> f64 {
+        let q = Vec3::new(
+            p.x().abs() - self.half_extents.x(),
+            p.y().abs() - self.half_extents.y(),
+            p.z().abs() - self.half_extents.z(),
+        );
+        let outside = Vec3::new(q.x().max(0.0), q.y().max(0.0), q.z().max(0.0)).length();
+        let inside = q.x().max(q.y()).max(q.z()).min(0.0);
+
+        outside + inside
+    }
+}
+
+/// Torus centered at the origin, lying on the XZ plane
+#[derive(Clone)]
+pub struct Torus {
+    /// Distance from the center to the middle of the tube
+    pub major_radius: f64,
+    /// Radius of the tube
+    pub minor_radius: f64,
+}
+impl Sdf for Torus {
+    fn distance(&self, p: &Vec3) -> f64 {
+        let ring_distance = p.x().hypot(p.z()) - self.major_radius;
+
+        ring_distance.hypot(p.y()) - self.minor_radius
+    }
+}
+
+#[derive(Clone)]
+pub struct Union<A: Sdf, B: Sdf>(A, B);
+impl<A: Sdf, B: Sdf> Sdf for Union<A, B> {
+    fn distance(&self, p: &Vec3) -> f64 {
+        self.0.distance(p).min(self.1.distance(p))
+    }
+}
+
+#[derive(Clone)]
+pub struct Intersection<A: Sdf, B: Sdf>(A, B);
+impl<A: Sdf, B: Sdf> Sdf for Intersection<A, B> {
+    fn distance(&self, p: &Vec3) -> f64 {
+        self.0.distance(p).max(self.1.distance(p))
+    }
+}
+
+#[derive(Clone)]
+pub struct Subtraction<A: Sdf, B: Sdf>(A, B);
+impl<A: Sdf, B: Sdf> Sdf for Subtraction<A, B> {
+    fn distance(&self, p: &Vec3) -> f64 {
+        self.0.distance(p).max(-self.1.distance(p))
+    }
+}
+
+#[derive(Clone)]
+pub struct SmoothUnion<A: Sdf, B: Sdf> {
+    a: A,
+    b: B,
+    k: f64,
+}
+impl<A: Sdf, B: Sdf> Sdf for SmoothUnion<A, B> {
+    fn distance(&self, p: &Vec3) -> f64 {
+        // Polynomial smooth minimum
+        let distance_a = self.a.distance(p);
+        let distance_b = self.b.distance(p);
+
+        let h = (0.5 + 0.5 * (distance_b - distance_a) / self.k).clamp(0.0, 1.0);
+        let blended = h.mul_add(distance_a - distance_b, distance_b);
+
+        (self.k * h).mul_add(h - 1.0, blended)
+    }
+}
+
+#[derive(Clone)]
+pub struct Translated<T: Sdf> {
+    sdf: T,
+    offset: Vec3,
+}
+impl<T: Sdf> Sdf for Translated<T> {
+    fn distance(&self, p: &Vec3) -> f64 {
+        self.sdf.distance(&(p - &self.offset))
+    }
+}
+
+/// Twisting doesn't preserve distances, so surfaces using it usually need a smaller
+/// step scale in `ImplicitSurface` to avoid overshooting.
+#[derive(Clone)]
+pub struct Twist<T: Sdf> {
+    sdf: T,
+    rate: f64,
+}
+impl<T: Sdf> Sdf for Twist<T> {
+    fn distance(&self, p: &Vec3) -> f64 {
+        let (sin, cos) = f64::sin_cos(self.rate * p.y());
+        let twisted = Vec3::new(
+            cos.mul_add(p.x(), -sin * p.z()),
+            p.y(),
+            sin.mul_add(p.x(), cos * p.z()),
+        );
+
+        self.sdf.distance(&twisted)
+    }
+}
+
+#[derive(Clone)]
+pub struct Repeat<T: Sdf> {
+    sdf: T,
+    spacing: Vec3,
+}
+impl<T: Sdf> Sdf for Repeat<T> {
+    fn distance(&self, p: &Vec3) -> f64 {
+        let mut cell_point = *p;
+
+        for axis in 0_u8..3 {
+            let spacing = self.spacing[axis];
+            if spacing > 0.0 {
+                cell_point[axis] -= spacing * (p[axis] / spacing).round();
+            }
+        }
+
+        self.sdf.distance(&cell_point)
+    }
+}
+
+/// A surface defined implicitly by a signed distance function, rendered by sphere tracing
+///
+/// Since the distance function can be anything, the user has to give a bounding box
+/// containing the whole surface. Rays are only marched inside of it.
+#[derive(Clone)]
+pub struct ImplicitSurface<S: Sdf, M: Material + Clone + 'static> {
+    sdf: S,
+    bounding_box: Aabb,
+    material: Arc<M>,
+    max_steps: u32,
+    epsilon: f64,
+    step_scale: f64,
+}
+
+impl<S: Sdf, M: Material + Clone + 'static> ImplicitSurface<S, M> {
+    pub fn new(sdf: S, bounding_box: Aabb, material: M) -> Self {
+        Self {
+            sdf,
+            bounding_box,
+            material: Arc::new(material),
+            max_steps: 256,
+            epsilon: 1e-4,
+            step_scale: 1.0,
+        }
+    }
+
+    /// Maximum amount of marching steps before giving up on a ray
+    #[must_use]
+    pub const fn max_steps(mut self, max_steps: u32) -> Self {
+        self.max_steps = max_steps;
+        self
+    }
+    /// How close to the surface a point has to be to count as a hit
+    #[must_use]
+    pub const fn epsilon(mut self, epsilon: f64) -> Self {
+        self.epsilon = epsilon;
+        self
+    }
+    /// Multiplies every step. Use values below 1 for distance functions that overestimate distances.
+    #[must_use]
+    pub const fn step_scale(mut self, step_scale: f64) -> Self {
+        self.step_scale = step_scale;
+        self
+    }
+
+    /// Estimates the gradient of the distance function with central differences
+    /// along the four vertices of a tetrahedron
+    fn normal_at(&self, p: &Vec3) -> Vec3 {
+        let h = self.epsilon;
+        let vertices = [
+            Vec3::new(1.0, -1.0, -1.0),
+            Vec3::new(-1.0, -1.0, 1.0),
+            Vec3::new(-1.0, 1.0, -1.0),
+            Vec3::new(1.0, 1.0, 1.0),
+        ];
+
+        let gradient = vertices
+            .iter()
+            .map(|vertex| vertex * self.sdf.distance(&(p + &(vertex * h))))
+            .fold(Vec3::new(0.0, 0.0, 0.0), |a, b| a + b);
+
+        gradient.unit_vector()
+    }
+
+    /// Spherical texture coordinates around the center of the bounding box
+    fn get_uv(&self, p: &Vec3) -> (f64, f64) {
+        let center = (self.bounding_box.minimum + self.bounding_box.maximum) / 2.0;
+        let direction = (p - &center).unit_vector();
+
+        let theta = f64::acos(-direction.y());
+        let phi = f64::atan2(-direction.z(), direction.x()) + PI;
+
+        (phi / TAU, theta / PI)
+    }
+
+    /// How the point `p`, on the surface with `normal`, moves along it when `u` and `v`
+    /// grow by 1, except where they don't change
+    fn uv_directions(&self, p: &Vec3, normal: &Vec3) -> Option<(Vec3, Vec3)> {
+        let center = (self.bounding_box.minimum + self.bounding_box.maximum) / 2.0;
+        let relative = p - &center;
+        let direction = relative.unit_vector();
+        let distance_to_axis = relative.x().hypot(relative.z());
+        let facing = Vec3::dot(&direction, normal);
+        if distance_to_axis < 1e-9 || facing.abs() < 1e-6 {
+            return None;
+        }
+
+        // On the sphere around the center that goes through the point, like on a `Sphere`
+        let around_axis = Vec3::new(relative.z(), 0.0, -relative.x()) * TAU;
+        let towards_top = Vec3::new(
+            -relative.y() * relative.x() / distance_to_axis,
+            distance_to_axis,
+            -relative.y() * relative.z() / distance_to_axis,
+        ) * PI;
+
+        // Moving towards the center doesn't change the texture coordinates, so the directions
+        // are moved that way onto the plane that touches the surface
+        let onto_surface = |v: Vec3| v - direction * (Vec3::dot(&v, normal) / facing);
+        Some((onto_surface(around_axis), onto_surface(towards_top)))
+    }
+}
+
+impl<S: Sdf, M: Material + Clone + 'static> Hittable for ImplicitSurface<S, M> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let (t_start, t_end) = self.bounding_box.clip(ray, t_min, t_max)?;
+
+        let ray_length = ray.direction.length();
+        let mut t = t_start;
+
+        // If the ray starts on the surface (e.g. after refracting into it)
+        // move away from it first, so it doesn't hit the same point again
+        loop {
+            if self.sdf.distance(&ray.at(t)).abs() >= self.epsilon {
+                break;
+            }
+
+            t += 2.0 * self.epsilon / ray_length;
+            if t > t_end {
+                return None;
+            }
+        }
+
+        for _ in 0..self.max_steps {
+            let p = ray.at(t);
+            let distance = self.sdf.distance(&p).abs();
+
+            if distance < self.epsilon {
+                let mut record = HitRecord::new(t, p, self.material.clone());
+                record.set_face_normal(ray, self.normal_at(&p));
+
+                let (u, v) = self.get_uv(&p);
+                record.set_texture_coordinates(u, v);
+                if let Some((around_axis, towards_top)) = self.uv_directions(&p, &record.normal) {
+                    record.set_tangent(around_axis);
+                    record.set_differentials(ray, around_axis, towards_top);
+                }
+
+                return Some(record);
+            }
+
+            t += self.step_scale * distance / ray_length;
+
+            if t > t_end {
+                return None;
+            }
+        }
+
+        None
+    }
+
+    fn bounding_box(&self, _time: (f64, f64)) -> Option<Aabb> {
+        Some(self.bounding_box.clone())
+    }
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::materials::Dielectric;
+    use crate::RayDifferentials;
+
+    /// Loosely around the unit spheres the tests trace
+    fn test_bounds() -> Aabb {
+        Aabb::new(Vec3::new(-2.0, -2.0, -2.0), Vec3::new(2.0, 2.0, 2.0))
+    }
+
+    #[test]
+    fn test_primitives() {
+        let sphere = Sphere { radius: 1.0 };
+        assert!((sphere.distance(&Vec3::new(3.0, 0.0, 0.0)) - 2.0).abs() < 1e-9);
+        assert!((sphere.distance(&Vec3::new(0.0, 0.0, 0.0)) + 1.0).abs() < 1e-9);
+
+        let cuboid = Cuboid {
+            half_extents: Vec3::new(1.0, 1.0, 1.0),
+        };
+        assert!((cuboid.distance(&Vec3::new(3.0, 0.0, 0.0)) - 2.0).abs() < 1e-9);
+        assert!((cuboid.distance(&Vec3::new(0.5, 0.0, 0.0)) + 0.5).abs() < 1e-9);
+
+        let torus = Torus {
+            major_radius: 2.0,
+            minor_radius: 0.5,
+        };
+        assert!(torus.distance(&Vec3::new(2.0, 0.0, 0.0)) < 0.0);
+        assert!(torus.distance(&Vec3::new(0.0, 0.0, 0.0)) > 0.0);
+    }
+
+    #[test]
+    fn test_smooth_union_is_below_union() {
+        let a = Sphere { radius: 1.0 };
+        let b = Sphere { radius: 1.0 }.translate(Vec3::new(1.5, 0.0, 0.0));
+        let p = Vec3::new(0.75, 1.0, 0.0);
+
+        let union = a.clone().union(b.clone()).distance(&p);
+        let smooth = a.smooth_union(b, 0.5).distance(&p);
+
+        assert!(smooth < union);
+    }
+
+    #[test]
+    fn test_closure_and_repeat() {
+        let plane = |p: &Vec3| p.y();
+        assert!((plane.distance(&Vec3::new(5.0, 2.0, 1.0)) - 2.0).abs() < 1e-9);
+
+        let spheres = Sphere { radius: 0.5 }.repeat(Vec3::new(4.0, 0.0, 0.0));
+        assert!(spheres.distance(&Vec3::new(8.0, 0.0, 0.0)) < 0.0);
+        assert!(spheres.distance(&Vec3::new(8.0, 4.0, 0.0)) > 0.0);
+    }
+
+    #[test]
+    fn test_hit() {
+        let surface =
+            ImplicitSurface::new(Sphere { radius: 1.0 }, test_bounds(), Dielectric::new(1.5));
+        let ray = Ray::new(Vec3::new(-5.0, 0.0, 0.0), Vec3::new(2.0, 0.0, 0.0), 0.0);
+
+        let hit = surface.hit(&ray, 0.0, f64::INFINITY).unwrap();
+        assert!((hit.t - 2.0).abs() < 1e-3);
+        assert!((hit.normal - Vec3::new(-1.0, 0.0, 0.0)).length() < 1e-3);
+        assert!(hit.front_face);
+
+        // Leaving the sphere from the inside
+        let inner_ray = Ray::new(hit.p, Vec3::new(1.0, 0.0, 0.0), 0.0);
+        let exit = surface.hit(&inner_ray, 0.0001, f64::INFINITY).unwrap();
+        assert!((exit.p.x() - 1.0).abs() < 1e-3);
+        assert!(!exit.front_face);
+
+        let missing_ray = Ray::new(Vec3::new(-5.0, 3.0, 0.0), Vec3::new(1.0, 0.0, 0.0), 0.0);
+        assert!(surface.hit(&missing_ray, 0.0, f64::INFINITY).is_none());
+    }
+
+    #[test]
+    fn test_differentials() {
+        let cube = ImplicitSurface::new(
+            Cuboid {
+                half_extents: Vec3::new(1.0, 1.0, 1.0),
+            },
+            test_bounds(),
+            Dielectric::new(1.5),
+        );
+
+        // At an angle to the front face, away from the seam of `u`
+        let origin = Vec3::new(-1.0, 2.0, 5.0);
+        let direction = Vec3::new(0.7, -1.6, -4.0);
+        let (x_direction, y_direction) = (
+            direction + Vec3::new(0.01, 0.0, 0.0),
+            direction + Vec3::new(0.0, 0.01, 0.0),
+        );
+        let ray = Ray::new(origin, direction, 0.0).with_differentials(RayDifferentials {
+            x_origin: origin,
+            x_direction,
+            y_origin: origin,
+            y_direction,
+        });
+        let hit = cube.hit(&ray, 0.0, f64::INFINITY).unwrap();
+        let differentials = hit.differentials.unwrap();
+
+        // The texture coordinates of the next pixels change as much as the differentials say
+        let uv_of = |direction: Vec3| {
+            let hit = cube
+                .hit(&Ray::new(origin, direction, 0.0), 0.0, f64::INFINITY)
+                .unwrap();
+            (hit.u, hit.v)
+        };
+        let (right, up) = (uv_of(x_direction), uv_of(y_direction));
+        for (expected, actual) in [
+            (right.0 - hit.u, differentials.du_dx),
+            (right.1 - hit.v, differentials.dv_dx),
+            (up.0 - hit.u, differentials.du_dy),
+            (up.1 - hit.v, differentials.dv_dy),
+        ] {
+            assert!(
+                (expected - actual).abs() < 0.02f64.mul_add(expected.abs(), 1e-6),
+                "{expected} {actual}"
+            );
+        }
+    }
+}
@@ -1,15 +1,16 @@
-use super::Aabb;
+use super::{Aabb, Interval, Solid};
 use crate::hittable::{HitRecord, Hittable};
 use crate::materials::Material;
 use crate::Ray;
 use crate::Vec3;
 use std::f64::consts::{PI, TAU};
+use std::sync::Arc;
 
 #[derive(Clone)]
 pub struct Sphere<T: Material + Clone + 'static> {
     center: Vec3,
     radius: f64,
-    material: T,
+    material: Arc<T>,
 }
 
 impl<T: Material + Clone + 'static> Sphere<T> {
@@ -17,27 +18,64 @@ impl<T: Material + Clone + 'static> Sphere<T> {
         Self {
             center,
             radius,
-            material,
+            material: Arc::new(material),
         }
     }
 
-    /// Return texture coordinates (u, v) from a point in a unit sphere
-    ///
-    /// # Arguments
-    /// * `p` - a given point on the sphere of radius one, centered at the origin.
-    ///
-    /// # Returns
-    /// (u: f64, v: f64)
-    /// * `u` - returned value \[0,1\] of angle around the Y axis from X=-1.
-    /// * `v` - returned value \[0,1\] of angle from Y=-1 to Y=+1.
-    fn get_sphere_uv(p: Vec3) -> (f64, f64) {
-        let theta = f64::acos(-p.y());
-        let phi = f64::atan2(-p.z(), p.x()) + PI;
-
-        let u = phi / TAU;
-        let v = theta / PI;
-
-        (u, v)
+    fn record_at(&self, ray: &Ray, t: f64) -> HitRecord {
+        let mut record = HitRecord::new(t, ray.at(t), self.material.clone());
+
+        let outward_normal = (record.p - self.center) / self.radius;
+        record.set_face_normal(ray, outward_normal);
+
+        set_sphere_coordinates(&mut record, ray, outward_normal, self.radius);
+
+        record
+    }
+}
+
+/// Return texture coordinates (u, v) from a point in a unit sphere
+///
+/// # Arguments
+/// * `p` - a given point on the sphere of radius one, centered at the origin.
+///
+/// # Returns
+/// (u: f64, v: f64)
+/// * `u` - returned value \[0,1\] of angle around the Y axis from X=-1.
+/// * `v` - returned value \[0,1\] of angle from Y=-1 to Y=+1.
+fn get_sphere_uv(p: Vec3) -> (f64, f64) {
+    let theta = f64::acos(-p.y());
+    let phi = f64::atan2(-p.z(), p.x()) + PI;
+
+    let u = phi / TAU;
+    let v = theta / PI;
+
+    (u, v)
+}
+
+/// Sets the texture coordinates, tangent and differentials of a hit on a sphere of `radius`,
+/// at the point with `outward_normal`
+pub(super) fn set_sphere_coordinates(
+    record: &mut HitRecord,
+    ray: &Ray,
+    outward_normal: Vec3,
+    radius: f64,
+) {
+    let (u, v) = get_sphere_uv(outward_normal);
+    record.set_texture_coordinates(u, v);
+
+    // How the point moves when u and v grow, except at the poles where u doesn't move it
+    let relative = outward_normal * radius;
+    let distance_to_axis = relative.x().hypot(relative.z());
+    if distance_to_axis > 1e-9 {
+        let around_axis = Vec3::new(relative.z(), 0.0, -relative.x()) * TAU;
+        let towards_top = Vec3::new(
+            -relative.y() * relative.x() / distance_to_axis,
+            distance_to_axis,
+            -relative.y() * relative.z() / distance_to_axis,
+        ) * PI;
+        record.set_tangent(around_axis);
+        record.set_differentials(ray, around_axis, towards_top);
     }
 }
 
@@ -82,15 +120,7 @@ impl<T: Material + Clone + 'static> Hittable for Sphere<T> {
             }
         }
 
-        let mut record = HitRecord::new(root, ray.at(root), self.material.clone());
-
-        let outward_normal = (record.p - self.center) / self.radius;
-        record.set_face_normal(ray, outward_normal);
-
-        let (u, v) = Self::get_sphere_uv(outward_normal);
-        record.set_texture_coordinates(u, v);
-
-        Some(record)
+        Some(self.record_at(ray, root))
     }
 
     fn bounding_box(&self, _time: (f64, f64)) -> Option<Aabb> {
@@ -99,4 +129,49 @@ impl<T: Material + Clone + 'static> Hittable for Sphere<T> {
             self.center + Vec3::new(self.radius, self.radius, self.radius),
         ))
     }
+
+    fn area(&self) -> f64 {
+        4.0 * PI * self.radius * self.radius
+    }
+
+    fn sample_surface(&self, sample: (f64, f64)) -> Option<HitRecord> {
+        let outward_normal = Vec3::unit_vector_from_sample(sample);
+
+        let mut record = HitRecord::new(
+            0.0,
+            self.center + outward_normal * self.radius,
+            self.material.clone(),
+        );
+        let (u, v) = get_sphere_uv(outward_normal);
+        record.set_texture_coordinates(u, v);
+        record.normal = outward_normal;
+        record.front_face = true;
+
+        Some(record)
+    }
+}
+
+impl<T: Material + Clone + 'static> Solid for Sphere<T> {
+    fn intervals(&self, ray: &Ray) -> Vec<Interval> {
+        let oc = ray.origin - self.center;
+        let a = ray.direction.length_squared();
+        let half_b = Vec3::dot(&oc, &ray.direction);
+        let c = self.radius.mul_add(-self.radius, oc.length_squared());
+
+        let discriminant = half_b.mul_add(half_b, -a * c);
+
+        // A ray tangent to the sphere never gets inside of it
+        if discriminant <= 0.0 {
+            return Vec::new();
+        }
+
+        let discriminant_sqrt = discriminant.sqrt();
+        let enter = (-half_b - discriminant_sqrt) / a;
+        let exit = (-half_b + discriminant_sqrt) / a;
+
+        vec![Interval::new(
+            self.record_at(ray, enter),
+            self.record_at(ray, exit),
+        )]
+    }
 }
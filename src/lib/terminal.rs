@@ -0,0 +1,359 @@
+//! Drawing the image in the terminal while it's being rendered
+//!
+//! Useful when rendering on another computer over SSH, where opening `out.png` isn't quick.
+//! The image is drawn with 24-bit colors, either with half block characters (`▀`), which show
+//! two pixels in each character, or with sixel graphics in terminals that support them.
+//!
+//! The image is shown as a message of the observer of the scene. The default progress bar
+//! prints it above itself, so they don't draw over each other, and like the progress bar,
+//! nothing is drawn when stderr isn't a terminal.
+
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+use crate::hittable::Hittable;
+use crate::progress::RenderObserver;
+use crate::scene::Scene;
+use crate::stats::RenderStats;
+use crate::{render_pass, Color};
+
+/// How the image is drawn in the terminal
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TerminalGraphics {
+    /// Characters with the top half in one color and the bottom half in another,
+    /// which works in any terminal with 24-bit colors
+    HalfBlocks,
+    /// Sixel graphics, with every pixel of the image but only 216 colors
+    Sixel,
+}
+
+impl TerminalGraphics {
+    /// Sixel if `TERM` or `TERM_PROGRAM` are of a terminal known to support it,
+    /// and half blocks otherwise
+    pub fn detect() -> Self {
+        let term = std::env::var("TERM").unwrap_or_default();
+        let program = std::env::var("TERM_PROGRAM").unwrap_or_default();
+
+        if ["sixel", "mlterm", "foot", "yaft"]
+            .iter()
+            .any(|name| term.contains(name))
+            || ["WezTerm", "iTerm.app", "mintty"].contains(&program.as_str())
+        {
+            Self::Sixel
+        } else {
+            Self::HalfBlocks
+        }
+    }
+}
+
+/// Renders the scene one sample per pixel at a time, drawing the image in the terminal as it
+/// gets better
+#[derive(Clone, Debug)]
+pub struct TerminalPreview {
+    graphics: TerminalGraphics,
+    columns: u32,
+    refresh_interval: Duration,
+}
+
+impl TerminalPreview {
+    /// Detects the graphics of the terminal, fits the image in `COLUMNS` characters (or 80,
+    /// if it isn't set) and draws it at most once a second
+    pub fn new() -> Self {
+        Self {
+            graphics: TerminalGraphics::detect(),
+            columns: std::env::var("COLUMNS")
+                .ok()
+                .and_then(|columns| columns.parse().ok())
+                .unwrap_or(80),
+            refresh_interval: Duration::from_secs(1),
+        }
+    }
+
+    #[must_use]
+    pub const fn graphics(mut self, graphics: TerminalGraphics) -> Self {
+        self.graphics = graphics;
+        self
+    }
+
+    /// Sets how many characters wide the image is drawn with half blocks.
+    /// Sixel graphics always have the size of the image.
+    #[must_use]
+    pub const fn columns(mut self, columns: u32) -> Self {
+        self.columns = columns;
+        self
+    }
+
+    /// Sets how long to wait at least between drawing the image, since drawing it too often
+    /// can be slow over SSH
+    #[must_use]
+    pub const fn refresh_interval(mut self, refresh_interval: Duration) -> Self {
+        self.refresh_interval = refresh_interval;
+        self
+    }
+
+    /// Renders the scene, returning the color of every pixel in the order of
+    /// `get_image_coordinates`
+    pub fn render<T: Hittable>(&self, scene: &Scene<T>) -> Vec<Color> {
+        let (image_width, image_height) = scene.image_size();
+        let samples_per_pixel = scene.samples_per_pixel();
+
+        let start_time = Instant::now();
+        let observer = scene.observer();
+        observer.start(samples_per_pixel as usize);
+
+        let mut accumulated =
+            vec![Color::new(0.0, 0.0, 0.0); (image_width * image_height) as usize];
+        let mut last_draw: Option<Instant> = None;
+        let mut drawn_lines = 0;
+
+        for sample_index in 0..samples_per_pixel {
+            for (total, color) in accumulated.iter_mut().zip(render_pass(scene, sample_index)) {
+                *total += color;
+            }
+            observer.step_done();
+
+            let finished = sample_index + 1 == samples_per_pixel;
+            if finished || last_draw.is_none_or(|time| time.elapsed() >= self.refresh_interval) {
+                let colors = average(&accumulated, sample_index + 1);
+                drawn_lines = self.draw(observer, &colors, image_width, image_height, drawn_lines);
+                last_draw = Some(Instant::now());
+            }
+        }
+        observer.finish(&RenderStats {
+            duration: start_time.elapsed(),
+            ..RenderStats::default()
+        });
+
+        average(&accumulated, samples_per_pixel)
+    }
+
+    /// Draws the image above the progress bar, over the last one that was drawn.
+    /// Returns how many lines the image takes.
+    fn draw(
+        &self,
+        observer: &dyn RenderObserver,
+        colors: &[Color],
+        image_width: u32,
+        image_height: u32,
+        drawn_lines: usize,
+    ) -> usize {
+        match self.graphics {
+            TerminalGraphics::HalfBlocks => {
+                let image = half_blocks(colors, image_width, image_height, self.columns);
+                let lines = image.lines().count();
+
+                // The last image is right above the progress bar
+                if drawn_lines > 0 {
+                    observer.message(&format!("\x1b[{drawn_lines}A{image}"));
+                } else {
+                    observer.message(&image);
+                }
+
+                lines
+            }
+            TerminalGraphics::Sixel => {
+                // How many lines a sixel image takes depends on the size of the font, so it's
+                // always drawn at the top of the screen instead
+                let clear = if drawn_lines > 0 { "" } else { "\x1b[2J" };
+                observer.message(&format!(
+                    "{clear}\x1b[H{}",
+                    sixel(colors, image_width, image_height)
+                ));
+
+                1
+            }
+        }
+    }
+}
+
+impl Default for TerminalPreview {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn average(accumulated: &[Color], samples: u32) -> Vec<Color> {
+    let scale = 1.0 / f64::from(samples.max(1));
+
+    accumulated.iter().map(|color| *color * scale).collect()
+}
+
+/// Draws the image, with its pixels in the order of `get_image_coordinates`, with half block
+/// characters that fit in `columns` characters. Every line ends with a new line.
+pub fn half_blocks(colors: &[Color], image_width: u32, image_height: u32, columns: u32) -> String {
+    let columns = columns.clamp(1, image_width);
+    // Characters are about twice as tall as they're wide, so two pixels in each one are square
+    let rows =
+        (u64::from(image_height) * u64::from(columns) / u64::from(image_width)).max(1) as u32;
+    let pixels = resize(colors, image_width, image_height, columns, rows);
+
+    let mut output = String::new();
+    for row in (0..rows).step_by(2) {
+        for column in 0..columns {
+            let [red, green, blue] = pixels[(row * columns + column) as usize].to_writeable_ints(1);
+            let _ = write!(output, "\x1b[38;2;{red};{green};{blue}m");
+
+            // The last row is left without a bottom half when there's an odd number of them
+            if row + 1 < rows {
+                let [red, green, blue] =
+                    pixels[((row + 1) * columns + column) as usize].to_writeable_ints(1);
+                let _ = write!(output, "\x1b[48;2;{red};{green};{blue}m▀");
+            } else {
+                output.push_str("\x1b[49m▀");
+            }
+        }
+        output.push_str("\x1b[0m\n");
+    }
+
+    output
+}
+
+/// Draws the image, with its pixels in the order of `get_image_coordinates`, with sixel
+/// graphics. The colors are rounded to a palette of 6 levels of red, green and blue.
+pub fn sixel(colors: &[Color], image_width: u32, image_height: u32) -> String {
+    let pixels = resize(colors, image_width, image_height, image_width, image_height);
+    let palette_indices = &pixels
+        .iter()
+        .map(|color| {
+            let [red, green, blue] = color.to_writeable_ints(1).map(|value| {
+                // Rounded to the closest of 0, 51, 102, 153, 204 and 255
+                ((u16::from(value) * 5 + 127) / 255) as u8
+            });
+            red * 36 + green * 6 + blue
+        })
+        .collect::<Vec<u8>>();
+
+    // Start, with square pixels, and the palette in percentages
+    let mut output = format!("\x1bPq\"1;1;{image_width};{image_height}");
+    for index in 0..216 {
+        let (red, green, blue) = (index / 36, index / 6 % 6, index % 6);
+        let _ = write!(
+            output,
+            "#{index};2;{};{};{}",
+            red * 20,
+            green * 20,
+            blue * 20
+        );
+    }
+
+    // Every band of 6 rows is drawn once for each of its colors, with a character for each
+    // column that has a bit for each row that has that color
+    for band in (0..image_height).step_by(6) {
+        let band_height = (image_height - band).min(6);
+        let band_indices = |x: u32| {
+            (0..band_height)
+                .map(move |row| palette_indices[((band + row) * image_width + x) as usize])
+        };
+
+        let mut used = [false; 216];
+        for x in 0..image_width {
+            for index in band_indices(x) {
+                used[index as usize] = true;
+            }
+        }
+
+        let mut first_color = true;
+        for index in (0..216).filter(|&index| used[index as usize]) {
+            let sixels: Vec<u8> = (0..image_width)
+                .map(|x| {
+                    band_indices(x)
+                        .enumerate()
+                        .filter(|&(_, pixel_index)| pixel_index == index)
+                        .fold(0, |bits, (row, _)| bits | 1 << row)
+                })
+                .collect();
+
+            // Go back to the start of the band to draw the next color
+            if !first_color {
+                output.push('$');
+            }
+            first_color = false;
+
+            let _ = write!(output, "#{index}");
+            for run in sixels.chunk_by(|a, b| a == b) {
+                let character = char::from(63 + run[0]);
+                if run.len() > 3 {
+                    let _ = write!(output, "!{}{character}", run.len());
+                } else {
+                    output.extend(std::iter::repeat_n(character, run.len()));
+                }
+            }
+        }
+        output.push('-');
+    }
+    output.push_str("\x1b\\");
+
+    output
+}
+
+/// Averages the pixels of the image, in the order of `get_image_coordinates`, into a smaller
+/// image, with its pixels from the top row to the bottom one
+fn resize(
+    colors: &[Color],
+    image_width: u32,
+    image_height: u32,
+    new_width: u32,
+    new_height: u32,
+) -> Vec<Color> {
+    // Every new pixel covers at least one of the old ones
+    let span = |i: u32, new_size: u32, old_size: u32| {
+        let min = i * old_size / new_size;
+        (min, ((i + 1) * old_size / new_size).max(min + 1))
+    };
+
+    (0..new_height)
+        .flat_map(|row| (0..new_width).map(move |column| (column, row)))
+        .map(|(column, row)| {
+            let (min_x, max_x) = span(column, new_width, image_width);
+            let (min_y, max_y) = span(row, new_height, image_height);
+
+            let mut total = Color::new(0.0, 0.0, 0.0);
+            for y in min_y..max_y {
+                for x in min_x..max_x {
+                    // The film starts from the bottom
+                    total += colors[((image_height - 1 - y) * image_width + x) as usize];
+                }
+            }
+            total * (1.0 / f64::from((max_x - min_x) * (max_y - min_y)))
+        })
+        .collect()
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: Color = Color::new(1.0, 0.0, 0.0);
+    const BLUE: Color = Color::new(0.0, 0.0, 1.0);
+
+    #[test]
+    fn test_half_blocks() {
+        // The bottom row is red and the top one is blue
+        let colors = [RED, RED, BLUE, BLUE];
+
+        let cell = "\x1b[38;2;0;0;255m\x1b[48;2;255;0;0m▀";
+        assert_eq!(
+            half_blocks(&colors, 2, 2, 2),
+            format!("{cell}{cell}\x1b[0m\n")
+        );
+
+        // Fitting it in a single column averages all the pixels
+        assert_eq!(
+            half_blocks(&colors, 2, 2, 1),
+            "\x1b[38;2;186;0;186m\x1b[49m▀\x1b[0m\n"
+        );
+    }
+
+    #[test]
+    fn test_sixel() {
+        let colors = vec![Color::new(1.0, 1.0, 1.0); 2 * 7];
+        let image = sixel(&colors, 2, 7);
+
+        assert!(image.starts_with("\x1bPq\"1;1;2;7#0;2;0;0;0"));
+        assert!(image.ends_with("\x1b\\"));
+        // White is the last color of the palette, the first band has all of its 6 rows and
+        // the second one only the first row
+        assert!(image.contains("#215;2;100;100;100#215~~-#215@@-"));
+    }
+}
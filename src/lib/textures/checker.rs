@@ -1,26 +1,102 @@
+use std::f64::consts::PI;
+
 use super::Texture;
+use crate::hittable::HitRecord;
 use crate::{Color, Vec3};
 
+/// Where the squares of a `CheckerTexture` are
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CheckerMode {
+    /// Cubes filling space, so the squares on curved surfaces aren't square
+    Solid,
+    /// Squares in texture coordinates, that follow the surface
+    Uv,
+}
+
 #[derive(Clone)]
 pub struct CheckerTexture<T: Texture, G: Texture> {
     odd: T,
     even: G,
+    mode: CheckerMode,
+    /// How many squares there are in a unit of distance or of texture coordinates
+    frequency: f64,
 }
 
 impl<T: Texture, G: Texture> CheckerTexture<T, G> {
-    pub fn new(odd: T, even: G) -> Self {
-        Self { odd, even }
+    /// Solid, with squares π/10 wide
+    pub const fn new(odd: T, even: G) -> Self {
+        Self {
+            odd,
+            even,
+            mode: CheckerMode::Solid,
+            frequency: 10.0 / PI,
+        }
+    }
+
+    #[must_use]
+    pub const fn mode(mut self, mode: CheckerMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    #[must_use]
+    pub const fn frequency(mut self, frequency: f64) -> Self {
+        self.frequency = frequency;
+        self
+    }
+
+    fn is_odd(&self, u: f64, v: f64, p: &Vec3) -> bool {
+        match self.mode {
+            CheckerMode::Solid => {
+                let sine = |coordinate: f64| f64::sin(PI * self.frequency * coordinate);
+                sine(p.x()) * sine(p.y()) * sine(p.z()) < 0.0
+            }
+            CheckerMode::Uv => {
+                let square = |coordinate: f64| (coordinate * self.frequency).floor();
+                (square(u) + square(v)).rem_euclid(2.0) != 0.0
+            }
+        }
     }
 }
 
 impl<T: Texture, G: Texture> Texture for CheckerTexture<T, G> {
     fn value(&self, u: f64, v: f64, p: &Vec3) -> Color {
-        let sines = f64::sin(10.0 * p.x()) * f64::sin(10.0 * p.y()) * f64::sin(10.0 * p.z());
-
-        if sines < 0.0 {
+        if self.is_odd(u, v, p) {
             self.odd.value(u, v, p)
         } else {
             self.even.value(u, v, p)
         }
     }
+
+    fn filtered_value(&self, record: &HitRecord) -> Color {
+        if self.is_odd(record.u, record.v, &record.p) {
+            self.odd.filtered_value(record)
+        } else {
+            self.even.filtered_value(record)
+        }
+    }
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLACK: Color = Color::new(0.0, 0.0, 0.0);
+    const WHITE: Color = Color::new(1.0, 1.0, 1.0);
+
+    #[test]
+    fn test_checker() {
+        let p = Vec3::new(0.1, 0.1, 0.1);
+        let checker = CheckerTexture::new(BLACK, WHITE);
+        assert!((checker.value(0.0, 0.0, &p).r() - 1.0).abs() < 1e-9);
+        assert!(checker.value(0.0, 0.0, &-p).r().abs() < 1e-9);
+
+        // 4 squares across, so the second square in u is odd
+        let checker = checker.mode(CheckerMode::Uv).frequency(4.0);
+        assert!((checker.value(0.1, 0.1, &p).r() - 1.0).abs() < 1e-9);
+        assert!(checker.value(0.3, 0.1, &p).r().abs() < 1e-9);
+        assert!((checker.value(0.3, 0.3, &p).r() - 1.0).abs() < 1e-9);
+        assert!(checker.value(-0.1, 0.1, &p).r().abs() < 1e-9);
+    }
 }
@@ -0,0 +1,241 @@
+use std::sync::Arc;
+
+use super::noise::NoiseFunction;
+use super::Texture;
+use crate::hittable::HitRecord;
+use crate::{Color, Vec3};
+
+/// Blends `a` into `b` by the luminance of `factor`, which can be a `Color` for a
+/// constant blend
+#[derive(Clone)]
+pub struct Mix<T: Texture, G: Texture, F: Texture> {
+    a: T,
+    b: G,
+    factor: F,
+}
+
+impl<T: Texture, G: Texture, F: Texture> Mix<T, G, F> {
+    pub const fn new(a: T, b: G, factor: F) -> Self {
+        Self { a, b, factor }
+    }
+}
+
+impl<T: Texture, G: Texture, F: Texture> Texture for Mix<T, G, F> {
+    fn value(&self, u: f64, v: f64, p: &Vec3) -> Color {
+        let factor = self.factor.value(u, v, p).luminance().clamp(0.0, 1.0);
+        Color::linear_blend(factor, &self.a.value(u, v, p), &self.b.value(u, v, p))
+    }
+
+    fn filtered_value(&self, record: &HitRecord) -> Color {
+        let factor = self
+            .factor
+            .filtered_value(record)
+            .luminance()
+            .clamp(0.0, 1.0);
+        Color::linear_blend(
+            factor,
+            &self.a.filtered_value(record),
+            &self.b.filtered_value(record),
+        )
+    }
+}
+
+/// The product of two textures, channel by channel
+#[derive(Clone)]
+pub struct Multiply<T: Texture, G: Texture> {
+    a: T,
+    b: G,
+}
+
+impl<T: Texture, G: Texture> Multiply<T, G> {
+    pub const fn new(a: T, b: G) -> Self {
+        Self { a, b }
+    }
+}
+
+impl<T: Texture, G: Texture> Texture for Multiply<T, G> {
+    fn value(&self, u: f64, v: f64, p: &Vec3) -> Color {
+        self.a.value(u, v, p) * self.b.value(u, v, p)
+    }
+
+    fn filtered_value(&self, record: &HitRecord) -> Color {
+        self.a.filtered_value(record) * self.b.filtered_value(record)
+    }
+}
+
+/// Maps every channel of `texture` from the range `from` to the range `to`, clamping the
+/// values outside of it
+#[derive(Clone)]
+pub struct Remap<T: Texture> {
+    texture: T,
+    from: (f64, f64),
+    to: (f64, f64),
+}
+
+impl<T: Texture> Remap<T> {
+    pub const fn new(texture: T, from: (f64, f64), to: (f64, f64)) -> Self {
+        Self { texture, from, to }
+    }
+
+    fn remap(&self, color: &Color) -> Color {
+        let remap = |channel: f64| {
+            let t = ((channel - self.from.0) / (self.from.1 - self.from.0)).clamp(0.0, 1.0);
+            t.mul_add(self.to.1 - self.to.0, self.to.0)
+        };
+        Color::new(remap(color.r()), remap(color.g()), remap(color.b()))
+    }
+}
+
+impl<T: Texture> Texture for Remap<T> {
+    fn value(&self, u: f64, v: f64, p: &Vec3) -> Color {
+        self.remap(&self.texture.value(u, v, p))
+    }
+
+    fn filtered_value(&self, record: &HitRecord) -> Color {
+        self.remap(&self.texture.filtered_value(record))
+    }
+}
+
+/// Colors the luminance of `input` with a gradient through colors at positions from 0 to 1,
+/// like turning a noise into the colors of a material
+#[derive(Clone)]
+pub struct ColorRamp<T: Texture> {
+    input: T,
+    /// Sorted by position
+    stops: Arc<[(f64, Color)]>,
+}
+
+impl<T: Texture> ColorRamp<T> {
+    /// `stops` are pairs of a position and the color there, in any order.
+    /// Before the first one it's the first color, and after the last one the last color.
+    ///
+    /// # Panics
+    /// If there are no stops
+    pub fn new(input: T, mut stops: Vec<(f64, Color)>) -> Self {
+        assert!(!stops.is_empty(), "A color ramp needs at least one color");
+        stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        Self {
+            input,
+            stops: stops.into(),
+        }
+    }
+
+    fn color_at(&self, position: f64) -> Color {
+        let after = self.stops.partition_point(|stop| stop.0 <= position);
+        if after == 0 {
+            return self.stops[0].1;
+        }
+        if after == self.stops.len() {
+            return self.stops[after - 1].1;
+        }
+
+        let (start, start_color) = self.stops[after - 1];
+        let (end, end_color) = self.stops[after];
+        Color::linear_blend((position - start) / (end - start), &start_color, &end_color)
+    }
+}
+
+impl<T: Texture> Texture for ColorRamp<T> {
+    fn value(&self, u: f64, v: f64, p: &Vec3) -> Color {
+        self.color_at(self.input.value(u, v, p).luminance())
+    }
+
+    fn filtered_value(&self, record: &HitRecord) -> Color {
+        self.color_at(self.input.filtered_value(record).luminance())
+    }
+}
+
+/// Looks `texture` up at points and texture coordinates moved around by a noise,
+/// which makes straight patterns wavy
+#[derive(Clone)]
+pub struct DomainWarp<T: Texture, N: NoiseFunction> {
+    texture: T,
+    noise: Arc<N>,
+    strength: f64,
+    scale: f64,
+}
+
+impl<T: Texture, N: NoiseFunction> DomainWarp<T, N> {
+    /// Moved up to a tenth of a unit, by the noise at its own scale
+    pub fn new(texture: T, noise: N) -> Self {
+        Self {
+            texture,
+            noise: Arc::new(noise),
+            strength: 0.1,
+            scale: 1.0,
+        }
+    }
+
+    /// How far the points are moved, for noise values of 1
+    #[must_use]
+    pub const fn strength(mut self, strength: f64) -> Self {
+        self.strength = strength;
+        self
+    }
+
+    /// How many times smaller the noise is made
+    #[must_use]
+    pub const fn scale(mut self, scale: f64) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// How much to move a point, with the noise at 3 far away places so every axis is different
+    fn warp(&self, p: &Vec3) -> Vec3 {
+        let point = p * self.scale;
+        Vec3::new(
+            self.noise.noise(&point),
+            self.noise.noise(&(point + Vec3::new(31.4, 15.9, 26.5))),
+            self.noise.noise(&(point + Vec3::new(-35.8, 97.9, -32.3))),
+        ) * self.strength
+    }
+}
+
+impl<T: Texture, N: NoiseFunction> Texture for DomainWarp<T, N> {
+    fn value(&self, u: f64, v: f64, p: &Vec3) -> Color {
+        let warp = self.warp(p);
+        self.texture.value(u + warp.x(), v + warp.y(), &(p + &warp))
+    }
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLACK: Color = Color::new(0.0, 0.0, 0.0);
+    const WHITE: Color = Color::new(1.0, 1.0, 1.0);
+
+    fn value<T: Texture>(texture: &T) -> Color {
+        texture.value(0.0, 0.0, &Vec3::new(0.0, 0.0, 0.0))
+    }
+
+    #[test]
+    fn test_mix_and_multiply() {
+        let red = Color::new(1.0, 0.0, 0.0);
+        let quarter = Color::new(0.25, 0.25, 0.25);
+
+        assert!(value(&Mix::new(BLACK, red, quarter)).is_close(Color::new(0.25, 0.0, 0.0), 1e-9));
+        assert!(value(&Multiply::new(red, quarter)).is_close(Color::new(0.25, 0.0, 0.0), 1e-9));
+    }
+
+    #[test]
+    fn test_remap() {
+        let color = Color::new(-1.0, 0.5, 2.0);
+        assert!(value(&Remap::new(color, (0.0, 1.0), (1.0, 3.0)))
+            .is_close(Color::new(1.0, 2.0, 3.0), 1e-9));
+    }
+
+    #[test]
+    fn test_color_ramp() {
+        let red = Color::new(1.0, 0.0, 0.0);
+        let stops = vec![(0.8, WHITE), (0.2, BLACK), (0.6, red)];
+
+        let ramp = |input: f64| value(&ColorRamp::new(WHITE * input, stops.clone()));
+        assert!(ramp(0.0).is_close(BLACK, 1e-9));
+        assert!(ramp(0.4).is_close(Color::new(0.5, 0.0, 0.0), 1e-9));
+        assert!(ramp(0.7).is_close(Color::new(1.0, 0.5, 0.5), 1e-9));
+        assert!(ramp(1.0).is_close(WHITE, 1e-9));
+    }
+}
@@ -1,35 +1,697 @@
-use image::{ImageBuffer, Rgb};
+use image::codecs::hdr::{HdrDecoder, HdrMetadata};
+use image::{DynamicImage, ImageBuffer, ImageResult, Pixel, Primitive};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
 use std::sync::Arc;
 
 use super::Texture;
+use crate::hittable::{HitRecord, TextureDifferentials};
 use crate::{Color, Vec3};
 
+/// How the texels around a point are looked up and averaged
+///
+/// Trilinear and EWA filtering need to know how much of the texture a pixel covers, which
+/// only hits of rays from the camera on surfaces that set their differentials have. Anywhere
+/// else, like in reflections or on parabolas and bowls, they blend the 4 closest texels of the
+/// full size image like `Bilinear`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextureFilter {
+    /// The texel the point is in
+    Nearest,
+    /// Blends the 4 texels closest to the point
+    Bilinear,
+    /// Blends bilinear lookups of the two mipmap levels with texels about as big as the pixel
+    Trilinear,
+    /// Averages the texels inside the ellipse that the pixel covers, with a Gaussian falloff.
+    /// Sharper than trilinear on surfaces seen at an angle.
+    Ewa,
+}
+
+/// What texture coordinates outside of \[0,1\] look up
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WrapMode {
+    /// The texel on the closest edge
+    Clamp,
+    /// The image tiled over and over
+    Repeat,
+    /// The image tiled, flipping every other copy so the edges match
+    Mirror,
+}
+
+impl WrapMode {
+    /// Turns a texel index into one inside of \[0,`size`)
+    const fn wrap(self, index: i64, size: i64) -> i64 {
+        match self {
+            Self::Clamp => {
+                if index < 0 {
+                    0
+                } else if index >= size {
+                    size - 1
+                } else {
+                    index
+                }
+            }
+            Self::Repeat => index.rem_euclid(size),
+            Self::Mirror => {
+                let index = index.rem_euclid(2 * size);
+                if index < size {
+                    index
+                } else {
+                    2 * size - 1 - index
+                }
+            }
+        }
+    }
+}
+
+/// How the channel values of an image are encoded
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColorSpace {
+    /// Gamma encoded like most 8 and 16 bit images, which are made to be shown directly
+    Srgb,
+    /// Proportional to the light, like floating point images or data that isn't a color
+    Linear,
+}
+
+impl ColorSpace {
+    /// Turns a value from 0 to 1 into a linear one
+    fn decode(self, value: f32) -> f32 {
+        match self {
+            Self::Srgb if value <= 0.040_45 => value / 12.92,
+            Self::Srgb => ((value + 0.055) / 1.055).powf(2.4),
+            Self::Linear => value,
+        }
+    }
+}
+
+/// The type of the channels of an image that an `Image` can be made from
+pub trait Channel: Primitive + 'static {
+    /// The color space that images with these channels are usually in
+    const COLOR_SPACE: ColorSpace;
+    /// The value with 0 as black and 1 as white
+    fn normalized(self) -> f32;
+}
+impl Channel for u8 {
+    const COLOR_SPACE: ColorSpace = ColorSpace::Srgb;
+    fn normalized(self) -> f32 {
+        f32::from(self) / f32::from(Self::MAX)
+    }
+}
+impl Channel for u16 {
+    const COLOR_SPACE: ColorSpace = ColorSpace::Srgb;
+    fn normalized(self) -> f32 {
+        f32::from(self) / f32::from(Self::MAX)
+    }
+}
+impl Channel for f32 {
+    const COLOR_SPACE: ColorSpace = ColorSpace::Linear;
+    fn normalized(self) -> f32 {
+        self
+    }
+}
+
+/// A linear color and its alpha
+type Texel = [f64; 4];
+
+const fn color(texel: &Texel) -> Color {
+    Color::new(texel[0], texel[1], texel[2])
+}
+
+fn blend(t: f64, a: &Texel, b: &Texel) -> Texel {
+    std::array::from_fn(|channel| t.mul_add(b[channel] - a[channel], a[channel]))
+}
+
+/// The image at one size, from the top left corner, with linear colors and alpha
+struct MipLevel {
+    width: u32,
+    height: u32,
+    texels: Vec<[f32; 4]>,
+}
+
+impl MipLevel {
+    /// Half the size of `self`, averaging every 2 by 2 texels
+    fn downsampled(&self) -> Self {
+        let width = (self.width / 2).max(1);
+        let height = (self.height / 2).max(1);
+
+        let texels = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let mut sum = [0.0; 4];
+                for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                    let source_x = (2 * x + dx).min(self.width - 1);
+                    let source_y = (2 * y + dy).min(self.height - 1);
+                    let texel = self.texels[(source_y * self.width + source_x) as usize];
+                    for channel in 0..4 {
+                        sum[channel] += texel[channel] / 4.0;
+                    }
+                }
+                sum
+            })
+            .collect();
+
+        Self {
+            width,
+            height,
+            texels,
+        }
+    }
+}
+
+/// A texture from an image, with an alpha channel that `Cutout` surfaces can use
 #[derive(Clone)]
 pub struct Image {
-    image: Arc<ImageBuffer<Rgb<u8>, Vec<u8>>>,
+    /// The image and smaller copies of it, each one half the size of the one before
+    /// down to a single texel
+    mipmap: Arc<Vec<MipLevel>>,
+    filter: TextureFilter,
+    wrap_mode: WrapMode,
+    uv_scale: (f64, f64),
+    uv_offset: (f64, f64),
+    /// Counterclockwise, in radians
+    uv_rotation: f64,
 }
 impl Image {
-    pub fn new(image: ImageBuffer<Rgb<u8>, Vec<u8>>) -> Self {
+    /// Decoded from the usual color space of its channels, filtered with
+    /// `TextureFilter::Trilinear` and clamped to the edges
+    pub fn new<P>(image: &ImageBuffer<P, Vec<P::Subpixel>>) -> Self
+    where
+        P: Pixel + 'static,
+        P::Subpixel: Channel,
+    {
+        Self::with_color_space(image, P::Subpixel::COLOR_SPACE)
+    }
+
+    /// Like `new`, but with the colors of `image` encoded in `color_space`
+    pub fn with_color_space<P>(
+        image: &ImageBuffer<P, Vec<P::Subpixel>>,
+        color_space: ColorSpace,
+    ) -> Self
+    where
+        P: Pixel + 'static,
+        P::Subpixel: Channel,
+    {
+        let base = MipLevel {
+            width: image.width(),
+            height: image.height(),
+            texels: image
+                .pixels()
+                .map(|pixel| {
+                    let [r, g, b, alpha] = pixel.to_rgba().0.map(Channel::normalized);
+                    [
+                        color_space.decode(r),
+                        color_space.decode(g),
+                        color_space.decode(b),
+                        // Images without alpha get the biggest value, which for floats isn't 1
+                        alpha.clamp(0.0, 1.0),
+                    ]
+                })
+                .collect(),
+        };
+
+        let mut mipmap = vec![base];
+        while let Some(level) = mipmap.last().filter(|level| level.width * level.height > 1) {
+            mipmap.push(level.downsampled());
+        }
+
         Self {
-            image: Arc::new(image),
+            mipmap: Arc::new(mipmap),
+            filter: TextureFilter::Trilinear,
+            wrap_mode: WrapMode::Clamp,
+            uv_scale: (1.0, 1.0),
+            uv_offset: (0.0, 0.0),
+            uv_rotation: 0.0,
+        }
+    }
+
+    /// Any image the `image` crate can decode
+    pub fn from_dynamic(image: &DynamicImage) -> Self {
+        match image {
+            DynamicImage::ImageLuma8(image) => Self::new(image),
+            DynamicImage::ImageLumaA8(image) => Self::new(image),
+            DynamicImage::ImageRgb8(image) => Self::new(image),
+            DynamicImage::ImageRgba8(image) => Self::new(image),
+            DynamicImage::ImageLuma16(image) => Self::new(image),
+            DynamicImage::ImageLumaA16(image) => Self::new(image),
+            DynamicImage::ImageRgb16(image) => Self::new(image),
+            DynamicImage::ImageRgba16(image) => Self::new(image),
+            // BGR images are 8 bit
+            _ => Self::new(&image.to_rgba8()),
+        }
+    }
+
+    /// Loads the image at `path`. Radiance HDR files are kept in floating point,
+    /// everything else is decoded by the `image` crate.
+    ///
+    /// # Errors
+    /// When the file can't be read or its format can't be decoded
+    pub fn open(path: impl AsRef<Path>) -> ImageResult<Self> {
+        let path = path.as_ref();
+        let is_hdr = path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("hdr"));
+
+        if is_hdr {
+            let decoder = HdrDecoder::new(BufReader::new(File::open(path)?))?;
+            let HdrMetadata { width, height, .. } = decoder.metadata();
+            let pixels = decoder.read_image_hdr()?;
+            let image =
+                ImageBuffer::from_fn(width, height, |x, y| pixels[(y * width + x) as usize]);
+            return Ok(Self::new(&image));
+        }
+
+        Ok(Self::from_dynamic(&image::open(path)?))
+    }
+
+    #[must_use]
+    pub const fn filter(mut self, filter: TextureFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    #[must_use]
+    pub const fn wrap_mode(mut self, wrap_mode: WrapMode) -> Self {
+        self.wrap_mode = wrap_mode;
+        self
+    }
+
+    /// How many times the image fits in one unit of each texture coordinate
+    #[must_use]
+    pub const fn uv_scale(mut self, u: f64, v: f64) -> Self {
+        self.uv_scale = (u, v);
+        self
+    }
+
+    /// Moves the image by `(u, v)`, after scaling and rotating it
+    #[must_use]
+    pub const fn uv_offset(mut self, u: f64, v: f64) -> Self {
+        self.uv_offset = (u, v);
+        self
+    }
+
+    /// Turns the image `angle` radians counterclockwise around its center
+    #[must_use]
+    pub const fn uv_rotation(mut self, angle: f64) -> Self {
+        self.uv_rotation = angle;
+        self
+    }
+
+    /// Scales and rotates a vector in texture coordinates
+    fn transform_vector(&self, (u, v): (f64, f64)) -> (f64, f64) {
+        let (sin, cos) = self.uv_rotation.sin_cos();
+        let (u, v) = (u * self.uv_scale.0, v * self.uv_scale.1);
+        (cos.mul_add(u, -sin * v), sin.mul_add(u, cos * v))
+    }
+
+    /// Where texture coordinates `(u, v)` are in the image
+    fn transform(&self, u: f64, v: f64) -> (f64, f64) {
+        let (u, v) = self.transform_vector((u - 0.5, v - 0.5));
+        (u + 0.5 + self.uv_offset.0, v + 0.5 + self.uv_offset.1)
+    }
+
+    fn texel(&self, level: usize, x: i64, y: i64) -> Texel {
+        let level = &self.mipmap[level];
+        let x = self.wrap_mode.wrap(x, i64::from(level.width));
+        let y = self.wrap_mode.wrap(y, i64::from(level.height));
+        let texel = level.texels[(y * i64::from(level.width) + x) as usize];
+
+        texel.map(f64::from)
+    }
+
+    /// Where the texture coordinates are in the image at `level`, in texels from the top left
+    fn texel_position(&self, level: usize, u: f64, v: f64) -> (f64, f64) {
+        let level = &self.mipmap[level];
+        // Flip V to image coordinates
+        (
+            u * f64::from(level.width),
+            (1.0 - v) * f64::from(level.height),
+        )
+    }
+
+    fn nearest(&self, u: f64, v: f64) -> Texel {
+        let (x, y) = self.texel_position(0, u, v);
+        self.texel(0, x.floor() as i64, y.floor() as i64)
+    }
+
+    fn bilinear(&self, level: usize, u: f64, v: f64) -> Texel {
+        let (x, y) = self.texel_position(level, u, v);
+        // Texel centers are in the middle of each texel
+        let (x, y) = (x - 0.5, y - 0.5);
+        let (x0, y0) = (x.floor(), y.floor());
+        let (tx, ty) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as i64, y0 as i64);
+
+        let bottom = blend(
+            tx,
+            &self.texel(level, x0, y0),
+            &self.texel(level, x0 + 1, y0),
+        );
+        let top = blend(
+            tx,
+            &self.texel(level, x0, y0 + 1),
+            &self.texel(level, x0 + 1, y0 + 1),
+        );
+        blend(ty, &bottom, &top)
+    }
+
+    /// The texel at `(u, v)` without mipmapping, already transformed
+    fn unfiltered(&self, u: f64, v: f64) -> Texel {
+        match self.filter {
+            TextureFilter::Nearest => self.nearest(u, v),
+            // Without differentials there's no way to know which mipmap level to use
+            TextureFilter::Bilinear | TextureFilter::Trilinear | TextureFilter::Ewa => {
+                self.bilinear(0, u, v)
+            }
+        }
+    }
+
+    /// The mipmap level with texels that are `width` of the texture coordinates wide,
+    /// which is a fraction between levels
+    #[allow(clippy::cast_precision_loss)] // There aren't anywhere close to 2^52 levels
+    fn level_of_detail(&self, width: f64) -> f64 {
+        let base = &self.mipmap[0];
+        let texels = width * f64::from(base.width.max(base.height));
+
+        texels
+            .max(1e-8)
+            .log2()
+            .clamp(0.0, (self.mipmap.len() - 1) as f64)
+    }
+
+    fn trilinear(&self, u: f64, v: f64, differentials: &TextureDifferentials) -> Texel {
+        let width = 2.0
+            * [
+                differentials.du_dx,
+                differentials.dv_dx,
+                differentials.du_dy,
+                differentials.dv_dy,
+            ]
+            .iter()
+            .fold(0.0_f64, |width, d| width.max(d.abs()));
+
+        let level = self.level_of_detail(width);
+        let lower = level.floor() as usize;
+        if lower + 1 >= self.mipmap.len() {
+            return self.bilinear(lower, u, v);
+        }
+
+        blend(
+            level - level.floor(),
+            &self.bilinear(lower, u, v),
+            &self.bilinear(lower + 1, u, v),
+        )
+    }
+
+    fn ewa(&self, u: f64, v: f64, differentials: &TextureDifferentials) -> Texel {
+        // Too long and thin ellipses are made wider, so they don't cover too many texels
+        const MAX_ANISOTROPY: f64 = 8.0;
+
+        let mut major = (differentials.du_dx, differentials.dv_dx);
+        let mut minor = (differentials.du_dy, differentials.dv_dy);
+        let length = |(du, dv): (f64, f64)| du.hypot(dv);
+        if length(major) < length(minor) {
+            std::mem::swap(&mut major, &mut minor);
+        }
+
+        let (major_length, mut minor_length) = (length(major), length(minor));
+        if minor_length * MAX_ANISOTROPY < major_length && minor_length > 0.0 {
+            let scale = major_length / (minor_length * MAX_ANISOTROPY);
+            minor = (minor.0 * scale, minor.1 * scale);
+            minor_length *= scale;
+        }
+        if minor_length == 0.0 {
+            return self.bilinear(0, u, v);
+        }
+
+        let level = self.level_of_detail(minor_length);
+        let lower = level.floor() as usize;
+        if lower + 1 >= self.mipmap.len() {
+            return self.ewa_level(lower, u, v, major, minor);
+        }
+
+        blend(
+            level - level.floor(),
+            &self.ewa_level(lower, u, v, major, minor),
+            &self.ewa_level(lower + 1, u, v, major, minor),
+        )
+    }
+
+    /// Averages the texels of `level` inside the ellipse around `(u, v)` with the axes
+    /// `major` and `minor`, in texture coordinates
+    #[allow(clippy::cast_precision_loss)] // Texel indices don't get anywhere close to 2^52
+    fn ewa_level(
+        &self,
+        level: usize,
+        u: f64,
+        v: f64,
+        major: (f64, f64),
+        minor: (f64, f64),
+    ) -> Texel {
+        const FALLOFF: f64 = 2.0;
+
+        let (width, height) = (
+            f64::from(self.mipmap[level].width),
+            f64::from(self.mipmap[level].height),
+        );
+        let (center_x, center_y) = self.texel_position(level, u, v);
+        let center = (center_x - 0.5, center_y - 0.5);
+        // The axes in texels, with y going down like in the image
+        let major = (major.0 * width, -major.1 * height);
+        let minor = (minor.0 * width, -minor.1 * height);
+
+        // The ellipse as xx * dx² + xy * dx * dy + yy * dy² < 1, made a texel bigger
+        // so it always has some texels inside
+        let mut xx = major.1.mul_add(major.1, minor.1 * minor.1) + 1.0;
+        let mut xy = -2.0 * major.0.mul_add(major.1, minor.0 * minor.1);
+        let mut yy = major.0.mul_add(major.0, minor.0 * minor.0) + 1.0;
+        let scale = 1.0 / xx.mul_add(yy, -xy * xy * 0.25);
+        xx *= scale;
+        xy *= scale;
+        yy *= scale;
+
+        let determinant = (-xy).mul_add(xy, 4.0 * xx * yy);
+        let half_width = 2.0 * (determinant * yy).sqrt() / determinant;
+        let half_height = 2.0 * (xx * determinant).sqrt() / determinant;
+        let first = (
+            (center.0 - half_width).ceil() as i64,
+            (center.1 - half_height).ceil() as i64,
+        );
+        let last = (
+            (center.0 + half_width).floor() as i64,
+            (center.1 + half_height).floor() as i64,
+        );
+
+        let mut sum = [0.0; 4];
+        let mut total_weight = 0.0;
+        for texel_y in first.1..=last.1 {
+            let dy = texel_y as f64 - center.1;
+            for texel_x in first.0..=last.0 {
+                let dx = texel_x as f64 - center.0;
+                let radius_squared = (xx * dx).mul_add(dx, (xy * dx).mul_add(dy, yy * dy * dy));
+                if radius_squared < 1.0 {
+                    let weight = (-FALLOFF * radius_squared).exp() - (-FALLOFF).exp();
+                    let texel = self.texel(level, texel_x, texel_y);
+                    for channel in 0..4 {
+                        sum[channel] += texel[channel] * weight;
+                    }
+                    total_weight += weight;
+                }
+            }
+        }
+
+        if total_weight > 0.0 {
+            sum.map(|channel| channel / total_weight)
+        } else {
+            self.bilinear(level, u, v)
         }
     }
 }
 
 impl Texture for Image {
     fn value(&self, u: f64, v: f64, _p: &Vec3) -> Color {
-        // Clamp input texture coordinates to [0,1] x [1,0]
-        let u = u.clamp(0.0, 1.0);
-        let v = 1.0 - v.clamp(0.0, 1.0); // Flip V to image coordinates
+        let (u, v) = self.transform(u, v);
+        color(&self.unfiltered(u, v))
+    }
+
+    fn filtered_value(&self, record: &HitRecord) -> Color {
+        let Some(differentials) = &record.differentials else {
+            return self.value(record.u, record.v, &record.p);
+        };
+
+        let (u, v) = self.transform(record.u, record.v);
+        let along_x = self.transform_vector((differentials.du_dx, differentials.dv_dx));
+        let along_y = self.transform_vector((differentials.du_dy, differentials.dv_dy));
+        let differentials = TextureDifferentials {
+            du_dx: along_x.0,
+            dv_dx: along_x.1,
+            du_dy: along_y.0,
+            dv_dy: along_y.1,
+        };
+
+        let texel = match self.filter {
+            TextureFilter::Nearest | TextureFilter::Bilinear => self.unfiltered(u, v),
+            TextureFilter::Trilinear => self.trilinear(u, v, &differentials),
+            TextureFilter::Ewa => self.ewa(u, v, &differentials),
+        };
+        color(&texel)
+    }
+
+    fn alpha(&self, u: f64, v: f64, _p: &Vec3) -> f64 {
+        let (u, v) = self.transform(u, v);
+        self.unfiltered(u, v)[3]
+    }
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use image::{LumaA, Rgb};
+
+    use super::*;
+    use crate::materials::Dielectric;
+
+    /// A 4 by 4 checkerboard of black and white texels
+    fn checkerboard() -> Image {
+        Image::new(&ImageBuffer::from_fn(4, 4, |x, y| {
+            if (x + y) % 2 == 0 {
+                Rgb([255_u8, 255, 255])
+            } else {
+                Rgb([0, 0, 0])
+            }
+        }))
+    }
+
+    fn record(u: f64, v: f64, width: f64) -> HitRecord {
+        let mut record = HitRecord::facing_up(Arc::new(Dielectric::new(1.5)));
+        record.set_texture_coordinates(u, v);
+        record.differentials = Some(TextureDifferentials {
+            du_dx: width,
+            dv_dx: 0.0,
+            du_dy: 0.0,
+            dv_dy: width,
+        });
+        record
+    }
+
+    #[test]
+    fn test_mipmap() {
+        let image = checkerboard();
+        assert_eq!(image.mipmap.len(), 3);
+
+        // The last level is the average of the whole image
+        let [r, g, b, alpha] = image.texel(2, 0, 0);
+        assert!(Color::new(r, g, b).is_close(Color::new(0.5, 0.5, 0.5), 1e-6));
+        assert!((alpha - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_filters() {
+        let image = checkerboard().filter(TextureFilter::Nearest);
+        // The top left texel, which is white
+        let p = Vec3::new(0.0, 0.0, 0.0);
+        assert!(image
+            .value(0.1, 0.9, &p)
+            .is_close(Color::new(1.0, 1.0, 1.0), 1e-6));
+
+        // Halfway between a white and a black texel
+        let image = image.filter(TextureFilter::Bilinear);
+        assert!(image
+            .value(0.25, 0.875, &p)
+            .is_close(Color::new(0.5, 0.5, 0.5), 1e-6));
+
+        // A pixel that covers the whole texture sees its average
+        for filter in [TextureFilter::Trilinear, TextureFilter::Ewa] {
+            let image = checkerboard().filter(filter);
+            let color = image.filtered_value(&record(0.1, 0.9, 1.0));
+            assert!(
+                color.is_close(Color::new(0.5, 0.5, 0.5), 1e-6),
+                "{filter:?}"
+            );
+
+            // And a tiny one sees the texel
+            let color = image.filtered_value(&record(0.125, 0.875, 1e-4));
+            assert!(
+                color.is_close(Color::new(1.0, 1.0, 1.0), 1e-6),
+                "{filter:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_wrap_modes() {
+        assert_eq!(WrapMode::Clamp.wrap(-3, 4), 0);
+        assert_eq!(WrapMode::Clamp.wrap(7, 4), 3);
+        assert_eq!(WrapMode::Repeat.wrap(-1, 4), 3);
+        assert_eq!(WrapMode::Repeat.wrap(5, 4), 1);
+        assert_eq!(WrapMode::Mirror.wrap(-1, 4), 0);
+        assert_eq!(WrapMode::Mirror.wrap(4, 4), 3);
+        assert_eq!(WrapMode::Mirror.wrap(9, 4), 1);
+
+        // The texel to the right of the image is the first one again when it repeats
+        let image = checkerboard()
+            .filter(TextureFilter::Nearest)
+            .wrap_mode(WrapMode::Repeat);
+        let p = Vec3::new(0.0, 0.0, 0.0);
+        assert!(image
+            .value(1.1, 0.9, &p)
+            .is_close(image.value(0.1, 0.9, &p), 1e-6));
+    }
+
+    #[test]
+    fn test_sources() {
+        let p = Vec3::new(0.0, 0.0, 0.0);
+        let gray = |image: Image| image.filter(TextureFilter::Nearest).value(0.5, 0.5, &p);
+
+        // Middle gray in sRGB is about a fifth of the light
+        let srgb = Image::new(&ImageBuffer::from_pixel(1, 1, Rgb([188_u8, 188, 188])));
+        assert!((gray(srgb).r() - 0.5029).abs() < 1e-3);
+        let linear = Image::with_color_space(
+            &ImageBuffer::from_pixel(1, 1, Rgb([128_u8, 128, 128])),
+            ColorSpace::Linear,
+        );
+        assert!((gray(linear).r() - 128.0 / 255.0).abs() < 1e-6);
+
+        // 16 bit images are decoded the same way, and floats are already linear
+        let deep = Image::new(&ImageBuffer::from_pixel(1, 1, Rgb([u16::MAX, 0, 0])));
+        assert!(gray(deep).is_close(Color::new(1.0, 0.0, 0.0), 1e-6));
+        let float = Image::new(&ImageBuffer::from_pixel(1, 1, Rgb([4.0_f32, 0.5, 0.0])));
+        assert!(gray(float.clone()).is_close(Color::new(4.0, 0.5, 0.0), 1e-6));
+        assert!((float.alpha(0.5, 0.5, &p) - 1.0).abs() < 1e-6);
+
+        let transparent = Image::new(&ImageBuffer::from_pixel(1, 1, LumaA([255_u8, 51])));
+        assert!((transparent.alpha(0.5, 0.5, &p) - 0.2).abs() < 1e-6);
+
+        assert!(Image::open("imgs/missing.png").is_err());
+    }
+
+    #[test]
+    fn test_uv_transform() {
+        let image = checkerboard().filter(TextureFilter::Nearest);
+        let p = Vec3::new(0.0, 0.0, 0.0);
 
-        let image_width = self.image.width();
-        let image_height = self.image.height();
+        // Moving by a texel turns white into black
+        let moved = image.clone().uv_offset(0.25, 0.0);
+        assert!(moved
+            .value(0.1, 0.9, &p)
+            .is_close(Color::new(0.0, 0.0, 0.0), 1e-6));
 
-        let pixel_coord_x = ((u * f64::from(image_width)) as u32).min(image_width - 1);
-        let pixel_coord_y = ((v * f64::from(image_height)) as u32).min(image_height - 1);
+        // Twice the scale makes texels half as big
+        let scaled = image.clone().uv_scale(2.0, 2.0);
+        assert!(scaled
+            .value(0.5 + 0.0625, 0.5 + 0.0625, &p)
+            .is_close(image.value(0.625, 0.625, &p), 1e-6));
 
-        let pixel = self.image[(pixel_coord_x, pixel_coord_y)];
+        // A quarter turn around the center takes the top left corner to the bottom left
+        let rotated = image.clone().uv_rotation(std::f64::consts::FRAC_PI_2);
+        assert!(rotated
+            .value(0.1, 0.9, &p)
+            .is_close(image.value(0.1, 0.1, &p), 1e-6));
 
-        pixel.into()
+        // Differentials are scaled with the texture coordinates
+        let scaled = checkerboard().uv_scale(0.25, 0.25);
+        assert!(scaled
+            .filtered_value(&record(0.5, 0.5, 1.0))
+            .is_close(checkerboard().filtered_value(&record(0.5, 0.5, 0.25)), 1e-6));
     }
 }
@@ -0,0 +1,208 @@
+use std::f64::consts::{PI, TAU};
+
+use super::Texture;
+use crate::hittable::HitRecord;
+use crate::vec3::Axis;
+use crate::{Color, Vec3};
+
+/// How the texture coordinates that a texture is looked up at are found from a hit
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TextureMapping {
+    /// The texture coordinates of the surface
+    Uv,
+    /// The point seen along the axis, with the other two coordinates as u and v
+    Planar(Axis),
+    /// The direction of the point from the origin, like the texture coordinates of a `Sphere`
+    Spherical,
+    /// Around the y axis like `Spherical`, with the height of the point as v
+    Cylindrical,
+    /// Planar along each axis, blended by how much the normal faces that axis.
+    /// A higher `sharpness` makes the blends between them narrower.
+    Triplanar { sharpness: f64 },
+}
+
+/// Which point and normal a `Mapped` texture uses
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextureSpace {
+    /// Where the hit is in the scene, so the texture stays in place when the surface moves
+    World,
+    /// Where the hit is on the surface before instances moved it, so the texture moves with it
+    Object,
+}
+
+/// A texture looked up at texture coordinates from a `TextureMapping` instead of the ones of
+/// the surface, and at the point in a `TextureSpace`
+#[derive(Clone)]
+pub struct Mapped<T: Texture> {
+    texture: T,
+    mapping: TextureMapping,
+    space: TextureSpace,
+    scale: f64,
+}
+
+impl<T: Texture> Mapped<T> {
+    /// In object space, at the scale of the space
+    pub const fn new(texture: T, mapping: TextureMapping) -> Self {
+        Self {
+            texture,
+            mapping,
+            space: TextureSpace::Object,
+            scale: 1.0,
+        }
+    }
+
+    #[must_use]
+    pub const fn space(mut self, space: TextureSpace) -> Self {
+        self.space = space;
+        self
+    }
+
+    /// How many times smaller the texture is made. The texture coordinates of the surface
+    /// aren't scaled, only the point and what's mapped from it.
+    #[must_use]
+    pub const fn scale(mut self, scale: f64) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    const fn planar(point: &Vec3, axis: Axis) -> (f64, f64) {
+        match axis {
+            Axis::X => (point.z(), point.y()),
+            Axis::Y => (point.x(), point.z()),
+            Axis::Z => (point.x(), point.y()),
+        }
+    }
+
+    fn around_y(point: &Vec3) -> f64 {
+        (f64::atan2(-point.z(), point.x()) + PI) / TAU
+    }
+
+    /// The mapped texture coordinates of a hit with texture coordinates `uv` at the already
+    /// scaled `point`, with how much of the color comes from each of them. Only triplanar
+    /// mapping uses more than one, and without a `normal` it blends the 3 axes evenly.
+    fn map(&self, uv: (f64, f64), point: &Vec3, normal: Option<&Vec3>) -> [(f64, f64, f64); 3] {
+        let single = |(u, v): (f64, f64)| [(u, v, 1.0), (0.0, 0.0, 0.0), (0.0, 0.0, 0.0)];
+
+        match self.mapping {
+            TextureMapping::Uv => single(uv),
+            TextureMapping::Planar(axis) => single(Self::planar(point, axis)),
+            TextureMapping::Spherical => {
+                let direction = point.unit_vector();
+                single((Self::around_y(&direction), f64::acos(-direction.y()) / PI))
+            }
+            TextureMapping::Cylindrical => single((Self::around_y(point), point.y())),
+            TextureMapping::Triplanar { sharpness } => {
+                let mut weights = normal.map_or([1.0; 3], |normal| {
+                    [normal.x(), normal.y(), normal.z()].map(|c| c.abs().powf(sharpness))
+                });
+                if weights.iter().sum::<f64>() <= 0.0 {
+                    weights = [1.0; 3];
+                }
+                let total: f64 = weights.iter().sum();
+
+                let mut planes = [Axis::X, Axis::Y, Axis::Z].into_iter().zip(weights);
+                [(); 3].map(|()| {
+                    let (axis, weight) = planes.next().unwrap_or((Axis::X, 0.0));
+                    let (u, v) = Self::planar(point, axis);
+                    (u, v, weight / total)
+                })
+            }
+        }
+    }
+}
+
+impl<T: Texture> Texture for Mapped<T> {
+    /// Both spaces are the point that's given, since there's nothing else to go by
+    fn value(&self, u: f64, v: f64, p: &Vec3) -> Color {
+        let point = p * self.scale;
+        self.map((u, v), &point, None)
+            .into_iter()
+            .filter(|&(_, _, weight)| weight > 0.0)
+            .map(|(u, v, weight)| self.texture.value(u, v, &point) * weight)
+            .sum()
+    }
+
+    fn filtered_value(&self, record: &HitRecord) -> Color {
+        let (point, normal) = match self.space {
+            TextureSpace::World => (record.p, record.normal),
+            TextureSpace::Object => (record.object_point, record.object_normal),
+        };
+        let point = point * self.scale;
+        let lookups = self.map((record.u, record.v), &point, Some(&normal));
+
+        // A single copy of the record for every lookup, since copying it is slow
+        let mut mapped = record.clone();
+        mapped.p = point;
+        if self.mapping != TextureMapping::Uv {
+            // The differentials are of the texture coordinates of the surface
+            mapped.differentials = None;
+        }
+
+        lookups
+            .into_iter()
+            .filter(|&(_, _, weight)| weight > 0.0)
+            .map(|(u, v, weight)| {
+                mapped.set_texture_coordinates(u, v);
+                self.texture.filtered_value(&mapped) * weight
+            })
+            .sum()
+    }
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::materials::Dielectric;
+
+    /// The texture coordinates as red and green, and the point as blue
+    #[derive(Clone)]
+    struct Coordinates;
+    impl Texture for Coordinates {
+        fn value(&self, u: f64, v: f64, p: &Vec3) -> Color {
+            Color::new(u, v, p.x())
+        }
+    }
+
+    #[test]
+    fn test_mappings() {
+        let p = Vec3::new(0.5, -2.0, 3.0);
+        let mapped = |mapping| Coordinates.mapped(mapping).value(0.1, 0.2, &p);
+
+        assert!(mapped(TextureMapping::Uv).is_close(Color::new(0.1, 0.2, 0.5), 1e-9));
+        assert!(mapped(TextureMapping::Planar(Axis::Y)).is_close(Color::new(0.5, 3.0, 0.5), 1e-9));
+        let around = Mapped::<Coordinates>::around_y(&p);
+        assert!(mapped(TextureMapping::Cylindrical).is_close(Color::new(around, -2.0, 0.5), 1e-9));
+
+        // The top of a sphere
+        let top = Coordinates.mapped(TextureMapping::Spherical).value(
+            0.0,
+            0.0,
+            &Vec3::new(0.0, 2.0, 0.0),
+        );
+        assert!((top.g() - 1.0).abs() < 1e-9);
+
+        // Without a normal the 3 planes are averaged
+        let triplanar = mapped(TextureMapping::Triplanar { sharpness: 4.0 });
+        assert!((triplanar.r() - (3.0 + 0.5 + 0.5) / 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_spaces() {
+        let mut record = HitRecord::facing_up(Arc::new(Dielectric::new(1.5)));
+        // Moved by an instance
+        record.p = Vec3::new(10.0, 1.0, 4.0);
+        record.normal = Vec3::new(1.0, 0.0, 0.0);
+
+        let texture = Coordinates.mapped(TextureMapping::Triplanar { sharpness: 8.0 });
+        // Facing up in object space, so it's the planar mapping along y
+        let object = texture.filtered_value(&record);
+        assert!(object.is_close(Color::new(0.0, 0.0, 0.0), 1e-9));
+
+        // And along x in the world
+        let world = texture.space(TextureSpace::World).filtered_value(&record);
+        assert!(world.is_close(Color::new(4.0, 1.0, 10.0), 1e-9));
+    }
+}
@@ -1,16 +1,41 @@
+use crate::hittable::HitRecord;
 use crate::materials::Lambertian;
 use crate::Color;
 use crate::Vec3;
 
 mod checker;
+mod combinators;
 mod image;
+mod mapping;
+mod noise;
+mod patterns;
 pub mod perlin;
-pub use self::image::Image;
-pub use checker::CheckerTexture;
-pub use perlin::Noise;
+mod simplex;
+mod worley;
+pub use self::image::{Channel, ColorSpace, Image, TextureFilter, WrapMode};
+pub use checker::{CheckerMode, CheckerTexture};
+pub use combinators::{ColorRamp, DomainWarp, Mix, Multiply, Remap};
+pub use mapping::{Mapped, TextureMapping, TextureSpace};
+pub use noise::{Fbm, NoiseFunction, NoiseTexture};
+pub use patterns::{Brick, Gradient, GradientShape, Grid, Marble, Wood};
+pub use perlin::{Noise, Perlin};
+pub use simplex::Simplex;
+pub use worley::{CellFeature, Worley};
 
 pub trait Texture: Send + Sync + Clone {
     fn value(&self, u: f64, v: f64, p: &Vec3) -> Color;
+    /// The value at the hit point of `record`, averaged over the part of the texture that
+    /// its pixel covers when it has differentials. Only textures that alias need to do that.
+    fn filtered_value(&self, record: &HitRecord) -> Color {
+        self.value(record.u, record.v, &record.p)
+    }
+    /// How opaque the texture is, from 0 to 1
+    fn alpha(&self, _u: f64, _v: f64, _p: &Vec3) -> f64 {
+        1.0
+    }
+    fn mapped(self, mapping: TextureMapping) -> Mapped<Self> {
+        Mapped::new(self, mapping)
+    }
     fn lambertian(self) -> Lambertian<Self> {
         Lambertian::new(self)
     }
@@ -0,0 +1,134 @@
+use std::sync::Arc;
+
+use super::Texture;
+use crate::{Color, Vec3};
+
+/// A smooth random function of a point in space, that procedural textures are made from
+pub trait NoiseFunction: Send + Sync + Clone {
+    /// A value that's usually between -1 and 1
+    fn noise(&self, point: &Vec3) -> f64;
+}
+
+/// Fractal Brownian motion: copies of a noise added together, each one with details
+/// `lacunarity` times smaller and `gain` times fainter than the one before
+#[derive(Clone)]
+pub struct Fbm<N: NoiseFunction> {
+    base: N,
+    octaves: u32,
+    lacunarity: f64,
+    gain: f64,
+}
+
+impl<N: NoiseFunction> Fbm<N> {
+    /// 6 octaves, each one twice as detailed and half as strong as the one before
+    pub const fn new(base: N) -> Self {
+        Self {
+            base,
+            octaves: 6,
+            lacunarity: 2.0,
+            gain: 0.5,
+        }
+    }
+
+    #[must_use]
+    pub const fn octaves(mut self, octaves: u32) -> Self {
+        self.octaves = octaves;
+        self
+    }
+
+    #[must_use]
+    pub const fn lacunarity(mut self, lacunarity: f64) -> Self {
+        self.lacunarity = lacunarity;
+        self
+    }
+
+    #[must_use]
+    pub const fn gain(mut self, gain: f64) -> Self {
+        self.gain = gain;
+        self
+    }
+}
+
+impl<N: NoiseFunction> NoiseFunction for Fbm<N> {
+    fn noise(&self, point: &Vec3) -> f64 {
+        let mut sum = 0.0;
+        let mut total_amplitude = 0.0;
+        let mut amplitude = 1.0;
+        let mut frequency = 1.0;
+
+        for _ in 0..self.octaves {
+            sum += amplitude * self.base.noise(&(point * frequency));
+            total_amplitude += amplitude;
+            amplitude *= self.gain;
+            frequency *= self.lacunarity;
+        }
+
+        // Divided by all the amplitudes so it stays in the range of the base noise
+        if total_amplitude > 0.0 {
+            sum / total_amplitude
+        } else {
+            0.0
+        }
+    }
+}
+
+/// A noise function as a gray texture, from black at -1 to white at 1
+#[derive(Clone)]
+pub struct NoiseTexture<N: NoiseFunction> {
+    noise: Arc<N>,
+    scale: f64,
+}
+
+impl<N: NoiseFunction> NoiseTexture<N> {
+    /// With the noise at its own scale
+    pub fn new(noise: N) -> Self {
+        Self {
+            noise: Arc::new(noise),
+            scale: 1.0,
+        }
+    }
+
+    /// How many times smaller the noise is made
+    #[must_use]
+    pub const fn scale(mut self, scale: f64) -> Self {
+        self.scale = scale;
+        self
+    }
+}
+
+impl<N: NoiseFunction> Texture for NoiseTexture<N> {
+    fn value(&self, _u: f64, _v: f64, p: &Vec3) -> Color {
+        let value = 0.5 * (1.0 + self.noise.noise(&(p * self.scale)));
+        Color::new(value, value, value)
+    }
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A noise that's the x coordinate, to see what `Fbm` does with it
+    #[derive(Clone)]
+    struct Ramp;
+    impl NoiseFunction for Ramp {
+        fn noise(&self, point: &Vec3) -> f64 {
+            point.x()
+        }
+    }
+
+    #[test]
+    fn test_fbm() {
+        let point = Vec3::new(0.5, 0.0, 0.0);
+
+        // 0.5 + 0.5 * 1.0, divided by 1.5
+        let fbm = Fbm::new(Ramp).octaves(2);
+        assert!((fbm.noise(&point) - 1.0 / 1.5).abs() < 1e-9);
+
+        // Without any loss of strength or change in detail, it's the base noise
+        let fbm = Fbm::new(Ramp).lacunarity(1.0).gain(1.0);
+        assert!((fbm.noise(&point) - 0.5).abs() < 1e-9);
+
+        assert!(Fbm::new(Ramp).octaves(0).noise(&point).abs() < 1e-9);
+    }
+}
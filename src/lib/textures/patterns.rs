@@ -0,0 +1,386 @@
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+use super::noise::NoiseFunction;
+use super::Texture;
+use crate::hittable::HitRecord;
+use crate::{Color, Vec3};
+
+/// Stripes along the z axis between `base` and `vein`, bent by a noise
+#[derive(Clone)]
+pub struct Marble<N: NoiseFunction, T: Texture, G: Texture> {
+    noise: Arc<N>,
+    base: T,
+    vein: G,
+    scale: f64,
+    turbulence: f64,
+}
+
+impl<N: NoiseFunction, T: Texture, G: Texture> Marble<N, T, G> {
+    /// With the stripes and the noise as in `Noise`
+    pub fn new(noise: N, base: T, vein: G) -> Self {
+        Self {
+            noise: Arc::new(noise),
+            base,
+            vein,
+            scale: 4.0,
+            turbulence: 10.0,
+        }
+    }
+
+    /// How many times smaller the stripes are made
+    #[must_use]
+    pub const fn scale(mut self, scale: f64) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// How much the noise bends the stripes
+    #[must_use]
+    pub const fn turbulence(mut self, turbulence: f64) -> Self {
+        self.turbulence = turbulence;
+        self
+    }
+
+    fn vein_amount(&self, p: &Vec3) -> f64 {
+        let phase = self
+            .turbulence
+            .mul_add(self.noise.noise(p).abs(), p.z() * self.scale);
+        0.5 * (1.0 + phase.sin())
+    }
+}
+
+impl<N: NoiseFunction, T: Texture, G: Texture> Texture for Marble<N, T, G> {
+    fn value(&self, u: f64, v: f64, p: &Vec3) -> Color {
+        Color::linear_blend(
+            self.vein_amount(p),
+            &self.base.value(u, v, p),
+            &self.vein.value(u, v, p),
+        )
+    }
+
+    fn filtered_value(&self, record: &HitRecord) -> Color {
+        Color::linear_blend(
+            self.vein_amount(&record.p),
+            &self.base.filtered_value(record),
+            &self.vein.filtered_value(record),
+        )
+    }
+}
+
+/// Growth rings around the y axis, that slowly go from `early` to `late` wood and then
+/// start over, with a noise to make them uneven
+#[derive(Clone)]
+pub struct Wood<N: NoiseFunction, T: Texture, G: Texture> {
+    noise: Arc<N>,
+    early: T,
+    late: G,
+    rings: f64,
+    distortion: f64,
+}
+
+impl<N: NoiseFunction, T: Texture, G: Texture> Wood<N, T, G> {
+    /// 8 rings per unit, moved up to half a ring by the noise
+    pub fn new(noise: N, early: T, late: G) -> Self {
+        Self {
+            noise: Arc::new(noise),
+            early,
+            late,
+            rings: 8.0,
+            distortion: 0.5,
+        }
+    }
+
+    /// How many rings there are in a unit of distance from the axis
+    #[must_use]
+    pub const fn rings(mut self, rings: f64) -> Self {
+        self.rings = rings;
+        self
+    }
+
+    /// How many rings the noise can move the rings by
+    #[must_use]
+    pub const fn distortion(mut self, distortion: f64) -> Self {
+        self.distortion = distortion;
+        self
+    }
+
+    fn late_amount(&self, p: &Vec3) -> f64 {
+        let ring = self
+            .distortion
+            .mul_add(self.noise.noise(p), p.x().hypot(p.z()) * self.rings);
+        // Late wood is only the end of every ring
+        ring.rem_euclid(1.0).powi(4)
+    }
+}
+
+impl<N: NoiseFunction, T: Texture, G: Texture> Texture for Wood<N, T, G> {
+    fn value(&self, u: f64, v: f64, p: &Vec3) -> Color {
+        Color::linear_blend(
+            self.late_amount(p),
+            &self.early.value(u, v, p),
+            &self.late.value(u, v, p),
+        )
+    }
+
+    fn filtered_value(&self, record: &HitRecord) -> Color {
+        Color::linear_blend(
+            self.late_amount(&record.p),
+            &self.early.filtered_value(record),
+            &self.late.filtered_value(record),
+        )
+    }
+}
+
+/// Rows of `brick` in texture coordinates, every other one moved by half a brick,
+/// with `mortar` between them
+#[derive(Clone)]
+pub struct Brick<T: Texture, G: Texture> {
+    face: T,
+    mortar: G,
+    /// How many bricks fit across u, and how many rows across v
+    count: (f64, f64),
+    /// In texture coordinates
+    mortar_width: f64,
+}
+
+impl<T: Texture, G: Texture> Brick<T, G> {
+    /// 4 bricks by 8 rows, with mortar a hundredth of the texture wide
+    pub const fn new(brick: T, mortar: G) -> Self {
+        Self {
+            face: brick,
+            mortar,
+            count: (4.0, 8.0),
+            mortar_width: 0.01,
+        }
+    }
+
+    #[must_use]
+    pub const fn bricks(mut self, across_u: f64, across_v: f64) -> Self {
+        self.count = (across_u, across_v);
+        self
+    }
+
+    #[must_use]
+    pub const fn mortar_width(mut self, mortar_width: f64) -> Self {
+        self.mortar_width = mortar_width;
+        self
+    }
+
+    fn is_mortar(&self, u: f64, v: f64) -> bool {
+        let row = v * self.count.1;
+        let shift = if row.floor().rem_euclid(2.0) == 0.0 {
+            0.0
+        } else {
+            0.5
+        };
+        let column = u.mul_add(self.count.0, shift);
+
+        // Half of the mortar is on each side of every joint
+        let distance_to_joint =
+            |position: f64, count: f64| (position - position.round()).abs() / count;
+        let half_width = self.mortar_width / 2.0;
+        distance_to_joint(row, self.count.1) < half_width
+            || distance_to_joint(column, self.count.0) < half_width
+    }
+}
+
+impl<T: Texture, G: Texture> Texture for Brick<T, G> {
+    fn value(&self, u: f64, v: f64, p: &Vec3) -> Color {
+        if self.is_mortar(u, v) {
+            self.mortar.value(u, v, p)
+        } else {
+            self.face.value(u, v, p)
+        }
+    }
+
+    fn filtered_value(&self, record: &HitRecord) -> Color {
+        if self.is_mortar(record.u, record.v) {
+            self.mortar.filtered_value(record)
+        } else {
+            self.face.filtered_value(record)
+        }
+    }
+}
+
+/// Lines of `line` in texture coordinates with `fill` between them
+#[derive(Clone)]
+pub struct Grid<T: Texture, G: Texture> {
+    line: T,
+    fill: G,
+    /// How many cells there are across u and v
+    cells: (f64, f64),
+    /// In texture coordinates
+    line_width: f64,
+}
+
+impl<T: Texture, G: Texture> Grid<T, G> {
+    /// 10 by 10 cells, with lines a hundredth of the texture wide
+    pub const fn new(line: T, fill: G) -> Self {
+        Self {
+            line,
+            fill,
+            cells: (10.0, 10.0),
+            line_width: 0.01,
+        }
+    }
+
+    #[must_use]
+    pub const fn cells(mut self, across_u: f64, across_v: f64) -> Self {
+        self.cells = (across_u, across_v);
+        self
+    }
+
+    #[must_use]
+    pub const fn line_width(mut self, line_width: f64) -> Self {
+        self.line_width = line_width;
+        self
+    }
+
+    fn is_line(&self, u: f64, v: f64) -> bool {
+        let distance_to_line = |position: f64, count: f64| {
+            let cells = position * count;
+            (cells - cells.round()).abs() / count
+        };
+        let half_width = self.line_width / 2.0;
+        distance_to_line(u, self.cells.0) < half_width
+            || distance_to_line(v, self.cells.1) < half_width
+    }
+}
+
+impl<T: Texture, G: Texture> Texture for Grid<T, G> {
+    fn value(&self, u: f64, v: f64, p: &Vec3) -> Color {
+        if self.is_line(u, v) {
+            self.line.value(u, v, p)
+        } else {
+            self.fill.value(u, v, p)
+        }
+    }
+
+    fn filtered_value(&self, record: &HitRecord) -> Color {
+        if self.is_line(record.u, record.v) {
+            self.line.filtered_value(record)
+        } else {
+            self.fill.filtered_value(record)
+        }
+    }
+}
+
+/// Which way a `Gradient` goes from its start to its end
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GradientShape {
+    /// From u = 0 to u = 1
+    Horizontal,
+    /// From v = 0 to v = 1
+    Vertical,
+    /// From the center of the texture coordinates to a distance of 0.5
+    Radial,
+    /// Around the center of the texture coordinates, counterclockwise from the right
+    Angular,
+}
+
+/// A smooth blend between two textures across the texture coordinates
+#[derive(Clone)]
+pub struct Gradient<T: Texture, G: Texture> {
+    start: T,
+    end: G,
+    shape: GradientShape,
+}
+
+impl<T: Texture, G: Texture> Gradient<T, G> {
+    /// A `GradientShape::Horizontal` gradient
+    pub const fn new(start: T, end: G) -> Self {
+        Self {
+            start,
+            end,
+            shape: GradientShape::Horizontal,
+        }
+    }
+
+    #[must_use]
+    pub const fn shape(mut self, shape: GradientShape) -> Self {
+        self.shape = shape;
+        self
+    }
+
+    fn position(&self, u: f64, v: f64) -> f64 {
+        let position = match self.shape {
+            GradientShape::Horizontal => u,
+            GradientShape::Vertical => v,
+            GradientShape::Radial => 2.0 * (u - 0.5).hypot(v - 0.5),
+            GradientShape::Angular => (v - 0.5).atan2(u - 0.5).rem_euclid(2.0 * PI) / (2.0 * PI),
+        };
+        position.clamp(0.0, 1.0)
+    }
+}
+
+impl<T: Texture, G: Texture> Texture for Gradient<T, G> {
+    fn value(&self, u: f64, v: f64, p: &Vec3) -> Color {
+        Color::linear_blend(
+            self.position(u, v),
+            &self.start.value(u, v, p),
+            &self.end.value(u, v, p),
+        )
+    }
+
+    fn filtered_value(&self, record: &HitRecord) -> Color {
+        Color::linear_blend(
+            self.position(record.u, record.v),
+            &self.start.filtered_value(record),
+            &self.end.filtered_value(record),
+        )
+    }
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLACK: Color = Color::new(0.0, 0.0, 0.0);
+    const WHITE: Color = Color::new(1.0, 1.0, 1.0);
+
+    fn brightness<T: Texture>(texture: &T, u: f64, v: f64) -> f64 {
+        texture.value(u, v, &Vec3::new(0.0, 0.0, 0.0)).r()
+    }
+
+    #[test]
+    fn test_brick() {
+        let brick = Brick::new(WHITE, BLACK);
+
+        // The middle of a brick, and the joints around it
+        assert!((brightness(&brick, 0.125, 0.0625) - 1.0).abs() < 1e-9);
+        assert!(brightness(&brick, 0.125, 0.125).abs() < 1e-9);
+        assert!(brightness(&brick, 0.25, 0.0625).abs() < 1e-9);
+
+        // The next row is moved by half a brick
+        assert!((brightness(&brick, 0.25, 0.1875) - 1.0).abs() < 1e-9);
+        assert!(brightness(&brick, 0.125, 0.1875).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_grid() {
+        let grid = Grid::new(WHITE, BLACK).cells(2.0, 4.0);
+
+        assert!((brightness(&grid, 0.5, 0.1) - 1.0).abs() < 1e-9);
+        assert!((brightness(&grid, 0.2, 0.752) - 1.0).abs() < 1e-9);
+        assert!(brightness(&grid, 0.2, 0.1).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_gradient() {
+        let gradient = Gradient::new(BLACK, WHITE);
+        assert!((brightness(&gradient, 0.25, 0.9) - 0.25).abs() < 1e-9);
+        assert!((brightness(&gradient, 2.0, 0.9) - 1.0).abs() < 1e-9);
+
+        let vertical = gradient.clone().shape(GradientShape::Vertical);
+        assert!((brightness(&vertical, 0.25, 0.9) - 0.9).abs() < 1e-9);
+
+        let radial = gradient.clone().shape(GradientShape::Radial);
+        assert!(brightness(&radial, 0.5, 0.5).abs() < 1e-9);
+        assert!((brightness(&radial, 0.5, 0.75) - 0.5).abs() < 1e-9);
+
+        let angular = gradient.shape(GradientShape::Angular);
+        assert!((brightness(&angular, 0.5, 0.75) - 0.25).abs() < 1e-9);
+    }
+}
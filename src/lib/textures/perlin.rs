@@ -2,6 +2,7 @@ use std::sync::Arc;
 
 use rand::Rng;
 
+use super::noise::NoiseFunction;
 use super::Texture;
 use crate::{Color, Vec3};
 
@@ -155,6 +156,12 @@ impl Perlin {
     }
 }
 
+impl NoiseFunction for Perlin {
+    fn noise(&self, point: &Vec3) -> f64 {
+        Self::noise(self, point)
+    }
+}
+
 const fn cast_to_range(x: i64) -> usize {
     // Only keep the last 8 bits (by bitwise AND)
     (x & 0xFF) as usize
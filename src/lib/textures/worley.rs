@@ -0,0 +1,135 @@
+use super::noise::NoiseFunction;
+use crate::Vec3;
+
+/// Which distance to the random points of `Worley` noise is used as its value
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CellFeature {
+    /// The distance to the closest point, which looks like round cells
+    Closest,
+    /// The distance to the second closest point
+    SecondClosest,
+    /// The difference between the two, which is 0 on the edges between cells
+    Edges,
+}
+
+/// Cellular noise: a random point in every unit cube, and the distances to the closest ones.
+/// The values go from 0 to about 1 instead of from -1 to 1.
+#[derive(Clone)]
+pub struct Worley {
+    seed: u64,
+    feature: CellFeature,
+}
+
+impl Default for Worley {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Worley {
+    /// The distance to the closest point
+    pub fn new() -> Self {
+        Self::with_seed(rand::random())
+    }
+
+    /// The same noise every time for the same `seed`
+    pub const fn with_seed(seed: u64) -> Self {
+        Self {
+            seed,
+            feature: CellFeature::Closest,
+        }
+    }
+
+    #[must_use]
+    pub const fn feature(mut self, feature: CellFeature) -> Self {
+        self.feature = feature;
+        self
+    }
+
+    /// The random point inside of the cube with its smallest corner at `cube`
+    #[allow(clippy::cast_precision_loss)] // Only the top 53 bits are kept anyways
+    fn feature_point(&self, cube: [i64; 3]) -> Vec3 {
+        let mut hash = self.seed;
+        let coordinates = cube.map(|coordinate| {
+            // Mixes the coordinates in with the finalizer of SplitMix64
+            hash ^= coordinate as u64;
+            hash = (hash ^ (hash >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+            hash = (hash ^ (hash >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+            hash ^= hash >> 31;
+            coordinate as f64 + (hash >> 11) as f64 / (1_u64 << 53) as f64
+        });
+
+        Vec3::new(coordinates[0], coordinates[1], coordinates[2])
+    }
+
+    /// The distances from `point` to the closest and the second closest random points
+    pub fn distances(&self, point: &Vec3) -> (f64, f64) {
+        let cube = [point.x(), point.y(), point.z()].map(|coordinate| coordinate.floor() as i64);
+
+        let mut closest = f64::INFINITY;
+        let mut second_closest = f64::INFINITY;
+        // Points further than the cubes around can't be closer than the one in the same cube
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                for dz in -1..=1 {
+                    let neighbor = [cube[0] + dx, cube[1] + dy, cube[2] + dz];
+                    let distance = (self.feature_point(neighbor) - *point).length();
+                    if distance < closest {
+                        second_closest = closest;
+                        closest = distance;
+                    } else if distance < second_closest {
+                        second_closest = distance;
+                    }
+                }
+            }
+        }
+
+        (closest, second_closest)
+    }
+}
+
+impl NoiseFunction for Worley {
+    fn noise(&self, point: &Vec3) -> f64 {
+        let (closest, second_closest) = self.distances(point);
+        match self.feature {
+            CellFeature::Closest => closest,
+            CellFeature::SecondClosest => second_closest,
+            CellFeature::Edges => second_closest - closest,
+        }
+    }
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_worley() {
+        let worley = Worley::new();
+
+        // The random points are where the noise is 0
+        let point = worley.feature_point([3, -2, 7]);
+        assert!(worley.noise(&point).abs() < 1e-9);
+        assert!((point.x() - 3.0).abs() < 1.0 && (point.z() - 7.0).abs() < 1.0);
+
+        for _ in 0..1000 {
+            let point = Vec3::random_min_max(-50.0, 50.0);
+            let (closest, second_closest) = worley.distances(&point);
+            assert!(closest <= second_closest);
+            assert!(closest < 3.0_f64.sqrt());
+
+            let edges = worley.clone().feature(CellFeature::Edges);
+            assert!((edges.noise(&point) - (second_closest - closest)).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_with_seed() {
+        let point = Vec3::new(1.3, -7.2, 4.9);
+        let value = Worley::with_seed(7).noise(&point);
+
+        assert!((Worley::with_seed(7).noise(&point) - value).abs() < f64::EPSILON);
+        assert!((Worley::with_seed(8).noise(&point) - value).abs() > f64::EPSILON);
+    }
+}
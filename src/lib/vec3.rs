@@ -1,7 +1,7 @@
 use rand::{distributions::Standard, prelude::Distribution, Rng};
 use std::ops;
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Axis {
     X,
     Y,
@@ -17,21 +17,36 @@ impl Distribution<Axis> for Standard {
     }
 }
 
+/// The floating point type that vectors, and so rays and boxes, are stored and computed in.
+///
+/// It's `f64`, or `f32` with the `f32` feature, which halves their size so more of them fit
+/// in the cache and in SIMD registers. Everything else stays in `f64`.
+#[cfg(not(feature = "f32"))]
+pub type Float = f64;
+#[cfg(feature = "f32")]
+pub type Float = f32;
+
+/// Converts a `Float` back to `f64`, which never loses precision
+#[allow(clippy::unnecessary_cast, clippy::cast_lossless)] // It's only a cast with `f32`
+pub(crate) const fn to_f64(value: Float) -> f64 {
+    value as f64
+}
+
 #[derive(Debug, Clone, Copy, Default)]
-pub struct Vec3(f64, f64, f64);
+pub struct Vec3(Float, Float, Float);
 
 impl Vec3 {
     pub const fn new(x: f64, y: f64, z: f64) -> Self {
-        Self(x, y, z)
+        Self(x as Float, y as Float, z as Float)
     }
     pub const fn x(&self) -> f64 {
-        self.0
+        to_f64(self.0)
     }
     pub const fn y(&self) -> f64 {
-        self.1
+        to_f64(self.1)
     }
     pub const fn z(&self) -> f64 {
-        self.2
+        to_f64(self.2)
     }
 
     /// Returns true if the vector is close to zero in all dimensions
@@ -80,6 +95,27 @@ impl Vec3 {
     pub fn random_unit_vector() -> Self {
         Self::random_in_unit_sphere().unit_vector()
     }
+    /// Maps a point in \[0,1)² to a `Vec3` with length 1, so evenly spread points
+    /// give evenly spread directions
+    pub fn unit_vector_from_sample((u, v): (f64, f64)) -> Self {
+        let z = 2.0f64.mul_add(-u, 1.0);
+        let radius = z.mul_add(-z, 1.0).max(0.0).sqrt();
+        let (sin, cos) = f64::sin_cos(2.0 * std::f64::consts::PI * v);
+
+        Self::new(radius * cos, radius * sin, z)
+    }
+    /// Maps a point in \[0,1)² to a point in the unit disk, like `unit_vector_from_sample`
+    pub fn in_unit_disk_from_sample((u, v): (f64, f64)) -> Self {
+        let radius = u.sqrt();
+        let (sin, cos) = f64::sin_cos(2.0 * std::f64::consts::PI * v);
+
+        Self::new(radius * cos, radius * sin, 0.0)
+    }
+    /// Maps a direction sample in \[0,1)² and a distance sample in \[0,1) to a point in the
+    /// unit sphere, like `unit_vector_from_sample`
+    pub fn in_unit_sphere_from_sample(direction: (f64, f64), distance: f64) -> Self {
+        Self::unit_vector_from_sample(direction) * distance.cbrt()
+    }
 
     pub fn reflect(v: &Self, n: &Self) -> Self {
         v - &(n * Self::dot(v, n) * 2.0)
@@ -94,9 +130,7 @@ impl Vec3 {
     }
 
     pub fn length_squared(&self) -> f64 {
-        // (self.x() ^ 2) + (self.y() ^ 2) + (self.z() ^ 2)
-        self.z()
-            .mul_add(self.z(), self.x().mul_add(self.x(), self.y() * self.y()))
+        Self::dot(self, self)
     }
     pub fn length(&self) -> f64 {
         self.length_squared().sqrt()
@@ -107,14 +141,14 @@ impl Vec3 {
     /// Dot product of two `Vec3`
     pub fn dot(u: &Self, v: &Self) -> f64 {
         // (u.x() * v.x()) + (u.y() * v.y()) + (u.z() * v.z())
-        u.z().mul_add(v.z(), u.x().mul_add(v.x(), u.y() * v.y()))
+        to_f64(u.2.mul_add(v.2, u.0.mul_add(v.0, u.1 * v.1)))
     }
     /// Cross product of two `Vec3`
     pub fn cross(u: &Self, v: &Self) -> Self {
-        Self::new(
-            u.y() * v.z() - u.z() * v.y(),
-            u.z() * v.x() - u.x() * v.z(),
-            u.x() * v.y() - u.y() * v.x(),
+        Self(
+            u.1 * v.2 - u.2 * v.1,
+            u.2 * v.0 - u.0 * v.2,
+            u.0 * v.1 - u.1 * v.0,
         )
     }
 }
@@ -123,7 +157,7 @@ impl Vec3 {
 
 // Indexing
 impl ops::Index<usize> for Vec3 {
-    type Output = f64;
+    type Output = Float;
     fn index(&self, index: usize) -> &Self::Output {
         match index {
             0 => &self.0,
@@ -134,7 +168,7 @@ impl ops::Index<usize> for Vec3 {
     }
 }
 impl ops::Index<u8> for Vec3 {
-    type Output = f64;
+    type Output = Float;
     fn index(&self, index: u8) -> &Self::Output {
         match index {
             0 => &self.0,
@@ -145,7 +179,7 @@ impl ops::Index<u8> for Vec3 {
     }
 }
 impl ops::Index<Axis> for Vec3 {
-    type Output = f64;
+    type Output = Float;
     fn index(&self, index: Axis) -> &Self::Output {
         match index {
             Axis::X => &self.0,
@@ -168,7 +202,7 @@ impl ops::IndexMut<u8> for Vec3 {
 // Comparison
 impl PartialEq for Vec3 {
     fn eq(&self, other: &Self) -> bool {
-        (self.x() == other.x()) && (self.y() == other.y()) && (self.z() == other.z())
+        (self.0 == other.0) && (self.1 == other.1) && (self.2 == other.2)
     }
 }
 impl Eq for Vec3 {}
@@ -177,84 +211,69 @@ impl Eq for Vec3 {}
 impl ops::Neg for Vec3 {
     type Output = Self;
     fn neg(self) -> Self::Output {
-        Self::new(-self.x(), -self.y(), -self.z())
+        Self(-self.0, -self.1, -self.2)
     }
 }
 impl ops::Neg for &Vec3 {
     type Output = Vec3;
     fn neg(self) -> Self::Output {
-        Vec3::new(-self.x(), -self.y(), -self.z())
+        Vec3(-self.0, -self.1, -self.2)
     }
 }
 
 // Addition
 impl ops::AddAssign for Vec3 {
     fn add_assign(&mut self, other: Self) {
-        self.0 += other.x();
-        self.1 += other.y();
-        self.2 += other.z();
+        self.0 += other.0;
+        self.1 += other.1;
+        self.2 += other.2;
     }
 }
 impl ops::Add for Vec3 {
     type Output = Self;
     fn add(self, other: Self) -> Self {
-        Self::new(
-            self.x() + other.x(),
-            self.y() + other.y(),
-            self.z() + other.z(),
-        )
+        Self(self.0 + other.0, self.1 + other.1, self.2 + other.2)
     }
 }
 impl ops::Add for &Vec3 {
     type Output = Vec3;
     fn add(self, other: Self) -> Vec3 {
-        Vec3::new(
-            self.x() + other.x(),
-            self.y() + other.y(),
-            self.z() + other.z(),
-        )
+        Vec3(self.0 + other.0, self.1 + other.1, self.2 + other.2)
     }
 }
 
 // Substraction
 impl ops::SubAssign for Vec3 {
     fn sub_assign(&mut self, other: Self) {
-        self.0 -= other.x();
-        self.1 -= other.y();
-        self.2 -= other.z();
+        self.0 -= other.0;
+        self.1 -= other.1;
+        self.2 -= other.2;
     }
 }
 impl ops::Sub for Vec3 {
     type Output = Self;
     fn sub(self, other: Self) -> Self {
-        Self::new(
-            self.x() - other.x(),
-            self.y() - other.y(),
-            self.z() - other.z(),
-        )
+        Self(self.0 - other.0, self.1 - other.1, self.2 - other.2)
     }
 }
 impl ops::Sub for &Vec3 {
     type Output = Vec3;
     fn sub(self, other: Self) -> Vec3 {
-        Vec3::new(
-            self.x() - other.x(),
-            self.y() - other.y(),
-            self.z() - other.z(),
-        )
+        Vec3(self.0 - other.0, self.1 - other.1, self.2 - other.2)
     }
 }
 
 // Multiplication
 impl ops::MulAssign for Vec3 {
     fn mul_assign(&mut self, rhs: Self) {
-        self.0 *= rhs.x();
-        self.1 *= rhs.y();
-        self.2 *= rhs.z();
+        self.0 *= rhs.0;
+        self.1 *= rhs.1;
+        self.2 *= rhs.2;
     }
 }
 impl ops::MulAssign<f64> for Vec3 {
     fn mul_assign(&mut self, rhs: f64) {
+        let rhs = rhs as Float;
         self.0 *= rhs;
         self.1 *= rhs;
         self.2 *= rhs;
@@ -263,46 +282,41 @@ impl ops::MulAssign<f64> for Vec3 {
 impl ops::Mul for Vec3 {
     type Output = Self;
     fn mul(self, other: Self) -> Self {
-        Self::new(
-            self.x() * other.x(),
-            self.y() * other.y(),
-            self.z() * other.z(),
-        )
+        Self(self.0 * other.0, self.1 * other.1, self.2 * other.2)
     }
 }
 impl ops::Mul for &Vec3 {
     type Output = Vec3;
     fn mul(self, other: Self) -> Vec3 {
-        Vec3::new(
-            self.x() * other.x(),
-            self.y() * other.y(),
-            self.z() * other.z(),
-        )
+        Vec3(self.0 * other.0, self.1 * other.1, self.2 * other.2)
     }
 }
 impl ops::Mul<f64> for Vec3 {
     type Output = Self;
     fn mul(self, other: f64) -> Self {
-        Self::new(self.x() * other, self.y() * other, self.z() * other)
+        let other = other as Float;
+        Self(self.0 * other, self.1 * other, self.2 * other)
     }
 }
 impl ops::Mul<f64> for &Vec3 {
     type Output = Vec3;
     fn mul(self, other: f64) -> Vec3 {
-        Vec3::new(self.x() * other, self.y() * other, self.z() * other)
+        let other = other as Float;
+        Vec3(self.0 * other, self.1 * other, self.2 * other)
     }
 }
 
 // Division
 impl ops::DivAssign for Vec3 {
     fn div_assign(&mut self, rhs: Self) {
-        self.0 /= rhs.x();
-        self.1 /= rhs.y();
-        self.2 /= rhs.z();
+        self.0 /= rhs.0;
+        self.1 /= rhs.1;
+        self.2 /= rhs.2;
     }
 }
 impl ops::DivAssign<f64> for Vec3 {
     fn div_assign(&mut self, rhs: f64) {
+        let rhs = rhs as Float;
         self.0 /= rhs;
         self.1 /= rhs;
         self.2 /= rhs;
@@ -311,33 +325,27 @@ impl ops::DivAssign<f64> for Vec3 {
 impl ops::Div for Vec3 {
     type Output = Self;
     fn div(self, other: Self) -> Self {
-        Self::new(
-            self.x() / other.x(),
-            self.y() / other.y(),
-            self.z() / other.z(),
-        )
+        Self(self.0 / other.0, self.1 / other.1, self.2 / other.2)
     }
 }
 impl ops::Div for &Vec3 {
     type Output = Vec3;
     fn div(self, other: Self) -> Vec3 {
-        Vec3::new(
-            self.x() / other.x(),
-            self.y() / other.y(),
-            self.z() / other.z(),
-        )
+        Vec3(self.0 / other.0, self.1 / other.1, self.2 / other.2)
     }
 }
 impl ops::Div<f64> for Vec3 {
     type Output = Self;
     fn div(self, other: f64) -> Self {
-        Self::new(self.x() / other, self.y() / other, self.z() / other)
+        let other = other as Float;
+        Self(self.0 / other, self.1 / other, self.2 / other)
     }
 }
 impl ops::Div<f64> for &Vec3 {
     type Output = Vec3;
     fn div(self, other: f64) -> Vec3 {
-        Vec3::new(self.x() / other, self.y() / other, self.z() / other)
+        let other = other as Float;
+        Vec3(self.0 / other, self.1 / other, self.2 / other)
     }
 }
 
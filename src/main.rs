@@ -8,20 +8,59 @@
     clippy::style
 )]
 
+use raytracing::farm::{Coordinator, Worker};
+use raytracing::hittable::Hittable;
+use raytracing::scene::Scene;
+use raytracing::terminal::TerminalPreview;
+
 mod scenes;
 
 fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let option = |name: &str| {
+        args.iter()
+            .position(|arg| arg == name)
+            .and_then(|i| args.get(i + 1))
+    };
+
+    // Render farm worker, with `cargo run --release -- --worker <address of the coordinator>`
+    if let Some(address) = option("--worker") {
+        let worker = Worker::connect(address).unwrap();
+        let seed = u64::from_le_bytes(worker.scene_description().try_into().unwrap());
+        worker.run(&scene(seed)).unwrap();
+        return;
+    }
+
     // Scene
-    let scene = scenes::scene3()
-        .image_width(1024)
-        .samples_per_pixel(300)
-        .max_depth(10)
-        .build();
+    let seed = rand::random();
+    let scene = scene(seed);
 
-    // Render
+    // Interactive preview, with `cargo run --release --features preview -- --preview`
+    #[cfg(feature = "preview")]
+    if args.iter().any(|arg| arg == "--preview") {
+        raytracing::preview::run(scene).unwrap();
+        return;
+    }
+
+    // Render, with the workers that connect to `--coordinator <address>`, or drawing the
+    // image in the terminal as it renders with `--terminal`
     let start_time = std::time::Instant::now();
 
-    let rendered_image = raytracing::render_chunked(&scene);
+    let (rendered_image, stats) = match option("--coordinator") {
+        Some(address) => {
+            let coordinator = Coordinator::bind(address, seed.to_le_bytes().to_vec()).unwrap();
+            let rendered_image = raytracing::render_distributed(&scene, &coordinator).unwrap();
+            (rendered_image, None)
+        }
+        None if args.iter().any(|arg| arg == "--terminal") => {
+            let rendered_image = raytracing::render_in_terminal(&scene, &TerminalPreview::new());
+            (rendered_image, None)
+        }
+        None => {
+            let (rendered_image, stats) = raytracing::render_chunked_with_stats(&scene);
+            (rendered_image, Some(stats))
+        }
+    };
 
     let render_duration = start_time.elapsed();
 
@@ -29,11 +68,24 @@ fn main() {
         "Done. Rendering took {}",
         get_elapsed_time_message(render_duration)
     );
+    if let Some(stats) = stats {
+        eprintln!("{stats}");
+    }
 
     // Output image
     rendered_image.save("out.png").unwrap();
 }
 
+/// The scene that's rendered. Render farm workers build it too, so everything random in it
+/// has to come from `seed`.
+fn scene(seed: u64) -> Scene<impl Hittable> {
+    scenes::scene3(seed)
+        .image_width(1024)
+        .samples_per_pixel(300)
+        .max_depth(10)
+        .build()
+}
+
 fn get_elapsed_time_message(start_time: std::time::Duration) -> String {
     let mut seconds_passed = start_time.as_secs();
 
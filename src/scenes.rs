@@ -1,14 +1,25 @@
-use rand::Rng;
+use std::sync::Arc;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 
 use raytracing::color;
 use raytracing::hittable::{Hittable, HittableList};
-use raytracing::instances::{RotateY, RotationY, Translate, Translation};
-use raytracing::materials::{Dielectric, DiffuseLight};
+use raytracing::instances::{Instance, RotateY, RotationY, Transform, Translate, Translation};
+use raytracing::integrators::BidirectionalPathTracer;
+use raytracing::materials::{
+    AnisotropicMetal, Dielectric, DiffuseLight, Layering, Material, ThinFilm, Velvet,
+};
 use raytracing::scene::SceneBuilder;
 use raytracing::surfaces::{
-    AABox, Bowl, BvhNode, ConstantMedium, MovingSphere, ParabolaX, Sphere, XYRect, XZRect, YZRect,
+    sdf, AABox, Aabb, Bowl, BvhNode, ConstantMedium, Csg, ImplicitSurface, MovingSphere, ParabolaX,
+    Quadric, Sdf, Sphere, XYRect, XZRect, YZRect,
+};
+use raytracing::textures::{
+    Brick, CellFeature, CheckerTexture, ColorRamp, DomainWarp, Fbm, Gradient, GradientShape, Grid,
+    Image, Marble, Mix, Multiply, Noise, NoiseTexture, Perlin, Remap, Simplex, Texture, Wood,
+    Worley,
 };
-use raytracing::textures::{CheckerTexture, Image, Noise, Texture};
 use raytracing::Camera;
 use raytracing::Color;
 use raytracing::Vec3;
@@ -197,7 +208,13 @@ pub fn scene2() -> SceneBuilder<impl Hittable> {
 
     SceneBuilder::new(world, camera, ASPECT_RATIO)
 }
-pub fn scene3() -> SceneBuilder<impl Hittable> {
+/// A color drawn from `rng`, unlike `Color::random`, so seeded scenes are the same every time
+fn random_color(rng: &mut impl Rng) -> Color {
+    Color::new(rng.gen(), rng.gen(), rng.gen())
+}
+
+/// Its random spheres come from `seed`, so the same seed always gives the same scene
+pub fn scene3(seed: u64) -> SceneBuilder<impl Hittable> {
     // Camera
     const LOOK_FROM: Vec3 = Vec3::new(13.0, 2.0, 3.0);
     const LOOK_AT: Vec3 = Vec3::new(0.0, 0.0, 0.0);
@@ -230,7 +247,7 @@ pub fn scene3() -> SceneBuilder<impl Hittable> {
     ));
 
     // Random spheres
-    let mut rng = rand::thread_rng();
+    let mut rng = StdRng::seed_from_u64(seed);
 
     for a in -20..20 {
         for b in -20..20 {
@@ -245,14 +262,15 @@ pub fn scene3() -> SceneBuilder<impl Hittable> {
                 match choose_mat {
                     // Lambertian 80% chance
                     x if x < 0.8 => {
-                        let sphere_material = (Color::random() * Color::random()).lambertian();
+                        let sphere_material =
+                            (random_color(&mut rng) * random_color(&mut rng)).lambertian();
 
                         world.push(Sphere::new(center, 0.2, sphere_material));
                     }
 
                     // Metal 15% chance
                     x if x < 0.95 => {
-                        let sphere_material = Color::random().metal(rng.gen_range(0.0..0.5));
+                        let sphere_material = random_color(&mut rng).metal(rng.gen_range(0.0..0.5));
 
                         world.push(Sphere::new(center, 0.2, sphere_material));
                     }
@@ -363,8 +381,7 @@ pub fn scene6() -> SceneBuilder<impl Hittable> {
         (0.0, 1.0),
     );
 
-    let earth_image = image::open("imgs/earthmap.jpg").unwrap();
-    let earth_texture = Image::new(earth_image.into_rgb8());
+    let earth_texture = Image::open("imgs/earthmap.jpg").unwrap();
     let globe = Sphere::new(Vec3::new(0.0, 0.0, 0.0), 2.0, earth_texture.lambertian());
 
     SceneBuilder::new(globe, camera, ASPECT_RATIO)
@@ -778,7 +795,7 @@ pub fn scene13() -> SceneBuilder<impl Hittable> {
     let boundary = Sphere::new(Vec3::new(0.0, 0.0, 0.0), 5000.0, Dielectric::new(1.5));
     world.push(ConstantMedium::new(boundary, color::WHITE, 0.0001));
 
-    let emat = Image::new(image::open("imgs/earthmap.jpg").unwrap().into_rgb8()).lambertian();
+    let emat = Image::open("imgs/earthmap.jpg").unwrap().lambertian();
     world.push(Sphere::new(Vec3::new(400.0, 200.0, 400.0), 100.0, emat));
     let pertext = Noise::new(0.1);
     world.push(Sphere::new(
@@ -911,8 +928,7 @@ pub fn scene15() -> SceneBuilder<impl Hittable> {
     let green = color::GREEN.lambertian();
     let checker = CheckerTexture::new(color::BLACKISH, color::WHITISH).lambertian();
     let mirror = color::WHITISH.metal(0.001);
-    let cakey = image::open("imgs/cakey_boss.jpg").unwrap();
-    let boss = Image::new(cakey.into_rgb8()).lambertian();
+    let boss = Image::open("imgs/cakey_boss.jpg").unwrap().lambertian();
 
     let mut world = HittableList::new();
 
@@ -989,3 +1005,561 @@ pub fn scene16() -> SceneBuilder<impl Hittable> {
     SceneBuilder::new(world, camera, ASPECT_RATIO).background_color(color::BLUE_SKY)
     // Use max depth=10
 }
+pub fn scene17() -> SceneBuilder<impl Hittable> {
+    // Camera
+    const LOOK_FROM: Vec3 = Vec3::new(6.0, 4.0, 8.0);
+    const LOOK_AT: Vec3 = Vec3::new(0.0, 1.0, 0.0);
+    const VUP: Vec3 = Vec3::new(0.0, 1.0, 0.0);
+    const FOV: f64 = 35.0;
+    const APERTURE: f64 = 0.0;
+    const DIST_TO_FOCUS: f64 = 10.0;
+    const ASPECT_RATIO: f64 = 16.0 / 9.0;
+
+    let camera = Camera::new(
+        LOOK_FROM,
+        LOOK_AT,
+        VUP,
+        FOV,
+        ASPECT_RATIO,
+        APERTURE,
+        DIST_TO_FOCUS,
+        (0.0, 1.0),
+    );
+
+    let mut world = HittableList::new();
+
+    // Ground
+    let checker = CheckerTexture::new(color::WHITISH, color::BLACKISH).lambertian();
+    world.push(XZRect::new((-100.0, 100.0), (-100.0, 100.0), 0.0, checker));
+
+    // Sphere with a box cut out of it
+    let red = color::RED.lambertian();
+    let sphere = Sphere::new(Vec3::new(-2.0, 1.2, 0.0), 1.2, red);
+    let cutter = AABox::new(
+        Vec3::new(-2.0, 1.2, 0.0),
+        Vec3::new(-0.5, 3.0, 1.5),
+        color::WHITE.lambertian(),
+    );
+    world.push(Csg::difference(sphere, cutter));
+
+    // Lens made from two intersecting spheres
+    let glass = Dielectric::new(1.5);
+    let lens = Csg::intersection(
+        Sphere::new(Vec3::new(1.0, 1.2, -1.0), 1.5, glass.clone()),
+        Sphere::new(Vec3::new(1.0, 1.2, 1.0), 1.5, glass),
+    );
+    world.push(lens);
+
+    // Rotated hollow cube
+    let green = color::GREEN.lambertian();
+    let hollow_cube = Csg::difference(
+        AABox::new(
+            Vec3::new(-0.75, 0.0, -0.75),
+            Vec3::new(0.75, 1.5, 0.75),
+            green.clone(),
+        ),
+        Sphere::new(Vec3::new(0.0, 0.75, 0.0), 0.95, green),
+    );
+    world.push(
+        hollow_cube
+            .rotate_y_by(0.6)
+            .translate_by(Vec3::new(3.5, 0.0, 1.5)),
+    );
+
+    // Light
+    let light = DiffuseLight::new(Color::new(6.0, 6.0, 6.0));
+    world.push(Sphere::new(Vec3::new(0.0, 8.0, 4.0), 2.0, light));
+
+    let world = world.into_bvh((0.0, 1.0));
+
+    SceneBuilder::new(world, camera, ASPECT_RATIO).background_color(color::BLUE_SKY * 0.3)
+}
+pub fn scene18() -> SceneBuilder<impl Hittable> {
+    // Camera
+    const LOOK_FROM: Vec3 = Vec3::new(0.0, 3.0, 9.0);
+    const LOOK_AT: Vec3 = Vec3::new(0.0, 1.2, 0.0);
+    const VUP: Vec3 = Vec3::new(0.0, 1.0, 0.0);
+    const FOV: f64 = 35.0;
+    const APERTURE: f64 = 0.0;
+    const DIST_TO_FOCUS: f64 = 10.0;
+    const ASPECT_RATIO: f64 = 16.0 / 9.0;
+
+    let camera = Camera::new(
+        LOOK_FROM,
+        LOOK_AT,
+        VUP,
+        FOV,
+        ASPECT_RATIO,
+        APERTURE,
+        DIST_TO_FOCUS,
+        (0.0, 1.0),
+    );
+
+    let mut world = HittableList::new();
+
+    // Ground
+    let ground = color::WHITISH.lambertian();
+    world.push(XZRect::new((-100.0, 100.0), (-100.0, 100.0), 0.0, ground));
+
+    // Twisted column melting into a ring
+    let column = sdf::Cuboid {
+        half_extents: Vec3::new(0.4, 1.2, 0.4),
+    }
+    .twist(1.2);
+    let ring = sdf::Torus {
+        major_radius: 0.9,
+        minor_radius: 0.2,
+    }
+    .translate(Vec3::new(0.0, 0.3, 0.0));
+    let sculpture = column
+        .smooth_union(ring, 0.3)
+        .translate(Vec3::new(0.0, 1.2, 0.0));
+    world.push(
+        ImplicitSurface::new(
+            sculpture,
+            Aabb::new(Vec3::new(-1.3, -0.1, -1.3), Vec3::new(1.3, 2.5, 1.3)),
+            Color::new(0.8, 0.6, 0.2).metal(0.2),
+        )
+        .step_scale(0.5),
+    );
+
+    // Row of spheres from a single repeated distance function
+    let spheres = sdf::Sphere { radius: 0.3 }
+        .repeat(Vec3::new(1.0, 0.0, 0.0))
+        .translate(Vec3::new(0.0, 0.3, 2.0));
+    world.push(ImplicitSurface::new(
+        spheres,
+        Aabb::new(Vec3::new(-3.3, 0.0, 1.7), Vec3::new(3.3, 0.6, 2.3)),
+        color::RED.lambertian(),
+    ));
+
+    // Light
+    let light = DiffuseLight::new(Color::new(6.0, 6.0, 6.0));
+    world.push(Sphere::new(Vec3::new(-3.0, 7.0, 4.0), 2.0, light));
+
+    let world = world.into_bvh((0.0, 1.0));
+
+    SceneBuilder::new(world, camera, ASPECT_RATIO).background_color(color::BLUE_SKY * 0.3)
+}
+pub fn scene19() -> SceneBuilder<impl Hittable> {
+    // Camera
+    const LOOK_FROM: Vec3 = Vec3::new(0.0, 4.0, 12.0);
+    const LOOK_AT: Vec3 = Vec3::new(0.0, 1.0, 0.0);
+    const VUP: Vec3 = Vec3::new(0.0, 1.0, 0.0);
+    const FOV: f64 = 35.0;
+    const APERTURE: f64 = 0.0;
+    const DIST_TO_FOCUS: f64 = 10.0;
+    const ASPECT_RATIO: f64 = 16.0 / 9.0;
+
+    let camera = Camera::new(
+        LOOK_FROM,
+        LOOK_AT,
+        VUP,
+        FOV,
+        ASPECT_RATIO,
+        APERTURE,
+        DIST_TO_FOCUS,
+        (0.0, 1.0),
+    );
+
+    let mut world = HittableList::new();
+
+    let earth = Image::open("imgs/earthmap.jpg").unwrap().lambertian();
+
+    // Ground
+    let ground = color::WHITISH.lambertian();
+    world.push(XZRect::new((-100.0, 100.0), (-100.0, 100.0), 0.0, ground));
+
+    world.push(Quadric::ellipsoid(
+        Vec3::new(-4.5, 1.0, 0.0),
+        Vec3::new(1.0, 1.0, 1.5),
+        earth.clone(),
+    ));
+    world.push(Quadric::paraboloid(
+        Vec3::new(-1.5, 0.0, 0.0),
+        1.0,
+        2.0,
+        earth.clone(),
+    ));
+    world.push(Quadric::hyperboloid(
+        Vec3::new(1.5, 1.0, 0.0),
+        Vec3::new(0.5, 0.8, 0.5),
+        2.0,
+        earth.clone(),
+    ));
+    world.push(Quadric::cylinder(Vec3::new(4.5, 1.0, 0.0), 1.0, 2.0, earth));
+
+    // Light
+    let light = DiffuseLight::new(Color::new(6.0, 6.0, 6.0));
+    world.push(Sphere::new(Vec3::new(0.0, 8.0, 6.0), 2.0, light));
+
+    let world = world.into_bvh((0.0, 1.0));
+
+    SceneBuilder::new(world, camera, ASPECT_RATIO).background_color(color::BLUE_SKY * 0.3)
+}
+pub fn scene20() -> SceneBuilder<impl Hittable> {
+    // Camera
+    const LOOK_FROM: Vec3 = Vec3::new(0.0, 6.0, 30.0);
+    const LOOK_AT: Vec3 = Vec3::new(0.0, 0.0, 0.0);
+    const VUP: Vec3 = Vec3::new(0.0, 1.0, 0.0);
+    const FOV: f64 = 40.0;
+    const APERTURE: f64 = 0.0;
+    const DIST_TO_FOCUS: f64 = 10.0;
+    const ASPECT_RATIO: f64 = 16.0 / 9.0;
+
+    let camera = Camera::new(
+        LOOK_FROM,
+        LOOK_AT,
+        VUP,
+        FOV,
+        ASPECT_RATIO,
+        APERTURE,
+        DIST_TO_FOCUS,
+        (0.0, 1.0),
+    );
+
+    // A single tree shared by every instance
+    let mut tree = HittableList::new();
+    let bark = Color::new(0.35, 0.2, 0.1).lambertian();
+    let leaves = color::GREEN.lambertian();
+    tree.push(AABox::new(
+        Vec3::new(-0.1, 0.0, -0.1),
+        Vec3::new(0.1, 1.0, 0.1),
+        bark,
+    ));
+    tree.push(Quadric::paraboloid(
+        Vec3::new(0.0, 0.6, 0.0),
+        -1.5,
+        -1.2,
+        leaves.clone(),
+    ));
+    tree.push(Sphere::new(Vec3::new(0.0, 1.3, 0.0), 0.45, leaves));
+    let tree: Arc<dyn Hittable> = Arc::new(tree.into_bvh((0.0, 1.0)));
+
+    let autumn: Arc<dyn Material> = Arc::new(Color::new(0.8, 0.4, 0.1).lambertian());
+
+    let mut world = HittableList::new();
+
+    // Ground
+    let ground = Color::new(0.4, 0.35, 0.25).lambertian();
+    world.push(XZRect::new(
+        (-1000.0, 1000.0),
+        (-1000.0, 1000.0),
+        0.0,
+        ground,
+    ));
+
+    // Forest of 10,000 trees
+    let mut rng = rand::thread_rng();
+    for x in -50..50 {
+        for z in -100..0 {
+            let position = Vec3::new(
+                f64::from(x) + rng.gen_range(-0.3..0.3),
+                0.0,
+                f64::from(z).mul_add(1.5, rng.gen_range(-0.3..0.3)),
+            );
+            let transform = Transform::scaling(Vec3::new(1.0, rng.gen_range(0.7..1.5), 1.0))
+                .then(&Transform::rotation_y(
+                    rng.gen_range(0.0..std::f64::consts::TAU),
+                ))
+                .then(&Transform::translation(position));
+
+            let instance = Instance::new(tree.clone(), transform);
+
+            if rng.gen::<f64>() < 0.2 {
+                world.push(instance.with_material(autumn.clone()));
+            } else {
+                world.push(instance);
+            }
+        }
+    }
+
+    // Sun
+    let light = DiffuseLight::new(Color::new(8.0, 8.0, 7.0));
+    world.push(Sphere::new(Vec3::new(-30.0, 40.0, 20.0), 10.0, light));
+
+    let world = world.into_bvh((0.0, 1.0));
+
+    SceneBuilder::new(world, camera, ASPECT_RATIO).background_color(color::BLUE_SKY * 0.5)
+}
+pub fn scene21() -> SceneBuilder<impl Hittable> {
+    // Camera
+    const LOOK_FROM: Vec3 = Vec3::new(278.0, 278.0, -800.0);
+    const LOOK_AT: Vec3 = Vec3::new(278.0, 278.0, 0.0);
+    const VUP: Vec3 = Vec3::new(0.0, 1.0, 0.0);
+    const FOV: f64 = 40.0;
+    const APERTURE: f64 = 0.0;
+    const DIST_TO_FOCUS: f64 = 10.0;
+    const ASPECT_RATIO: f64 = 1.0;
+
+    let camera = Camera::new(
+        LOOK_FROM,
+        LOOK_AT,
+        VUP,
+        FOV,
+        ASPECT_RATIO,
+        APERTURE,
+        DIST_TO_FOCUS,
+        (0.0, 1.0),
+    );
+
+    let mut world = HittableList::new();
+
+    let red = color::RED.lambertian();
+    let white = color::WHITISH.lambertian();
+    let green = color::GREEN.lambertian();
+
+    // Walls
+    world.push(YZRect::new((0.0, 555.0), (0.0, 555.0), 555.0, green));
+    world.push(YZRect::new((0.0, 555.0), (0.0, 555.0), 0.0, red));
+    world.push(XZRect::new((0.0, 555.0), (0.0, 555.0), 0.0, white.clone()));
+    world.push(XZRect::new(
+        (0.0, 555.0),
+        (0.0, 555.0),
+        555.0,
+        white.clone(),
+    ));
+    world.push(XYRect::new((0.0, 555.0), (0.0, 555.0), 555.0, white));
+
+    // A lamp inside a glass ball, which only lights the room through the glass.
+    // Paths from the camera almost never find it, but paths from the lamp do.
+    let lamp = Sphere::new(
+        Vec3::new(278.0, 300.0, 278.0),
+        15.0,
+        DiffuseLight::new(Color::new(200.0, 180.0, 150.0)),
+    );
+    world.push(Sphere::new(
+        Vec3::new(278.0, 300.0, 278.0),
+        120.0,
+        Dielectric::new(1.5),
+    ));
+    world.push(lamp.clone());
+
+    let world = world.into_bvh((0.0, 1.0));
+
+    SceneBuilder::new(world, camera, ASPECT_RATIO)
+        .background_color(color::BLACK)
+        .integrator(BidirectionalPathTracer::new())
+        .light(lamp)
+}
+
+pub fn scene22() -> SceneBuilder<impl Hittable> {
+    // Camera
+    const LOOK_FROM: Vec3 = Vec3::new(0.0, 4.0, 14.0);
+    const LOOK_AT: Vec3 = Vec3::new(0.0, 1.0, 0.0);
+    const VUP: Vec3 = Vec3::new(0.0, 1.0, 0.0);
+    const FOV: f64 = 30.0;
+    const APERTURE: f64 = 0.0;
+    const DIST_TO_FOCUS: f64 = 10.0;
+    const ASPECT_RATIO: f64 = 16.0 / 9.0;
+
+    let camera = Camera::new(
+        LOOK_FROM,
+        LOOK_AT,
+        VUP,
+        FOV,
+        ASPECT_RATIO,
+        APERTURE,
+        DIST_TO_FOCUS,
+        (0.0, 1.0),
+    );
+
+    let mut world = HittableList::new();
+
+    // Procedural textures made out of noises, patterns and combinators
+    let marble = Marble::new(
+        Fbm::new(Perlin::new()),
+        Color::new(0.9, 0.9, 0.85),
+        Color::new(0.2, 0.25, 0.3),
+    );
+    let wood = Wood::new(
+        Simplex::new(),
+        Color::new(0.75, 0.5, 0.3),
+        Color::new(0.35, 0.2, 0.1),
+    )
+    .rings(4.0);
+    let cells = ColorRamp::new(
+        NoiseTexture::new(Worley::new().feature(CellFeature::Edges)).scale(4.0),
+        vec![
+            (0.5, Color::new(0.1, 0.1, 0.4)),
+            (0.6, Color::new(0.3, 0.6, 0.9)),
+            (1.0, Color::new(0.9, 0.95, 1.0)),
+        ],
+    );
+    let bricks = Brick::new(
+        Multiply::new(
+            Color::new(0.6, 0.25, 0.15),
+            Remap::new(
+                NoiseTexture::new(Fbm::new(Simplex::new())).scale(8.0),
+                (0.0, 1.0),
+                (0.6, 1.0),
+            ),
+        ),
+        Color::new(0.8, 0.8, 0.75),
+    )
+    .bricks(8.0, 8.0)
+    .mortar_width(0.005);
+    let warped_grid = DomainWarp::new(
+        Grid::new(
+            Color::new(0.1, 0.1, 0.1),
+            Gradient::new(Color::new(0.9, 0.3, 0.3), Color::new(0.9, 0.8, 0.3))
+                .shape(GradientShape::Vertical),
+        )
+        .cells(16.0, 8.0),
+        Perlin::new(),
+    )
+    .scale(2.0)
+    .strength(0.02);
+
+    for (x, material) in [-4.4, -2.2, 0.0, 2.2, 4.4].into_iter().zip([
+        Arc::new(marble.lambertian()) as Arc<dyn Material>,
+        Arc::new(wood.lambertian()),
+        Arc::new(cells.lambertian()),
+        Arc::new(bricks.lambertian()),
+        Arc::new(warped_grid.lambertian()),
+    ]) {
+        world.push(Sphere::new(Vec3::new(x, 1.0, 0.0), 1.0, material));
+    }
+
+    let ground = Mix::new(
+        Color::new(0.5, 0.5, 0.5),
+        Color::new(0.3, 0.35, 0.3),
+        NoiseTexture::new(Fbm::new(Perlin::new())),
+    );
+    world.push(Sphere::new(
+        Vec3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        ground.lambertian(),
+    ));
+
+    let world = world.into_bvh((0.0, 1.0));
+
+    SceneBuilder::new(world, camera, ASPECT_RATIO)
+}
+
+pub fn scene23() -> SceneBuilder<impl Hittable> {
+    // Camera
+    const LOOK_FROM: Vec3 = Vec3::new(0.0, 3.0, 12.0);
+    const LOOK_AT: Vec3 = Vec3::new(0.0, 1.0, 0.0);
+    const VUP: Vec3 = Vec3::new(0.0, 1.0, 0.0);
+    const FOV: f64 = 30.0;
+    const APERTURE: f64 = 0.0;
+    const DIST_TO_FOCUS: f64 = 10.0;
+    const ASPECT_RATIO: f64 = 16.0 / 9.0;
+
+    let camera = Camera::new(
+        LOOK_FROM,
+        LOOK_AT,
+        VUP,
+        FOV,
+        ASPECT_RATIO,
+        APERTURE,
+        DIST_TO_FOCUS,
+        (0.0, 1.0),
+    );
+
+    let mut world = HittableList::new();
+
+    // Mostly matte, with metal flecks where a cellular noise is high
+    let flecks = ColorRamp::new(
+        NoiseTexture::new(Worley::new()).scale(20.0),
+        vec![(0.8, color::BLACK), (0.85, color::WHITE)],
+    );
+    let flecked = Color::new(0.2, 0.3, 0.6)
+        .lambertian()
+        .mixed_with(Color::new(0.9, 0.9, 0.9).metal(0.2), flecks);
+    // Car paint: the same flecks under a clear coat
+    let car_paint = flecked.clone().coated(1.5);
+    // A glossy coat over half wood and half gold, nested the other way around
+    let gold = Color::new(0.9, 0.7, 0.3).metal(0.1);
+    let wood = Wood::new(
+        Simplex::new(),
+        Color::new(0.75, 0.5, 0.3),
+        Color::new(0.35, 0.2, 0.1),
+    )
+    .lambertian()
+    .coated(1.5)
+    .mixed_with(gold, Color::new(0.5, 0.5, 0.5));
+
+    world.push(Sphere::new(Vec3::new(-2.2, 1.0, 0.0), 1.0, flecked));
+    world.push(Sphere::new(Vec3::new(0.0, 1.0, 0.0), 1.0, car_paint));
+    world.push(Sphere::new(Vec3::new(2.2, 1.0, 0.0), 1.0, wood));
+
+    let checker = CheckerTexture::new(color::BLACKISH, color::WHITISH).lambertian();
+    world.push(Sphere::new(Vec3::new(0.0, -1000.0, 0.0), 1000.0, checker));
+
+    let world = world.into_bvh((0.0, 1.0));
+
+    SceneBuilder::new(world, camera, ASPECT_RATIO)
+}
+
+pub fn scene24() -> SceneBuilder<impl Hittable> {
+    // Camera
+    const LOOK_FROM: Vec3 = Vec3::new(0.0, 3.0, 12.0);
+    const LOOK_AT: Vec3 = Vec3::new(0.0, 1.0, 0.0);
+    const VUP: Vec3 = Vec3::new(0.0, 1.0, 0.0);
+    const FOV: f64 = 30.0;
+    const APERTURE: f64 = 0.0;
+    const DIST_TO_FOCUS: f64 = 10.0;
+    const ASPECT_RATIO: f64 = 16.0 / 9.0;
+
+    let camera = Camera::new(
+        LOOK_FROM,
+        LOOK_AT,
+        VUP,
+        FOV,
+        ASPECT_RATIO,
+        APERTURE,
+        DIST_TO_FOCUS,
+        (0.0, 1.0),
+    );
+
+    let mut world = HittableList::new();
+
+    // A soap bubble, brushed around the y axis, and red velvet
+    let bubble = ThinFilm::new(450.0, 1.33);
+    let brushed = AnisotropicMetal::new(Color::new(0.9, 0.9, 0.9), 0.05, 0.5);
+    let velvet = Velvet::new(Color::new(0.4, 0.02, 0.05), Color::new(1.0, 0.6, 0.6)).roughness(0.4);
+
+    world.push(Sphere::new(Vec3::new(-2.2, 1.0, 0.0), 1.0, bubble));
+    world.push(Sphere::new(Vec3::new(0.0, 1.0, 0.0), 1.0, brushed));
+    world.push(Sphere::new(Vec3::new(2.2, 1.0, 0.0), 1.0, velvet));
+
+    let checker = CheckerTexture::new(color::BLACKISH, color::WHITISH).lambertian();
+    world.push(Sphere::new(Vec3::new(0.0, -1000.0, 0.0), 1000.0, checker));
+
+    let world = world.into_bvh((0.0, 1.0));
+
+    SceneBuilder::new(world, camera, ASPECT_RATIO)
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use raytracing::Ray;
+
+    use super::*;
+
+    #[test]
+    fn test_seeded_scene() {
+        let first = scene3(7).build();
+        let second = scene3(7).build();
+
+        // Looking down on every small sphere finds the same ones with the same colors
+        for x in -40..40 {
+            for z in -40..40 {
+                let origin = Vec3::new(f64::from(x) / 2.0, 10.0, f64::from(z) / 2.0);
+                let ray = Ray::new(origin, Vec3::new(0.0, -1.0, 0.0), 0.0);
+                let first_hit = first.world().hit(&ray, 0.001, f64::INFINITY).unwrap();
+                let second_hit = second.world().hit(&ray, 0.001, f64::INFINITY).unwrap();
+
+                assert!((first_hit.t - second_hit.t).abs() < 1e-9);
+                let first_albedo = first_hit.material.albedo(&first_hit);
+                let second_albedo = second_hit.material.albedo(&second_hit);
+                assert!((first_albedo.r() - second_albedo.r()).abs() < 1e-9);
+                assert!((first_albedo.g() - second_albedo.g()).abs() < 1e-9);
+                assert!((first_albedo.b() - second_albedo.b()).abs() < 1e-9);
+            }
+        }
+    }
+}